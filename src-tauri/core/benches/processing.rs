@@ -0,0 +1,67 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use image::{Rgba, RgbaImage};
+
+use image_analyzer_core::analysis::palette::DEFAULT_PALETTE_SIZE;
+use image_analyzer_core::analysis::{analyze, sharpness::variance_of_laplacian};
+use image_analyzer_core::processing::{self, looks};
+
+// A 1024x1024 gradient with some high-frequency detail, so histogram and
+// sharpness work sees a realistic spread of values.
+fn sample_image() -> RgbaImage {
+    RgbaImage::from_fn(1024, 1024, |x, y| {
+        let detail = ((x ^ y) & 0x1f) as u8;
+        Rgba([
+            (x / 4) as u8,
+            (y / 4) as u8,
+            ((x + y) / 8) as u8 ^ detail,
+            255,
+        ])
+    })
+}
+
+fn analysis(c: &mut Criterion) {
+    let img = sample_image();
+    c.bench_function("analyze 1MP", |b| {
+        b.iter(|| analyze(black_box(&img), None, DEFAULT_PALETTE_SIZE))
+    });
+
+    let gray = image::DynamicImage::ImageRgba8(img).to_luma8();
+    c.bench_function("variance_of_laplacian 1MP", |b| {
+        b.iter(|| variance_of_laplacian(black_box(&gray)))
+    });
+}
+
+fn adjustments(c: &mut Criterion) {
+    let img = sample_image();
+    c.bench_function("adjust_brightness 1MP", |b| {
+        b.iter_batched_ref(
+            || img.clone(),
+            |img| processing::adjust_brightness(img, 1.2),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("adjust_contrast 1MP", |b| {
+        b.iter_batched_ref(
+            || img.clone(),
+            |img| processing::adjust_contrast(img, 1.2),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("convert_to_grayscale 1MP", |b| {
+        b.iter_batched_ref(
+            || img.clone(),
+            |img| processing::convert_to_grayscale(img),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("apply_look classic_chrome 1MP", |b| {
+        b.iter_batched_ref(
+            || img.clone(),
+            |img| looks::apply_look(img, "classic_chrome", 0.8),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, analysis, adjustments);
+criterion_main!(benches);
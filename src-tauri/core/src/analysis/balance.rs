@@ -0,0 +1,128 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::color::srgb_to_lab;
+use crate::error::AppError;
+use crate::models::{BalanceAnalysis, HalfBalance};
+
+// Symmetry is measured on a thumbnail so sensor noise and sub-pixel offsets
+// don't count as asymmetry.
+const SYMMETRY_DIMENSION: u32 = 128;
+// A colour difference this large (clearly different hues) scores 0.
+const MAX_COLOR_DIFFERENCE: f32 = 30.0;
+
+#[derive(Default, Clone, Copy)]
+struct Sums {
+    weight: f64,
+    luminance: f64,
+    lab: [f64; 3],
+}
+
+impl Sums {
+    fn add(&mut self, px: &image::Rgba<u8>, weight: f64) {
+        let lum = 0.299 * px[0] as f64 + 0.587 * px[1] as f64 + 0.114 * px[2] as f64;
+        let lab = srgb_to_lab(px[0], px[1], px[2]);
+        self.weight += weight;
+        self.luminance += lum / 255.0 * weight;
+        for (sum, v) in self.lab.iter_mut().zip(lab) {
+            *sum += v as f64 * weight;
+        }
+    }
+
+    fn mean(&self) -> (f64, [f64; 3]) {
+        let w = self.weight.max(f64::EPSILON);
+        (self.luminance / w, self.lab.map(|v| v / w))
+    }
+}
+
+fn compare(first: Sums, second: Sums) -> HalfBalance {
+    let (lum_a, lab_a) = first.mean();
+    let (lum_b, lab_b) = second.mean();
+    let luminance_difference = (lum_a - lum_b) as f32;
+    let color_difference = (0..3)
+        .map(|c| (lab_a[c] - lab_b[c]).powi(2))
+        .sum::<f64>()
+        .sqrt() as f32;
+    let score = (1.0 - luminance_difference.abs())
+        * (1.0 - (color_difference / MAX_COLOR_DIFFERENCE).min(1.0));
+    HalfBalance {
+        luminance_difference,
+        color_difference,
+        score,
+    }
+}
+
+// Pearson correlation between luminance values and their mirrored
+// counterparts, clamped to 0-1. Flat images count as perfectly symmetric.
+fn mirror_correlation(lum: &[f32], width: usize, height: usize, left_right: bool) -> f32 {
+    let n = lum.len() as f64;
+    let mean = lum.iter().map(|&v| v as f64).sum::<f64>() / n;
+    let (mut cov, mut var) = (0f64, 0f64);
+    for y in 0..height {
+        for x in 0..width {
+            let (mx, my) = if left_right {
+                (width - 1 - x, y)
+            } else {
+                (x, height - 1 - y)
+            };
+            let a = lum[y * width + x] as f64 - mean;
+            let b = lum[my * width + mx] as f64 - mean;
+            cov += a * b;
+            var += a * a;
+        }
+    }
+    if var <= f64::EPSILON {
+        return 1.0;
+    }
+    (cov / var).clamp(0.0, 1.0) as f32
+}
+
+// Left/right and top/bottom balance of luminance and colour, plus mirror
+// symmetry about both centre lines. Transparent pixels carry no weight.
+pub fn analyze_balance(img: &RgbaImage) -> Result<BalanceAnalysis, AppError> {
+    let (width, height) = img.dimensions();
+    if width < 2 || height < 2 {
+        return Err(AppError::invalid("Image is too small to compare halves"));
+    }
+
+    let (mut left, mut right, mut top, mut bottom) = Default::default();
+    // The centre row or column of odd-sized images belongs to neither half.
+    for (x, y, px) in img.enumerate_pixels() {
+        let weight = px[3] as f64 / 255.0;
+        if weight == 0.0 {
+            continue;
+        }
+        if 2 * x + 1 < width {
+            Sums::add(&mut left, px, weight);
+        } else if 2 * x + 1 > width {
+            Sums::add(&mut right, px, weight);
+        }
+        if 2 * y + 1 < height {
+            Sums::add(&mut top, px, weight);
+        } else if 2 * y + 1 > height {
+            Sums::add(&mut bottom, px, weight);
+        }
+    }
+
+    let scale = (width.max(height) as f32 / SYMMETRY_DIMENSION as f32).max(1.0);
+    let small = imageops::resize(
+        img,
+        ((width as f32 / scale).round() as u32).max(2),
+        ((height as f32 / scale).round() as u32).max(2),
+        FilterType::Triangle,
+    );
+    let lum: Vec<f32> = small
+        .pixels()
+        .map(|p| {
+            (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) * p[3] as f32 / 255.0
+        })
+        .collect();
+    let (sw, sh) = (small.width() as usize, small.height() as usize);
+
+    Ok(BalanceAnalysis {
+        left_right: compare(left, right),
+        top_bottom: compare(top, bottom),
+        left_right_symmetry: mirror_correlation(&lum, sw, sh, true),
+        top_bottom_symmetry: mirror_correlation(&lum, sw, sh, false),
+    })
+}
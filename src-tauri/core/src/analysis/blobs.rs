@@ -0,0 +1,105 @@
+use image::RgbaImage;
+
+use crate::error::AppError;
+use crate::models::{BlobAnalysis, BlobStats, PixelRect, Point};
+
+pub(crate) fn luminance(p: &image::Rgba<u8>) -> f32 {
+    0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32
+}
+
+// The 8-connected components of `foreground`, a row-major `width`x`height`
+// mask, as lists of pixel indices.
+pub(crate) fn components(foreground: &[bool], width: usize, height: usize) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; width * height];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    for start in 0..width * height {
+        if visited[start] || !foreground[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let mut pixels = Vec::new();
+        while let Some(i) = stack.pop() {
+            pixels.push(i);
+            let (x, y) = (i % width, i / width);
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let n = ny * width + nx;
+                    if !visited[n] && foreground[n] {
+                        visited[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+        components.push(pixels);
+    }
+    components
+}
+
+// Thresholds luminance and labels 8-connected components. Pixels brighter
+// than `threshold` are foreground, or darker with `invert` for dark objects
+// on a light field. Transparent pixels are always background.
+pub fn count_blobs(
+    img: &RgbaImage,
+    threshold: u8,
+    min_area: u32,
+    invert: bool,
+) -> Result<BlobAnalysis, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let (w, h) = (width as usize, height as usize);
+    let lum: Vec<f32> = img.pixels().map(luminance).collect();
+    let foreground: Vec<bool> = img
+        .pixels()
+        .zip(&lum)
+        .map(|(p, &l)| p[3] > 0 && ((l > threshold as f32) != invert))
+        .collect();
+
+    let mut blobs = Vec::new();
+    let mut rejected = 0;
+    for pixels in components(&foreground, w, h) {
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        let (mut sum_x, mut sum_y, mut sum_l) = (0f64, 0f64, 0f64);
+        for &i in &pixels {
+            let (x, y) = (i % w, i / w);
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+            sum_x += x as f64 + 0.5;
+            sum_y += y as f64 + 0.5;
+            sum_l += lum[i] as f64;
+        }
+        let area = pixels.len() as u32;
+
+        if area < min_area {
+            rejected += 1;
+            continue;
+        }
+        blobs.push(BlobStats {
+            area,
+            centroid: Point {
+                x: (sum_x / area as f64) as f32,
+                y: (sum_y / area as f64) as f32,
+            },
+            bounds: PixelRect {
+                x: x0 as u32,
+                y: y0 as u32,
+                width: (x1 - x0 + 1) as u32,
+                height: (y1 - y0 + 1) as u32,
+            },
+            mean_intensity: (sum_l / area as f64) as f32,
+        });
+    }
+
+    Ok(BlobAnalysis {
+        count: blobs.len(),
+        total_area: blobs.iter().map(|b| b.area as u64).sum(),
+        rejected,
+        blobs,
+    })
+}
@@ -0,0 +1,435 @@
+use image::RgbaImage;
+
+use crate::analysis::saliency::{saliency_map, SaliencyMap};
+use crate::error::AppError;
+use crate::models::{
+    CompositionAnalysis, CropSuggestion, GuideKind, GuideLine, GuideOffset, PixelRect, Point,
+    Subject,
+};
+
+const THIRDS: [f32; 2] = [1.0 / 3.0, 2.0 / 3.0];
+// 1 / phi^2 and 1 / phi.
+const GOLDEN: [f32; 2] = [0.381_966, 0.618_034];
+// Crops within this share of the best saliency mass count as equally good.
+const CROP_MASS_TOLERANCE: f64 = 0.02;
+// Candidate crop sizes relative to the largest that fits, and how finely
+// each is slid across the frame.
+const CROP_SCALES: [f64; 5] = [1.0, 0.9, 0.8, 0.7, 0.6];
+const CROP_POSITIONS: u32 = 16;
+// Score weights for kept saliency, thirds placement and clean borders.
+const COVERAGE_WEIGHT: f64 = 0.6;
+const THIRDS_WEIGHT: f64 = 0.25;
+const BORDER_WEIGHT: f64 = 0.15;
+// Subjects this far (of the crop diagonal) from a thirds point score 0.
+const THIRDS_FALLOFF: f64 = 0.3;
+// Suggestions overlapping a better one by more than this are dropped.
+const MAX_OVERLAP: f64 = 0.6;
+
+// Finds the subject as the region saliency singles out: pixels at least twice
+// the mean saliency, the adaptive threshold from the frequency-tuned paper.
+pub fn locate_subject(map: &SaliencyMap) -> Subject {
+    let mean = map.values.iter().sum::<f32>() / map.values.len() as f32;
+    let threshold = (2.0 * mean).min(0.95);
+
+    let (mut sum_w, mut sum_x, mut sum_y) = (0f64, 0f64, 0f64);
+    let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+    let mut count = 0u32;
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let v = map.get(x, y);
+            if v < threshold || v == 0.0 {
+                continue;
+            }
+            sum_w += v as f64;
+            sum_x += v as f64 * (x as f64 + 0.5);
+            sum_y += v as f64 * (y as f64 + 0.5);
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+            count += 1;
+        }
+    }
+
+    let full_width = (map.width as f32 * map.scale).round() as u32;
+    let full_height = (map.height as f32 * map.scale).round() as u32;
+    // A flat image has no subject; treat the whole frame as one.
+    if count == 0 {
+        return Subject {
+            center: Point {
+                x: full_width as f32 / 2.0,
+                y: full_height as f32 / 2.0,
+            },
+            bounds: PixelRect {
+                x: 0,
+                y: 0,
+                width: full_width,
+                height: full_height,
+            },
+            coverage: 1.0,
+        };
+    }
+
+    let to_pixels = |v: u32| (v as f32 * map.scale).round() as u32;
+    let bounds_x = to_pixels(x0);
+    let bounds_y = to_pixels(y0);
+    Subject {
+        center: Point {
+            x: (sum_x / sum_w) as f32 * map.scale,
+            y: (sum_y / sum_w) as f32 * map.scale,
+        },
+        bounds: PixelRect {
+            x: bounds_x,
+            y: bounds_y,
+            width: (to_pixels(x1 + 1) - bounds_x).min(full_width - bounds_x),
+            height: (to_pixels(y1 + 1) - bounds_y).min(full_height - bounds_y),
+        },
+        coverage: count as f32 / map.values.len() as f32,
+    }
+}
+
+// The largest `aspect` (width / height) crop of a `width`x`height` image,
+// slid along the free axis to hold as much saliency as possible. Ties go to
+// the position that best centres the subject.
+pub fn frame_crop(map: &SaliencyMap, width: u32, height: u32, aspect: f32) -> PixelRect {
+    let horizontal = width as f32 / height as f32 > aspect;
+    let (crop_w, crop_h) = if horizontal {
+        (
+            ((height as f32 * aspect).round() as u32).clamp(1, width),
+            height,
+        )
+    } else {
+        (
+            width,
+            ((width as f32 / aspect).round() as u32).clamp(1, height),
+        )
+    };
+
+    // Saliency summed across the fixed axis, as a prefix sum along the free one.
+    let (len, span) = if horizontal {
+        (map.width, crop_w)
+    } else {
+        (map.height, crop_h)
+    };
+    let mut prefix = vec![0f64; len as usize + 1];
+    for i in 0..len {
+        let line: f32 = if horizontal {
+            (0..map.height).map(|y| map.get(i, y)).sum()
+        } else {
+            (0..map.width).map(|x| map.get(x, i)).sum()
+        };
+        prefix[i as usize + 1] = prefix[i as usize] + line as f64;
+    }
+    let window = ((span as f32 / map.scale).round() as u32).clamp(1, len);
+    let mass = |start: u32| prefix[(start + window) as usize] - prefix[start as usize];
+    let most = (0..=len - window).map(mass).fold(0f64, f64::max);
+
+    // Of the windows holding nearly all of that, take the one that centres
+    // the subject best.
+    let subject = locate_subject(map);
+    let target = if horizontal {
+        subject.center.x
+    } else {
+        subject.center.y
+    } / map.scale;
+    let best = (0..=len - window)
+        .filter(|&start| mass(start) >= most * (1.0 - CROP_MASS_TOLERANCE))
+        .min_by(|&a, &b| {
+            let off = |start: u32| (start as f32 + window as f32 / 2.0 - target).abs();
+            off(a).total_cmp(&off(b))
+        })
+        .unwrap_or(0);
+
+    let offset = (best as f32 * map.scale).round() as u32;
+    let (x, y) = if horizontal {
+        (offset.min(width - crop_w), 0)
+    } else {
+        (0, offset.min(height - crop_h))
+    };
+    PixelRect {
+        x,
+        y,
+        width: crop_w,
+        height: crop_h,
+    }
+}
+
+fn grid_points(width: f32, height: f32, fractions: [f32; 2]) -> Vec<Point> {
+    fractions
+        .iter()
+        .flat_map(|fy| {
+            fractions.iter().map(move |fx| Point {
+                x: fx * width,
+                y: fy * height,
+            })
+        })
+        .collect()
+}
+
+fn grid_lines(width: f32, height: f32, fractions: [f32; 2], kind: GuideKind) -> Vec<GuideLine> {
+    fractions
+        .iter()
+        .flat_map(|f| {
+            [
+                GuideLine {
+                    kind,
+                    from: Point {
+                        x: f * width,
+                        y: 0.0,
+                    },
+                    to: Point {
+                        x: f * width,
+                        y: height,
+                    },
+                },
+                GuideLine {
+                    kind,
+                    from: Point {
+                        x: 0.0,
+                        y: f * height,
+                    },
+                    to: Point {
+                        x: width,
+                        y: f * height,
+                    },
+                },
+            ]
+        })
+        .collect()
+}
+
+fn nearest(points: &[Point], subject: Point, diagonal: f32) -> GuideOffset {
+    points
+        .iter()
+        .map(|p| {
+            let (dx, dy) = (subject.x - p.x, subject.y - p.y);
+            GuideOffset {
+                point: *p,
+                dx,
+                dy,
+                distance: dx.hypot(dy) / diagonal,
+            }
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+        .expect("grids have four points")
+}
+
+// Where the main subject sits relative to rule-of-thirds and golden-ratio
+// intersections, with guide lines for drawing both grids over the image.
+pub fn analyze_composition(img: &RgbaImage) -> Result<CompositionAnalysis, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+
+    let subject = locate_subject(&saliency_map(img));
+    let (w, h) = (width as f32, height as f32);
+    let diagonal = w.hypot(h);
+    let thirds_points = grid_points(w, h, THIRDS);
+    let golden_points = grid_points(w, h, GOLDEN);
+    let mut guides = grid_lines(w, h, THIRDS, GuideKind::Thirds);
+    guides.extend(grid_lines(w, h, GOLDEN, GuideKind::GoldenRatio));
+
+    Ok(CompositionAnalysis {
+        width,
+        height,
+        nearest_third: nearest(&thirds_points, subject.center, diagonal),
+        nearest_golden: nearest(&golden_points, subject.center, diagonal),
+        subject,
+        thirds_points,
+        golden_points,
+        guides,
+    })
+}
+
+// Summed-area tables of saliency and its first moments, for O(1) sums over
+// any rectangle of the map.
+struct Integral {
+    width: usize,
+    mass: Vec<f64>,
+    moment_x: Vec<f64>,
+    moment_y: Vec<f64>,
+}
+
+impl Integral {
+    fn new(map: &SaliencyMap) -> Self {
+        let (w, h) = (map.width as usize, map.height as usize);
+        let stride = w + 1;
+        let mut mass = vec![0f64; stride * (h + 1)];
+        let mut moment_x = mass.clone();
+        let mut moment_y = mass.clone();
+        for y in 0..h {
+            for x in 0..w {
+                let v = map.get(x as u32, y as u32) as f64;
+                let i = (y + 1) * stride + x + 1;
+                let (up, left, diag) = (i - stride, i - 1, i - stride - 1);
+                mass[i] = v + mass[up] + mass[left] - mass[diag];
+                moment_x[i] = v * (x as f64 + 0.5) + moment_x[up] + moment_x[left] - moment_x[diag];
+                moment_y[i] = v * (y as f64 + 0.5) + moment_y[up] + moment_y[left] - moment_y[diag];
+            }
+        }
+        Integral {
+            width: stride,
+            mass,
+            moment_x,
+            moment_y,
+        }
+    }
+
+    // Sum over cells x0..x1, y0..y1 of one table.
+    fn sum(&self, table: &[f64], x0: usize, y0: usize, x1: usize, y1: usize) -> f64 {
+        let at = |x: usize, y: usize| table[y * self.width + x];
+        at(x1, y1) - at(x0, y1) - at(x1, y0) + at(x0, y0)
+    }
+}
+
+struct Candidate {
+    rect: [usize; 4],
+    score: f64,
+    coverage: f64,
+    thirds_distance: f64,
+}
+
+fn overlap(a: [usize; 4], b: [usize; 4]) -> f64 {
+    let ix = a[2].min(b[2]).saturating_sub(a[0].max(b[0]));
+    let iy = a[3].min(b[3]).saturating_sub(a[1].max(b[1]));
+    let inter = (ix * iy) as f64;
+    let area = |r: [usize; 4]| ((r[2] - r[0]) * (r[3] - r[1])) as f64;
+    inter / (area(a) + area(b) - inter).max(1.0)
+}
+
+fn score(integral: &Integral, total: f64, [x0, y0, x1, y1]: [usize; 4]) -> Candidate {
+    let mass = integral.sum(&integral.mass, x0, y0, x1, y1);
+    let coverage = if total > 0.0 { mass / total } else { 1.0 };
+
+    let (w, h) = ((x1 - x0) as f64, (y1 - y0) as f64);
+    let thirds_distance = if mass > 0.0 {
+        let cx = integral.sum(&integral.moment_x, x0, y0, x1, y1) / mass;
+        let cy = integral.sum(&integral.moment_y, x0, y0, x1, y1) / mass;
+        THIRDS
+            .iter()
+            .flat_map(|fy| THIRDS.iter().map(move |fx| (*fx as f64, *fy as f64)))
+            .map(|(fx, fy)| (cx - (x0 as f64 + fx * w)).hypot(cy - (y0 as f64 + fy * h)))
+            .fold(f64::MAX, f64::min)
+            / w.hypot(h)
+    } else {
+        0.0
+    };
+    let thirds_score = 1.0 - (thirds_distance / THIRDS_FALLOFF).min(1.0);
+
+    // Saliency on the outermost ring means the crop cuts through something.
+    let inner = if x1 - x0 > 2 && y1 - y0 > 2 {
+        integral.sum(&integral.mass, x0 + 1, y0 + 1, x1 - 1, y1 - 1)
+    } else {
+        0.0
+    };
+    let ring_cells = (w * h - (w - 2.0).max(0.0) * (h - 2.0).max(0.0)).max(1.0);
+    let border_mean = (mass - inner) / ring_cells;
+    let mean = mass / (w * h).max(1.0);
+    let border_score = if mean > 0.0 {
+        1.0 - (border_mean / mean).min(1.0)
+    } else {
+        1.0
+    };
+
+    Candidate {
+        rect: [x0, y0, x1, y1],
+        score: COVERAGE_WEIGHT * coverage
+            + THIRDS_WEIGHT * thirds_score
+            + BORDER_WEIGHT * border_score,
+        coverage,
+        thirds_distance,
+    }
+}
+
+// Proposes up to `per_aspect` crops for each aspect ratio (width / height),
+// ranked by how much saliency they keep, how close the subject sits to a
+// thirds point and how cleanly the edges avoid it. Results are grouped by
+// aspect ratio in the order given, best first.
+pub fn suggest_crops(
+    img: &RgbaImage,
+    aspect_ratios: &[f32],
+    per_aspect: usize,
+) -> Result<Vec<CropSuggestion>, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if aspect_ratios.is_empty() {
+        return Err(AppError::invalid("No aspect ratios given"));
+    }
+    if let Some(bad) = aspect_ratios.iter().find(|a| !(a.is_finite() && **a > 0.0)) {
+        return Err(AppError::invalid(format!("Invalid aspect ratio {}", bad)));
+    }
+
+    let map = saliency_map(img);
+    let integral = Integral::new(&map);
+    let (mw, mh) = (map.width as usize, map.height as usize);
+    let total = integral.sum(&integral.mass, 0, 0, mw, mh);
+    let to_pixels = |v: usize, limit: u32| ((v as f32 * map.scale).round() as u32).min(limit);
+
+    let mut suggestions = Vec::new();
+    for &aspect in aspect_ratios {
+        let aspect = aspect as f64;
+        let mut candidates = Vec::new();
+        for scale in CROP_SCALES {
+            let (cw, ch) = if mw as f64 / mh as f64 > aspect {
+                (mh as f64 * aspect * scale, mh as f64 * scale)
+            } else {
+                (mw as f64 * scale, mw as f64 / aspect * scale)
+            };
+            let (cw, ch) = (
+                (cw.round() as usize).clamp(1, mw),
+                (ch.round() as usize).clamp(1, mh),
+            );
+            for iy in 0..=CROP_POSITIONS {
+                for ix in 0..=CROP_POSITIONS {
+                    let x0 = (mw - cw) * ix as usize / CROP_POSITIONS as usize;
+                    let y0 = (mh - ch) * iy as usize / CROP_POSITIONS as usize;
+                    candidates.push(score(&integral, total, [x0, y0, x0 + cw, y0 + ch]));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut kept: Vec<Candidate> = Vec::new();
+        for candidate in candidates {
+            if kept.len() == per_aspect {
+                break;
+            }
+            if kept
+                .iter()
+                .all(|k| overlap(k.rect, candidate.rect) <= MAX_OVERLAP)
+            {
+                kept.push(candidate);
+            }
+        }
+
+        suggestions.extend(kept.into_iter().map(|c| {
+            let [x0, y0, x1, y1] = c.rect;
+            let x = to_pixels(x0, width - 1);
+            let y = to_pixels(y0, height - 1);
+            // Shrink to the exact ratio, which the map's rounding loses.
+            let mut crop_w = (to_pixels(x1, width) - x).max(1);
+            let mut crop_h = (to_pixels(y1, height) - y).max(1);
+            if crop_w as f64 / crop_h as f64 > aspect {
+                crop_w = ((crop_h as f64 * aspect).round() as u32).max(1);
+            } else {
+                crop_h = ((crop_w as f64 / aspect).round() as u32).max(1);
+            }
+            CropSuggestion {
+                aspect_ratio: aspect as f32,
+                bounds: PixelRect {
+                    x,
+                    y,
+                    width: crop_w,
+                    height: crop_h,
+                },
+                score: c.score as f32,
+                saliency_coverage: c.coverage as f32,
+                thirds_distance: c.thirds_distance as f32,
+            }
+        }));
+    }
+    Ok(suggestions)
+}
@@ -0,0 +1,40 @@
+use crate::color::srgb_to_linear;
+use crate::models::{Color, ContrastReport};
+
+// WCAG 2 success criteria 1.4.3, 1.4.6 and 1.4.11.
+const AA_NORMAL: f32 = 4.5;
+const AA_LARGE: f32 = 3.0;
+const AAA_NORMAL: f32 = 7.0;
+const AAA_LARGE: f32 = 4.5;
+
+// WCAG relative luminance of an sRGB colour: linear light weighted by the
+// Rec. 709 primaries.
+pub fn relative_luminance(r: u8, g: u8, b: u8) -> f32 {
+    0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b)
+}
+
+// `a` is the foreground and `b` the background. A translucent foreground
+// is judged as it shows over the background; the background's own alpha is
+// ignored, there being nothing behind it to show through.
+pub fn check_contrast(a: Color, b: Color) -> ContrastReport {
+    let alpha = a.a as f32 / 255.0;
+    let over = |fg: u8, bg: u8| (fg as f32 * alpha + bg as f32 * (1.0 - alpha)).round() as u8;
+    let luminance_a = relative_luminance(over(a.r, b.r), over(a.g, b.g), over(a.b, b.b));
+    let luminance_b = relative_luminance(b.r, b.g, b.b);
+
+    let (lighter, darker) = if luminance_a >= luminance_b {
+        (luminance_a, luminance_b)
+    } else {
+        (luminance_b, luminance_a)
+    };
+    let ratio = (lighter + 0.05) / (darker + 0.05);
+    ContrastReport {
+        luminance_a,
+        luminance_b,
+        ratio,
+        aa_normal: ratio >= AA_NORMAL,
+        aa_large: ratio >= AA_LARGE,
+        aaa_normal: ratio >= AAA_NORMAL,
+        aaa_large: ratio >= AAA_LARGE,
+    }
+}
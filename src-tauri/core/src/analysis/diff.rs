@@ -0,0 +1,106 @@
+use crate::color::srgb_to_lab;
+use crate::models::{
+    AnalysisDiff, AnalysisResult, ChannelShifts, ColorSample, HistogramShift, PaletteChange,
+};
+
+fn normalized(histogram: &[u32]) -> Vec<f64> {
+    let total: u64 = histogram.iter().map(|&v| v as u64).sum();
+    let total = total.max(1) as f64;
+    histogram.iter().map(|&v| v as f64 / total).collect()
+}
+
+// In one dimension the earth mover's distance is the area between the two
+// cumulative distributions.
+fn shift(a: &[u32], b: &[u32]) -> HistogramShift {
+    let (a, b) = (normalized(a), normalized(b));
+    let bins = a.len().max(b.len());
+    let (mut cdf_a, mut cdf_b, mut emd) = (0f64, 0f64, 0f64);
+    let (mut mean_a, mut mean_b) = (0f64, 0f64);
+    for i in 0..bins {
+        let (pa, pb) = (
+            a.get(i).copied().unwrap_or(0.0),
+            b.get(i).copied().unwrap_or(0.0),
+        );
+        cdf_a += pa;
+        cdf_b += pb;
+        emd += (cdf_a - cdf_b).abs();
+        mean_a += pa * i as f64;
+        mean_b += pb * i as f64;
+    }
+    HistogramShift {
+        emd: emd as f32,
+        mean_shift: (mean_b - mean_a) as f32,
+    }
+}
+
+fn delta_e(a: &ColorSample, b: &ColorSample) -> f32 {
+    let (la, lb) = (srgb_to_lab(a.r, a.g, a.b), srgb_to_lab(b.r, b.g, b.b));
+    la.iter()
+        .zip(lb)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+// Quantifies what an edit changed by comparing the analysis of the image
+// before (`a`) and after (`b`).
+pub fn diff_analysis(a: &AnalysisResult, b: &AnalysisResult) -> AnalysisDiff {
+    let (ha, hb) = (&a.histogram, &b.histogram);
+
+    // Greedy pairing: repeatedly take the closest remaining pair.
+    let mut pairs: Vec<(usize, usize, f32)> = a
+        .dominant_colors
+        .iter()
+        .enumerate()
+        .flat_map(|(i, ca)| {
+            b.dominant_colors
+                .iter()
+                .enumerate()
+                .map(move |(j, cb)| (i, j, delta_e(ca, cb)))
+        })
+        .collect();
+    pairs.sort_by(|x, y| x.2.total_cmp(&y.2));
+    let mut used_a = vec![false; a.dominant_colors.len()];
+    let mut used_b = vec![false; b.dominant_colors.len()];
+    let mut palette_changes = Vec::new();
+    for (i, j, delta_e) in pairs {
+        if used_a[i] || used_b[j] {
+            continue;
+        }
+        used_a[i] = true;
+        used_b[j] = true;
+        palette_changes.push(PaletteChange {
+            before: a.dominant_colors[i].clone(),
+            after: b.dominant_colors[j].clone(),
+            delta_e,
+        });
+    }
+    let palette_distance = if palette_changes.is_empty() {
+        0.0
+    } else {
+        palette_changes.iter().map(|c| c.delta_e).sum::<f32>() / palette_changes.len() as f32
+    };
+    let unmatched = |colors: &[ColorSample], used: &[bool]| {
+        colors
+            .iter()
+            .zip(used)
+            .filter(|(_, &used)| !used)
+            .map(|(c, _)| c.clone())
+            .collect()
+    };
+
+    AnalysisDiff {
+        brightness_delta: b.average_brightness - a.average_brightness,
+        contrast_delta: b.contrast - a.contrast,
+        histogram_shift: ChannelShifts {
+            red: shift(&ha.red, &hb.red),
+            green: shift(&ha.green, &hb.green),
+            blue: shift(&ha.blue, &hb.blue),
+            luminosity: shift(&ha.luminosity, &hb.luminosity),
+        },
+        palette_distance,
+        added_colors: unmatched(&b.dominant_colors, &used_b),
+        removed_colors: unmatched(&a.dominant_colors, &used_a),
+        palette_changes,
+    }
+}
@@ -0,0 +1,167 @@
+use image::{imageops, GrayImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{Color, EdgeMethod, EdgeOutput, EdgeThresholds};
+
+// Canny's smoothing, so noise and fine texture don't count as edges.
+const CANNY_SIGMA: f32 = 1.4;
+
+fn check_thresholds(thresholds: EdgeThresholds) -> Result<(), AppError> {
+    let EdgeThresholds { low, high } = thresholds;
+    if !low.is_finite() || !high.is_finite() || low < 0.0 || low > high {
+        return Err(AppError::invalid(
+            "Edge thresholds must satisfy 0 <= low <= high",
+        ));
+    }
+    Ok(())
+}
+
+// Sobel gradients (gx, gy) of every pixel, the border clamped. Divided by
+// the kernel's weight so a step of n levels has a magnitude of n.
+fn gradients(gray: &GrayImage) -> Vec<(f32, f32)> {
+    let (width, height) = (gray.width() as i64, gray.height() as i64);
+    let p = |x: i64, y: i64| {
+        gray.get_pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32)[0] as f32
+    };
+    (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            (0..width).map(move |x| {
+                let gx = p(x + 1, y - 1) + 2.0 * p(x + 1, y) + p(x + 1, y + 1)
+                    - p(x - 1, y - 1)
+                    - 2.0 * p(x - 1, y)
+                    - p(x - 1, y + 1);
+                let gy = p(x - 1, y + 1) + 2.0 * p(x, y + 1) + p(x + 1, y + 1)
+                    - p(x - 1, y - 1)
+                    - 2.0 * p(x, y - 1)
+                    - p(x + 1, y - 1);
+                (gx / 4.0, gy / 4.0)
+            })
+        })
+        .collect()
+}
+
+// Zeroes every magnitude that isn't the largest of its neighbours across the
+// edge, thinning edges to a pixel wide. Directions are rounded to the
+// nearest 45 degrees.
+fn suppress_non_maxima(gradients: &[(f32, f32)], width: usize, height: usize) -> Vec<f32> {
+    let magnitudes: Vec<f32> = gradients.iter().map(|(gx, gy)| gx.hypot(*gy)).collect();
+    let tan_22_5 = 22.5f32.to_radians().tan();
+    (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let magnitude = magnitudes[i];
+            let (gx, gy) = gradients[i];
+            let (x, y) = ((i % width) as i64, (i / width) as i64);
+            let (dx, dy) = if gy.abs() <= gx.abs() * tan_22_5 {
+                (1, 0)
+            } else if gx.abs() <= gy.abs() * tan_22_5 {
+                (0, 1)
+            } else if (gx > 0.0) == (gy > 0.0) {
+                (1, 1)
+            } else {
+                (1, -1)
+            };
+            let at = |x: i64, y: i64| {
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    0.0
+                } else {
+                    magnitudes[y as usize * width + x as usize]
+                }
+            };
+            // Ties go to the pixel before, so a plateau keeps one line.
+            if magnitude > at(x - dx, y - dy) && magnitude >= at(x + dx, y + dy) {
+                magnitude
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// Keeps magnitudes reaching `high`, and those reaching `low` connected to
+// one through their eight neighbours.
+fn hysteresis(thin: &[f32], width: usize, height: usize, thresholds: EdgeThresholds) -> Vec<bool> {
+    let mut edges = vec![false; thin.len()];
+    let mut stack: Vec<usize> = (0..thin.len())
+        .filter(|&i| thin[i] > 0.0 && thin[i] >= thresholds.high)
+        .collect();
+    for &i in &stack {
+        edges[i] = true;
+    }
+    while let Some(i) = stack.pop() {
+        let (x, y) = (i % width, i / width);
+        for ny in y.saturating_sub(1)..(y + 2).min(height) {
+            for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                let n = ny * width + nx;
+                if !edges[n] && thin[n] > 0.0 && thin[n] >= thresholds.low {
+                    edges[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+    edges
+}
+
+// Finds edges in the image's luminance. Alpha is ignored. `color` is what an
+// overlay draws the edges in; Sobel scales its alpha by edge strength.
+pub fn detect_edges(
+    img: &RgbaImage,
+    method: EdgeMethod,
+    thresholds: EdgeThresholds,
+    output: EdgeOutput,
+    color: Color,
+) -> Result<RgbaImage, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    check_thresholds(thresholds)?;
+    let (w, h) = (width as usize, height as usize);
+    let gray = imageops::grayscale(img);
+
+    // How strongly each pixel is an edge, 0-1.
+    let strength: Vec<f32> = match method {
+        EdgeMethod::Sobel => {
+            let EdgeThresholds { low, high } = thresholds;
+            gradients(&gray)
+                .par_iter()
+                .map(|(gx, gy)| {
+                    let magnitude = gx.hypot(*gy);
+                    if magnitude < low || magnitude == 0.0 {
+                        0.0
+                    } else if high > low {
+                        ((magnitude - low) / (high - low)).min(1.0)
+                    } else {
+                        1.0
+                    }
+                })
+                .collect()
+        }
+        EdgeMethod::Canny => {
+            let smoothed = imageops::blur(&gray, CANNY_SIGMA);
+            let thin = suppress_non_maxima(&gradients(&smoothed), w, h);
+            hysteresis(&thin, w, h, thresholds)
+                .into_iter()
+                .map(|edge| if edge { 1.0 } else { 0.0 })
+                .collect()
+        }
+    };
+
+    let pixel = |s: f32| match output {
+        EdgeOutput::Overlay if s > 0.0 => Rgba([
+            color.r,
+            color.g,
+            color.b,
+            (color.a as f32 * s).round() as u8,
+        ]),
+        EdgeOutput::Overlay => Rgba([0, 0, 0, 0]),
+        EdgeOutput::Mask if s > 0.0 => Rgba([255, 255, 255, 255]),
+        EdgeOutput::Mask => Rgba([0, 0, 0, 255]),
+    };
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        pixel(strength[y as usize * w + x as usize])
+    }))
+}
@@ -0,0 +1,253 @@
+//! Face localisation with an UltraFace style ONNX model, and a guess from the
+//! edges around the eyes at whether each face has them open.
+
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{FaceRegion, PixelRect};
+
+// Anchors scoring lower are background.
+const SCORE_THRESHOLD: f32 = 0.7;
+// Boxes overlapping a stronger one by more than this are the same face.
+const OVERLAP_THRESHOLD: f32 = 0.3;
+// In smaller faces the eyes are too few pixels to judge.
+const MIN_EYE_FACE_SIZE: u32 = 48;
+
+// Where the eyes sit in a face box, as fractions of its size: the band from
+// just under the brows to the cheekbones, and each eye's span across it.
+const EYE_BAND: (f32, f32) = (0.28, 0.5);
+const EYE_SPANS: [(f32, f32); 2] = [(0.12, 0.45), (0.55, 0.88)];
+// Each eye is compared at this size, whatever the face's.
+const EYE_PATCH: (u32, u32) = (32, 16);
+// An open eye shows the upright sides of the iris as well as the lids; a
+// closed one is little but the horizontal lash line. Open eyes have at least
+// this much horizontal gradient for their vertical gradient.
+const OPEN_EYE_RATIO: f32 = 0.5;
+// Mean gradient, in levels, below which a patch is too flat to say, as with
+// sunglasses in shadow.
+const MIN_EYE_DETAIL: f32 = 4.0;
+
+const UNSUPPORTED: &str = "This build was made without face detection support";
+
+fn overlap(a: &PixelRect, b: &PixelRect) -> f32 {
+    let x0 = a.x.max(b.x);
+    let y0 = a.y.max(b.y);
+    let x1 = (a.x + a.width).min(b.x + b.width);
+    let y1 = (a.y + a.height).min(b.y + b.height);
+    if x1 <= x0 || y1 <= y0 {
+        return 0.0;
+    }
+    let shared = ((x1 - x0) * (y1 - y0)) as f32;
+    shared / ((a.width * a.height + b.width * b.height) as f32 - shared)
+}
+
+// Faces from per-anchor `scores` (background, face) and `boxes` (corners as
+// fractions of the frame), in the coordinates of a `width`x`height` image.
+// Of overlapping boxes only the most confident is kept.
+#[cfg_attr(not(feature = "face-detection"), allow(dead_code))]
+fn faces_from_anchors(
+    scores: &[f32],
+    boxes: &[f32],
+    width: u32,
+    height: u32,
+) -> Vec<(PixelRect, f32)> {
+    let mut candidates: Vec<(PixelRect, f32)> = scores
+        .chunks_exact(2)
+        .zip(boxes.chunks_exact(4))
+        .filter(|(score, _)| score[1] >= SCORE_THRESHOLD)
+        .filter_map(|(score, corners)| {
+            let x0 = (corners[0].clamp(0.0, 1.0) * width as f32) as u32;
+            let y0 = (corners[1].clamp(0.0, 1.0) * height as f32) as u32;
+            let x1 = (corners[2].clamp(0.0, 1.0) * width as f32).round() as u32;
+            let y1 = (corners[3].clamp(0.0, 1.0) * height as f32).round() as u32;
+            let rect = PixelRect {
+                x: x0,
+                y: y0,
+                width: x1.checked_sub(x0).filter(|&w| w > 0)?,
+                height: y1.checked_sub(y0).filter(|&h| h > 0)?,
+            };
+            Some((rect, score[1]))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut faces: Vec<(PixelRect, f32)> = Vec::new();
+    for (rect, score) in candidates {
+        if faces
+            .iter()
+            .all(|(kept, _)| overlap(kept, &rect) <= OVERLAP_THRESHOLD)
+        {
+            faces.push((rect, score));
+        }
+    }
+    faces
+}
+
+// Horizontal over vertical gradient of one eye, or None when it's too flat
+// to tell.
+fn eye_ratio(patch: &GrayImage) -> Option<f32> {
+    let (width, height) = patch.dimensions();
+    let p = |x: u32, y: u32| patch.get_pixel(x, y)[0] as f32;
+    let (mut across, mut down) = (0f32, 0f32);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            across += (p(x + 1, y) - p(x - 1, y)).abs();
+            down += (p(x, y + 1) - p(x, y - 1)).abs();
+        }
+    }
+    let samples = ((width - 2) * (height - 2)) as f32;
+    if (across + down) / samples < MIN_EYE_DETAIL {
+        return None;
+    }
+    Some(across / down.max(f32::EPSILON))
+}
+
+// The open-eye heuristic: the face counts as eyes open if either eye looks
+// open, since one eye is often hidden by hair or turned away. None when the
+// face is too small or its eyes too featureless to judge.
+pub fn eyes_open(img: &RgbaImage, face: PixelRect) -> Option<bool> {
+    if face.width.min(face.height) < MIN_EYE_FACE_SIZE {
+        return None;
+    }
+    let (width, height) = img.dimensions();
+    let y0 = face.y + (face.height as f32 * EYE_BAND.0) as u32;
+    let y1 = (face.y + (face.height as f32 * EYE_BAND.1) as u32).min(height);
+    let ratios: Vec<f32> = EYE_SPANS
+        .iter()
+        .filter_map(|&(from, to)| {
+            let x0 = face.x + (face.width as f32 * from) as u32;
+            let x1 = (face.x + (face.width as f32 * to) as u32).min(width);
+            if x1 <= x0 || y1 <= y0 {
+                return None;
+            }
+            let eye = imageops::crop_imm(img, x0, y0, x1 - x0, y1 - y0).to_image();
+            let gray = imageops::grayscale(&eye);
+            eye_ratio(&imageops::resize(
+                &gray,
+                EYE_PATCH.0,
+                EYE_PATCH.1,
+                FilterType::Triangle,
+            ))
+        })
+        .collect();
+    if ratios.is_empty() {
+        return None;
+    }
+    Some(ratios.iter().any(|&ratio| ratio >= OPEN_EYE_RATIO))
+}
+
+#[cfg(feature = "face-detection")]
+mod model {
+    use std::path::Path;
+
+    use image::imageops::{self, FilterType};
+    use image::RgbaImage;
+    use tract_onnx::prelude::*;
+
+    use super::faces_from_anchors;
+    use crate::analysis::onnx::cached;
+    use crate::error::AppError;
+    use crate::models::PixelRect;
+
+    // UltraFace's 320 model takes a fixed 320x240 frame, stretched to fit.
+    const INPUT_WIDTH: usize = 320;
+    const INPUT_HEIGHT: usize = 240;
+    const MEAN: f32 = 127.0;
+    const SCALE: f32 = 128.0;
+
+    pub fn detect(img: &RgbaImage, path: &Path) -> Result<Vec<(PixelRect, f32)>, AppError> {
+        let failed = |e: TractError| AppError::OperationFailed {
+            message: format!("Face detection failed: {}", e),
+        };
+        let resized = imageops::resize(
+            img,
+            INPUT_WIDTH as u32,
+            INPUT_HEIGHT as u32,
+            FilterType::Triangle,
+        );
+        let plan = cached(path)?
+            .as_ref()
+            .clone()
+            .with_input_fact(0, f32::fact([1, 3, INPUT_HEIGHT, INPUT_WIDTH]).into())
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(failed)?;
+
+        let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+            (1, 3, INPUT_HEIGHT, INPUT_WIDTH),
+            |(_, c, y, x)| (resized.get_pixel(x as u32, y as u32)[c] as f32 - MEAN) / SCALE,
+        )
+        .into();
+        let outputs = plan.run(tvec!(input.into())).map_err(failed)?;
+
+        // Scores are [1, anchors, 2] and boxes [1, anchors, 4]; exports
+        // differ in which comes first, so they're told apart by shape.
+        let mut scores = None;
+        let mut boxes = None;
+        for output in outputs.iter() {
+            let view = output.to_array_view::<f32>().map_err(failed)?;
+            match view.shape().last() {
+                Some(2) => scores = Some(view.iter().copied().collect::<Vec<f32>>()),
+                Some(4) => boxes = Some(view.iter().copied().collect::<Vec<f32>>()),
+                _ => {}
+            }
+        }
+        let (Some(scores), Some(boxes)) = (scores, boxes) else {
+            return Err(AppError::invalid(
+                "Model output is not face scores and boxes",
+            ));
+        };
+        Ok(faces_from_anchors(
+            &scores,
+            &boxes,
+            img.width(),
+            img.height(),
+        ))
+    }
+}
+
+// Whether this build can run face models at all.
+pub fn is_supported() -> bool {
+    cfg!(feature = "face-detection")
+}
+
+// Fails unless `model_path` exists and this build can run it, so a folder
+// tool can stop before decoding anything.
+pub fn check_model(model_path: &Path) -> Result<(), AppError> {
+    if !model_path.is_file() {
+        return Err(AppError::FileNotFound {
+            path: model_path.display().to_string(),
+        });
+    }
+    if !is_supported() {
+        return Err(UNSUPPORTED.into());
+    }
+    Ok(())
+}
+
+// Finds faces in `img` with the ONNX model at `model_path`, most confident
+// first, each with a guess at whether its eyes are open. The model takes a
+// 320x240 RGB tensor and outputs per-anchor scores and boxes, as UltraFace's
+// version-RFB-320 does.
+pub fn detect_faces(img: &RgbaImage, model_path: &Path) -> Result<Vec<FaceRegion>, AppError> {
+    check_model(model_path)?;
+    #[cfg(feature = "face-detection")]
+    {
+        Ok(model::detect(img, model_path)?
+            .into_iter()
+            .map(|(bounds, confidence)| FaceRegion {
+                bounds,
+                confidence,
+                eyes_open: eyes_open(img, bounds),
+            })
+            .collect())
+    }
+    #[cfg(not(feature = "face-detection"))]
+    {
+        let _ = img;
+        Err(UNSUPPORTED.into())
+    }
+}
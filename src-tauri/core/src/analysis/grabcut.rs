@@ -0,0 +1,598 @@
+use std::collections::VecDeque;
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbaImage};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{PixelRect, Scribble};
+use crate::processing::canvas::clip_rect;
+use crate::progress::Progress;
+
+// Segmentation runs on a copy no larger than this along its longer side and
+// the mask is scaled back up; the graph cut is what takes the time.
+const MAX_SIDE: u32 = 512;
+const COMPONENTS: usize = 5;
+const KMEANS_ROUNDS: usize = 10;
+// K-means runs on at most this many samples of each region.
+const KMEANS_SAMPLES: usize = 20_000;
+// Added to each covariance's diagonal so flat colours don't make it singular.
+const COVARIANCE_FLOOR: f64 = 4.0;
+// Smoothness weight from Rother et al.
+const GAMMA: f64 = 50.0;
+// Costs are fixed-point for the max-flow, in these steps.
+const COST_SCALE: f64 = 100.0;
+const MAX_COLOR_COST: f64 = 100.0;
+// Neighbours right, down and on both diagonals; each link goes both ways.
+const NEIGHBOURS: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (-1, 1)];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Label {
+    Background,
+    Foreground,
+    MaybeBackground,
+    MaybeForeground,
+}
+
+impl Label {
+    fn is_foreground(self) -> bool {
+        matches!(self, Label::Foreground | Label::MaybeForeground)
+    }
+
+    fn is_fixed(self) -> bool {
+        matches!(self, Label::Foreground | Label::Background)
+    }
+}
+
+type Rgb = [f64; 3];
+
+fn distance_sq(a: &Rgb, b: &Rgb) -> f64 {
+    (0..3).map(|c| (a[c] - b[c]).powi(2)).sum()
+}
+
+struct Component {
+    mean: Rgb,
+    inverse: [[f64; 3]; 3],
+    // log(weight) - log(sqrt((2 pi)^3 det)).
+    log_scale: f64,
+}
+
+impl Component {
+    fn log_density(&self, z: &Rgb) -> f64 {
+        let d = [
+            z[0] - self.mean[0],
+            z[1] - self.mean[1],
+            z[2] - self.mean[2],
+        ];
+        let mahalanobis: f64 = (0..3)
+            .map(|i| d[i] * (0..3).map(|j| self.inverse[i][j] * d[j]).sum::<f64>())
+            .sum();
+        self.log_scale - 0.5 * mahalanobis
+    }
+}
+
+struct Gmm {
+    components: Vec<Component>,
+}
+
+impl Gmm {
+    // Fits each cluster of `samples` as a Gaussian.
+    fn fit(samples: &[Rgb], assignment: &[usize]) -> Gmm {
+        let components = (0..COMPONENTS)
+            .filter_map(|k| {
+                let members: Vec<&Rgb> = samples
+                    .iter()
+                    .zip(assignment)
+                    .filter(|(_, &a)| a == k)
+                    .map(|(s, _)| s)
+                    .collect();
+                if members.is_empty() {
+                    return None;
+                }
+                let n = members.len() as f64;
+                let mut mean = [0.0; 3];
+                for m in &members {
+                    (0..3).for_each(|c| mean[c] += m[c] / n);
+                }
+                let mut cov = [[0.0; 3]; 3];
+                for m in &members {
+                    for i in 0..3 {
+                        for j in 0..3 {
+                            cov[i][j] += (m[i] - mean[i]) * (m[j] - mean[j]) / n;
+                        }
+                    }
+                }
+                (0..3).for_each(|i| cov[i][i] += COVARIANCE_FLOOR);
+                let (inverse, det) = invert(&cov);
+                let weight = n / samples.len() as f64;
+                Some(Component {
+                    mean,
+                    inverse,
+                    log_scale: weight.ln()
+                        - 0.5 * (3.0 * (2.0 * std::f64::consts::PI).ln() + det.ln()),
+                })
+            })
+            .collect();
+        Gmm { components }
+    }
+
+    // Clusters with k-means, seeded at evenly spaced brightness quantiles.
+    fn train(samples: &[Rgb]) -> Gmm {
+        let step = samples.len().div_ceil(KMEANS_SAMPLES).max(1);
+        let sampled: Vec<Rgb> = samples.iter().step_by(step).copied().collect();
+        let mut by_brightness = sampled.clone();
+        by_brightness.sort_by(|a, b| (a[0] + a[1] + a[2]).total_cmp(&(b[0] + b[1] + b[2])));
+        let mut centres: Vec<Rgb> = (0..COMPONENTS)
+            .map(|k| by_brightness[(2 * k + 1) * by_brightness.len() / (2 * COMPONENTS)])
+            .collect();
+        let mut assignment = vec![0; sampled.len()];
+        for _ in 0..KMEANS_ROUNDS {
+            for (a, s) in assignment.iter_mut().zip(&sampled) {
+                *a = (0..COMPONENTS)
+                    .min_by(|&i, &j| {
+                        distance_sq(s, &centres[i]).total_cmp(&distance_sq(s, &centres[j]))
+                    })
+                    .unwrap_or(0);
+            }
+            for (k, centre) in centres.iter_mut().enumerate() {
+                let (mut sum, mut n) = ([0.0; 3], 0.0);
+                for (s, _) in sampled.iter().zip(&assignment).filter(|(_, &a)| a == k) {
+                    (0..3).for_each(|c| sum[c] += s[c]);
+                    n += 1.0;
+                }
+                if n > 0.0 {
+                    *centre = sum.map(|v| v / n);
+                }
+            }
+        }
+        Gmm::fit(&sampled, &assignment)
+    }
+
+    // Refits with each sample moved to the component most likely to have
+    // produced it, as GrabCut does between cuts.
+    fn refine(&self, samples: &[Rgb]) -> Gmm {
+        let step = samples.len().div_ceil(KMEANS_SAMPLES).max(1);
+        let sampled: Vec<Rgb> = samples.iter().step_by(step).copied().collect();
+        let assignment: Vec<usize> = sampled
+            .iter()
+            .map(|s| {
+                (0..self.components.len())
+                    .max_by(|&i, &j| {
+                        self.components[i]
+                            .log_density(s)
+                            .total_cmp(&self.components[j].log_density(s))
+                    })
+                    .unwrap_or(0)
+            })
+            .collect();
+        Gmm::fit(&sampled, &assignment)
+    }
+
+    // -log p(z), capped so one odd colour can't outweigh everything else.
+    fn cost(&self, z: &Rgb) -> f64 {
+        let logs: Vec<f64> = self.components.iter().map(|c| c.log_density(z)).collect();
+        let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = logs.iter().map(|l| (l - max).exp()).sum();
+        (-(max + sum.ln())).clamp(0.0, MAX_COLOR_COST)
+    }
+}
+
+// Inverse and determinant of a symmetric positive definite 3x3 matrix.
+fn invert(m: &[[f64; 3]; 3]) -> ([[f64; 3]; 3], f64) {
+    let cofactor = |i: usize, j: usize| {
+        let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+        let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let det = (0..3).map(|j| m[0][j] * cofactor(0, j)).sum::<f64>();
+    let mut inverse = [[0.0; 3]; 3];
+    for (i, row) in inverse.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = cofactor(j, i) / det;
+        }
+    }
+    (inverse, det)
+}
+
+// A residual graph for Dinic's max-flow, edges stored by their tail.
+struct FlowGraph {
+    start: Vec<usize>,
+    to: Vec<u32>,
+    capacity: Vec<i64>,
+    reverse: Vec<u32>,
+}
+
+impl FlowGraph {
+    // `edges` are (u, v, capacity u to v, capacity v to u).
+    fn new(nodes: usize, edges: &[(u32, u32, i64, i64)]) -> Self {
+        let mut degree = vec![0usize; nodes + 1];
+        for &(u, v, _, _) in edges {
+            degree[u as usize + 1] += 1;
+            degree[v as usize + 1] += 1;
+        }
+        for i in 0..nodes {
+            degree[i + 1] += degree[i];
+        }
+        let start = degree.clone();
+        let mut next = degree;
+        let total = edges.len() * 2;
+        let (mut to, mut capacity, mut reverse) = (vec![0; total], vec![0; total], vec![0; total]);
+        for &(u, v, forward, backward) in edges {
+            let (a, b) = (next[u as usize], next[v as usize]);
+            next[u as usize] += 1;
+            next[v as usize] += 1;
+            (to[a], capacity[a], reverse[a]) = (v, forward, b as u32);
+            (to[b], capacity[b], reverse[b]) = (u, backward, a as u32);
+        }
+        Self {
+            start,
+            to,
+            capacity,
+            reverse,
+        }
+    }
+
+    fn levels(&self, source: usize) -> Vec<u32> {
+        let mut level = vec![u32::MAX; self.start.len() - 1];
+        level[source] = 0;
+        let mut queue = VecDeque::from([source]);
+        while let Some(u) = queue.pop_front() {
+            for e in self.start[u]..self.start[u + 1] {
+                let v = self.to[e] as usize;
+                if self.capacity[e] > 0 && level[v] == u32::MAX {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+        level
+    }
+
+    // Saturates every shortest augmenting path, walking the level graph
+    // without recursion since paths can be thousands of pixels long.
+    fn blocking_flow(&mut self, source: usize, sink: usize, level: &mut [u32]) {
+        let mut next = self.start[..self.start.len() - 1].to_vec();
+        let mut path: Vec<usize> = Vec::new();
+        let mut u = source;
+        loop {
+            if u == sink {
+                let flow = path.iter().map(|&e| self.capacity[e]).min().unwrap_or(0);
+                let mut retreat_to = None;
+                for (i, &e) in path.iter().enumerate() {
+                    self.capacity[e] -= flow;
+                    self.capacity[self.reverse[e] as usize] += flow;
+                    if self.capacity[e] == 0 && retreat_to.is_none() {
+                        retreat_to = Some(i);
+                    }
+                }
+                let i = retreat_to.unwrap_or(0);
+                u = self.to[self.reverse[path[i]] as usize] as usize;
+                path.truncate(i);
+                continue;
+            }
+            let mut advanced = false;
+            while next[u] < self.start[u + 1] {
+                let e = next[u];
+                let v = self.to[e] as usize;
+                if self.capacity[e] > 0 && level[v] == level[u] + 1 {
+                    path.push(e);
+                    u = v;
+                    advanced = true;
+                    break;
+                }
+                next[u] += 1;
+            }
+            if !advanced {
+                if u == source {
+                    return;
+                }
+                // A dead end: nothing through here reaches the sink.
+                level[u] = u32::MAX;
+                let e = path.pop().expect("only the source has an empty path");
+                u = self.to[self.reverse[e] as usize] as usize;
+                next[u] += 1;
+            }
+        }
+    }
+
+    // Runs max-flow and returns which nodes are still reachable from the
+    // source: its side of the minimum cut.
+    fn min_cut(&mut self, source: usize, sink: usize) -> Vec<bool> {
+        loop {
+            let mut level = self.levels(source);
+            if level[sink] == u32::MAX {
+                return level.iter().map(|&l| l != u32::MAX).collect();
+            }
+            self.blocking_flow(source, sink, &mut level);
+        }
+    }
+}
+
+// Marks every pixel within the stroke's radius, in coordinates divided by
+// `scale`.
+fn paint(
+    labels: &mut [Label],
+    width: u32,
+    height: u32,
+    scribble: &Scribble,
+    scale: f32,
+    label: Label,
+) {
+    let radius = (scribble.radius / scale).max(0.5);
+    let mut dab = |cx: f32, cy: f32| {
+        let x0 = ((cx - radius).floor() as i64).max(0);
+        let x1 = ((cx + radius).ceil() as i64).min(width as i64 - 1);
+        let y0 = ((cy - radius).floor() as i64).max(0);
+        let y1 = ((cy + radius).ceil() as i64).min(height as i64 - 1);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    labels[(y * width as i64 + x) as usize] = label;
+                }
+            }
+        }
+    };
+    let points: Vec<(f32, f32)> = scribble
+        .points
+        .iter()
+        .map(|p| (p.x / scale, p.y / scale))
+        .collect();
+    if let [(x, y)] = points[..] {
+        dab(x, y);
+    }
+    for pair in points.windows(2) {
+        let ((ax, ay), (bx, by)) = (pair[0], pair[1]);
+        let steps = ((bx - ax).hypot(by - ay).ceil() as usize).max(1);
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            dab(ax + (bx - ax) * t, ay + (by - ay) * t);
+        }
+    }
+}
+
+// GrabCut (Rother, Kolmogorov and Blake, 2004): separates the subject from
+// its background by alternating between modelling each one's colours and
+// finding the cheapest boundary between them with a graph cut. Pixels
+// outside `rect` and under `background` strokes are fixed as background,
+// those under `foreground` strokes as subject; a refinement is a rerun
+// with more strokes. Returns a mask, white for the subject; on large images
+// its edges are soft from being scaled up.
+pub fn grabcut(
+    img: &RgbaImage,
+    rect: Option<PixelRect>,
+    foreground: &[Scribble],
+    background: &[Scribble],
+    iterations: u32,
+    progress: &dyn Progress,
+) -> Result<GrayImage, AppError> {
+    let (full_width, full_height) = img.dimensions();
+    if full_width < 2 || full_height < 2 {
+        return Err(AppError::invalid("Image is too small to segment"));
+    }
+    if !(1..=20).contains(&iterations) {
+        return Err(AppError::invalid(
+            "GrabCut iterations must be between 1 and 20",
+        ));
+    }
+    let scale = (full_width.max(full_height) as f32 / MAX_SIDE as f32).max(1.0);
+    let small = if scale > 1.0 {
+        imageops::resize(
+            img,
+            ((full_width as f32 / scale).round() as u32).max(2),
+            ((full_height as f32 / scale).round() as u32).max(2),
+            FilterType::Triangle,
+        )
+    } else {
+        img.clone()
+    };
+    let (width, height) = small.dimensions();
+    let n = (width * height) as usize;
+
+    let mut labels = match rect {
+        Some(rect) => {
+            let rect = clip_rect(
+                rect.x as i64,
+                rect.y as i64,
+                rect.width,
+                rect.height,
+                full_width,
+                full_height,
+            )
+            .ok_or_else(|| AppError::invalid("The rectangle is outside the image"))?;
+            let (x0, y0) = (rect.x as f32 / scale, rect.y as f32 / scale);
+            let (x1, y1) = (
+                (rect.x + rect.width) as f32 / scale,
+                (rect.y + rect.height) as f32 / scale,
+            );
+            (0..n)
+                .map(|i| {
+                    let (x, y) = (
+                        (i as u32 % width) as f32 + 0.5,
+                        (i as u32 / width) as f32 + 0.5,
+                    );
+                    if x >= x0 && x < x1 && y >= y0 && y < y1 {
+                        Label::MaybeForeground
+                    } else {
+                        Label::Background
+                    }
+                })
+                .collect()
+        }
+        None => vec![Label::MaybeForeground; n],
+    };
+    for scribble in background {
+        paint(
+            &mut labels,
+            width,
+            height,
+            scribble,
+            scale,
+            Label::Background,
+        );
+    }
+    for scribble in foreground {
+        paint(
+            &mut labels,
+            width,
+            height,
+            scribble,
+            scale,
+            Label::Foreground,
+        );
+    }
+    if !labels.contains(&Label::Background) {
+        return Err(AppError::invalid(
+            "GrabCut needs some background: a rectangle smaller than the image, or background strokes",
+        ));
+    }
+    if !labels.iter().any(|l| l.is_foreground()) {
+        return Err(AppError::invalid("Nothing is left to be the subject"));
+    }
+
+    let colors: Vec<Rgb> = small
+        .pixels()
+        .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+        .collect();
+    let index = |x: i64, y: i64| (y * width as i64 + x) as usize;
+    let inside = |x: i64, y: i64| x >= 0 && y >= 0 && x < width as i64 && y < height as i64;
+
+    // Smoothness: cutting between similar neighbours costs more. Beta
+    // adapts the falloff to the image's typical contrast.
+    let mut neighbours = Vec::new();
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            for (dx, dy) in NEIGHBOURS {
+                if inside(x + dx, y + dy) {
+                    neighbours.push((
+                        index(x, y),
+                        index(x + dx, y + dy),
+                        ((dx * dx + dy * dy) as f64).sqrt(),
+                    ));
+                }
+            }
+        }
+    }
+    let mean_sq = neighbours
+        .iter()
+        .map(|&(a, b, _)| distance_sq(&colors[a], &colors[b]))
+        .sum::<f64>()
+        / neighbours.len().max(1) as f64;
+    let beta = if mean_sq > 0.0 {
+        1.0 / (2.0 * mean_sq)
+    } else {
+        0.0
+    };
+    let smoothness: Vec<(u32, u32, i64, i64)> = neighbours
+        .iter()
+        .map(|&(a, b, distance)| {
+            let w = GAMMA / distance * (-beta * distance_sq(&colors[a], &colors[b])).exp();
+            let w = (w * COST_SCALE).round() as i64;
+            (a as u32, b as u32, w, w)
+        })
+        .collect();
+    // Cheaper to cut every link around a pixel than to override its fixed
+    // label.
+    let fixed = (GAMMA * 8.0 + MAX_COLOR_COST) as i64 * COST_SCALE as i64 * 2;
+
+    let samples = |labels: &[Label], foreground: bool| -> Vec<Rgb> {
+        colors
+            .iter()
+            .zip(labels)
+            .filter(|(_, l)| l.is_foreground() == foreground)
+            .map(|(c, _)| *c)
+            .collect()
+    };
+    let mut models: Option<(Gmm, Gmm)> = None;
+    let (source, sink) = (n, n + 1);
+    for round in 0..iterations {
+        progress.check()?;
+        let (fg_samples, bg_samples) = (samples(&labels, true), samples(&labels, false));
+        if fg_samples.is_empty() || bg_samples.is_empty() {
+            break;
+        }
+        models = Some(match &models {
+            None => (Gmm::train(&fg_samples), Gmm::train(&bg_samples)),
+            Some((fg, bg)) => (fg.refine(&fg_samples), bg.refine(&bg_samples)),
+        });
+        let (fg, bg) = models.as_ref().expect("just set");
+
+        // Source is the subject. Only the difference of a pixel's two costs
+        // matters to the cut, so each gets one link.
+        let data: Vec<(u32, u32, i64, i64)> = labels
+            .par_iter()
+            .zip(&colors)
+            .enumerate()
+            .filter_map(|(i, (label, z))| {
+                let (to_fg, to_bg) = match label {
+                    Label::Foreground => (fixed, 0),
+                    Label::Background => (0, fixed),
+                    _ => (
+                        (bg.cost(z) * COST_SCALE).round() as i64,
+                        (fg.cost(z) * COST_SCALE).round() as i64,
+                    ),
+                };
+                match to_fg.cmp(&to_bg) {
+                    std::cmp::Ordering::Greater => {
+                        Some((source as u32, i as u32, to_fg - to_bg, 0))
+                    }
+                    std::cmp::Ordering::Less => Some((i as u32, sink as u32, to_bg - to_fg, 0)),
+                    std::cmp::Ordering::Equal => None,
+                }
+            })
+            .collect();
+        let mut graph = FlowGraph::new(n + 2, &[smoothness.as_slice(), data.as_slice()].concat());
+        let subject = graph.min_cut(source, sink);
+        for (label, &is_subject) in labels.iter_mut().zip(&subject) {
+            if !label.is_fixed() {
+                *label = if is_subject {
+                    Label::MaybeForeground
+                } else {
+                    Label::MaybeBackground
+                };
+            }
+        }
+        progress.report((round + 1) as f32 / iterations as f32);
+    }
+
+    let mask = GrayImage::from_fn(width, height, |x, y| {
+        Luma([if labels[(y * width + x) as usize].is_foreground() {
+            255
+        } else {
+            0
+        }])
+    });
+    Ok(if scale > 1.0 {
+        imageops::resize(&mask, full_width, full_height, FilterType::Triangle)
+    } else {
+        mask
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_cut_separates_at_the_bottleneck() {
+        // 0 -> {1, 2} -> 3, where 1 -> 3 is the only narrow link.
+        let mut graph = FlowGraph::new(
+            4,
+            &[(0, 1, 10, 0), (0, 2, 10, 0), (1, 3, 1, 0), (2, 3, 10, 0)],
+        );
+        assert_eq!(graph.min_cut(0, 3), vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn min_cut_follows_capacity_both_ways() {
+        // The link from 1 to 2 is given as the reverse capacity of 2 -> 1.
+        let mut graph = FlowGraph::new(3, &[(0, 1, 5, 0), (2, 1, 7, 2)]);
+        assert_eq!(graph.min_cut(0, 2), vec![true, true, false]);
+    }
+
+    #[test]
+    fn disconnected_sink_leaves_only_the_source_side() {
+        let mut graph = FlowGraph::new(4, &[(0, 1, 3, 0), (2, 3, 3, 0)]);
+        assert_eq!(graph.min_cut(0, 3), vec![true, true, false, false]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use rayon::prelude::*;
+
+use crate::decode::decode_file;
+use crate::error::AppError;
+use crate::library::list_images;
+use crate::models::{
+    NearDuplicateGroup, NearDuplicateReport, PerceptualHashAlgorithm, SkippedFile,
+};
+
+// Difference hashes compare a 9x8 greyscale thumbnail's horizontal
+// gradients, which survive re-encoding and small exposure changes.
+const DHASH_WIDTH: u32 = 9;
+const DHASH_HEIGHT: u32 = 8;
+const AHASH_SIZE: u32 = 8;
+// pHash keeps the lowest 8x8 frequencies of a 32x32 thumbnail's DCT.
+const PHASH_SIZE: usize = 32;
+const PHASH_FREQUENCIES: usize = 8;
+
+fn thumbnail(img: &DynamicImage, width: u32, height: u32) -> GrayImage {
+    img.resize_exact(width, height, FilterType::Triangle)
+        .to_luma8()
+}
+
+fn from_bits(bits: impl Iterator<Item = bool>) -> u64 {
+    bits.fold(0u64, |hash, bit| hash << 1 | bit as u64)
+}
+
+pub fn average_hash(img: &DynamicImage) -> u64 {
+    let small = thumbnail(img, AHASH_SIZE, AHASH_SIZE);
+    let mean = small.iter().map(|&v| v as u32).sum::<u32>() as f32 / small.len() as f32;
+    from_bits(small.iter().map(|&v| v as f32 > mean))
+}
+
+pub fn difference_hash(img: &DynamicImage) -> u64 {
+    let small = thumbnail(img, DHASH_WIDTH, DHASH_HEIGHT);
+    from_bits((0..DHASH_HEIGHT).flat_map(|y| {
+        let small = &small;
+        (0..DHASH_WIDTH - 1).map(move |x| small.get_pixel(x + 1, y)[0] > small.get_pixel(x, y)[0])
+    }))
+}
+
+// Each bit is whether a low-frequency DCT coefficient is above their median.
+// The DC term is left out of the median, since it's only the mean
+// brightness and would skew it.
+pub fn perceptual_hash(img: &DynamicImage) -> u64 {
+    let n = PHASH_SIZE;
+    let small = thumbnail(img, n as u32, n as u32);
+    let basis: Vec<f32> = (0..PHASH_FREQUENCIES)
+        .flat_map(|u| {
+            let scale = if u == 0 {
+                (1.0 / n as f32).sqrt()
+            } else {
+                (2.0 / n as f32).sqrt()
+            };
+            (0..n).map(move |x| scale * ((2 * x + 1) as f32 * u as f32 * PI / (2 * n) as f32).cos())
+        })
+        .collect();
+    let basis = |u: usize, x: usize| basis[u * n + x];
+
+    // Rows first, then columns, for just the frequencies kept.
+    let rows: Vec<f32> = (0..n)
+        .flat_map(|y| {
+            let small = &small;
+            (0..PHASH_FREQUENCIES).map(move |u| {
+                (0..n)
+                    .map(|x| small.get_pixel(x as u32, y as u32)[0] as f32 * basis(u, x))
+                    .sum()
+            })
+        })
+        .collect();
+    let coefficients: Vec<f32> = (0..PHASH_FREQUENCIES)
+        .flat_map(|v| {
+            let rows = &rows;
+            (0..PHASH_FREQUENCIES).map(move |u| {
+                (0..n)
+                    .map(|y| rows[y * PHASH_FREQUENCIES + u] * basis(v, y))
+                    .sum()
+            })
+        })
+        .collect();
+
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    from_bits(coefficients.iter().map(|&c| c > median))
+}
+
+pub fn image_hash(img: &DynamicImage, algorithm: PerceptualHashAlgorithm) -> u64 {
+    match algorithm {
+        PerceptualHashAlgorithm::Ahash => average_hash(img),
+        PerceptualHashAlgorithm::Dhash => difference_hash(img),
+        PerceptualHashAlgorithm::Phash => perceptual_hash(img),
+    }
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// Folders are expanded to the images directly inside them.
+fn expand(paths: &[String]) -> Result<Vec<PathBuf>, AppError> {
+    let mut files = Vec::new();
+    for path in paths {
+        if Path::new(path).is_dir() {
+            files.extend(list_images(path)?);
+        } else {
+            files.push(PathBuf::from(path));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+// Hashes every image in `paths`, files or folders, and groups those whose
+// hashes differ by at most `threshold` bits, directly or through others in
+// the group. Around 10 suits pHash for copies that were re-encoded or
+// resized; larger thresholds also group looser variations. Largest groups first.
+pub fn find_duplicates(
+    paths: &[String],
+    threshold: u32,
+    algorithm: PerceptualHashAlgorithm,
+) -> Result<NearDuplicateReport, AppError> {
+    if threshold > 64 {
+        return Err(AppError::invalid("The threshold can be at most 64 bits"));
+    }
+    let results: Vec<(PathBuf, Result<u64, AppError>)> = expand(paths)?
+        .into_par_iter()
+        .map(|path| {
+            let hash = decode_file(&path, false)
+                .map(|decoded| image_hash(&DynamicImage::ImageRgba8(decoded.image), algorithm));
+            (path, hash)
+        })
+        .collect();
+
+    let mut hashed = Vec::new();
+    let mut skipped = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(hash) => hashed.push((path.to_string_lossy().into_owned(), hash)),
+            Err(e) => skipped.push(SkippedFile {
+                path: path.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    let mut group: Vec<usize> = (0..hashed.len()).collect();
+    fn root(group: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for a in 0..hashed.len() {
+        for b in a + 1..hashed.len() {
+            if hamming_distance(hashed[a].1, hashed[b].1) <= threshold {
+                let (ra, rb) = (root(&mut group, a), root(&mut group, b));
+                group[ra] = rb;
+            }
+        }
+    }
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); hashed.len()];
+    for i in 0..hashed.len() {
+        members[root(&mut group, i)].push(i);
+    }
+
+    let mut groups: Vec<NearDuplicateGroup> = members
+        .into_iter()
+        .filter(|m| m.len() > 1)
+        .map(|m| {
+            let max_distance = m
+                .iter()
+                .flat_map(|&a| m.iter().map(move |&b| (a, b)))
+                .map(|(a, b)| hamming_distance(hashed[a].1, hashed[b].1))
+                .max()
+                .unwrap_or(0);
+            NearDuplicateGroup {
+                paths: m.iter().map(|&i| hashed[i].0.clone()).collect(),
+                max_distance,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.paths
+            .len()
+            .cmp(&a.paths.len())
+            .then(a.paths.cmp(&b.paths))
+    });
+
+    Ok(NearDuplicateReport {
+        algorithm,
+        groups,
+        skipped,
+    })
+}
@@ -0,0 +1,163 @@
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{HorizonAnalysis, LineEstimate};
+
+// Edges are found on a copy no larger than this.
+const MAX_DIMENSION: u32 = 800;
+// Only lines within this many degrees of level or plumb are considered.
+const MAX_TILT: f32 = 20.0;
+const STEP_DEGREES: f32 = 0.1;
+// Edges weaker than this share of the strongest are texture, not lines.
+const MIN_MAGNITUDE: f32 = 0.2;
+const BLUR_SIGMA: f32 = 1.5;
+// Edge points voting for a direction must have a gradient within this many
+// degrees of its normal.
+const GATE_DEGREES: f32 = 35.0;
+const MIN_EDGE_POINTS: usize = 50;
+
+#[derive(Clone, Copy)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+// Hough-style search over tilts: for each candidate the edge points are
+// projected onto the line normal, and the sum of squared bin counts rewards
+// tilts along which many points line up. Unlike per-pixel gradient angles this
+// isn't fooled by the stair-steps of shallow lines.
+fn estimate(points: &[(f32, f32)], axis: Axis, extent: usize) -> Option<LineEstimate> {
+    if points.len() < MIN_EDGE_POINTS {
+        return None;
+    }
+    let steps = (2.0 * MAX_TILT / STEP_DEGREES).round() as usize + 1;
+    let tilt_of = |step: usize| step as f32 * STEP_DEGREES - MAX_TILT;
+
+    let energies: Vec<f64> = (0..steps)
+        .into_par_iter()
+        .map(|step| {
+            let (sin, cos) = tilt_of(step).to_radians().sin_cos();
+            let mut bins = vec![0u32; 2 * extent + 1];
+            for &(x, y) in points {
+                let rho = match axis {
+                    Axis::Horizontal => x * sin + y * cos,
+                    Axis::Vertical => x * cos - y * sin,
+                };
+                bins[(rho.round() as isize + extent as isize) as usize] += 1;
+            }
+            bins.iter().map(|&n| (n as f64).powi(2)).sum()
+        })
+        .collect();
+
+    let best = (0..steps)
+        .max_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+        .expect("at least one tilt");
+    // Parabolic interpolation between neighbouring steps.
+    let offset = if best > 0 && best + 1 < steps {
+        let (l, c, r) = (energies[best - 1], energies[best], energies[best + 1]);
+        let denom = l - 2.0 * c + r;
+        if denom < 0.0 {
+            (0.5 * (l - r) / denom) as f32
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+
+    let mut sorted = energies.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[steps / 2];
+    Some(LineEstimate {
+        angle: tilt_of(best) + offset * STEP_DEGREES,
+        confidence: (1.0 - median / energies[best]).clamp(0.0, 1.0) as f32,
+    })
+}
+
+// Sobel gradients (gx, gy) for the interior pixels of `gray`.
+fn sobel(gray: &GrayImage) -> Vec<(f32, f32)> {
+    let (width, height) = gray.dimensions();
+    let p = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f32;
+    let mut gradients = Vec::with_capacity(((width - 2) * (height - 2)) as usize);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let gx = p(x + 1, y - 1) + 2.0 * p(x + 1, y) + p(x + 1, y + 1)
+                - p(x - 1, y - 1)
+                - 2.0 * p(x - 1, y)
+                - p(x - 1, y + 1);
+            let gy = p(x - 1, y + 1) + 2.0 * p(x, y + 1) + p(x + 1, y + 1)
+                - p(x - 1, y - 1)
+                - 2.0 * p(x, y - 1)
+                - p(x + 1, y - 1);
+            gradients.push((gx, gy));
+        }
+    }
+    gradients
+}
+
+// Estimates how far the dominant near-horizontal and near-vertical lines are
+// tilted from level and plumb.
+pub fn detect_horizon(img: &RgbaImage) -> Result<HorizonAnalysis, AppError> {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return Err(AppError::invalid("Image is too small to find lines in"));
+    }
+    let scale = (width.max(height) as f32 / MAX_DIMENSION as f32).max(1.0);
+    let gray = imageops::grayscale(img);
+    // The short side of a very elongated image would round to nothing, so it
+    // is kept at the 3 pixels the gradients need.
+    let gray = if scale > 1.0 {
+        imageops::resize(
+            &gray,
+            ((width as f32 / scale).round() as u32).max(3),
+            ((height as f32 / scale).round() as u32).max(3),
+            FilterType::Triangle,
+        )
+    } else {
+        gray
+    };
+    // Smoothing keeps noise and fine texture from counting as edges.
+    let gray = imageops::blur(&gray, BLUR_SIGMA);
+
+    let gradients = sobel(&gray);
+    let magnitudes: Vec<f32> = gradients.iter().map(|(gx, gy)| gx.hypot(*gy)).collect();
+    let threshold = magnitudes.iter().copied().fold(0.0, f32::max) * MIN_MAGNITUDE;
+    let gate = GATE_DEGREES.to_radians().tan();
+
+    // Split strong edge points by which axis their line runs along. Gradients
+    // come from interior pixels, so positions are offset by one.
+    let inner_width = gray.width() - 2;
+    let mut horizontal = Vec::new();
+    let mut vertical = Vec::new();
+    for (i, (&(gx, gy), &magnitude)) in gradients.iter().zip(&magnitudes).enumerate() {
+        if magnitude == 0.0 || magnitude < threshold {
+            continue;
+        }
+        let point = (
+            (i as u32 % inner_width + 1) as f32,
+            (i as u32 / inner_width + 1) as f32,
+        );
+        if gx.abs() <= gy.abs() * gate {
+            horizontal.push(point);
+        } else if gy.abs() <= gx.abs() * gate {
+            vertical.push(point);
+        }
+    }
+
+    let extent = (gray.width() + gray.height()) as usize;
+    let horizon = estimate(&horizontal, Axis::Horizontal, extent);
+    let vertical = estimate(&vertical, Axis::Vertical, extent);
+    let best = [horizon, vertical]
+        .into_iter()
+        .flatten()
+        .max_by(|a, b| a.confidence.total_cmp(&b.confidence));
+
+    Ok(HorizonAnalysis {
+        horizon,
+        vertical,
+        rotation: best.map(|line| -line.angle).unwrap_or(0.0),
+        confidence: best.map(|line| line.confidence).unwrap_or(0.0),
+    })
+}
@@ -0,0 +1,109 @@
+use crate::error::AppError;
+use crate::models::{Edit, Measurement, Point, SpatialScale};
+
+const PIXEL_UNIT: &str = "px";
+
+// A scale from a reference of known size, e.g. a ruler spanning
+// `pixel_distance` pixels that is `real_distance` units long.
+pub fn calibrate(
+    pixel_distance: f64,
+    real_distance: f64,
+    unit: &str,
+) -> Result<SpatialScale, AppError> {
+    if !(pixel_distance > 0.0 && pixel_distance.is_finite()) {
+        return Err(AppError::invalid("Pixel distance must be positive"));
+    }
+    if !(real_distance > 0.0 && real_distance.is_finite()) {
+        return Err(AppError::invalid("Real distance must be positive"));
+    }
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return Err(AppError::invalid("A unit is required"));
+    }
+    Ok(SpatialScale {
+        units_per_pixel: real_distance / pixel_distance,
+        unit: unit.to_string(),
+    })
+}
+
+// How much `edit`, which took the image from `before` to `after` pixels,
+// multiplied the real size of a pixel. Only resampling changes it; crops,
+// rotations and canvas changes keep the pixel spacing.
+pub fn pixel_size_change(edit: &Edit, before: (u32, u32), after: (u32, u32)) -> f64 {
+    match edit {
+        // Stretching changes the two axes differently; the geometric mean
+        // keeps areas right.
+        Edit::Resize { .. } | Edit::ScalePixelArt { .. } => {
+            (before.0 as f64 / after.0 as f64 * before.1 as f64 / after.1 as f64).sqrt()
+        }
+        _ => 1.0,
+    }
+}
+
+// Image y grows downwards, so it is flipped to keep angles counter-clockwise
+// as seen on screen.
+fn direction(a: Point, b: Point) -> f64 {
+    (-(b.y - a.y) as f64).atan2((b.x - a.x) as f64).to_degrees()
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((b.x - a.x) as f64).hypot((b.y - a.y) as f64)
+}
+
+// Measures the path through `points`: each segment's length and direction,
+// the angle at every interior point, and for three or more points the area
+// and perimeter of the polygon they close.
+pub fn measure(points: &[Point], scale: Option<&SpatialScale>) -> Result<Measurement, AppError> {
+    if points.len() < 2 {
+        return Err(AppError::invalid("At least two points are needed"));
+    }
+    let k = scale.map_or(1.0, |s| s.units_per_pixel);
+
+    let segment_lengths: Vec<f64> = points
+        .windows(2)
+        .map(|pair| distance(pair[0], pair[1]) * k)
+        .collect();
+    let segment_angles = points
+        .windows(2)
+        .map(|pair| direction(pair[0], pair[1]))
+        .collect();
+    let vertex_angles = points
+        .windows(3)
+        .map(|tri| {
+            let turn = (direction(tri[1], tri[0]) - direction(tri[1], tri[2])).abs();
+            if turn > 180.0 {
+                360.0 - turn
+            } else {
+                turn
+            }
+        })
+        .collect();
+
+    let (area, perimeter) = if points.len() >= 3 {
+        let n = points.len();
+        let twice_area: f64 = (0..n)
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % n]);
+                a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64
+            })
+            .sum();
+        let closing = distance(points[n - 1], points[0]) * k;
+        (
+            Some(twice_area.abs() / 2.0 * k * k),
+            Some(segment_lengths.iter().sum::<f64>() + closing),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(Measurement {
+        unit: scale.map_or(PIXEL_UNIT.to_string(), |s| s.unit.clone()),
+        calibrated: scale.is_some(),
+        total_length: segment_lengths.iter().sum(),
+        segment_lengths,
+        segment_angles,
+        vertex_angles,
+        area,
+        perimeter,
+    })
+}
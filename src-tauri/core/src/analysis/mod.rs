@@ -0,0 +1,235 @@
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{AnalysisResult, HistogramData, SourceColorType};
+use crate::progress::Progress;
+
+pub mod balance;
+pub mod blobs;
+pub mod composition;
+pub mod contrast;
+pub mod diff;
+pub mod edges;
+pub mod faces;
+pub mod grabcut;
+pub mod hashing;
+pub mod horizon;
+pub mod measure;
+pub mod noise;
+#[cfg(any(feature = "text-detection", feature = "face-detection"))]
+mod onnx;
+pub mod palette;
+pub mod profile;
+pub mod saliency;
+pub mod sampling;
+pub mod selection;
+pub mod sharpness;
+pub mod similarity;
+pub mod stars;
+pub mod text_regions;
+
+// HSL saturation and lightness of an 8-bit colour, both scaled to 0..=255.
+fn hsl_saturation_lightness(r: usize, g: usize, b: usize) -> (usize, usize) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let sum = max + min;
+    let lightness = sum / 2;
+
+    // Chroma relative to the largest chroma possible at this lightness.
+    let headroom = 255 - sum.abs_diff(255);
+    let saturation = ((max - min) * 255 + headroom / 2)
+        .checked_div(headroom)
+        .unwrap_or(0);
+    (saturation.min(255), lightness)
+}
+
+// Perceptual brightness of an 8-bit colour, 0..=255, as binned in the
+// luminosity histogram.
+pub fn luminosity(r: u8, g: u8, b: u8) -> usize {
+    (((0.299 * r as f32) + (0.587 * g as f32) + (0.114 * b as f32)) as usize).min(255)
+}
+
+fn empty_histogram() -> HistogramData {
+    HistogramData {
+        red: vec![0; 256],
+        green: vec![0; 256],
+        blue: vec![0; 256],
+        luminosity: vec![0; 256],
+        saturation: vec![0; 256],
+        lightness: vec![0; 256],
+    }
+}
+
+fn add_histogram(mut total: HistogramData, band: HistogramData) -> HistogramData {
+    let channels = [
+        (&mut total.red, &band.red),
+        (&mut total.green, &band.green),
+        (&mut total.blue, &band.blue),
+        (&mut total.luminosity, &band.luminosity),
+        (&mut total.saturation, &band.saturation),
+        (&mut total.lightness, &band.lightness),
+    ];
+    for (total, band) in channels {
+        for (t, b) in total.iter_mut().zip(band) {
+            *t += b;
+        }
+    }
+    total
+}
+
+// Per-channel, luminosity, saturation and lightness histograms of the visible
+// pixels of an RGBA buffer. Each thread counts its own chunks and the counts
+// are summed at the end.
+pub fn histogram(data: &[u8]) -> HistogramData {
+    data.par_chunks(PARALLEL_CHUNK * 4)
+        .fold(empty_histogram, |mut histogram, chunk| {
+            for px in chunk.chunks_exact(4) {
+                if px[3] == 0 {
+                    continue;
+                }
+                let (r, g, b) = (px[0] as usize, px[1] as usize, px[2] as usize);
+                histogram.red[r] += 1;
+                histogram.green[g] += 1;
+                histogram.blue[b] += 1;
+                histogram.luminosity[luminosity(px[0], px[1], px[2])] += 1;
+
+                let (saturation, lightness) = hsl_saturation_lightness(r, g, b);
+                histogram.saturation[saturation] += 1;
+                histogram.lightness[lightness] += 1;
+            }
+            histogram
+        })
+        .reduce(empty_histogram, add_histogram)
+}
+
+// Pixels per chunk handed to a thread. Large enough that splitting costs
+// nothing next to the work.
+pub(crate) const PARALLEL_CHUNK: usize = 1 << 16;
+
+// Bands the histogram is built in between progress reports; the histogram
+// is most of the work, palette sampling being capped.
+const HISTOGRAM_BANDS: usize = 32;
+const HISTOGRAM_SHARE: f32 = 0.8;
+
+// Histogram, brightness, contrast and a `palette_size`-colour palette over the
+// visible pixels of an RGBA buffer, plus colour-management warnings for the
+// source it came from.
+pub fn analyze(
+    data: &[u8],
+    source: Option<&SourceColorType>,
+    palette_size: usize,
+) -> AnalysisResult {
+    analyze_tracked(data, source, palette_size, &()).expect("untracked analysis isn't cancelled")
+}
+
+// `analyze`, reporting progress and stopping if cancelled.
+pub fn analyze_tracked(
+    data: &[u8],
+    source: Option<&SourceColorType>,
+    palette_size: usize,
+    progress: &dyn Progress,
+) -> Result<AnalysisResult, AppError> {
+    let band = (data.len() / 4).div_ceil(HISTOGRAM_BANDS).max(1) * 4;
+    let bands = data.len().div_ceil(band).max(1);
+    let mut histogram = empty_histogram();
+    for (i, chunk) in data.chunks(band).enumerate() {
+        progress.check()?;
+        histogram = add_histogram(histogram, self::histogram(chunk));
+        progress.report((i + 1) as f32 / bands as f32 * HISTOGRAM_SHARE);
+    }
+
+    // Calculate average brightness over the visible pixels only, so transparent
+    // padding in gray+alpha or palette images with tRNS doesn't darken the result
+    let total_pixels = histogram.luminosity.iter().sum::<u32>().max(1) as f32;
+    let avg_brightness = histogram
+        .luminosity
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| (i as f32) * (count as f32))
+        .sum::<f32>()
+        / total_pixels
+        / 255.0;
+
+    // Calculate contrast (using standard deviation)
+    let mean = avg_brightness * 255.0;
+    let variance = histogram
+        .luminosity
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let diff = (i as f32) - mean;
+            (diff * diff) * (count as f32)
+        })
+        .sum::<f32>()
+        / total_pixels;
+    let contrast = variance.sqrt() / 255.0;
+
+    progress.check()?;
+    let dominant_colors = palette::dominant_colors(data, palette_size);
+    progress.report((1.0 + HISTOGRAM_SHARE) / 2.0);
+    progress.check()?;
+    let warnings = profile::color_warnings(data, source);
+    progress.report(1.0);
+
+    Ok(AnalysisResult {
+        histogram,
+        dominant_colors,
+        average_brightness: avg_brightness,
+        contrast,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counted(channel: &[u32]) -> u32 {
+        channel.iter().sum()
+    }
+
+    #[test]
+    fn histogram_counts_every_visible_pixel_once() {
+        // Enough pixels to be split across several chunks and summed.
+        let pixels = PARALLEL_CHUNK * 3 + 17;
+        let data: Vec<u8> = (0..pixels)
+            .flat_map(|i| {
+                let alpha = if i % 5 == 0 { 0 } else { 255 };
+                [
+                    (i % 256) as u8,
+                    (i / 7 % 256) as u8,
+                    (i / 13 % 256) as u8,
+                    alpha,
+                ]
+            })
+            .collect();
+        let visible = (0..pixels).filter(|i| i % 5 != 0).count() as u32;
+        let h = histogram(&data);
+        for channel in [
+            &h.red,
+            &h.green,
+            &h.blue,
+            &h.luminosity,
+            &h.saturation,
+            &h.lightness,
+        ] {
+            assert_eq!(counted(channel), visible);
+        }
+    }
+
+    #[test]
+    fn histogram_bins_by_value() {
+        let data = [10, 20, 30, 255, 10, 200, 30, 255, 99, 99, 99, 0];
+        let h = histogram(&data);
+        assert_eq!(h.red[10], 2);
+        assert_eq!(h.green[20], 1);
+        assert_eq!(h.green[200], 1);
+        assert_eq!(h.blue[30], 2);
+        assert_eq!(h.red[99], 0);
+    }
+
+    #[test]
+    fn empty_buffer_has_empty_histogram() {
+        assert_eq!(counted(&histogram(&[]).luminosity), 0);
+    }
+}
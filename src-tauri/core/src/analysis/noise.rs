@@ -0,0 +1,119 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::luminosity;
+use crate::error::AppError;
+use crate::models::{NoiseEstimate, NoiseMap};
+
+// The map has at most this many cells along its longer side.
+const MAP_CELLS: u32 = 64;
+const MIN_CELL: u32 = 8;
+// Largest residual magnitude: the mask's weights sum to 16 in absolute value.
+const MAX_RESIDUAL: usize = 16 * 255;
+// A median absolute deviation of a normal distribution is 0.6745 sigma, and
+// the mask below multiplies the noise's standard deviation by 6.
+const MAD_TO_SIGMA: f32 = 1.0 / (0.6745 * 6.0);
+
+// Immerkær's noise mask: the difference of two Laplacians, which cancels
+// flat areas, gradients and most smooth shading, leaving mostly noise.
+fn residual(plane: &[u8], width: usize, x: usize, y: usize, stride: usize) -> u16 {
+    let p = |dx: isize, dy: isize| {
+        let i = ((y as isize + dy) as usize * width + (x as isize + dx) as usize) * stride;
+        plane[i] as i32
+    };
+    let r = p(-1, -1) + p(1, -1) + p(-1, 1) + p(1, 1)
+        - 2 * (p(0, -1) + p(-1, 0) + p(1, 0) + p(0, 1))
+        + 4 * p(0, 0);
+    r.unsigned_abs() as u16
+}
+
+fn median_of_histogram(histogram: &[u64]) -> f32 {
+    let total: u64 = histogram.iter().sum();
+    let mut seen = 0;
+    for (value, &count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen * 2 >= total {
+            return value as f32;
+        }
+    }
+    0.0
+}
+
+// Estimates noise per channel from the median residual of Immerkær's mask.
+// The median ignores edges and texture, which only a minority of pixels
+// fall on, so this is the noise of the image's smooth areas. Also maps the
+// luminance noise over a coarse grid, to show where it sits. Transparent
+// pixels are skipped.
+pub fn estimate_noise(img: &RgbaImage) -> Result<NoiseEstimate, AppError> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    if width < 3 || height < 3 {
+        return Err(AppError::invalid(
+            "Noise can only be estimated on images at least 3 pixels wide and tall",
+        ));
+    }
+    let data = img.as_raw();
+    let luma: Vec<u8> = data
+        .chunks_exact(4)
+        .map(|px| luminosity(px[0], px[1], px[2]) as u8)
+        .collect();
+    let visible = |x: usize, y: usize| data[(y * width + x) * 4 + 3] > 0;
+
+    // Red, green, blue and luminance.
+    let histograms = (1..height - 1)
+        .into_par_iter()
+        .fold(
+            || vec![vec![0u64; MAX_RESIDUAL + 1]; 4],
+            |mut histograms, y| {
+                for x in (1..width - 1).filter(|&x| visible(x, y)) {
+                    for (c, histogram) in histograms.iter_mut().enumerate().take(3) {
+                        histogram[residual(&data[c..], width, x, y, 4) as usize] += 1;
+                    }
+                    histograms[3][residual(&luma, width, x, y, 1) as usize] += 1;
+                }
+                histograms
+            },
+        )
+        .reduce_with(|mut a, b| {
+            for (a, b) in a.iter_mut().zip(&b) {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+            }
+            a
+        })
+        .unwrap_or_default();
+    let sigmas: Vec<f32> = histograms
+        .iter()
+        .map(|h| median_of_histogram(h) * MAD_TO_SIGMA)
+        .collect();
+
+    let cell = (img.width().max(img.height()).div_ceil(MAP_CELLS)).max(MIN_CELL) as usize;
+    let (columns, rows) = (width.div_ceil(cell), height.div_ceil(cell));
+    let cells = (0..columns * rows)
+        .into_par_iter()
+        .map(|i| {
+            let (x0, y0) = (i % columns * cell, i / columns * cell);
+            let mut residuals = Vec::with_capacity(cell * cell);
+            for y in y0.max(1)..(y0 + cell).min(height - 1) {
+                for x in (x0.max(1)..(x0 + cell).min(width - 1)).filter(|&x| visible(x, y)) {
+                    residuals.push(residual(&luma, width, x, y, 1));
+                }
+            }
+            if residuals.is_empty() {
+                return 0.0;
+            }
+            let mid = residuals.len() / 2;
+            *residuals.select_nth_unstable(mid).1 as f32 * MAD_TO_SIGMA
+        })
+        .collect();
+
+    Ok(NoiseEstimate {
+        sigma: [sigmas[0], sigmas[1], sigmas[2]],
+        luminance_sigma: sigmas[3],
+        score: sigmas[3] / 255.0 * 100.0,
+        map: NoiseMap {
+            columns: columns as u32,
+            rows: rows as u32,
+            cell_size: cell as u32,
+            sigma: cells,
+        },
+    })
+}
@@ -0,0 +1,27 @@
+//! ONNX models run through tract, shared by the detectors.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tract_onnx::prelude::*;
+
+use crate::error::AppError;
+
+// Parsing a model takes a while, so each one used is kept around.
+pub fn cached(path: &Path) -> Result<Arc<InferenceModel>, AppError> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<InferenceModel>>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+    if let Some(model) = cache.get(path) {
+        return Ok(model.clone());
+    }
+    let model = tract_onnx::onnx()
+        .model_for_path(path)
+        .map_err(|e| AppError::DecodeFailed {
+            path: Some(path.display().to_string()),
+            reason: e.to_string(),
+        })?;
+    let model = Arc::new(model);
+    cache.insert(path.to_path_buf(), model.clone());
+    Ok(model)
+}
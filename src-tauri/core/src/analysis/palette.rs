@@ -0,0 +1,134 @@
+use crate::color::srgb_to_lab;
+use crate::models::ColorSample;
+
+pub const DEFAULT_PALETTE_SIZE: usize = 5;
+pub const MAX_PALETTE_SIZE: usize = 32;
+
+// Clustering a regular sample is indistinguishable from clustering every
+// pixel at palette precision, and keeps large images fast.
+const MAX_SAMPLES: usize = 20_000;
+const MAX_ITERATIONS: usize = 24;
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn nearest(centres: &[[f32; 3]], point: &[f32; 3]) -> usize {
+    centres
+        .iter()
+        .enumerate()
+        .map(|(i, centre)| (i, distance(centre, point)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+// k-means++ seeding with a fixed seed, so the same image always yields the
+// same palette. Stops early when every remaining point is already a centre.
+fn seed(points: &[[f32; 3]], count: usize) -> Vec<[f32; 3]> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    let mut centres = vec![points[(random() * points.len() as f64) as usize % points.len()]];
+    let mut nearest_sq: Vec<f32> = points.iter().map(|p| distance(p, &centres[0])).collect();
+    while centres.len() < count {
+        let total: f64 = nearest_sq.iter().map(|&d| d as f64).sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = random() * total;
+        let index = nearest_sq
+            .iter()
+            .position(|&d| {
+                target -= d as f64;
+                target <= 0.0
+            })
+            .unwrap_or(points.len() - 1);
+        let centre = points[index];
+        for (d, p) in nearest_sq.iter_mut().zip(points) {
+            *d = d.min(distance(p, &centre));
+        }
+        centres.push(centre);
+    }
+    centres
+}
+
+// The `count` most representative colours of the visible pixels of an RGBA
+// buffer, by k-means in CIE L*a*b*, most populous first. Each swatch is the
+// mean sRGB colour of its cluster.
+pub fn dominant_colors(data: &[u8], count: usize) -> Vec<ColorSample> {
+    let count = count.clamp(1, MAX_PALETTE_SIZE);
+    let pixels = data.len() / 4;
+    let stride = pixels.div_ceil(MAX_SAMPLES).max(1);
+    let samples: Vec<[u8; 3]> = data
+        .chunks_exact(4)
+        .step_by(stride)
+        .filter(|px| px[3] > 0)
+        .map(|px| [px[0], px[1], px[2]])
+        .collect();
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let points: Vec<[f32; 3]> = samples
+        .iter()
+        .map(|&[r, g, b]| srgb_to_lab(r, g, b))
+        .collect();
+
+    let mut centres = seed(&points, count);
+    let mut labels = vec![usize::MAX; points.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (label, point) in labels.iter_mut().zip(&points) {
+            let closest = nearest(&centres, point);
+            changed |= *label != closest;
+            *label = closest;
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![([0f64; 3], 0usize); centres.len()];
+        for (&label, point) in labels.iter().zip(&points) {
+            let (sum, n) = &mut sums[label];
+            for (s, v) in sum.iter_mut().zip(point) {
+                *s += *v as f64;
+            }
+            *n += 1;
+        }
+        for (centre, (sum, n)) in centres.iter_mut().zip(&sums) {
+            // An emptied cluster keeps its centre and may win points back.
+            if *n > 0 {
+                *centre = sum.map(|s| (s / *n as f64) as f32);
+            }
+        }
+    }
+
+    let mut clusters = vec![([0u64; 3], 0usize); centres.len()];
+    for (&label, rgb) in labels.iter().zip(&samples) {
+        let (sum, n) = &mut clusters[label];
+        for (s, v) in sum.iter_mut().zip(rgb) {
+            *s += *v as u64;
+        }
+        *n += 1;
+    }
+    clusters.retain(|(_, n)| *n > 0);
+    clusters.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+    clusters
+        .into_iter()
+        .map(|(sum, n)| {
+            let [r, g, b] = sum.map(|s| ((s as f64 / n as f64).round()) as u8);
+            ColorSample {
+                r,
+                g,
+                b,
+                hex: format!("#{:02x}{:02x}{:02x}", r, g, b),
+                population: n as f32 / samples.len() as f32 * 100.0,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,111 @@
+use crate::models::{AnalysisWarning, AnalysisWarningKind, SourceColorType};
+
+// Profile descriptions that mean the pixels are already sRGB (or close enough
+// that showing them untagged is harmless).
+const SRGB_PROFILES: &[&str] = &["srgb", "iec61966", "iec 61966", "scrgb"];
+
+// Share of visible pixels with one channel pinned near 255 and another near 0
+// before untagged content starts to look like it came from a wide-gamut source.
+const SATURATED_FRACTION: f64 = 0.02;
+
+// How far (as a fraction of the mean) a channel may drift from the gray-world
+// average over midtones before the image is reported as having a cast.
+const CAST_TOLERANCE: f64 = 0.15;
+
+fn is_srgb(description: &str) -> bool {
+    let lower = description.to_lowercase();
+    SRGB_PROFILES.iter().any(|name| lower.contains(name))
+}
+
+// Flags color-management problems that make the histogram and on-screen view
+// disagree with what the file actually encodes.
+pub fn color_warnings(rgba: &[u8], source: Option<&SourceColorType>) -> Vec<AnalysisWarning> {
+    let mut warnings = Vec::new();
+    let profile = source.and_then(|s| s.icc_profile.as_deref());
+
+    // CMYK sources carry a print profile that was applied during decode.
+    let converted = source.is_some_and(|s| s.conversion.is_some());
+    if let Some(profile) = profile.filter(|p| !converted && !is_srgb(p)) {
+        warnings.push(AnalysisWarning {
+            kind: AnalysisWarningKind::EmbeddedProfileIgnored,
+            message: format!(
+                "Image embeds the \"{}\" profile but is displayed and analyzed as sRGB; \
+                 colors will look less saturated than intended",
+                profile
+            ),
+        });
+    }
+
+    let mut visible = 0u64;
+    let mut saturated = 0u64;
+    let mut midtones = 0u64;
+    let mut sums = [0u64; 3];
+    for px in rgba.chunks_exact(4) {
+        if px[3] == 0 {
+            continue;
+        }
+        visible += 1;
+        let (r, g, b) = (px[0], px[1], px[2]);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        if max >= 250 && min <= 5 {
+            saturated += 1;
+        }
+        // Highlights and shadows clip towards neutral, so judge casts on midtones.
+        let lum = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as u8;
+        if (32..=224).contains(&lum) {
+            midtones += 1;
+            sums[0] += r as u64;
+            sums[1] += g as u64;
+            sums[2] += b as u64;
+        }
+    }
+
+    if profile.is_none() && visible > 0 {
+        let fraction = saturated as f64 / visible as f64;
+        if fraction > SATURATED_FRACTION {
+            warnings.push(AnalysisWarning {
+                kind: AnalysisWarningKind::MissingProfileWideGamut,
+                message: format!(
+                    "Image has no embedded color profile and {:.1}% of its pixels are clipped \
+                     to fully saturated values; it may have been exported from a wide-gamut \
+                     space without tagging",
+                    fraction * 100.0
+                ),
+            });
+        }
+    }
+
+    if midtones > 0 {
+        let means = sums.map(|sum| sum as f64 / midtones as f64);
+        let gray = (means[0] + means[1] + means[2]) / 3.0;
+        let deviations = means.map(|mean| (mean - gray) / gray.max(1.0));
+        let (channel, deviation) = deviations
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .unwrap_or((0, 0.0));
+        if deviation.abs() > CAST_TOLERANCE {
+            let cast = match (channel, deviation > 0.0) {
+                (0, true) => "red",
+                (0, false) => "cyan",
+                (1, true) => "green",
+                (1, false) => "magenta",
+                (_, true) => "blue",
+                (_, false) => "yellow",
+            };
+            warnings.push(AnalysisWarning {
+                kind: AnalysisWarningKind::ColorCast,
+                message: format!(
+                    "Midtones have a strong {} cast ({:+.0}% on the {} channel)",
+                    cast,
+                    deviation * 100.0,
+                    ["red", "green", "blue"][channel]
+                ),
+            });
+        }
+    }
+
+    warnings
+}
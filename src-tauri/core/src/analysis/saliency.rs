@@ -0,0 +1,70 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::color::srgb_to_lab;
+
+// Saliency is computed on a thumbnail; detail finer than this doesn't change
+// where the subject is.
+const MAX_DIMENSION: u32 = 256;
+
+// A per-pixel saliency map over a downscaled copy of the image. Values are
+// normalized to 0-1; `scale` maps map coordinates back to image pixels.
+pub struct SaliencyMap {
+    pub width: u32,
+    pub height: u32,
+    pub scale: f32,
+    pub values: Vec<f32>,
+}
+
+impl SaliencyMap {
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.values[(y * self.width + x) as usize]
+    }
+}
+
+// Frequency-tuned saliency (Achanta et al. 2009): how far each slightly
+// blurred pixel's Lab colour is from the image's mean colour. Transparent
+// pixels are never salient.
+pub fn saliency_map(img: &RgbaImage) -> SaliencyMap {
+    let (width, height) = img.dimensions();
+    let scale = (width.max(height) as f32 / MAX_DIMENSION as f32).max(1.0);
+    let small_width = ((width as f32 / scale).round() as u32).max(1);
+    let small_height = ((height as f32 / scale).round() as u32).max(1);
+    let small = imageops::resize(img, small_width, small_height, FilterType::Triangle);
+    let blurred = imageops::blur(&small, 1.0);
+
+    let lab: Vec<[f32; 3]> = blurred
+        .pixels()
+        .map(|p| srgb_to_lab(p[0], p[1], p[2]))
+        .collect();
+    let alpha: Vec<f32> = blurred.pixels().map(|p| p[3] as f32 / 255.0).collect();
+
+    let total_alpha = alpha.iter().sum::<f32>().max(f32::EPSILON);
+    let mut mean = [0f32; 3];
+    for (px, a) in lab.iter().zip(&alpha) {
+        for c in 0..3 {
+            mean[c] += px[c] * a;
+        }
+    }
+    let mean = mean.map(|sum| sum / total_alpha);
+
+    let mut values: Vec<f32> = lab
+        .iter()
+        .zip(&alpha)
+        .map(|(px, a)| {
+            let d: f32 = (0..3).map(|c| (px[c] - mean[c]).powi(2)).sum();
+            d.sqrt() * a
+        })
+        .collect();
+    let max = values.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        values.iter_mut().for_each(|v| *v /= max);
+    }
+
+    SaliencyMap {
+        width: small_width,
+        height: small_height,
+        scale: width as f32 / small_width as f32,
+        values,
+    }
+}
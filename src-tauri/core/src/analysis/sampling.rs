@@ -0,0 +1,83 @@
+use image::RgbaImage;
+
+use crate::analysis::contrast::relative_luminance;
+use crate::error::AppError;
+use crate::models::{LineProfile, Point, SampledColor};
+use crate::processing::tone::sample_average;
+
+const MAX_SAMPLES: u32 = 100_000;
+
+fn inside(img: &RgbaImage, p: Point) -> bool {
+    (0.0..img.width() as f32).contains(&p.x) && (0.0..img.height() as f32).contains(&p.y)
+}
+
+// Samples `samples` evenly spaced points from `from` to `to`, both included.
+// Each sample averages a (2 * radius + 1)^2 box so noise doesn't swamp the
+// profile; radius 0 reads single pixels.
+pub fn sample_line(
+    img: &RgbaImage,
+    from: Point,
+    to: Point,
+    samples: u32,
+    radius: u32,
+) -> Result<LineProfile, AppError> {
+    if !inside(img, from) || !inside(img, to) {
+        return Err(AppError::invalid(
+            "Line endpoints must lie inside the image",
+        ));
+    }
+    if !(2..=MAX_SAMPLES).contains(&samples) {
+        return Err(AppError::invalid(format!(
+            "Sample count must be between 2 and {}",
+            MAX_SAMPLES
+        )));
+    }
+
+    let length = (to.x - from.x).hypot(to.y - from.y);
+    let count = samples as usize;
+    let mut profile = LineProfile {
+        length,
+        distances: Vec::with_capacity(count),
+        points: Vec::with_capacity(count),
+        red: Vec::with_capacity(count),
+        green: Vec::with_capacity(count),
+        blue: Vec::with_capacity(count),
+        luminance: Vec::with_capacity(count),
+    };
+    for i in 0..samples {
+        let t = i as f32 / (samples - 1) as f32;
+        let point = Point {
+            x: from.x + (to.x - from.x) * t,
+            y: from.y + (to.y - from.y) * t,
+        };
+        let [r, g, b] = sample_average(img, point.x as u32, point.y as u32, radius);
+        profile.distances.push(length * t);
+        profile.points.push(point);
+        profile.red.push(r);
+        profile.green.push(g);
+        profile.blue.push(b);
+        profile.luminance.push(0.299 * r + 0.587 * g + 0.114 * b);
+    }
+    Ok(profile)
+}
+
+// Eyedropper pick at pixel (x, y), averaged over a (2 * radius + 1)^2 box
+// clipped to the image.
+pub fn sample_color_at(
+    img: &RgbaImage,
+    x: u32,
+    y: u32,
+    radius: u32,
+) -> Result<SampledColor, AppError> {
+    if x >= img.width() || y >= img.height() {
+        return Err(AppError::invalid("Sample point must lie inside the image"));
+    }
+    let [r, g, b] = sample_average(img, x, y, radius).map(|v| v.round().clamp(0.0, 255.0) as u8);
+    Ok(SampledColor {
+        r,
+        g,
+        b,
+        hex: format!("#{:02x}{:02x}{:02x}", r, g, b),
+        relative_luminance: relative_luminance(r, g, b),
+    })
+}
@@ -0,0 +1,93 @@
+use image::{GrayImage, RgbaImage};
+use rayon::prelude::*;
+
+use crate::color::srgb_to_lab;
+use crate::error::AppError;
+use crate::models::PixelRect;
+use crate::processing::canvas::clip_rect;
+
+// Lab histogram bins. Lightness is binned coarsely so the model tolerates
+// shading; a and b, which carry the colour, finely.
+const L_BINS: usize = 8;
+const AB_BINS: usize = 32;
+// a and b past this are rare in sRGB and share the outer bins.
+const AB_RANGE: f32 = 100.0;
+
+fn bin(r: u8, g: u8, b: u8) -> usize {
+    let [l, a, b] = srgb_to_lab(r, g, b);
+    let l = ((l / 100.0 * L_BINS as f32) as usize).min(L_BINS - 1);
+    let ab = |v: f32| {
+        (((v + AB_RANGE) / (2.0 * AB_RANGE) * AB_BINS as f32).max(0.0) as usize).min(AB_BINS - 1)
+    };
+    (l * AB_BINS + ab(a)) * AB_BINS + ab(b)
+}
+
+// Spreads each bin into its neighbours with [1 2 1] along each axis, so
+// colours a small sample just missed still count.
+fn smooth(histogram: &[f32]) -> Vec<f32> {
+    let dims = [L_BINS, AB_BINS, AB_BINS];
+    let strides = [AB_BINS * AB_BINS, AB_BINS, 1];
+    let mut current = histogram.to_vec();
+    for axis in 0..3 {
+        let (len, stride) = (dims[axis], strides[axis]);
+        current = (0..current.len())
+            .map(|i| {
+                let pos = i / stride % len;
+                let mut sum = 2.0 * current[i];
+                if pos > 0 {
+                    sum += current[i - stride];
+                }
+                if pos + 1 < len {
+                    sum += current[i + stride];
+                }
+                sum
+            })
+            .collect();
+    }
+    current
+}
+
+// Histogram backprojection: builds a colour model from the pixels in
+// `region` and maps how likely each pixel is to belong to it, white for
+// colours most common in the sample and black for those absent from it.
+// Unlike a tolerance-based fill it scores every pixel against the whole
+// sample, so textured or shaded areas are picked up as a piece. Transparent
+// pixels are left out of the sample and score 0.
+pub fn select_by_histogram(img: &RgbaImage, region: PixelRect) -> Result<GrayImage, AppError> {
+    let (width, height) = img.dimensions();
+    let region = clip_rect(
+        region.x as i64,
+        region.y as i64,
+        region.width,
+        region.height,
+        width,
+        height,
+    )
+    .ok_or_else(|| AppError::invalid("Sample region is outside the image"))?;
+
+    let mut histogram = vec![0f32; L_BINS * AB_BINS * AB_BINS];
+    for y in region.y..region.y + region.height {
+        for x in region.x..region.x + region.width {
+            let px = img.get_pixel(x, y);
+            if px[3] > 0 {
+                histogram[bin(px[0], px[1], px[2])] += 1.0;
+            }
+        }
+    }
+    let histogram = smooth(&histogram);
+    let peak = histogram.iter().copied().fold(0.0, f32::max);
+    if peak == 0.0 {
+        return Err(AppError::invalid("Sample region is fully transparent"));
+    }
+
+    let mut mask = GrayImage::new(width, height);
+    mask.par_iter_mut()
+        .zip(img.par_chunks(4))
+        .for_each(|(out, px)| {
+            if px[3] > 0 {
+                let p = histogram[bin(px[0], px[1], px[2])] / peak;
+                *out = (p * 255.0).round() as u8;
+            }
+        });
+    Ok(mask)
+}
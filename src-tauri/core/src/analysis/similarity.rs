@@ -0,0 +1,225 @@
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use super::{luminosity, PARALLEL_CHUNK};
+use crate::error::AppError;
+use crate::models::{EdgeMode, ImageComparison, ImageData};
+use crate::processing::filters::blur_planes;
+
+// Wang et al.'s SSIM window and stabilising constants for 8-bit values.
+const SSIM_SIGMA: f32 = 1.5;
+const SSIM_C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+
+#[derive(Default)]
+struct Totals {
+    differing: u64,
+    alpha_differing: u64,
+    max: u8,
+    abs: [u64; 3],
+    squared: u64,
+}
+
+fn totals(a: &[u8], b: &[u8]) -> Totals {
+    a.chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .fold(Totals::default(), |mut t, (a, b)| {
+            let d: [u8; 3] = std::array::from_fn(|c| a[c].abs_diff(b[c]));
+            if d != [0; 3] {
+                t.differing += 1;
+            }
+            if a[3] != b[3] {
+                t.alpha_differing += 1;
+            }
+            for (c, &d) in d.iter().enumerate() {
+                t.max = t.max.max(d);
+                t.abs[c] += d as u64;
+                t.squared += d as u64 * d as u64;
+            }
+            t
+        })
+}
+
+// Mean SSIM of the two luminance planes, with Gaussian-weighted local
+// statistics.
+fn ssim(a: &RgbaImage, b: &RgbaImage) -> f32 {
+    let (width, height) = (a.width() as usize, a.height() as usize);
+    let luma = |px: &Rgba<u8>| luminosity(px[0], px[1], px[2]) as f32;
+    let values: Vec<(f32, f32)> = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(a, b)| (luma(a), luma(b)))
+        .collect();
+    let squares: Vec<f32> = values
+        .iter()
+        .flat_map(|&(x, y)| [x, y, x * x, y * y])
+        .collect();
+    let products: Vec<f32> = values
+        .iter()
+        .flat_map(|&(x, y)| [x * y, 0.0, 0.0, 0.0])
+        .collect();
+    let squares = blur_planes(&squares, width, height, SSIM_SIGMA, EdgeMode::Mirror);
+    let products = blur_planes(&products, width, height, SSIM_SIGMA, EdgeMode::Mirror);
+
+    let total: f64 = squares
+        .par_chunks(4)
+        .zip(products.par_chunks(4))
+        .map(|(s, p)| {
+            let (mx, my) = (s[0], s[1]);
+            let vx = (s[2] - mx * mx).max(0.0);
+            let vy = (s[3] - my * my).max(0.0);
+            let cov = p[0] - mx * my;
+            let ssim = ((2.0 * mx * my + SSIM_C1) * (2.0 * cov + SSIM_C2))
+                / ((mx * mx + my * my + SSIM_C1) * (vx + vy + SSIM_C2));
+            ssim as f64
+        })
+        .sum();
+    (total / (width * height) as f64) as f32
+}
+
+// Black through red and yellow to white.
+fn heat(t: f32) -> Rgba<u8> {
+    let ramp = |offset: f32| ((t * 3.0 - offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([ramp(0.0), ramp(1.0), ramp(2.0), 255])
+}
+
+fn heatmap(a: &RgbaImage, b: &RgbaImage, max: u8) -> RgbaImage {
+    let scale = 1.0 / max.max(1) as f32;
+    let mut map = RgbaImage::new(a.width(), a.height());
+    map.par_chunks_mut(PARALLEL_CHUNK * 4)
+        .zip(
+            a.par_chunks(PARALLEL_CHUNK * 4)
+                .zip(b.par_chunks(PARALLEL_CHUNK * 4)),
+        )
+        .for_each(|(out, (a, b))| {
+            for (px, (a, b)) in out
+                .chunks_exact_mut(4)
+                .zip(a.chunks_exact(4).zip(b.chunks_exact(4)))
+            {
+                let d = (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0);
+                px.copy_from_slice(&heat(d as f32 * scale).0);
+            }
+        });
+    map
+}
+
+// Compares `a` and `b` pixel by pixel, for checking that a re-encode or
+// another tool's export is visually lossless. With `heatmap` also maps
+// where they differ.
+pub fn compare_images(
+    a: &RgbaImage,
+    b: &RgbaImage,
+    heatmap: bool,
+) -> Result<ImageComparison, AppError> {
+    if a.dimensions() != b.dimensions() {
+        return Err(AppError::invalid(format!(
+            "The images must be the same size, but one is {}x{} and the other {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        )));
+    }
+    let pixels = a.width() as u64 * a.height() as u64;
+    if pixels == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let t = a
+        .as_raw()
+        .par_chunks(PARALLEL_CHUNK * 4)
+        .zip(b.as_raw().par_chunks(PARALLEL_CHUNK * 4))
+        .map(|(a, b)| totals(a, b))
+        .reduce(Totals::default, |x, y| Totals {
+            differing: x.differing + y.differing,
+            alpha_differing: x.alpha_differing + y.alpha_differing,
+            max: x.max.max(y.max),
+            abs: std::array::from_fn(|c| x.abs[c] + y.abs[c]),
+            squared: x.squared + y.squared,
+        });
+
+    let mse = t.squared as f64 / (pixels * 3) as f64;
+    let psnr = (mse > 0.0).then(|| (10.0 * (255.0 * 255.0 / mse).log10()) as f32);
+    let heatmap = heatmap.then(|| {
+        let map = self::heatmap(a, b, t.max);
+        ImageData {
+            width: map.width(),
+            height: map.height(),
+            data: map.into_raw(),
+            format: "rgba".to_string(),
+            recovered: false,
+            source_color_type: None,
+        }
+    });
+    Ok(ImageComparison {
+        identical: t.differing == 0 && t.alpha_differing == 0,
+        differing_pixels: t.differing,
+        differing_percent: t.differing as f32 / pixels as f32 * 100.0,
+        alpha_differing_pixels: t.alpha_differing,
+        max_difference: t.max,
+        mean_difference: t.abs.map(|v| (v as f64 / pixels as f64) as f32),
+        rmse: mse.sqrt() as f32,
+        psnr,
+        ssim: if t.differing == 0 { 1.0 } else { ssim(a, b) },
+        heatmap,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let v = ((x * 37 + y * 91) % 256) as u8;
+            Rgba([v, v / 2, 255 - v, 255])
+        })
+    }
+
+    #[test]
+    fn identical_images_are_reported_lossless() {
+        let a = pattern(16, 12);
+        let report = compare_images(&a, &a, false).unwrap();
+        assert!(report.identical);
+        assert_eq!(report.psnr, None);
+        assert_eq!(report.ssim, 1.0);
+        assert_eq!(report.rmse, 0.0);
+    }
+
+    #[test]
+    fn psnr_matches_a_uniform_error() {
+        let a = RgbaImage::from_pixel(10, 10, Rgba([100, 100, 100, 255]));
+        let b = RgbaImage::from_pixel(10, 10, Rgba([110, 90, 110, 255]));
+        let report = compare_images(&a, &b, true).unwrap();
+        // MSE is 100, so PSNR is 10 log10(255^2 / 100).
+        let expected = 10.0 * (255.0f32 * 255.0 / 100.0).log10();
+        assert!((report.psnr.unwrap() - expected).abs() < 1e-3);
+        assert_eq!(report.rmse, 10.0);
+        assert_eq!(report.max_difference, 10);
+        assert_eq!(report.differing_pixels, 100);
+        assert_eq!(report.heatmap.unwrap().data.len(), 10 * 10 * 4);
+    }
+
+    #[test]
+    fn ssim_falls_with_distortion() {
+        let a = pattern(32, 32);
+        let mut slight = a.clone();
+        slight.get_pixel_mut(5, 5)[0] ^= 4;
+        let noisy = RgbaImage::from_fn(32, 32, |x, y| {
+            let mut px = *a.get_pixel(x, y);
+            let noise = if (x * 7 + y * 3) % 2 == 0 { 40 } else { 0 };
+            px[0] = px[0].wrapping_add(noise);
+            px[1] = px[1].wrapping_sub(noise);
+            px
+        });
+        let slight = ssim(&a, &slight);
+        let noisy = ssim(&a, &noisy);
+        assert!(slight > 0.99 && slight < 1.0, "{}", slight);
+        assert!(noisy < slight, "{} >= {}", noisy, slight);
+        assert!((ssim(&a, &a) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn different_sizes_are_rejected() {
+        assert!(compare_images(&pattern(4, 4), &pattern(4, 5), false).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use image::RgbaImage;
+
+use crate::analysis::blobs::{components, luminance};
+use crate::error::AppError;
+use crate::models::{Point, Star, StarAnalysis};
+
+// Stars are pixels this many noise sigmas above the local sky.
+const DETECTION_SIGMAS: f32 = 5.0;
+// Smaller components are hot pixels or noise; larger ones are nebulae,
+// foreground or clouds rather than point sources.
+const MIN_STAR_AREA: usize = 3;
+const MAX_STAR_AREA: usize = 2500;
+// The sky is estimated per cell of this many pixels, following gradients
+// from light pollution or the moon.
+const SKY_CELL: u32 = 64;
+// Pixels at or above this luminance are clipped and spread a star's profile.
+const SATURATED: f32 = 250.0;
+// Only the brightest stars are listed individually.
+const MAX_LISTED_STARS: usize = 500;
+
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
+}
+
+// Median sky level per cell and over the whole image. Medians ignore the
+// stars themselves, which are a small fraction of any cell.
+fn sky(lum: &[f32], width: u32, height: u32) -> (Vec<f32>, u32, f32) {
+    let cols = width.div_ceil(SKY_CELL);
+    let rows = height.div_ceil(SKY_CELL);
+    let mut cells = Vec::with_capacity((cols * rows) as usize);
+    for cy in 0..rows {
+        for cx in 0..cols {
+            let mut values: Vec<f32> = (cy * SKY_CELL..((cy + 1) * SKY_CELL).min(height))
+                .flat_map(|y| {
+                    (cx * SKY_CELL..((cx + 1) * SKY_CELL).min(width))
+                        .map(move |x| lum[(y * width + x) as usize])
+                })
+                .collect();
+            cells.push(median(&mut values));
+        }
+    }
+    let mut all = lum.to_vec();
+    (cells, cols, median(&mut all))
+}
+
+// Detects stars and measures their profiles from intensity-weighted second
+// moments. FWHM tracks focus and seeing; eccentricity near 0 is round, and
+// consistently elongated stars in one direction point to tracking error or
+// wind shake.
+pub fn analyze_stars(img: &RgbaImage) -> Result<StarAnalysis, AppError> {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return Err(AppError::invalid("Image is too small to detect stars"));
+    }
+    let (w, h) = (width as usize, height as usize);
+    let lum: Vec<f32> = img.pixels().map(luminance).collect();
+    let (cells, cols, background) = sky(&lum, width, height);
+    let sky_at = |i: usize| {
+        let (x, y) = ((i % w) as u32, (i / w) as u32);
+        cells[((y / SKY_CELL) * cols + x / SKY_CELL) as usize]
+    };
+    let signal: Vec<f32> = (0..lum.len()).map(|i| lum[i] - sky_at(i)).collect();
+    // The MAD of a normal distribution is 0.6745 sigma. A floor keeps a
+    // perfectly flat synthetic sky from making every speck a star.
+    let mut deviations: Vec<f32> = signal.iter().map(|v| v.abs()).collect();
+    let noise = (median(&mut deviations) / 0.6745).max(0.5);
+    let threshold = DETECTION_SIGMAS * noise;
+    let foreground: Vec<bool> = signal
+        .iter()
+        .zip(img.pixels())
+        .map(|(&s, px)| px[3] > 0 && s > threshold)
+        .collect();
+
+    let mut stars = Vec::new();
+    for pixels in components(&foreground, w, h) {
+        if !(MIN_STAR_AREA..=MAX_STAR_AREA).contains(&pixels.len()) {
+            continue;
+        }
+        let touches_edge = pixels.iter().any(|&i| {
+            let (x, y) = (i % w, i / w);
+            x == 0 || y == 0 || x == w - 1 || y == h - 1
+        });
+        if touches_edge {
+            continue;
+        }
+
+        let (mut flux, mut sx, mut sy, mut peak, mut peak_signal) = (0f64, 0f64, 0f64, 0f32, 0f32);
+        for &i in &pixels {
+            let v = signal[i] as f64;
+            flux += v;
+            sx += v * ((i % w) as f64 + 0.5);
+            sy += v * ((i / w) as f64 + 0.5);
+            peak = peak.max(lum[i]);
+            peak_signal = peak_signal.max(signal[i]);
+        }
+        let (cx, cy) = (sx / flux, sy / flux);
+
+        // The width comes from the area above half the peak, which for faint
+        // stars reaches past the detection threshold. Shape comes from the
+        // moments within whichever contour is lower, since more pixels give a
+        // steadier estimate, and a region bounded by one contour keeps the
+        // star's shape.
+        let half = peak_signal / 2.0;
+        let contour = half.min(threshold);
+        let reach = (2.0 * (pixels.len() as f64 / std::f64::consts::PI).sqrt() + 3.0).ceil();
+        let x_range = (cx - reach).max(0.0) as usize..((cx + reach) as usize + 1).min(w);
+        let y_range = (cy - reach).max(0.0) as usize..((cy + reach) as usize + 1).min(h);
+        let (mut half_area, mut weight) = (0usize, 0f64);
+        let (mut xx, mut yy, mut xy) = (0f64, 0f64, 0f64);
+        for y in y_range {
+            for x in x_range.clone() {
+                let v = signal[y * w + x];
+                if v < contour {
+                    continue;
+                }
+                if v >= half {
+                    half_area += 1;
+                }
+                let v = v as f64;
+                let dx = x as f64 + 0.5 - cx;
+                let dy = y as f64 + 0.5 - cy;
+                weight += v;
+                xx += v * dx * dx;
+                yy += v * dy * dy;
+                xy += v * dx * dy;
+            }
+        }
+        let (xx, yy, xy) = (xx / weight, yy / weight, xy / weight);
+        // Eigenvalues of the covariance are the variances along the major and
+        // minor axes.
+        let spread = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        let major = (xx + yy) / 2.0 + spread;
+        let minor = ((xx + yy) / 2.0 - spread).max(0.0);
+        let eccentricity = if major > 0.0 {
+            (1.0 - minor / major).sqrt() as f32
+        } else {
+            0.0
+        };
+        stars.push(Star {
+            position: Point {
+                x: cx as f32,
+                y: cy as f32,
+            },
+            flux: flux as f32,
+            peak,
+            // The diameter of a circle with the half-maximum area, which for an
+            // elongated star is the geometric mean of its two widths.
+            fwhm: 2.0 * (half_area as f32 / std::f32::consts::PI).sqrt(),
+            eccentricity,
+            // Counter-clockwise from the positive x axis, with y pointing up.
+            angle: (-0.5 * (2.0 * xy).atan2(xx - yy)).to_degrees() as f32,
+            saturated: peak >= SATURATED,
+        });
+    }
+    stars.sort_by(|a, b| b.flux.total_cmp(&a.flux));
+
+    // Saturated stars' flat tops make them look wider and rounder than they
+    // are, so the summary uses the rest when there are any.
+    let measured: Vec<&Star> = {
+        let unsaturated: Vec<&Star> = stars.iter().filter(|s| !s.saturated).collect();
+        if unsaturated.is_empty() {
+            stars.iter().collect()
+        } else {
+            unsaturated
+        }
+    };
+    let summary = |value: fn(&Star) -> f32| {
+        let mut values: Vec<f32> = measured.iter().map(|s| value(s)).collect();
+        (!values.is_empty()).then(|| median(&mut values))
+    };
+    let fwhm = summary(|s| s.fwhm);
+    let eccentricity = summary(|s| s.eccentricity);
+    // Axes are directionless, so angles are averaged as doubled vectors,
+    // weighted by how elongated each star is.
+    let (sin, cos) = measured.iter().fold((0f32, 0f32), |(sin, cos), s| {
+        let theta = (2.0 * s.angle).to_radians();
+        (
+            sin + s.eccentricity * theta.sin(),
+            cos + s.eccentricity * theta.cos(),
+        )
+    });
+    let elongation_angle = (!measured.is_empty()).then(|| 0.5 * sin.atan2(cos).to_degrees());
+
+    let star_count = stars.len();
+    stars.truncate(MAX_LISTED_STARS);
+    Ok(StarAnalysis {
+        star_count,
+        background,
+        noise,
+        fwhm,
+        eccentricity,
+        elongation_angle,
+        stars,
+    })
+}
@@ -0,0 +1,190 @@
+//! Text localisation with a DB or EAST style ONNX model. Only the model's
+//! text probability map is used, so regions are axis-aligned boxes around
+//! connected blobs of text rather than recognised words.
+
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::error::AppError;
+use crate::models::{PixelRect, TextRegion};
+
+// Pixels above this probability are text.
+const PIXEL_THRESHOLD: f32 = 0.3;
+// Blobs whose mean probability is lower are discarded as noise.
+const REGION_THRESHOLD: f32 = 0.6;
+const MIN_REGION_PIXELS: usize = 4;
+// DB models predict shrunken text kernels; this grows them back out.
+const UNCLIP_RATIO: f32 = 1.5;
+
+// Groups above-threshold pixels of a `map_width`x`map_height` probability map
+// into regions in the coordinates of an `image_width`x`image_height` image.
+#[cfg_attr(not(feature = "text-detection"), allow(dead_code))]
+fn regions_from_map(
+    map: &[f32],
+    map_width: usize,
+    map_height: usize,
+    image_width: u32,
+    image_height: u32,
+) -> Vec<TextRegion> {
+    let sx = image_width as f32 / map_width as f32;
+    let sy = image_height as f32 / map_height as f32;
+    let mut visited = vec![false; map.len()];
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..map.len() {
+        if visited[start] || map[start] < PIXEL_THRESHOLD {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+        let (mut count, mut total) = (0usize, 0f32);
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % map_width, i / map_width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+            count += 1;
+            total += map[i];
+
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < map_width).then(|| i + 1),
+                (y > 0).then(|| i - map_width),
+                (y + 1 < map_height).then(|| i + map_width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !visited[n] && map[n] >= PIXEL_THRESHOLD {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let confidence = total / count as f32;
+        if count < MIN_REGION_PIXELS || confidence < REGION_THRESHOLD {
+            continue;
+        }
+
+        // Offset from the DB paper: area * ratio / perimeter.
+        let (w, h) = ((max_x - min_x + 1) as f32, (max_y - min_y + 1) as f32);
+        let grow = w * h * UNCLIP_RATIO / (2.0 * (w + h));
+        let left = ((min_x as f32 - grow) * sx).max(0.0);
+        let top = ((min_y as f32 - grow) * sy).max(0.0);
+        let right = ((max_x as f32 + 1.0 + grow) * sx).min(image_width as f32);
+        let bottom = ((max_y as f32 + 1.0 + grow) * sy).min(image_height as f32);
+        regions.push(TextRegion {
+            bounds: PixelRect {
+                x: left as u32,
+                y: top as u32,
+                width: (right - left).round().max(1.0) as u32,
+                height: (bottom - top).round().max(1.0) as u32,
+            },
+            confidence,
+        });
+    }
+
+    regions.sort_by_key(|r| (r.bounds.y, r.bounds.x));
+    regions
+}
+
+#[cfg(feature = "text-detection")]
+mod model {
+    use std::path::Path;
+
+    use image::imageops::{self, FilterType};
+    use image::RgbaImage;
+    use tract_onnx::prelude::*;
+
+    use super::regions_from_map;
+    use crate::analysis::onnx::cached;
+    use crate::error::AppError;
+    use crate::models::TextRegion;
+
+    // Longest side fed to the model. Inputs are rounded to multiples of 32,
+    // which both model families require.
+    const MAX_INPUT_SIDE: u32 = 960;
+    const INPUT_STRIDE: u32 = 32;
+    const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+    const STD: [f32; 3] = [0.229, 0.224, 0.225];
+    fn input_size(width: u32, height: u32) -> (u32, u32) {
+        let scale = (MAX_INPUT_SIDE as f32 / width.max(height) as f32).min(1.0);
+        let round = |v: u32| {
+            let v = (v as f32 * scale / INPUT_STRIDE as f32).round() as u32;
+            v.max(1) * INPUT_STRIDE
+        };
+        (round(width), round(height))
+    }
+
+    pub fn detect(img: &RgbaImage, path: &Path) -> Result<Vec<TextRegion>, AppError> {
+        let failed = |e: TractError| AppError::OperationFailed {
+            message: format!("Text detection failed: {}", e),
+        };
+        let (width, height) = input_size(img.width(), img.height());
+        let resized = imageops::resize(img, width, height, FilterType::Triangle);
+        let (w, h) = (width as usize, height as usize);
+
+        // The input shape is fixed per call so models with dynamic axes can
+        // still be optimised.
+        let plan = cached(path)?
+            .as_ref()
+            .clone()
+            .with_input_fact(0, f32::fact([1, 3, h, w]).into())
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(failed)?;
+
+        let input: Tensor = tract_ndarray::Array4::from_shape_fn((1, 3, h, w), |(_, c, y, x)| {
+            let v = resized.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+            (v - MEAN[c]) / STD[c]
+        })
+        .into();
+        let outputs = plan.run(tvec!(input.into())).map_err(failed)?;
+        let map = outputs[0].to_array_view::<f32>().map_err(failed)?;
+
+        // DB emits [1, 1, h, w] at input resolution, EAST its score map at a
+        // quarter of it. Either way the last two axes are the map.
+        let shape = map.shape();
+        if shape.len() < 2 {
+            return Err(AppError::invalid(
+                "Model output is not a text probability map",
+            ));
+        }
+        let (map_height, map_width) = (shape[shape.len() - 2], shape[shape.len() - 1]);
+        let values: Vec<f32> = map.iter().take(map_width * map_height).copied().collect();
+        Ok(regions_from_map(
+            &values,
+            map_width,
+            map_height,
+            img.width(),
+            img.height(),
+        ))
+    }
+}
+
+// Finds text in `img` with the ONNX model at `model_path`. The model takes a
+// normalised NCHW RGB tensor and outputs a text probability map, as exported
+// DB (e.g. PaddleOCR) and EAST detectors do.
+pub fn detect_text_regions(
+    img: &RgbaImage,
+    model_path: &Path,
+) -> Result<Vec<TextRegion>, AppError> {
+    if !model_path.is_file() {
+        return Err(AppError::FileNotFound {
+            path: model_path.display().to_string(),
+        });
+    }
+    #[cfg(feature = "text-detection")]
+    {
+        model::detect(img, model_path)
+    }
+    #[cfg(not(feature = "text-detection"))]
+    {
+        let _ = img;
+        Err("This build was made without text detection support".into())
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::Local;
+use image::RgbaImage;
+
+use crate::checksum::{hash_bytes, hash_path};
+use crate::error::AppError;
+use crate::models::{AuditRecord, Edit, HashAlgorithm, OperationLog, SignedAudit};
+
+// SHA-256 rather than BLAKE3, as it's what forensic tools expect to check.
+const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256;
+const CSV_HEADER: &str = "sequence,step,parameters,hash,width,height";
+const SIGNATURE_ROW: &str = ",signature,,";
+
+// Hashes the source file, the edited pixels and, if given, the file they were
+// exported to. `current` must be the state `log` ends in.
+pub fn build_audit(
+    log: OperationLog,
+    current: &RgbaImage,
+    output: Option<&Path>,
+) -> Result<AuditRecord, AppError> {
+    let source_hash = log
+        .source_path
+        .as_ref()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .map(|path| hash_path(path, ALGORITHM))
+        .transpose()?;
+    let output_hash = match output {
+        Some(path) if !path.is_file() => {
+            return Err(AppError::FileNotFound {
+                path: path.to_string_lossy().into_owned(),
+            })
+        }
+        Some(path) => Some(hash_path(path, ALGORITHM)?),
+        None => None,
+    };
+    let mut pixels = Vec::with_capacity(8 + current.as_raw().len());
+    pixels.extend_from_slice(&current.width().to_le_bytes());
+    pixels.extend_from_slice(&current.height().to_le_bytes());
+    pixels.extend_from_slice(current.as_raw());
+
+    Ok(AuditRecord {
+        created_at: Local::now().to_rfc3339(),
+        algorithm: ALGORITHM,
+        source_path: log.source_path,
+        source_hash,
+        source_width: log.width,
+        source_height: log.height,
+        edits: log.entries,
+        output_path: output.map(|path| path.to_string_lossy().into_owned()),
+        output_hash,
+        pixel_hash: hash_bytes(&pixels, ALGORITHM),
+        width: current.width(),
+        height: current.height(),
+    })
+}
+
+fn sign(body: &[u8], key: &[u8; 32]) -> String {
+    blake3::keyed_hash(key, body).to_hex().to_string()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// The step name of an edit and its other fields as JSON. Operations are
+// named after the operation rather than "operation". Going through a string
+// keeps f32 parameters at their own precision.
+fn describe(edit: &Edit) -> (String, String) {
+    let mut value: serde_json::Value = serde_json::to_string(edit)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if let Edit::Operation { .. } = edit {
+        value = value["operation"].take();
+    }
+    let step = value["type"].as_str().unwrap_or_default().to_string();
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("type");
+    }
+    (step, value.to_string())
+}
+
+// One row per edit between a source row and the output and pixel rows, so
+// the file reads top to bottom as what happened.
+fn to_csv(record: &AuditRecord) -> String {
+    let row = |sequence: &str, step: &str, parameters: &str, hash: &str, w: u32, h: u32| {
+        format!(
+            "{},{},{},{},{},{}\n",
+            sequence,
+            step,
+            csv_field(parameters),
+            hash,
+            w,
+            h
+        )
+    };
+    let source = record.source_path.as_deref().unwrap_or_default();
+    let mut csv = format!("{}\n", CSV_HEADER);
+    csv += &row(
+        "0",
+        "source",
+        source,
+        record.source_hash.as_deref().unwrap_or_default(),
+        record.source_width,
+        record.source_height,
+    );
+    for entry in &record.edits {
+        let (step, parameters) = describe(&entry.edit);
+        csv += &row(
+            &entry.sequence.to_string(),
+            &step,
+            &parameters,
+            "",
+            entry.width,
+            entry.height,
+        );
+    }
+    if let Some(output) = &record.output_path {
+        csv += &row(
+            "",
+            "output",
+            output,
+            record.output_hash.as_deref().unwrap_or_default(),
+            record.width,
+            record.height,
+        );
+    }
+    csv += &row(
+        "",
+        "pixels",
+        &record.created_at,
+        &record.pixel_hash,
+        record.width,
+        record.height,
+    );
+    csv
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+// Writes CSV for a `.csv` path and JSON otherwise. CSV ends with a signature
+// row covering every line above it.
+pub fn write_audit(record: AuditRecord, key: &[u8; 32], path: &Path) -> Result<(), AppError> {
+    let contents = if is_csv(path) {
+        let csv = to_csv(&record);
+        let signature = sign(csv.as_bytes(), key);
+        format!("{}{}{},,\n", csv, SIGNATURE_ROW, signature)
+    } else {
+        let body = serde_json::to_vec(&record)
+            .map_err(|e| format!("Failed to serialize the audit record: {}", e))?;
+        let signed = SignedAudit {
+            signature: sign(&body, key),
+            record,
+        };
+        serde_json::to_string_pretty(&signed)
+            .map_err(|e| format!("Failed to serialize the audit record: {}", e))?
+    };
+    fs::write(path, contents).map_err(|e| AppError::io(path, e, true))
+}
+
+// Whether an audit file written with `key` is unchanged since.
+pub fn verify_audit(path: &Path, key: &[u8; 32]) -> Result<bool, AppError> {
+    let contents = fs::read_to_string(path).map_err(|e| AppError::io(path, e, false))?;
+    let (body, signature) = if is_csv(path) {
+        let Some(start) = contents.rfind(SIGNATURE_ROW) else {
+            return Err(AppError::invalid("The audit file has no signature row"));
+        };
+        let signature = contents[start + SIGNATURE_ROW.len()..]
+            .split(',')
+            .next()
+            .unwrap_or_default();
+        (contents.as_bytes()[..start].to_vec(), signature.to_string())
+    } else {
+        let signed: SignedAudit = serde_json::from_str(&contents)
+            .map_err(|e| AppError::invalid(format!("Not an audit record: {}", e)))?;
+        let body = serde_json::to_vec(&signed.record)
+            .map_err(|e| format!("Failed to serialize the audit record: {}", e))?;
+        (body, signed.signature)
+    };
+    Ok(sign(&body, key) == signature)
+}
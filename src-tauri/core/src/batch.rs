@@ -0,0 +1,121 @@
+//! Runs one edit pipeline over every image in a folder.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::decode::decode_file;
+use crate::encode::write_image;
+use crate::error::AppError;
+use crate::library::list_images;
+use crate::models::{BatchProgress, BatchReport, Edit, ExportResult, SkippedFile};
+use crate::processing::edits::apply_edit;
+
+// Where `image` is exported to in `output_dir`: the same name with `format`
+// (or the original's) as the extension. Refuses to overwrite the original.
+fn output_path(image: &Path, output_dir: &Path, format: Option<&str>) -> Result<PathBuf, AppError> {
+    let extension = format
+        .map(str::to_string)
+        .or_else(|| Some(image.extension()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "png".to_string());
+    let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+    let output = output_dir.join(format!("{}.{}", stem, extension));
+    if output.exists() && fs::canonicalize(&output).ok() == fs::canonicalize(image).ok() {
+        return Err(AppError::invalid("Exporting would overwrite the original"));
+    }
+    Ok(output)
+}
+
+// `output_path` for each of `images`, in order. Where several would export
+// to the same file, as `a.jpg` and `a.png` do when converted, the first keeps
+// the name and the rest are refused, so no two workers write one file. Names
+// are compared ignoring case, as Windows and macOS do.
+pub(crate) fn output_paths(
+    images: &[PathBuf],
+    output_dir: &Path,
+    format: Option<&str>,
+) -> Vec<Result<PathBuf, AppError>> {
+    let mut claimed: HashMap<String, &Path> = HashMap::new();
+    images
+        .iter()
+        .map(|image| {
+            let output = output_path(image, output_dir, format)?;
+            match claimed.entry(output.to_string_lossy().to_lowercase()) {
+                Entry::Occupied(first) => Err(AppError::invalid(format!(
+                    "Would export to the same file as {}",
+                    first.get().display()
+                ))),
+                Entry::Vacant(slot) => {
+                    slot.insert(image);
+                    Ok(output)
+                }
+            }
+        })
+        .collect()
+}
+
+fn process(image: &Path, edits: &[Edit], output: &Path) -> Result<ExportResult, AppError> {
+    let mut decoded = decode_file(image, false)?;
+    for edit in edits {
+        apply_edit(&mut decoded.image, edit, Some(image))?;
+    }
+    let output = output.to_string_lossy().into_owned();
+    let file_size = write_image(&decoded.image, decoded.source.as_ref(), &output)?;
+    Ok(ExportResult {
+        output,
+        width: decoded.image.width(),
+        height: decoded.image.height(),
+        file_size,
+    })
+}
+
+// Applies `edits` in order to every image in `dir` and writes the results to
+// `output_dir`, converting to `format` if given. `on_progress` is called from
+// the worker threads as each image finishes, in no particular order.
+pub fn batch_process(
+    dir: &str,
+    output_dir: &Path,
+    edits: &[Edit],
+    format: Option<&str>,
+    on_progress: impl Fn(BatchProgress) + Sync,
+) -> Result<BatchReport, AppError> {
+    let images = list_images(dir)?;
+    fs::create_dir_all(output_dir).map_err(|e| AppError::io(output_dir, e, true))?;
+
+    let total = images.len();
+    let outputs = output_paths(&images, output_dir, format);
+    let completed = AtomicUsize::new(0);
+    let results: Vec<(PathBuf, Result<ExportResult, AppError>)> = images
+        .into_par_iter()
+        .zip(outputs)
+        .map(|(image, output)| {
+            let result = output.and_then(|output| process(&image, edits, &output));
+            on_progress(BatchProgress {
+                completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                path: image.to_string_lossy().into_owned(),
+                error: result.as_ref().err().map(AppError::to_string),
+            });
+            (image, result)
+        })
+        .collect();
+
+    let mut report = BatchReport {
+        exported: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (image, result) in results {
+        match result {
+            Ok(exported) => report.exported.push(exported),
+            Err(e) => report.skipped.push(SkippedFile {
+                path: image.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+use crate::models::HashAlgorithm;
+
+const READ_BUFFER: usize = 1 << 20;
+
+fn stream_file(path: &Path, mut update: impl FnMut(&[u8])) -> Result<(), AppError> {
+    let file = File::open(path).map_err(|e| AppError::io(path, e, false))?;
+    let mut reader = BufReader::with_capacity(READ_BUFFER, file);
+    let mut buffer = vec![0u8; READ_BUFFER];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| AppError::io(path, e, false))?;
+        if read == 0 {
+            return Ok(());
+        }
+        update(&buffer[..read]);
+    }
+}
+
+// Returns the lowercase hex digest of the file's contents.
+pub fn hash_path(path: &Path, algorithm: HashAlgorithm) -> Result<String, AppError> {
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            stream_file(path, |chunk| hasher.update(chunk))?;
+            Ok(hex(&hasher.finalize()))
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            stream_file(path, |chunk| {
+                hasher.update(chunk);
+            })?;
+            Ok(hasher.finalize().to_hex().to_string())
+        }
+    }
+}
+
+// Returns the lowercase hex digest of an in-memory buffer.
+pub fn hash_bytes(data: &[u8], algorithm: HashAlgorithm) -> String {
+    match algorithm {
+        HashAlgorithm::Sha256 => hex(&Sha256::digest(data)),
+        HashAlgorithm::Blake3 => blake3::hash(data).to_hex().to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,95 @@
+//! Colour space conversions shared by analysis and processing.
+
+pub fn srgb_to_linear(v: u8) -> f32 {
+    decode_srgb(v as f32 / 255.0)
+}
+
+// `srgb_to_linear` for encoded values in 0-1.
+pub fn decode_srgb(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// The inverse of `srgb_to_linear`, for linear values in 0-1.
+pub fn linear_to_srgb(v: f32) -> u8 {
+    (encode_srgb(v) * 255.0).round() as u8
+}
+
+// `linear_to_srgb` without the rounding to 8 bits.
+pub fn encode_srgb(v: f32) -> f32 {
+    let v = v.clamp(0.0, 1.0);
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// CIE L*a*b* under D65.
+pub fn srgb_to_lab(r: u8, g: u8, b: u8) -> [f32; 3] {
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+// Hue in degrees 0-360, saturation and lightness 0-1, from 8-bit RGB.
+pub fn rgb_to_hsl(r: u8, g: u8, b: u8) -> [f32; 3] {
+    rgb_to_hsl_f32(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+// `rgb_to_hsl` for channels in 0-1.
+pub fn rgb_to_hsl_f32(r: f32, g: f32, b: f32) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma <= 0.0 {
+        return [0.0, 0.0, lightness];
+    }
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    [hue * 60.0, saturation.min(1.0), lightness]
+}
+
+// The inverse of `rgb_to_hsl`; hue wraps and the others are clamped to 0-1.
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    hsl_to_rgb_f32(hue, saturation, lightness).map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+// `hsl_to_rgb` with channels in 0-1.
+pub fn hsl_to_rgb_f32(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r, g, b].map(|v| (v + m).clamp(0.0, 1.0))
+}
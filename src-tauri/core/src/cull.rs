@@ -0,0 +1,224 @@
+//! First-pass culling of a shoot: each image is kept, flagged for review or
+//! rejected by sharpness, clipped exposure, closed eyes and near-duplicate
+//! grouping, with the reasons alongside.
+
+use std::path::{Path, PathBuf};
+
+use image::DynamicImage;
+use rayon::prelude::*;
+
+use crate::analysis::faces::{check_model, detect_faces};
+use crate::analysis::hashing::{difference_hash, hamming_distance};
+use crate::analysis::sharpness::variance_of_laplacian;
+use crate::analysis::{histogram, luminosity};
+use crate::decode::decode_file;
+use crate::error::AppError;
+use crate::library::{list_images, SHARPNESS_SAMPLE_SIZE};
+use crate::models::{CullEntry, CullReport, CullRules, CullVerdict, SkippedFile};
+
+struct Inspected {
+    path: PathBuf,
+    sharpness: f64,
+    clipped_shadows: f32,
+    clipped_highlights: f32,
+    hash: u64,
+    // None when faces weren't looked for.
+    faces: Option<usize>,
+    closed_eyes: usize,
+}
+
+fn inspect(path: &Path, face_model: Option<&Path>) -> Result<Inspected, AppError> {
+    let decoded = decode_file(path, false)?;
+    // Faces are found at full size, where the eyes have pixels to judge.
+    let faces = face_model
+        .map(|model| detect_faces(&decoded.image, model))
+        .transpose()?;
+    let thumbnail = DynamicImage::ImageRgba8(decoded.image)
+        .thumbnail(SHARPNESS_SAMPLE_SIZE, SHARPNESS_SAMPLE_SIZE);
+    let rgba = thumbnail.to_rgba8();
+    let counts = histogram(&rgba).luminosity;
+    let total = counts.iter().sum::<u32>().max(1) as f32;
+    // Pure black or white in every channel, not just luminosity, so
+    // saturated colours don't count as blown.
+    let (mut shadows, mut highlights) = (0u32, 0u32);
+    for px in rgba.pixels().filter(|px| px[3] > 0) {
+        match luminosity(px[0], px[1], px[2]) {
+            0 if px.0[..3].iter().all(|&v| v == 0) => shadows += 1,
+            255 if px.0[..3].iter().all(|&v| v == 255) => highlights += 1,
+            _ => {}
+        }
+    }
+    Ok(Inspected {
+        path: path.to_path_buf(),
+        sharpness: variance_of_laplacian(&thumbnail.to_luma8()),
+        clipped_shadows: shadows as f32 / total * 100.0,
+        clipped_highlights: highlights as f32 / total * 100.0,
+        hash: difference_hash(&thumbnail),
+        faces: faces.as_ref().map(Vec::len),
+        closed_eyes: faces
+            .iter()
+            .flatten()
+            .filter(|face| face.eyes_open == Some(false))
+            .count(),
+    })
+}
+
+fn verdict_for(image: &Inspected, rules: &CullRules) -> (CullVerdict, Vec<String>) {
+    let mut verdict = CullVerdict::Keep;
+    let mut reasons = Vec::new();
+    let mut flag = |level: CullVerdict, reason: String| {
+        verdict = verdict.max(level);
+        reasons.push(reason);
+    };
+    if image.sharpness < rules.reject_sharpness {
+        flag(
+            CullVerdict::Reject,
+            format!("Blurry (sharpness {:.0})", image.sharpness),
+        );
+    } else if image.sharpness < rules.review_sharpness {
+        flag(
+            CullVerdict::Review,
+            format!("Possibly soft (sharpness {:.0})", image.sharpness),
+        );
+    }
+    for (clipped, name) in [
+        (image.clipped_highlights, "highlights"),
+        (image.clipped_shadows, "shadows"),
+    ] {
+        if clipped > rules.reject_clipped_percent {
+            flag(
+                CullVerdict::Reject,
+                format!("{:.1}% of pixels are clipped {}", clipped, name),
+            );
+        } else if clipped > rules.review_clipped_percent {
+            flag(
+                CullVerdict::Review,
+                format!("{:.1}% of pixels are clipped {}", clipped, name),
+            );
+        }
+    }
+    // The eyes are a heuristic, so they only ever ask for a second look.
+    if let Some(faces) = image.faces {
+        if rules.review_closed_eyes && image.closed_eyes > 0 {
+            flag(
+                CullVerdict::Review,
+                format!(
+                    "Eyes may be closed ({} of {} faces)",
+                    image.closed_eyes, faces
+                ),
+            );
+        }
+    }
+    (verdict, reasons)
+}
+
+// Groups images whose hashes are within `max_distance` bits of any other in
+// the group, and returns for each image the group's best shot when that's
+// a different image.
+fn best_of_duplicates(images: &[Inspected], max_distance: u32) -> Vec<Option<usize>> {
+    let mut group: Vec<usize> = (0..images.len()).collect();
+    fn root(group: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for a in 0..images.len() {
+        for b in a + 1..images.len() {
+            if hamming_distance(images[a].hash, images[b].hash) <= max_distance {
+                let (ra, rb) = (root(&mut group, a), root(&mut group, b));
+                group[ra] = rb;
+            }
+        }
+    }
+    let roots: Vec<usize> = (0..images.len()).map(|i| root(&mut group, i)).collect();
+    let clipped = |i: usize| images[i].clipped_highlights + images[i].clipped_shadows;
+    (0..images.len())
+        .map(|i| {
+            // The sharpest, then least clipped, of the group.
+            let best = (0..images.len())
+                .filter(|&j| roots[j] == roots[i])
+                .max_by(|&a, &b| {
+                    images[a]
+                        .sharpness
+                        .total_cmp(&images[b].sharpness)
+                        .then(clipped(b).total_cmp(&clipped(a)))
+                })
+                .unwrap_or(i);
+            (best != i).then_some(best)
+        })
+        .collect()
+}
+
+// Sorts the images in `dir` into keep, review and reject lists by
+// `rules`. Near-duplicates other than the best of each group go to review,
+// since only a person can tell which expression or moment to keep. Faces
+// are checked for closed eyes only with a `face_model` to find them.
+pub fn cull_folder(
+    dir: &str,
+    rules: &CullRules,
+    face_model: Option<&Path>,
+) -> Result<CullReport, AppError> {
+    if let Some(model) = face_model {
+        check_model(model)?;
+    }
+    let results: Vec<(PathBuf, Result<Inspected, AppError>)> = list_images(dir)?
+        .into_par_iter()
+        .map(|path| {
+            let inspected = inspect(&path, face_model);
+            (path, inspected)
+        })
+        .collect();
+
+    let mut report = CullReport {
+        keep: Vec::new(),
+        review: Vec::new(),
+        reject: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut images = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(image) => images.push(image),
+            Err(e) => report.skipped.push(SkippedFile {
+                path: path.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    let best = match rules.duplicate_distance {
+        Some(distance) => best_of_duplicates(&images, distance),
+        None => vec![None; images.len()],
+    };
+    for (image, best) in images.iter().zip(best) {
+        let (mut verdict, mut reasons) = verdict_for(image, rules);
+        let duplicate_of = best.map(|b| images[b].path.to_string_lossy().into_owned());
+        if let Some(original) = &duplicate_of {
+            verdict = verdict.max(CullVerdict::Review);
+            reasons.push(format!(
+                "Near-duplicate of {}, the best of its group",
+                original
+            ));
+        }
+        let entry = CullEntry {
+            path: image.path.to_string_lossy().into_owned(),
+            verdict,
+            reasons,
+            sharpness: image.sharpness,
+            clipped_shadows: image.clipped_shadows,
+            clipped_highlights: image.clipped_highlights,
+            faces: image.faces,
+            closed_eyes: image.closed_eyes,
+            duplicate_of,
+        };
+        match verdict {
+            CullVerdict::Keep => report.keep.push(entry),
+            CullVerdict::Review => report.review.push(entry),
+            CullVerdict::Reject => report.reject.push(entry),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,158 @@
+use std::io::Cursor;
+
+use image::RgbaImage;
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::tags::Tag as TiffTag;
+
+use super::describe_icc_profile;
+use crate::models::{ColorConversion, ColorKind, SourceColorType};
+
+const TIFF_ICC_PROFILE: u16 = 34675;
+
+pub struct CmykImage {
+    pub image: RgbaImage,
+    pub source: SourceColorType,
+}
+
+// Photoshop marks its (inverted) CMYK JPEGs with an Adobe APP14 segment; most
+// other writers store ink amounts directly.
+fn has_adobe_marker(bytes: &[u8]) -> bool {
+    let mut i = 2;
+    while i + 4 <= bytes.len() && bytes[i] == 0xFF {
+        let marker = bytes[i + 1];
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        if marker == 0xEE && bytes[i + 4..].starts_with(b"Adobe") {
+            return true;
+        }
+        i += 2 + length;
+    }
+    false
+}
+
+#[cfg(feature = "color-management")]
+fn icc_to_srgb(pixels: &[[u8; 4]], icc: &[u8]) -> Option<Vec<[u8; 3]>> {
+    use lcms2::{Intent, PixelFormat, Profile, Transform};
+
+    let input = Profile::new_icc(icc).ok()?;
+    let transform = Transform::new(
+        &input,
+        PixelFormat::CMYK_8,
+        &Profile::new_srgb(),
+        PixelFormat::RGB_8,
+        Intent::Perceptual,
+    )
+    .ok()?;
+    let mut rgb = vec![[0u8; 3]; pixels.len()];
+    transform.transform_pixels(pixels, &mut rgb);
+    Some(rgb)
+}
+
+// Without a colour management engine every file gets the naive conversion.
+#[cfg(not(feature = "color-management"))]
+fn icc_to_srgb(_pixels: &[[u8; 4]], _icc: &[u8]) -> Option<Vec<[u8; 3]>> {
+    None
+}
+
+// `ink` holds C, M, Y, K ink amounts (0 = no ink).
+pub(super) fn cmyk_to_rgba(
+    ink: &[u8],
+    width: u32,
+    height: u32,
+    icc: Option<&[u8]>,
+) -> (RgbaImage, ColorConversion) {
+    let pixels: Vec<[u8; 4]> = ink
+        .chunks_exact(4)
+        .map(|p| [p[0], p[1], p[2], p[3]])
+        .collect();
+
+    let (rgb, conversion) = match icc.and_then(|data| icc_to_srgb(&pixels, data)) {
+        Some(rgb) => (rgb, ColorConversion::Icc),
+        None => {
+            let rgb = pixels
+                .iter()
+                .map(|&[c, m, y, k]| {
+                    let white = 255 - k as u16;
+                    let channel = |ink: u8| ((255 - ink as u16) * white / 255) as u8;
+                    [channel(c), channel(m), channel(y)]
+                })
+                .collect();
+            (rgb, ColorConversion::Naive)
+        }
+    };
+
+    let data = rgb.iter().flat_map(|&[r, g, b]| [r, g, b, 255]).collect();
+    let image = RgbaImage::from_raw(width, height, data).expect("buffer matches dimensions");
+    (image, conversion)
+}
+
+// Reads just the header.
+pub fn is_cmyk_jpeg(bytes: &[u8]) -> bool {
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(bytes));
+    decoder.read_info().is_ok()
+        && decoder
+            .info()
+            .is_some_and(|info| info.pixel_format == jpeg_decoder::PixelFormat::CMYK32)
+}
+
+pub fn decode_cmyk_jpeg(bytes: &[u8]) -> Option<CmykImage> {
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(bytes));
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    if info.pixel_format != jpeg_decoder::PixelFormat::CMYK32 {
+        return None;
+    }
+
+    let mut ink = decoder.decode().ok()?;
+    // The decoder always undoes Adobe's inversion; re-invert files that were
+    // never inverted in the first place.
+    if !has_adobe_marker(bytes) {
+        ink.iter_mut().for_each(|v| *v = 255 - *v);
+    }
+    let icc = decoder.icc_profile();
+    let (width, height) = (info.width as u32, info.height as u32);
+    let (image, conversion) = cmyk_to_rgba(&ink, width, height, icc.as_deref());
+
+    Some(CmykImage {
+        image,
+        source: SourceColorType {
+            container: "jpeg".to_string(),
+            kind: ColorKind::Cmyk,
+            bit_depth: 8,
+            interlaced: false,
+            palette_size: None,
+            conversion: Some(conversion),
+            icc_profile: icc.as_deref().and_then(describe_icc_profile),
+        },
+    })
+}
+
+pub fn decode_cmyk_tiff(bytes: &[u8]) -> Option<CmykImage> {
+    let mut decoder = TiffDecoder::new(Cursor::new(bytes)).ok()?;
+    if decoder.colortype().ok()? != tiff::ColorType::CMYK(8) {
+        return None;
+    }
+    let (width, height) = decoder.dimensions().ok()?;
+    let icc = decoder
+        .get_tag_u8_vec(TiffTag::Unknown(TIFF_ICC_PROFILE))
+        .ok();
+    let DecodingResult::U8(ink) = decoder.read_image().ok()? else {
+        return None;
+    };
+    let (image, conversion) = cmyk_to_rgba(&ink, width, height, icc.as_deref());
+
+    Some(CmykImage {
+        image,
+        source: SourceColorType {
+            container: "tiff".to_string(),
+            kind: ColorKind::Cmyk,
+            bit_depth: 8,
+            interlaced: false,
+            palette_size: None,
+            conversion: Some(conversion),
+            icc_profile: icc.as_deref().and_then(describe_icc_profile),
+        },
+    })
+}
@@ -0,0 +1,176 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frames, ImageFormat, RgbaImage};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::ColorType as TiffColor;
+
+use super::cmyk::cmyk_to_rgba;
+use super::{decode_bytes, decode_error, is_raw_file, DecodedImage};
+use crate::error::AppError;
+use crate::models::ImageFrames;
+
+// The frames of an animated GIF, PNG or WebP, each composited onto the ones
+// before it as a viewer would show it. `None` for still images.
+fn animation<'a>(bytes: &'a [u8], format: ImageFormat) -> Option<Frames<'a>> {
+    let reader = Cursor::new(bytes);
+    match format {
+        ImageFormat::Gif => Some(GifDecoder::new(reader).ok()?.into_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader).ok()?;
+            decoder.is_apng().then(|| decoder.apng().into_frames())
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader).ok()?;
+            decoder.has_animation().then(|| decoder.into_frames())
+        }
+        _ => None,
+    }
+}
+
+fn tiff_error(path: &Path, error: tiff::TiffError) -> AppError {
+    AppError::DecodeFailed {
+        path: Some(path.display().to_string()),
+        reason: error.to_string(),
+    }
+}
+
+fn tiff_page_count(bytes: &[u8], path: &Path) -> Result<usize, AppError> {
+    let mut decoder = TiffDecoder::new(Cursor::new(bytes)).map_err(|e| tiff_error(path, e))?;
+    let mut pages = 1;
+    while decoder.more_images() {
+        decoder.next_image().map_err(|e| tiff_error(path, e))?;
+        pages += 1;
+    }
+    Ok(pages)
+}
+
+// Pages after the first, which `image` can't reach. 16-bit samples are cut
+// to their high byte; layouts other than gray, RGB and CMYK are refused.
+fn tiff_page(bytes: &[u8], path: &Path, index: usize) -> Result<RgbaImage, AppError> {
+    let mut decoder = TiffDecoder::new(Cursor::new(bytes)).map_err(|e| tiff_error(path, e))?;
+    decoder
+        .seek_to_image(index)
+        .map_err(|e| tiff_error(path, e))?;
+    let (width, height) = decoder.dimensions().map_err(|e| tiff_error(path, e))?;
+    let color = decoder.colortype().map_err(|e| tiff_error(path, e))?;
+    let samples = match decoder.read_image().map_err(|e| tiff_error(path, e))? {
+        DecodingResult::U8(samples) => samples,
+        DecodingResult::U16(samples) => samples.iter().map(|s| (s >> 8) as u8).collect(),
+        _ => Vec::new(),
+    };
+    let unsupported = || AppError::UnsupportedFormat {
+        format: format!("TIFF page with {:?} samples", color),
+    };
+    let channels = match color {
+        TiffColor::Gray(8 | 16) => 1,
+        TiffColor::GrayA(8 | 16) => 2,
+        TiffColor::RGB(8 | 16) => 3,
+        TiffColor::RGBA(8 | 16) => 4,
+        TiffColor::CMYK(8) => return Ok(cmyk_to_rgba(&samples, width, height, None).0),
+        _ => return Err(unsupported()),
+    };
+    if samples.len() != (width * height) as usize * channels {
+        return Err(unsupported());
+    }
+    let rgba = samples
+        .chunks_exact(channels)
+        .flat_map(|px| match channels {
+            1 => [px[0], px[0], px[0], 255],
+            2 => [px[0], px[0], px[0], px[1]],
+            3 => [px[0], px[1], px[2], 255],
+            _ => [px[0], px[1], px[2], px[3]],
+        })
+        .collect();
+    Ok(RgbaImage::from_raw(width, height, rgba).expect("buffer matches dimensions"))
+}
+
+fn out_of_range(index: usize, count: usize) -> AppError {
+    if count == 1 {
+        return AppError::invalid("The image has only one frame");
+    }
+    AppError::invalid(format!(
+        "Frame {} is out of range; the image has {} frames",
+        index, count
+    ))
+}
+
+// How many frames or pages the file holds and how long each is shown. Every
+// frame of an animation is decoded to find its delay, so this costs about as
+// much as reading them all.
+pub fn frame_info(path: &Path) -> Result<ImageFrames, AppError> {
+    let bytes = std::fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let format = image::guess_format(&bytes).ok();
+    let (mut delays, mut size) = (Vec::new(), None);
+    let animated = match format.and_then(|format| animation(&bytes, format)) {
+        Some(frames) => {
+            for frame in frames {
+                let frame = frame.map_err(|e| decode_error(e, Some(path)))?;
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                delays.push((numer as f64 / denom.max(1) as f64).round() as u32);
+                size.get_or_insert(frame.buffer().dimensions());
+            }
+            true
+        }
+        None => false,
+    };
+    let (width, height) = match size {
+        Some(size) => size,
+        None => decode_bytes(&bytes, Some(path), false)?.image.dimensions(),
+    };
+    if !animated {
+        let pages = match format {
+            Some(ImageFormat::Tiff) if !is_raw_file(path) => tiff_page_count(&bytes, path)?,
+            _ => 1,
+        };
+        delays = vec![0; pages];
+    }
+    Ok(ImageFrames {
+        path: path.to_string_lossy().into_owned(),
+        frame_count: delays.len(),
+        delays,
+        width,
+        height,
+        animated,
+    })
+}
+
+// Frame `index` of an animation, composited as shown at that point, or page
+// `index` of a multi-page TIFF. Frame 0 of any image is its usual decode;
+// later frames carry no source layout.
+pub fn decode_frame(path: &Path, index: usize) -> Result<DecodedImage, AppError> {
+    let bytes = std::fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    if index == 0 {
+        return decode_bytes(&bytes, Some(path), false);
+    }
+    let format = image::guess_format(&bytes).ok();
+    let image = if let Some(frames) = format.and_then(|format| animation(&bytes, format)) {
+        let mut count = 0;
+        let mut found = None;
+        for frame in frames {
+            let frame = frame.map_err(|e| decode_error(e, Some(path)))?;
+            if count == index {
+                found = Some(frame.into_buffer());
+                break;
+            }
+            count += 1;
+        }
+        found.ok_or_else(|| out_of_range(index, count))?
+    } else if format == Some(ImageFormat::Tiff) && !is_raw_file(path) {
+        let count = tiff_page_count(&bytes, path)?;
+        if index >= count {
+            return Err(out_of_range(index, count));
+        }
+        tiff_page(&bytes, path, index)?
+    } else {
+        return Err(out_of_range(index, 1));
+    };
+    Ok(DecodedImage {
+        image,
+        recovered: false,
+        source: None,
+    })
+}
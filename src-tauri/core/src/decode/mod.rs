@@ -0,0 +1,504 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegDecoder;
+use image::{DynamicImage, ImageError, ImageFormat, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{ColorKind, ImageVerification, SourceColorType};
+
+mod cmyk;
+pub mod frames;
+#[cfg(feature = "raw")]
+mod raw;
+
+use cmyk::{decode_cmyk_jpeg, decode_cmyk_tiff, is_cmyk_jpeg};
+#[cfg(feature = "raw")]
+use raw::load_raw;
+#[cfg(feature = "raw")]
+pub use raw::load_raw_mosaic;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_EOI: [u8; 2] = [0xFF, 0xD9];
+
+pub struct DecodedImage {
+    pub image: RgbaImage,
+    // True when the file was damaged and only part of it could be decoded.
+    pub recovered: bool,
+    pub source: Option<SourceColorType>,
+}
+
+// Camera RAW formats, recognised by extension since most are TIFF inside.
+pub const RAW_EXTENSIONS: &[&str] = &[
+    "3fr", "arw", "cr2", "crw", "dcr", "dng", "erf", "iiq", "kdc", "mef", "mos", "mrw", "nef",
+    "nrw", "orf", "pef", "raf", "rw2", "sr2", "srf", "srw",
+];
+
+pub fn is_raw_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| RAW_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[cfg(not(feature = "raw"))]
+fn load_raw(_bytes: &[u8], _path: Option<&Path>) -> Result<DecodedImage, AppError> {
+    Err(AppError::UnsupportedFormat {
+        format: "camera RAW (this build was made without RAW support)".to_string(),
+    })
+}
+
+#[cfg(not(feature = "raw"))]
+pub fn load_raw_mosaic(
+    _path: &Path,
+    _colorize: bool,
+) -> Result<crate::models::RawMosaic, AppError> {
+    Err(AppError::UnsupportedFormat {
+        format: "camera RAW (this build was made without RAW support)".to_string(),
+    })
+}
+
+pub fn decode_file(path: &Path, recover: bool) -> Result<DecodedImage, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    decode_bytes(&bytes, Some(path), recover)
+}
+
+// `path` lets formats that can't be sniffed from their bytes fall back to the
+// file extension.
+pub fn decode_bytes(
+    bytes: &[u8],
+    path: Option<&Path>,
+    recover: bool,
+) -> Result<DecodedImage, AppError> {
+    if path.is_some_and(is_raw_file) {
+        return load_raw(bytes, path);
+    }
+    let format = image::guess_format(bytes)
+        .ok()
+        .or_else(|| path.and_then(|p| ImageFormat::from_path(p).ok()));
+
+    // `image` converts CMYK naively and assumes every CMYK JPEG is
+    // Adobe-inverted, so handle those files ourselves.
+    let cmyk = match format {
+        Some(ImageFormat::Jpeg) => decode_cmyk_jpeg(bytes),
+        Some(ImageFormat::Tiff) => decode_cmyk_tiff(bytes),
+        _ => None,
+    };
+    if let Some(cmyk) = cmyk {
+        return Ok(DecodedImage {
+            image: cmyk.image,
+            recovered: false,
+            source: Some(cmyk.source),
+        });
+    }
+
+    let decoded = match format {
+        Some(format) => image::load_from_memory_with_format(bytes, format),
+        None => image::load_from_memory(bytes),
+    };
+
+    let source = format.and_then(|format| match format {
+        ImageFormat::Png => png_color_type(bytes),
+        _ => None,
+    });
+
+    let source = source
+        .or_else(|| {
+            let format = format?;
+            decoded.as_ref().ok().map(|img| color_type_of(img, format))
+        })
+        .map(|mut source| {
+            source.icc_profile = format
+                .and_then(|format| read_icc_profile(bytes, format))
+                .and_then(|icc| describe_icc_profile(&icc));
+            source
+        });
+
+    match decoded {
+        Ok(img) => Ok(DecodedImage {
+            source,
+            image: img.to_rgba8(),
+            recovered: false,
+        }),
+        Err(e) if recover => recover_partial(bytes, format)
+            .map(|image| DecodedImage {
+                image,
+                recovered: true,
+                source,
+            })
+            .ok_or_else(|| decode_error(e, path)),
+        Err(e) => Err(decode_error(e, path)),
+    }
+}
+
+fn decode_error(error: ImageError, path: Option<&Path>) -> AppError {
+    match error {
+        ImageError::Unsupported(e) => AppError::UnsupportedFormat {
+            format: e.format_hint().to_string(),
+        },
+        e => AppError::DecodeFailed {
+            path: path.map(|p| p.display().to_string()),
+            reason: e.to_string(),
+        },
+    }
+}
+
+#[cfg(feature = "color-management")]
+pub fn describe_icc_profile(data: &[u8]) -> Option<String> {
+    let profile = lcms2::Profile::new_icc(data).ok()?;
+    let name = profile
+        .info(lcms2::InfoType::Description, lcms2::Locale::none())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    Some(name.unwrap_or_else(|| "Unnamed profile".to_string()))
+}
+
+#[cfg(not(feature = "color-management"))]
+pub fn describe_icc_profile(_data: &[u8]) -> Option<String> {
+    None
+}
+
+pub(crate) fn read_icc_profile(bytes: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    use image::codecs::png::PngDecoder;
+    use image::codecs::tiff::TiffDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::ImageDecoder;
+
+    let cursor = Cursor::new(bytes);
+    match format {
+        ImageFormat::Png => PngDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::Jpeg => JpegDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(cursor).ok()?.icc_profile(),
+        _ => None,
+    }
+}
+
+pub(crate) fn format_name(format: ImageFormat) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+// PNG keeps palette and interlace details that `image` discards on decode, so
+// read them straight from the IHDR and PLTE chunks.
+fn png_color_type(bytes: &[u8]) -> Option<SourceColorType> {
+    if bytes.len() < 33 || !bytes.starts_with(PNG_SIGNATURE) || &bytes[12..16] != b"IHDR" {
+        return None;
+    }
+    let bit_depth = bytes[24];
+    let kind = match bytes[25] {
+        0 => ColorKind::Gray,
+        2 => ColorKind::Rgb,
+        3 => ColorKind::Indexed,
+        4 => ColorKind::GrayAlpha,
+        6 => ColorKind::Rgba,
+        _ => return None,
+    };
+    let interlaced = bytes[28] == 1;
+
+    let mut palette_size = None;
+    let mut offset = PNG_SIGNATURE.len();
+    while kind == ColorKind::Indexed && offset + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        match &bytes[offset + 4..offset + 8] {
+            b"PLTE" => {
+                palette_size = Some((length / 3) as u16);
+                break;
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => offset += 12 + length,
+        }
+    }
+
+    Some(SourceColorType {
+        container: format_name(ImageFormat::Png),
+        kind,
+        bit_depth,
+        interlaced,
+        palette_size,
+        conversion: None,
+        icc_profile: None,
+    })
+}
+
+fn color_type_of(img: &DynamicImage, format: ImageFormat) -> SourceColorType {
+    use image::ColorType;
+
+    let color = img.color();
+    let kind = match color {
+        ColorType::L8 | ColorType::L16 => ColorKind::Gray,
+        ColorType::La8 | ColorType::La16 => ColorKind::GrayAlpha,
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => ColorKind::Rgb,
+        _ => ColorKind::Rgba,
+    };
+    // GIF frames are always palette-based even though `image` expands them.
+    let kind = if format == ImageFormat::Gif {
+        ColorKind::Indexed
+    } else {
+        kind
+    };
+
+    SourceColorType {
+        container: format_name(format),
+        kind,
+        bit_depth: (color.bits_per_pixel() / color.channel_count() as u16) as u8,
+        interlaced: false,
+        palette_size: None,
+        conversion: None,
+        icc_profile: None,
+    }
+}
+
+fn recover_partial(bytes: &[u8], format: Option<ImageFormat>) -> Option<RgbaImage> {
+    match format? {
+        ImageFormat::Jpeg => recover_jpeg(bytes),
+        ImageFormat::Png => recover_png(bytes),
+        _ => None,
+    }
+}
+
+// The JPEG entropy decoder pads with zero bits once it meets a marker, so
+// terminating a truncated stream with EOI yields the scans that did arrive and
+// flat grey for the rest.
+fn recover_jpeg(bytes: &[u8]) -> Option<RgbaImage> {
+    let mut patched = bytes.to_vec();
+    patched.extend_from_slice(&JPEG_EOI);
+    image::load_from_memory_with_format(&patched, ImageFormat::Jpeg)
+        .ok()
+        .map(|img| img.to_rgba8())
+}
+
+// Decode rows until the stream breaks; rows that never arrived stay transparent.
+fn recover_png(bytes: &[u8]) -> Option<RgbaImage> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let info = reader.info();
+    // Interlaced rows arrive as partial passes that can't be placed without the
+    // rest of the image.
+    if info.interlaced {
+        return None;
+    }
+    let (width, height) = (info.width, info.height);
+    let (color_type, _) = reader.output_color_type();
+    let channels = color_type.samples();
+
+    let mut out = RgbaImage::new(width, height);
+    let mut y = 0;
+    while y < height {
+        let Ok(Some(row)) = reader.next_row() else {
+            break;
+        };
+        for (x, sample) in row
+            .data()
+            .chunks_exact(channels)
+            .enumerate()
+            .take(width as usize)
+        {
+            let rgba = match channels {
+                1 => [sample[0], sample[0], sample[0], 255],
+                2 => [sample[0], sample[0], sample[0], sample[1]],
+                3 => [sample[0], sample[1], sample[2], 255],
+                _ => [sample[0], sample[1], sample[2], sample[3]],
+            };
+            out.put_pixel(x as u32, y, image::Rgba(rgba));
+        }
+        y += 1;
+    }
+
+    (y > 0).then_some(out)
+}
+
+fn check_jpeg_structure(bytes: &[u8], issues: &mut Vec<String>) -> bool {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        issues.push("Missing JPEG start-of-image marker".to_string());
+    }
+    // Some writers pad after EOI, so ignore trailing zero bytes.
+    let end = bytes
+        .iter()
+        .rposition(|&b| b != 0)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let truncated = !bytes[..end].ends_with(&JPEG_EOI);
+    if truncated {
+        issues.push("JPEG is truncated: end-of-image marker not found".to_string());
+    }
+    truncated
+}
+
+fn check_png_structure(bytes: &[u8], issues: &mut Vec<String>) -> bool {
+    let mut offset = PNG_SIGNATURE.len();
+    loop {
+        if offset + 8 > bytes.len() {
+            issues.push("PNG is truncated: IEND chunk not found".to_string());
+            return true;
+        }
+        let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        let chunk_type = &bytes[offset + 4..offset + 8];
+        let name = String::from_utf8_lossy(chunk_type).into_owned();
+        let data_end = offset + 8 + length;
+        if data_end + 4 > bytes.len() {
+            issues.push(format!("PNG is truncated inside the {} chunk", name));
+            return true;
+        }
+
+        let stored_crc = u32::from_be_bytes(bytes[data_end..data_end + 4].try_into().unwrap());
+        if crc32fast::hash(&bytes[offset + 4..data_end]) != stored_crc {
+            issues.push(format!("CRC mismatch in {} chunk at byte {}", name, offset));
+        }
+        if chunk_type == b"IEND" {
+            return false;
+        }
+        offset = data_end + 4;
+    }
+}
+
+pub fn verify_file(path: &Path) -> Result<ImageVerification, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let format = image::guess_format(&bytes).ok();
+    let mut issues = Vec::new();
+
+    let truncated = match format {
+        Some(ImageFormat::Jpeg) => check_jpeg_structure(&bytes, &mut issues),
+        Some(ImageFormat::Png) => check_png_structure(&bytes, &mut issues),
+        Some(_) => false,
+        None => {
+            issues.push("Unrecognized image format".to_string());
+            false
+        }
+    };
+
+    let decoded = match format {
+        Some(format) => image::load_from_memory_with_format(&bytes, format),
+        None => image::open(path),
+    };
+    let (width, height, decodes) = match decoded {
+        Ok(img) => (Some(img.width()), Some(img.height()), true),
+        Err(e) => {
+            issues.push(format!("Decode failed: {}", e));
+            (None, None, false)
+        }
+    };
+
+    let recoverable = !decodes && recover_partial(&bytes, format).is_some();
+
+    Ok(ImageVerification {
+        path: path.to_string_lossy().into_owned(),
+        format: format.map(format_name),
+        valid: issues.is_empty(),
+        decodes,
+        truncated,
+        recoverable,
+        width,
+        height,
+        issues,
+    })
+}
+
+pub struct JpegScanLayout {
+    pub progressive: bool,
+    // Byte offset just past each complete scan's entropy-coded data.
+    pub scan_ends: Vec<usize>,
+}
+
+// Walks the marker segments of a (possibly still incomplete) JPEG buffer.
+pub fn jpeg_scan_layout(bytes: &[u8]) -> JpegScanLayout {
+    let mut layout = JpegScanLayout {
+        progressive: false,
+        scan_ends: Vec::new(),
+    };
+    let is_rst = |m: u8| (0xD0..=0xD7).contains(&m);
+
+    let mut i = 2;
+    while i + 1 < bytes.len() {
+        if bytes[i] != 0xFF {
+            i += 1;
+            continue;
+        }
+        let marker = bytes[i + 1];
+        match marker {
+            0xFF => i += 1,
+            0xD8 | 0x01 => i += 2,
+            m if is_rst(m) => i += 2,
+            0xD9 => break,
+            _ => {
+                if i + 4 > bytes.len() {
+                    break;
+                }
+                let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+                if marker == 0xC2 {
+                    layout.progressive = true;
+                }
+                if marker != 0xDA {
+                    i += 2 + length;
+                    continue;
+                }
+
+                // Entropy-coded data runs until the next non-RST marker.
+                let mut j = i + 2 + length;
+                let mut complete = false;
+                while j + 1 < bytes.len() {
+                    if bytes[j] == 0xFF && bytes[j + 1] != 0 && !is_rst(bytes[j + 1]) {
+                        complete = true;
+                        break;
+                    }
+                    j += 1;
+                }
+                if !complete {
+                    break;
+                }
+                layout.scan_ends.push(j);
+                i = j;
+            }
+        }
+    }
+
+    layout
+}
+
+// Decodes the scans that end before `end` at reduced size using DCT scaling.
+pub fn decode_jpeg_prefix(bytes: &[u8], end: usize, max_dimension: u32) -> Option<RgbaImage> {
+    let mut prefix = bytes[..end.min(bytes.len())].to_vec();
+    prefix.extend_from_slice(&JPEG_EOI);
+    decode_jpeg_scaled(&prefix, max_dimension)
+}
+
+// Decodes at the smallest of 1/8, 1/4, 1/2 or full size that still covers
+// `max_dimension`, reading the DCT coefficients without the full inverse
+// transform, then shrinks the rest of the way.
+fn decode_jpeg_scaled(bytes: &[u8], max_dimension: u32) -> Option<RgbaImage> {
+    let mut decoder = JpegDecoder::new(Cursor::new(bytes)).ok()?;
+    let requested = max_dimension.min(u16::MAX as u32) as u16;
+    decoder.scale(requested, requested).ok()?;
+    let img = DynamicImage::from_decoder(decoder).ok()?;
+    Some(img.thumbnail(max_dimension, max_dimension).to_rgba8())
+}
+
+// A copy of the file that fits within `max_size`, decoding no more than it
+// needs to; never scaled up. JPEGs other than CMYK ones, which need our own
+// conversion, are decoded at reduced size.
+pub fn decode_thumbnail(path: &Path, max_size: u32) -> Result<RgbaImage, AppError> {
+    if max_size == 0 {
+        return Err(AppError::invalid("Thumbnail size must be at least 1 pixel"));
+    }
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    if image::guess_format(&bytes).ok() == Some(ImageFormat::Jpeg) && !is_cmyk_jpeg(&bytes) {
+        let fits = image::io::Reader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .is_some_and(|(w, h)| w.max(h) <= max_size);
+        if !fits {
+            if let Some(img) = decode_jpeg_scaled(&bytes, max_size) {
+                return Ok(img);
+            }
+        }
+    }
+    let image = decode_bytes(&bytes, Some(path), false)?.image;
+    if image.width().max(image.height()) <= max_size {
+        return Ok(image);
+    }
+    Ok(DynamicImage::ImageRgba8(image)
+        .thumbnail(max_size, max_size)
+        .to_rgba8())
+}
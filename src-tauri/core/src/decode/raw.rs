@@ -0,0 +1,407 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::{imageops, RgbaImage};
+use rawloader::{RawImage, RawImageData, CFA};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{CfaChannelStats, CfaColor, ColorKind, ImageData, RawMosaic, SourceColorType};
+
+use super::DecodedImage;
+
+const CFA_COLORS: [CfaColor; 4] = [
+    CfaColor::Red,
+    CfaColor::Green,
+    CfaColor::Blue,
+    CfaColor::Emerald,
+];
+const HISTOGRAM_BINS: usize = 256;
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412_453, 0.357_580, 0.180_423],
+    [0.212_671, 0.715_160, 0.072_169],
+    [0.019_334, 0.119_193, 0.950_227],
+];
+
+// Sensor values scaled so black is 0 and clipping is 1, cropped to the
+// usable area; `cpp` values per pixel.
+fn normalized(raw: &RawImage) -> (Vec<f32>, usize, usize) {
+    let [top, right, bottom, left] = raw.crops;
+    let width = raw.width.saturating_sub(left + right).max(1);
+    let height = raw.height.saturating_sub(top + bottom).max(1);
+    let cpp = raw.cpp;
+    let value = |i: usize| match &raw.data {
+        RawImageData::Integer(data) => data[i] as f32,
+        RawImageData::Float(data) => data[i],
+    };
+    let float = matches!(raw.data, RawImageData::Float(_));
+    let mut out = vec![0f32; width * height * cpp];
+    out.par_chunks_mut(width * cpp)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let sy = y + top;
+            for x in 0..width {
+                let sx = x + left;
+                for c in 0..cpp {
+                    // Levels are per CFA colour for mosaics, per channel
+                    // otherwise.
+                    let channel = if cpp == 1 {
+                        raw.cfa.color_at(sy, sx).min(3)
+                    } else {
+                        c
+                    };
+                    let v = value((sy * raw.width + sx) * cpp + c);
+                    // Float DNGs are already normalised.
+                    row[x * cpp + c] = if float {
+                        v
+                    } else {
+                        let black = raw.blacklevels[channel] as f32;
+                        let white = (raw.whitelevels[channel] as f32).max(black + 1.0);
+                        (v - black) / (white - black)
+                    }
+                    .clamp(0.0, 1.0);
+                }
+            }
+        });
+    (out, width, height)
+}
+
+// Red, green and blue at every pixel by bilinear interpolation: each
+// missing colour is the mean of the nearest pixels of that colour in the
+// 3x3 neighbourhood. Works for any CFA, including X-Trans, whose 3x3
+// blocks always hold all three colours. Emerald and second-green sites
+// count as green.
+fn demosaic(mosaic: &[f32], width: usize, height: usize, cfa: &CFA) -> Vec<f32> {
+    let color = |x: usize, y: usize| cfa.color_at(y, x).min(3) % 3;
+    let mut out = vec![0f32; width * height * 3];
+    out.par_chunks_mut(width * 3)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width {
+                let own = color(x, y);
+                let mut sum = [0f32; 3];
+                let mut count = [0u32; 3];
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        let c = color(nx, ny);
+                        sum[c] += mosaic[ny * width + nx];
+                        count[c] += 1;
+                    }
+                }
+                for c in 0..3 {
+                    row[x * 3 + c] = if c == own {
+                        mosaic[y * width + x]
+                    } else if count[c] > 0 {
+                        sum[c] / count[c] as f32
+                    } else {
+                        0.0
+                    };
+                }
+            }
+        });
+    out
+}
+
+// Multipliers that make a white surface neutral: the camera's as-shot
+// balance, or daylight for files that don't record one.
+fn white_balance(raw: &RawImage) -> [f32; 3] {
+    let usable = |wb: &[f32; 4]| wb[..3].iter().all(|v| v.is_finite() && *v > 0.0);
+    [raw.wb_coeffs, raw.neutralwb()]
+        .iter()
+        .find(|wb| usable(wb))
+        .map(|wb| [wb[0] / wb[1], 1.0, wb[2] / wb[1]])
+        .unwrap_or([1.0; 3])
+}
+
+// Camera RGB to linear sRGB, built as dcraw does: the camera's response to
+// each sRGB primary, scaled so camera white is sRGB white, then inverted.
+// Without a usable matrix the channels are taken as sRGB already.
+fn camera_to_srgb(raw: &RawImage) -> [[f32; 3]; 3] {
+    let mut cam_rgb = [[0f32; 3]; 4];
+    for (i, row) in cam_rgb.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3)
+                .map(|k| raw.xyz_to_cam[i][k] * SRGB_TO_XYZ[k][j])
+                .sum();
+        }
+        let sum: f32 = row.iter().sum();
+        if sum != 0.0 {
+            row.iter_mut().for_each(|v| *v /= sum);
+        }
+    }
+    let inverse = RawImage::pseudoinverse(cam_rgb);
+    let matrix = inverse.map(|row| [row[0], row[1], row[2]]);
+    let valid = matrix.iter().flatten().all(|v| v.is_finite())
+        && cam_rgb[..3].iter().all(|row| row.iter().any(|v| *v != 0.0));
+    if valid {
+        matrix
+    } else {
+        IDENTITY
+    }
+}
+
+fn decode_raw(bytes: &[u8], path: Option<&Path>) -> Result<RawImage, AppError> {
+    rawloader::decode(&mut Cursor::new(bytes)).map_err(|e| AppError::DecodeFailed {
+        path: path.map(|p| p.display().to_string()),
+        reason: e.to_string(),
+    })
+}
+
+fn srgb_gamma(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let encoded = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+fn orient(img: RgbaImage, raw: &RawImage) -> RgbaImage {
+    // Flips come before the transpose.
+    let (transpose, flip_x, flip_y) = raw.orientation.to_flips();
+    let mut img = img;
+    if flip_x {
+        img = imageops::flip_horizontal(&img);
+    }
+    if flip_y {
+        img = imageops::flip_vertical(&img);
+    }
+    if transpose {
+        img = imageops::flip_horizontal(&imageops::rotate90(&img));
+    }
+    img
+}
+
+// Develops a camera RAW file into sRGB with its as-shot white balance, no
+// exposure compensation and bilinear demosaicing: enough to analyse its
+// histogram and colours, not a substitute for a RAW editor.
+pub fn load_raw(bytes: &[u8], path: Option<&Path>) -> Result<DecodedImage, AppError> {
+    let raw = decode_raw(bytes, path)?;
+    let (values, width, height) = normalized(&raw);
+    let monochrome = raw.is_monochrome();
+    let rgb = match raw.cpp {
+        1 if monochrome => values.iter().flat_map(|&v| [v; 3]).collect(),
+        1 => demosaic(&values, width, height, &raw.cropped_cfa()),
+        _ => values
+            .chunks_exact(raw.cpp)
+            .flat_map(|px| [px[0], px[1.min(raw.cpp - 1)], px[2.min(raw.cpp - 1)]])
+            .collect(),
+    };
+
+    let (wb, matrix) = if monochrome {
+        ([1.0; 3], IDENTITY)
+    } else {
+        (white_balance(&raw), camera_to_srgb(&raw))
+    };
+    let mut pixels = vec![0u8; width * height * 4];
+    pixels
+        .par_chunks_mut(4)
+        .zip(rgb.par_chunks(3))
+        .for_each(|(out, px)| {
+            // Channels clip after balancing so blown highlights stay
+            // white instead of turning magenta.
+            let balanced: [f32; 3] = std::array::from_fn(|c| (px[c] * wb[c]).min(1.0));
+            for (c, row) in matrix.iter().enumerate() {
+                let v: f32 = (0..3).map(|k| row[k] * balanced[k]).sum();
+                out[c] = srgb_gamma(v);
+            }
+            out[3] = 255;
+        });
+    let image = RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .expect("buffer matches dimensions");
+
+    let white = raw.whitelevels.iter().copied().max().unwrap_or(u16::MAX);
+    let bit_depth = match raw.data {
+        RawImageData::Float(_) => 32,
+        RawImageData::Integer(_) => (16 - white.leading_zeros()).max(8) as u8,
+    };
+    let container = path
+        .and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_else(|| "raw".to_string());
+    Ok(DecodedImage {
+        image: orient(image, &raw),
+        recovered: false,
+        source: Some(SourceColorType {
+            container,
+            kind: if monochrome {
+                ColorKind::Gray
+            } else {
+                ColorKind::Rgb
+            },
+            bit_depth,
+            interlaced: false,
+            palette_size: None,
+            conversion: None,
+            icc_profile: None,
+        }),
+    })
+}
+
+// The undemosaiced sensor data of the RAW file at `path`, with a histogram
+// per filter colour for judging exposure at the sensor. The image shows each
+// site's level gamma-encoded so shadows are visible, tinted in its filter
+// colour when `colorize` is set. Monochrome sensors and RAW files that are
+// stored already demosaiced have no mosaic to show.
+pub fn load_raw_mosaic(path: &Path, colorize: bool) -> Result<RawMosaic, AppError> {
+    let bytes = std::fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let raw = decode_raw(&bytes, Some(path))?;
+    if raw.cpp != 1 || raw.is_monochrome() || !raw.cfa.is_valid() {
+        return Err(AppError::invalid(
+            "The file holds no colour filter mosaic; it is monochrome or already demosaiced",
+        ));
+    }
+    let (values, width, height) = normalized(&raw);
+    let cfa = raw.cropped_cfa();
+    let color = |x: usize, y: usize| cfa.color_at(y, x).min(3);
+
+    let mut histograms = vec![[0u32; HISTOGRAM_BINS]; 4];
+    let mut sums = [0f64; 4];
+    for (i, &v) in values.iter().enumerate() {
+        let c = color(i % width, i / width);
+        histograms[c][(v * (HISTOGRAM_BINS - 1) as f32).round() as usize] += 1;
+        sums[c] += v as f64;
+    }
+    let channels = histograms
+        .into_iter()
+        .enumerate()
+        .filter_map(|(c, histogram)| {
+            let sites: u64 = histogram.iter().map(|&n| n as u64).sum();
+            (sites > 0).then(|| CfaChannelStats {
+                color: CFA_COLORS[c],
+                sites,
+                black_level: raw.blacklevels[c],
+                white_level: raw.whitelevels[c],
+                mean: (sums[c] / sites as f64) as f32,
+                clipped: histogram[HISTOGRAM_BINS - 1] as f32 / sites as f32,
+                histogram: histogram.to_vec(),
+            })
+        })
+        .collect();
+
+    let mut pixels = vec![0u8; width * height * 4];
+    pixels
+        .par_chunks_mut(width * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, out) in row.chunks_exact_mut(4).enumerate() {
+                let level = srgb_gamma(values[y * width + x]);
+                let tint = match color(x, y) {
+                    _ if !colorize => [true; 3],
+                    0 => [true, false, false],
+                    1 => [false, true, false],
+                    2 => [false, false, true],
+                    _ => [false, true, true],
+                };
+                for c in 0..3 {
+                    out[c] = if tint[c] { level } else { 0 };
+                }
+                out[3] = 255;
+            }
+        });
+    Ok(RawMosaic {
+        image: ImageData {
+            width: width as u32,
+            height: height as u32,
+            data: pixels,
+            format: "rgba".to_string(),
+            recovered: false,
+            source_color_type: None,
+        },
+        pattern: cfa.name.clone(),
+        channels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rawloader::Orientation;
+
+    // sRGB's own XYZ to RGB matrix, for a camera that sees as sRGB does.
+    const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+        [3.2406, -1.5372, -0.4986],
+        [-0.9689, 1.8758, 0.0415],
+        [0.0557, -0.2040, 1.0570],
+    ];
+
+    fn raw_with_matrix(xyz_to_cam: [[f32; 3]; 4]) -> RawImage {
+        RawImage {
+            make: String::new(),
+            model: String::new(),
+            clean_make: String::new(),
+            clean_model: String::new(),
+            width: 2,
+            height: 2,
+            cpp: 1,
+            wb_coeffs: [1.0; 4],
+            whitelevels: [4095; 4],
+            blacklevels: [0; 4],
+            xyz_to_cam,
+            cfa: CFA::new("RGGB"),
+            crops: [0; 4],
+            blackareas: Vec::new(),
+            orientation: Orientation::Normal,
+            data: RawImageData::Integer(vec![0; 4]),
+        }
+    }
+
+    fn assert_near(matrix: [[f32; 3]; 3], expected: [[f32; 3]; 3]) {
+        for (row, expected) in matrix.iter().zip(expected) {
+            for (v, e) in row.iter().zip(expected) {
+                assert!((v - e).abs() < 1e-3, "{:?}", matrix);
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_camera_needs_no_conversion() {
+        let [a, b, c] = XYZ_TO_SRGB;
+        assert_near(
+            camera_to_srgb(&raw_with_matrix([a, b, c, [0.0; 3]])),
+            IDENTITY,
+        );
+        // Scaling the camera's response changes nothing once white is white.
+        let scaled = [a, b, c].map(|row| row.map(|v| v * 3.0));
+        let [a, b, c] = scaled;
+        assert_near(
+            camera_to_srgb(&raw_with_matrix([a, b, c, [0.0; 3]])),
+            IDENTITY,
+        );
+    }
+
+    #[test]
+    fn missing_matrix_falls_back_to_identity() {
+        assert_eq!(camera_to_srgb(&raw_with_matrix([[0.0; 3]; 4])), IDENTITY);
+    }
+
+    #[test]
+    fn flat_field_demosaics_to_one_colour() {
+        let cfa = CFA::new("RGGB");
+        let (width, height) = (6, 4);
+        let level = [0.8, 0.5, 0.2];
+        let mosaic: Vec<f32> = (0..width * height)
+            .map(|i| level[cfa.color_at(i / width, i % width) % 3])
+            .collect();
+        let rgb = demosaic(&mosaic, width, height, &cfa);
+        for px in rgb.chunks_exact(3) {
+            assert_eq!(px, level);
+        }
+    }
+
+    #[test]
+    fn demosaic_keeps_each_sites_own_value() {
+        let cfa = CFA::new("RGGB");
+        let mosaic: Vec<f32> = (0..16).map(|i| i as f32 / 16.0).collect();
+        let rgb = demosaic(&mosaic, 4, 4, &cfa);
+        for (i, &v) in mosaic.iter().enumerate() {
+            let own = cfa.color_at(i / 4, i % 4) % 3;
+            assert_eq!(rgb[i * 3 + own], v);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::decode::decode_file;
+use crate::error::AppError;
+use crate::models::{AnimationFormat, Operation};
+use crate::processing::apply_operations;
+
+use super::{save_error, write_failed};
+
+// `frame` scaled to `width`x`height`, if it isn't that size already.
+pub fn fit_frame(frame: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    if frame.dimensions() == (width, height) {
+        frame
+    } else {
+        imageops::resize(&frame, width, height, FilterType::Triangle)
+    }
+}
+
+// A timelapse frame, decoded through `decode_file` as opened images are, so
+// CMYK, ICC and RAW sources come out right, with `operations` applied.
+pub fn load_frame(path: &Path, operations: &[Operation]) -> Result<RgbaImage, AppError> {
+    let mut frame = decode_file(path, false)?.image;
+    apply_operations(&mut frame, operations);
+    Ok(frame)
+}
+
+// A single delay applies to every frame; otherwise there must be one per frame.
+fn frame_delays(delays: &[u32], frame_count: usize) -> Result<Vec<u32>, AppError> {
+    match delays.len() {
+        1 => Ok(vec![delays[0]; frame_count]),
+        n if n == frame_count => Ok(delays.to_vec()),
+        n => Err(AppError::invalid(format!(
+            "Expected 1 or {} frame delays, got {}",
+            frame_count, n
+        ))),
+    }
+}
+
+fn encode_gif(
+    frames: Vec<RgbaImage>,
+    delays: &[u32],
+    loop_count: u32,
+    output: &str,
+) -> Result<(), AppError> {
+    let file = File::create(output).map_err(|e| AppError::io(Path::new(output), e, true))?;
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+    let repeat = match loop_count {
+        0 => Repeat::Infinite,
+        n => Repeat::Finite(n.min(u16::MAX as u32) as u16),
+    };
+    encoder
+        .set_repeat(repeat)
+        .map_err(|e| save_error(output, e))?;
+
+    let frames = frames.into_iter().zip(delays).map(|(buffer, &delay)| {
+        Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(delay, 1))
+    });
+    encoder
+        .encode_frames(frames)
+        .map_err(|e| save_error(output, e))
+}
+
+fn encode_apng(
+    frames: Vec<RgbaImage>,
+    delays: &[u32],
+    loop_count: u32,
+    output: &str,
+) -> Result<(), AppError> {
+    let (width, height) = frames[0].dimensions();
+    let file = File::create(output).map_err(|e| AppError::io(Path::new(output), e, true))?;
+    let to_err = |e: png::EncodingError| match e {
+        png::EncodingError::IoError(e) => AppError::io(Path::new(output), e, true),
+        e => write_failed(output, format!("Failed to encode APNG: {}", e)),
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, loop_count)
+        .map_err(to_err)?;
+
+    let mut writer = encoder.write_header().map_err(to_err)?;
+    for (frame, &delay) in frames.iter().zip(delays) {
+        writer
+            .set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)
+            .map_err(to_err)?;
+        writer.write_image_data(frame.as_raw()).map_err(to_err)?;
+    }
+    writer.finish().map_err(to_err)
+}
+
+#[cfg(feature = "webp-animation")]
+fn encode_webp(
+    frames: Vec<RgbaImage>,
+    delays: &[u32],
+    loop_count: u32,
+    output: &str,
+) -> Result<(), AppError> {
+    use webp_animation::{AnimParams, Encoder as WebpEncoder, EncoderOptions};
+
+    let to_err =
+        |e: webp_animation::Error| write_failed(output, format!("Failed to encode WebP: {:?}", e));
+    let options = EncoderOptions {
+        anim_params: AnimParams {
+            loop_count: loop_count.min(i32::MAX as u32) as i32,
+        },
+        ..Default::default()
+    };
+    let mut encoder =
+        WebpEncoder::new_with_options(frames[0].dimensions(), options).map_err(to_err)?;
+
+    // WebP frames are placed on a timeline rather than given durations.
+    let mut timestamp: i32 = 0;
+    for (frame, &delay) in frames.iter().zip(delays) {
+        encoder
+            .add_frame(frame.as_raw(), timestamp)
+            .map_err(to_err)?;
+        timestamp = timestamp.saturating_add(delay.min(i32::MAX as u32) as i32);
+    }
+    let data = encoder.finalize(timestamp).map_err(to_err)?;
+    fs::write(output, &*data).map_err(|e| AppError::io(Path::new(output), e, true))
+}
+
+#[cfg(not(feature = "webp-animation"))]
+fn encode_webp(
+    _frames: Vec<RgbaImage>,
+    _delays: &[u32],
+    _loop_count: u32,
+    _output: &str,
+) -> Result<(), AppError> {
+    Err(AppError::UnsupportedFormat {
+        format: "animated WebP (this build was made without animated WebP support)".to_string(),
+    })
+}
+
+// Writes `frames` as an animation and returns the saved file's size. Every
+// frame is scaled to the size of the first one.
+pub fn write_animation(
+    frames: Vec<RgbaImage>,
+    delays: &[u32],
+    loop_count: u32,
+    format: AnimationFormat,
+    output: &str,
+) -> Result<u64, AppError> {
+    let (width, height) = frames
+        .first()
+        .ok_or_else(|| AppError::invalid("No frames to animate"))?
+        .dimensions();
+    let delays = frame_delays(delays, frames.len())?;
+    let frames: Vec<RgbaImage> = frames
+        .into_iter()
+        .map(|frame| fit_frame(frame, width, height))
+        .collect();
+
+    match format {
+        AnimationFormat::Gif => encode_gif(frames, &delays, loop_count, output)?,
+        AnimationFormat::Apng => encode_apng(frames, &delays, loop_count, output)?,
+        AnimationFormat::Webp => encode_webp(frames, &delays, loop_count, output)?,
+    }
+
+    fs::metadata(output)
+        .map(|m| m.len())
+        .map_err(|e| AppError::io(Path::new(output), e, false))
+}
@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::analysis::blobs::luminance;
+use crate::error::AppError;
+use crate::models::{AsciiCharset, AsciiColorMode};
+
+const MAX_COLUMNS: u32 = 1000;
+// Terminal and browser monospace cells are about twice as tall as wide.
+const CELL_ASPECT: f32 = 2.0;
+
+// Glyphs from emptiest to fullest.
+const STANDARD: &str = " .:-=+*#%@";
+const DETAILED: &str = " .'`^\",:;Il!i><~+_-?][}{1)(|\\/tfjrxnuvczXYUJCLQ0OZmwqpdbkhao*#MW&8%B@$";
+const BLOCKS: &str = " ░▒▓█";
+
+fn glyphs(charset: &AsciiCharset) -> Result<Vec<char>, AppError> {
+    let glyphs: Vec<char> = match charset {
+        AsciiCharset::Standard => STANDARD.chars().collect(),
+        AsciiCharset::Detailed => DETAILED.chars().collect(),
+        AsciiCharset::Blocks => BLOCKS.chars().collect(),
+        AsciiCharset::Custom { chars } => chars.chars().filter(|c| !c.is_control()).collect(),
+    };
+    if glyphs.len() < 2 {
+        return Err(AppError::invalid("A charset needs at least two characters"));
+    }
+    Ok(glyphs)
+}
+
+fn escape_html(c: char, out: &mut String) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        c => out.push(c),
+    }
+}
+
+// Renders the image as text `columns` characters wide, one character per
+// cell of its luminance. Plain text is meant to be read dark on light, so
+// darker cells get fuller glyphs; the coloured modes draw each glyph in its
+// cell's colour on black, so there brighter cells get fuller glyphs instead.
+// Transparent cells are left blank.
+pub fn export_ascii(
+    img: &RgbaImage,
+    columns: u32,
+    charset: &AsciiCharset,
+    color_mode: AsciiColorMode,
+) -> Result<String, AppError> {
+    if columns == 0 || columns > MAX_COLUMNS {
+        return Err(AppError::invalid(format!(
+            "Columns must be between 1 and {}",
+            MAX_COLUMNS
+        )));
+    }
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let glyphs = glyphs(charset)?;
+    let rows = ((columns as f32 * img.height() as f32 / img.width() as f32 / CELL_ASPECT).round()
+        as u32)
+        .max(1);
+    let cells = imageops::resize(img, columns, rows, FilterType::Triangle);
+
+    let mut out = String::new();
+    if color_mode == AsciiColorMode::Html {
+        out.push_str("<pre style=\"background:#000;line-height:1\">");
+    }
+    for y in 0..rows {
+        let mut current = None;
+        for x in 0..columns {
+            let px = cells.get_pixel(x, y);
+            let alpha = px[3] as f32 / 255.0;
+            let level = luminance(px) / 255.0;
+            let fill = match color_mode {
+                AsciiColorMode::Plain => 1.0 - level,
+                _ => level,
+            } * alpha;
+            let index = (fill * (glyphs.len() - 1) as f32).round() as usize;
+            let glyph = glyphs[index.min(glyphs.len() - 1)];
+            let color = [px[0], px[1], px[2]];
+            match color_mode {
+                AsciiColorMode::Plain => out.push(glyph),
+                // Colour codes are only written where the colour changes.
+                AsciiColorMode::Ansi => {
+                    if glyph != ' ' && current != Some(color) {
+                        let _ = write!(out, "\x1b[38;2;{};{};{}m", color[0], color[1], color[2]);
+                        current = Some(color);
+                    }
+                    out.push(glyph);
+                }
+                AsciiColorMode::Html => {
+                    if glyph != ' ' && current != Some(color) {
+                        if current.is_some() {
+                            out.push_str("</span>");
+                        }
+                        let _ = write!(
+                            out,
+                            "<span style=\"color:#{:02x}{:02x}{:02x}\">",
+                            color[0], color[1], color[2]
+                        );
+                        current = Some(color);
+                    }
+                    escape_html(glyph, &mut out);
+                }
+            }
+        }
+        match color_mode {
+            AsciiColorMode::Ansi if current.is_some() => out.push_str("\x1b[0m"),
+            AsciiColorMode::Html if current.is_some() => out.push_str("</span>"),
+            _ => {}
+        }
+        // Trailing blanks only pad the line.
+        if color_mode == AsciiColorMode::Plain {
+            out.truncate(out.trim_end_matches(' ').len());
+        }
+        out.push('\n');
+    }
+    if color_mode == AsciiColorMode::Html {
+        out.push_str("</pre>\n");
+    }
+    Ok(out)
+}
@@ -0,0 +1,362 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use exif::experimental::Writer as ExifWriter;
+use exif::{Field, In, Tag, Value};
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ColorType, DynamicImage, ImageEncoder, RgbaImage};
+
+use crate::decode::read_icc_profile;
+use crate::error::AppError;
+use crate::metadata::read_exif;
+use crate::models::{ColorKind, ExportFormat, ExportOptions, PngCompression, SourceColorType};
+
+use super::{is_gray, is_opaque, palette_of, save_error, write_failed};
+
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+// Data an APP2 segment has room for after its length and ICC_PROFILE header.
+const ICC_CHUNK: usize = 65_519;
+
+fn flatten_onto_white(img: &RgbaImage) -> RgbaImage {
+    let mut flat = img.clone();
+    for px in flat.pixels_mut() {
+        let alpha = px[3] as f32 / 255.0;
+        for c in 0..3 {
+            px[c] = (px[c] as f32 * alpha + 255.0 * (1.0 - alpha)).round() as u8;
+        }
+        px[3] = 255;
+    }
+    flat
+}
+
+// The source's EXIF data rebuilt from its main directory, dropping the
+// thumbnail, which would still show the unedited image, and with the pixel
+// size brought up to date.
+fn updated_exif(source: &Path, width: u32, height: u32) -> Option<Vec<u8>> {
+    let exif = read_exif(source)?;
+    let size = [
+        (Tag::PixelXDimension, width),
+        (Tag::PixelYDimension, height),
+    ]
+    .map(|(tag, value)| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Long(vec![value]),
+    });
+    let mut writer = ExifWriter::new();
+    for field in exif.fields() {
+        if field.ifd_num == In::PRIMARY && !size.iter().any(|s| s.tag == field.tag) {
+            writer.push_field(field);
+        }
+    }
+    for field in &size {
+        writer.push_field(field);
+    }
+    let mut out = Cursor::new(Vec::new());
+    writer.write(&mut out, exif.little_endian()).ok()?;
+    Some(out.into_inner())
+}
+
+// Like `save_png`, keeps the source layout where the pixels still fit it.
+fn encode_png(
+    img: &RgbaImage,
+    source: Option<&SourceColorType>,
+    compression: PngCompression,
+    icc: Option<&[u8]>,
+    exif: Option<&[u8]>,
+    path: &str,
+) -> Result<Vec<u8>, AppError> {
+    let to_err = |e: png::EncodingError| write_failed(path, e.to_string());
+    let mut info = png::Info::with_size(img.width(), img.height());
+    info.bit_depth = png::BitDepth::Eight;
+    info.compression = match compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    };
+    info.icc_profile = icc.map(Cow::Borrowed);
+
+    let kind = source.map(|s| s.kind);
+    let rgba = DynamicImage::ImageRgba8(img.clone());
+    let indexed = match kind {
+        Some(ColorKind::Indexed) => palette_of(img),
+        _ => None,
+    };
+    let data = if let Some((palette, indices)) = indexed {
+        info.color_type = png::ColorType::Indexed;
+        info.palette = Some(Cow::Owned(
+            palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect(),
+        ));
+        if palette.iter().any(|c| c[3] != 255) {
+            info.trns = Some(Cow::Owned(palette.iter().map(|c| c[3]).collect()));
+        }
+        indices
+    } else if matches!(kind, Some(ColorKind::Gray | ColorKind::GrayAlpha)) && is_gray(img) {
+        if kind == Some(ColorKind::Gray) && is_opaque(img) {
+            info.color_type = png::ColorType::Grayscale;
+            rgba.to_luma8().into_raw()
+        } else {
+            info.color_type = png::ColorType::GrayscaleAlpha;
+            rgba.to_luma_alpha8().into_raw()
+        }
+    } else if is_opaque(img) {
+        info.color_type = png::ColorType::Rgb;
+        rgba.to_rgb8().into_raw()
+    } else {
+        info.color_type = png::ColorType::Rgba;
+        rgba.into_rgba8().into_raw()
+    };
+
+    let mut out = Vec::new();
+    let mut writer = png::Encoder::with_info(&mut out, info)
+        .map_err(to_err)?
+        .write_header()
+        .map_err(to_err)?;
+    if let Some(exif) = exif {
+        writer
+            .write_chunk(png::chunk::ChunkType(*b"eXIf"), exif)
+            .map_err(to_err)?;
+    }
+    writer.write_image_data(&data).map_err(to_err)?;
+    writer.finish().map_err(to_err)?;
+    Ok(out)
+}
+
+// Inserts APP1 EXIF and APP2 ICC segments after the encoder's JFIF header.
+fn add_jpeg_metadata(jpeg: Vec<u8>, icc: Option<&[u8]>, exif: Option<&[u8]>) -> Vec<u8> {
+    let mut segments = Vec::new();
+    let mut segment = |marker: u8, parts: &[&[u8]]| {
+        let length = parts.iter().map(|p| p.len()).sum::<usize>() + 2;
+        segments.extend_from_slice(&[0xFF, marker]);
+        segments.extend_from_slice(&(length as u16).to_be_bytes());
+        parts.iter().for_each(|p| segments.extend_from_slice(p));
+    };
+    if let Some(exif) = exif.filter(|e| e.len() + 8 <= u16::MAX as usize) {
+        segment(0xE1, &[b"Exif\0\0", exif]);
+    }
+    if let Some(icc) = icc {
+        let chunks: Vec<&[u8]> = icc.chunks(ICC_CHUNK).collect();
+        if chunks.len() <= 255 {
+            for (i, chunk) in chunks.iter().enumerate() {
+                let sequence = [i as u8 + 1, chunks.len() as u8];
+                segment(0xE2, &[b"ICC_PROFILE\0", &sequence, chunk]);
+            }
+        }
+    }
+    let mut at = 2;
+    if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        at += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+    }
+    let mut out = Vec::with_capacity(jpeg.len() + segments.len());
+    out.extend_from_slice(&jpeg[..at]);
+    out.extend_from_slice(&segments);
+    out.extend_from_slice(&jpeg[at..]);
+    out
+}
+
+#[cfg(feature = "webp-animation")]
+fn encode_lossy_webp(img: &RgbaImage, quality: u8, path: &str) -> Result<Vec<u8>, AppError> {
+    use webp_animation::{Encoder as WebpEncoder, EncoderOptions, EncodingConfig};
+
+    let to_err =
+        |e: webp_animation::Error| write_failed(path, format!("Failed to encode WebP: {:?}", e));
+    let options = EncoderOptions {
+        encoding_config: Some(EncodingConfig::new_lossy(quality as f32)),
+        ..Default::default()
+    };
+    let mut encoder = WebpEncoder::new_with_options(img.dimensions(), options).map_err(to_err)?;
+    encoder.add_frame(img.as_raw(), 0).map_err(to_err)?;
+    // A single frame comes out as a still image.
+    let data = encoder.finalize(0).map_err(to_err)?;
+    Ok(data.to_vec())
+}
+
+#[cfg(not(feature = "webp-animation"))]
+fn encode_lossy_webp(_img: &RgbaImage, _quality: u8, _path: &str) -> Result<Vec<u8>, AppError> {
+    Err(AppError::UnsupportedFormat {
+        format: "lossy WebP (this build was made without lossy WebP support)".to_string(),
+    })
+}
+
+// Writes `img` in the format and with the settings `options` ask for, and
+// returns the written file size. Unless stripped, PNG and JPEG output carries
+// the EXIF data and ICC profile of the file at `source_path`.
+pub fn export_image(
+    img: &RgbaImage,
+    source: Option<&SourceColorType>,
+    source_path: Option<&Path>,
+    options: &ExportOptions,
+    path: &str,
+) -> Result<u64, AppError> {
+    if matches!(options.quality, Some(q) if !(1..=100).contains(&q)) {
+        return Err(AppError::invalid("Quality must be between 1 and 100"));
+    }
+    let keeps_alpha = matches!(
+        options.format,
+        ExportFormat::Png | ExportFormat::Webp | ExportFormat::Tiff
+    );
+    let img = if (options.strip_alpha || !keeps_alpha) && !is_opaque(img) {
+        Cow::Owned(flatten_onto_white(img))
+    } else {
+        Cow::Borrowed(img)
+    };
+    let (width, height) = img.dimensions();
+
+    let carries_metadata = matches!(options.format, ExportFormat::Png | ExportFormat::Jpeg);
+    let (icc, exif) = match source_path.filter(|_| carries_metadata && !options.strip_metadata) {
+        Some(source) => {
+            let icc = fs::read(source)
+                .ok()
+                .and_then(|bytes| read_icc_profile(&bytes, image::guess_format(&bytes).ok()?));
+            (icc, updated_exif(source, width, height))
+        }
+        None => (None, None),
+    };
+    let gray = matches!(
+        source.map(|s| s.kind),
+        Some(ColorKind::Gray | ColorKind::GrayAlpha)
+    ) && is_gray(&img);
+    let rgba = DynamicImage::ImageRgba8(img.into_owned());
+
+    let mut out = Vec::new();
+    match options.format {
+        ExportFormat::Png => {
+            out = encode_png(
+                rgba.as_rgba8().expect("RGBA image"),
+                source,
+                options.png_compression,
+                icc.as_deref(),
+                exif.as_deref(),
+                path,
+            )?;
+        }
+        ExportFormat::Jpeg => {
+            let quality = options.quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+            let encoder = JpegEncoder::new_with_quality(&mut out, quality);
+            if gray {
+                encoder.write_image(&rgba.to_luma8(), width, height, ColorType::L8)
+            } else {
+                encoder.write_image(&rgba.to_rgb8(), width, height, ColorType::Rgb8)
+            }
+            .map_err(|e| save_error(path, e))?;
+            out = add_jpeg_metadata(out, icc.as_deref(), exif.as_deref());
+        }
+        ExportFormat::Webp => match options.quality {
+            Some(quality) => {
+                out = encode_lossy_webp(rgba.as_rgba8().expect("RGBA image"), quality, path)?;
+            }
+            None => WebPEncoder::new_lossless(&mut out)
+                .write_image(rgba.as_bytes(), width, height, ColorType::Rgba8)
+                .map_err(|e| save_error(path, e))?,
+        },
+        ExportFormat::Tiff => {
+            let opaque = is_opaque(rgba.as_rgba8().expect("RGBA image"));
+            let encoder = TiffEncoder::new(Cursor::new(&mut out));
+            if gray && opaque {
+                encoder.write_image(&rgba.to_luma8(), width, height, ColorType::L8)
+            } else if opaque {
+                encoder.write_image(&rgba.to_rgb8(), width, height, ColorType::Rgb8)
+            } else {
+                encoder.write_image(rgba.as_bytes(), width, height, ColorType::Rgba8)
+            }
+            .map_err(|e| save_error(path, e))?;
+        }
+        ExportFormat::Bmp => BmpEncoder::new(&mut out)
+            .write_image(&rgba.to_rgb8(), width, height, ColorType::Rgb8)
+            .map_err(|e| save_error(path, e))?,
+    }
+
+    fs::write(path, &out).map_err(|e| AppError::io(Path::new(path), e, true))?;
+    Ok(out.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{ImageFormat, Rgba};
+
+    fn jpeg(img: &RgbaImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 90)
+            .write_image(
+                &DynamicImage::ImageRgba8(img.clone()).to_rgb8(),
+                img.width(),
+                img.height(),
+                ColorType::Rgb8,
+            )
+            .unwrap();
+        out
+    }
+
+    fn exif_bytes(fields: &[Field]) -> Vec<u8> {
+        let mut writer = ExifWriter::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out, false).unwrap();
+        out.into_inner()
+    }
+
+    fn ascii(tag: Tag, text: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![text.as_bytes().to_vec()]),
+        }
+    }
+
+    #[test]
+    fn jpeg_metadata_round_trips_through_the_decoder() {
+        let img = RgbaImage::from_fn(24, 16, |x, y| Rgba([x as u8 * 10, y as u8 * 15, 80, 255]));
+        // Large enough to be split over two APP2 segments.
+        let icc: Vec<u8> = (0..ICC_CHUNK + 1000).map(|i| (i % 251) as u8).collect();
+        let exif = exif_bytes(&[ascii(Tag::Make, "Test camera")]);
+        let out = add_jpeg_metadata(jpeg(&img), Some(&icc), Some(&exif));
+
+        let decoded = image::load_from_memory(&out).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (24, 16));
+        assert_eq!(read_icc_profile(&out, ImageFormat::Jpeg), Some(icc));
+        let read = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&out))
+            .unwrap();
+        let make = read.get_field(Tag::Make, In::PRIMARY).unwrap();
+        assert_eq!(make.display_value().to_string(), "\"Test camera\"");
+    }
+
+    #[test]
+    fn jpeg_without_metadata_is_unchanged() {
+        let encoded = jpeg(&RgbaImage::from_pixel(8, 8, Rgba([1, 2, 3, 255])));
+        assert_eq!(add_jpeg_metadata(encoded.clone(), None, None), encoded);
+    }
+
+    #[test]
+    fn updated_exif_drops_stale_size_and_keeps_the_rest() {
+        let img = RgbaImage::from_pixel(8, 8, Rgba([50, 60, 70, 255]));
+        let exif = exif_bytes(&[
+            ascii(Tag::Make, "Test camera"),
+            Field {
+                tag: Tag::PixelXDimension,
+                ifd_num: In::PRIMARY,
+                value: Value::Long(vec![4000]),
+            },
+        ]);
+        let source =
+            std::env::temp_dir().join(format!("image-analyzer-exif-{}.jpg", std::process::id()));
+        fs::write(&source, add_jpeg_metadata(jpeg(&img), None, Some(&exif))).unwrap();
+        let updated = updated_exif(&source, 640, 480);
+        fs::remove_file(&source).unwrap();
+
+        let read = exif::Reader::new().read_raw(updated.unwrap()).unwrap();
+        let long = |tag| read.get_field(tag, In::PRIMARY).unwrap().value.get_uint(0);
+        assert_eq!(long(Tag::PixelXDimension), Some(640));
+        assert_eq!(long(Tag::PixelYDimension), Some(480));
+        assert!(read.get_field(Tag::Make, In::PRIMARY).is_some());
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::{self, FilterType};
+use image::{ColorType, ImageBuffer, ImageFormat, Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{ExportResult, IconTarget};
+
+const ICO_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+const MAX_ICO_SIZE: u32 = 256;
+const MAX_ICON_SIZE: u32 = 1024;
+// Padding past this leaves nothing of the image to see.
+const MAX_PADDING: f32 = 0.4;
+
+// ICNS element types that hold PNG data, with their pixel size.
+const ICNS_ELEMENTS: [(&[u8; 4], u32); 11] = [
+    (b"icp4", 16),
+    (b"ic11", 32),
+    (b"icp5", 32),
+    (b"ic12", 64),
+    (b"icp6", 64),
+    (b"ic07", 128),
+    (b"ic13", 256),
+    (b"ic08", 256),
+    (b"ic14", 512),
+    (b"ic09", 512),
+    (b"ic10", 1024),
+];
+
+type Premultiplied = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+// Resampling premultiplied colour keeps transparent pixels' hidden colour
+// from bleeding into the edges as dark fringes.
+fn premultiply(img: &RgbaImage) -> Premultiplied {
+    ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y);
+        let a = p[3] as f32 / 255.0;
+        Rgba([
+            p[0] as f32 / 255.0 * a,
+            p[1] as f32 / 255.0 * a,
+            p[2] as f32 / 255.0 * a,
+            a,
+        ])
+    })
+}
+
+// Fits the source, less `padding` on each side, centred in a transparent
+// `size` square. Every size is resampled from the full-resolution source.
+fn render(source: &Premultiplied, size: u32, padding: f32) -> RgbaImage {
+    let inner = size as f32 * (1.0 - 2.0 * padding);
+    let scale = inner / source.width().max(source.height()) as f32;
+    let w = ((source.width() as f32 * scale).round() as u32).clamp(1, size);
+    let h = ((source.height() as f32 * scale).round() as u32).clamp(1, size);
+    let resized = imageops::resize(source, w, h, FilterType::Lanczos3);
+
+    let mut icon = RgbaImage::new(size, size);
+    let (ox, oy) = ((size - w) / 2, (size - h) / 2);
+    for (x, y, p) in resized.enumerate_pixels() {
+        let a = p[3].clamp(0.0, 1.0);
+        if a <= 0.0 {
+            continue;
+        }
+        let channel = |v: f32| ((v / a).clamp(0.0, 1.0) * 255.0).round() as u8;
+        icon.put_pixel(
+            x + ox,
+            y + oy,
+            Rgba([
+                channel(p[0]),
+                channel(p[1]),
+                channel(p[2]),
+                (a * 255.0).round() as u8,
+            ]),
+        );
+    }
+    icon
+}
+
+fn png_bytes(img: &RgbaImage, path: &Path) -> Result<Vec<u8>, AppError> {
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| AppError::WriteFailed {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+    Ok(png.into_inner())
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<u64, AppError> {
+    fs::write(path, data).map_err(|e| AppError::io(path, e, true))?;
+    Ok(data.len() as u64)
+}
+
+fn icns(source: &Premultiplied, padding: f32, path: &Path) -> Result<Vec<u8>, AppError> {
+    let mut body = Vec::new();
+    for (kind, size) in ICNS_ELEMENTS {
+        let png = png_bytes(&render(source, size, padding), path)?;
+        body.extend_from_slice(kind);
+        body.extend_from_slice(&(png.len() as u32 + 8).to_be_bytes());
+        body.extend_from_slice(&png);
+    }
+    let mut data = Vec::with_capacity(body.len() + 8);
+    data.extend_from_slice(b"icns");
+    data.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    data.extend_from_slice(&body);
+    Ok(data)
+}
+
+fn ico(
+    source: &Premultiplied,
+    sizes: &[u32],
+    padding: f32,
+    path: &Path,
+) -> Result<Vec<u8>, AppError> {
+    let icons: Vec<RgbaImage> = sizes.iter().map(|&s| render(source, s, padding)).collect();
+    let to_err = |e: image::ImageError| AppError::WriteFailed {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    };
+    let frames = icons
+        .iter()
+        .map(|icon| IcoFrame::as_png(icon.as_raw(), icon.width(), icon.height(), ColorType::Rgba8))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(to_err)?;
+    let mut data = Vec::new();
+    IcoEncoder::new(&mut data)
+        .encode_images(&frames)
+        .map_err(to_err)?;
+    Ok(data)
+}
+
+// Writes each target into `output_dir` as `<name>.ico`, `<name>.icns` or
+// `<name>-<size>.png`. `padding` is the transparent margin on each side as a
+// fraction of the icon size; non-square images are centred.
+pub fn export_icon_set(
+    img: &RgbaImage,
+    targets: &[IconTarget],
+    output_dir: &Path,
+    name: &str,
+    padding: f32,
+) -> Result<Vec<ExportResult>, AppError> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if targets.is_empty() {
+        return Err(AppError::invalid("No icon targets given"));
+    }
+    if !(0.0..=MAX_PADDING).contains(&padding) {
+        return Err(AppError::invalid(format!(
+            "Padding must be between 0 and {}",
+            MAX_PADDING
+        )));
+    }
+    for target in targets {
+        let (sizes, max) = match target {
+            IconTarget::Ico { sizes } => (sizes.as_slice(), MAX_ICO_SIZE),
+            IconTarget::Png { size } => (std::slice::from_ref(size), MAX_ICON_SIZE),
+            IconTarget::Icns => (&[][..], MAX_ICON_SIZE),
+        };
+        if let Some(size) = sizes.iter().find(|&&s| s == 0 || s > max) {
+            return Err(AppError::invalid(format!(
+                "Icon size {} is outside 1-{}",
+                size, max
+            )));
+        }
+    }
+
+    fs::create_dir_all(output_dir).map_err(|e| AppError::io(output_dir, e, true))?;
+    let source = premultiply(img);
+    let mut results = Vec::with_capacity(targets.len());
+    for target in targets {
+        let (path, data, size): (PathBuf, Vec<u8>, u32) = match target {
+            IconTarget::Ico { sizes } => {
+                let sizes = if sizes.is_empty() {
+                    &ICO_SIZES[..]
+                } else {
+                    sizes
+                };
+                let path = output_dir.join(format!("{}.ico", name));
+                let data = ico(&source, sizes, padding, &path)?;
+                (path, data, sizes.iter().copied().max().unwrap_or_default())
+            }
+            IconTarget::Icns => {
+                let path = output_dir.join(format!("{}.icns", name));
+                let data = icns(&source, padding, &path)?;
+                (path, data, MAX_ICON_SIZE)
+            }
+            IconTarget::Png { size } => {
+                let path = output_dir.join(format!("{}-{}.png", name, size));
+                let data = png_bytes(&render(&source, *size, padding), &path)?;
+                (path, data, *size)
+            }
+        };
+        let file_size = write_file(&path, &data)?;
+        results.push(ExportResult {
+            output: path.display().to_string(),
+            width: size,
+            height: size,
+            file_size,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> Premultiplied {
+        premultiply(&RgbaImage::from_fn(12, 6, |x, y| {
+            Rgba([200, (x * 20) as u8, (y * 40) as u8, 255])
+        }))
+    }
+
+    #[test]
+    fn render_centres_the_image_in_a_transparent_square() {
+        let icon = render(&source(), 32, 0.0);
+        assert_eq!(icon.dimensions(), (32, 32));
+        // A 2:1 source fills the middle half of the height.
+        assert_eq!(icon.get_pixel(16, 2)[3], 0);
+        assert_eq!(icon.get_pixel(16, 16)[3], 255);
+        assert_eq!(icon.get_pixel(16, 29)[3], 0);
+    }
+
+    #[test]
+    fn ico_holds_every_size() {
+        let path = Path::new("test.ico");
+        let data = ico(&source(), &[16, 32, 48], 0.1, path).unwrap();
+        // ICONDIR: reserved, type 1, then the image count.
+        assert_eq!(&data[..4], &[0, 0, 1, 0]);
+        assert_eq!(u16::from_le_bytes([data[4], data[5]]), 3);
+        let decoded = image::load_from_memory_with_format(&data, ImageFormat::Ico).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (48, 48));
+    }
+
+    #[test]
+    fn icns_elements_are_sized_pngs() {
+        let data = icns(&source(), 0.0, Path::new("test.icns")).unwrap();
+        assert_eq!(&data[..4], b"icns");
+        assert_eq!(
+            u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize,
+            data.len()
+        );
+
+        let mut at = 8;
+        for (kind, size) in ICNS_ELEMENTS {
+            assert_eq!(&data[at..at + 4], kind);
+            let len = u32::from_be_bytes(data[at + 4..at + 8].try_into().unwrap()) as usize;
+            let png =
+                image::load_from_memory_with_format(&data[at + 8..at + len], ImageFormat::Png)
+                    .unwrap();
+            assert_eq!((png.width(), png.height()), (size, size));
+            at += len;
+        }
+        assert_eq!(at, data.len());
+    }
+}
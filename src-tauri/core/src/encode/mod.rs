@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+
+use base64::prelude::*;
+use image::{DynamicImage, ImageError, ImageFormat, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{ColorKind, SourceColorType};
+
+pub mod animation;
+pub mod ascii;
+pub mod export;
+pub mod icons;
+pub mod slices;
+pub mod social;
+
+fn is_opaque(img: &RgbaImage) -> bool {
+    img.pixels().all(|p| p[3] == 255)
+}
+
+fn is_gray(img: &RgbaImage) -> bool {
+    img.pixels().all(|p| p[0] == p[1] && p[1] == p[2])
+}
+
+// Collects the distinct colours in first-seen order, giving up past 256.
+fn palette_of(img: &RgbaImage) -> Option<(Vec<[u8; 4]>, Vec<u8>)> {
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity((img.width() * img.height()) as usize);
+    for pixel in img.pixels() {
+        let index = match lookup.get(&pixel.0) {
+            Some(&index) => index,
+            None => {
+                if palette.len() == 256 {
+                    return None;
+                }
+                let index = palette.len() as u8;
+                lookup.insert(pixel.0, index);
+                palette.push(pixel.0);
+                index
+            }
+        };
+        indices.push(index);
+    }
+    Some((palette, indices))
+}
+
+fn write_indexed_png(
+    img: &RgbaImage,
+    palette: &[[u8; 4]],
+    indices: &[u8],
+    path: &str,
+) -> Result<(), AppError> {
+    let file = File::create(path).map_err(|e| AppError::io(Path::new(path), e, true))?;
+    let to_err = |e: png::EncodingError| write_failed(path, e.to_string());
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), img.width(), img.height());
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(
+        palette
+            .iter()
+            .flat_map(|c| [c[0], c[1], c[2]])
+            .collect::<Vec<_>>(),
+    );
+    if palette.iter().any(|c| c[3] != 255) {
+        encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<_>>());
+    }
+
+    let mut writer = encoder.write_header().map_err(to_err)?;
+    writer.write_image_data(indices).map_err(to_err)?;
+    writer.finish().map_err(to_err)
+}
+
+// Picks the narrowest representation that still holds the edited pixels: the
+// source layout when the edits allow it, otherwise RGB(A). Interlacing and bit
+// depths above 8 are not written back.
+fn save_png(img: &RgbaImage, source: Option<&SourceColorType>, path: &str) -> Result<(), AppError> {
+    let kind = source.map(|s| s.kind);
+    let rgba = DynamicImage::ImageRgba8(img.clone());
+    let saved = match kind {
+        Some(ColorKind::Indexed) => {
+            if let Some((palette, indices)) = palette_of(img) {
+                return write_indexed_png(img, &palette, &indices, path);
+            }
+            rgba.save(path)
+        }
+        Some(ColorKind::Gray) if is_gray(img) && is_opaque(img) => rgba.to_luma8().save(path),
+        Some(ColorKind::Gray | ColorKind::GrayAlpha) if is_gray(img) => {
+            rgba.to_luma_alpha8().save(path)
+        }
+        Some(ColorKind::Gray | ColorKind::Rgb | ColorKind::Cmyk) if is_opaque(img) => {
+            rgba.to_rgb8().save(path)
+        }
+        _ => rgba.save(path),
+    };
+    saved.map_err(|e| save_error(path, e))
+}
+
+// Saves by extension, dropping alpha for formats that can't store it, and
+// returns the written file size.
+pub fn write_image(
+    img: &RgbaImage,
+    source: Option<&SourceColorType>,
+    path: &str,
+) -> Result<u64, AppError> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let gray_source = matches!(
+        source.map(|s| s.kind),
+        Some(ColorKind::Gray | ColorKind::GrayAlpha)
+    );
+
+    match ext.as_str() {
+        "png" => save_png(img, source, path)?,
+        "jpg" | "jpeg" if gray_source && is_gray(img) => DynamicImage::ImageRgba8(img.clone())
+            .to_luma8()
+            .save(path)
+            .map_err(|e| save_error(path, e))?,
+        "jpg" | "jpeg" | "bmp" => DynamicImage::ImageRgba8(img.clone())
+            .to_rgb8()
+            .save(path)
+            .map_err(|e| save_error(path, e))?,
+        _ => img.save(path).map_err(|e| save_error(path, e))?,
+    }
+
+    fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| AppError::io(Path::new(path), e, false))
+}
+
+fn write_failed(path: &str, reason: String) -> AppError {
+    AppError::WriteFailed {
+        path: path.to_string(),
+        reason,
+    }
+}
+
+fn save_error(path: &str, error: ImageError) -> AppError {
+    match error {
+        ImageError::IoError(e) => AppError::io(Path::new(path), e, true),
+        ImageError::Unsupported(e) => AppError::UnsupportedFormat {
+            format: e.format_hint().to_string(),
+        },
+        e => write_failed(path, e.to_string()),
+    }
+}
+
+pub fn png_data_url(img: &RgbaImage) -> Result<String, AppError> {
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| AppError::OperationFailed {
+            message: format!("Failed to encode preview: {}", e),
+        })?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        BASE64_STANDARD.encode(png.into_inner())
+    ))
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+
+use image::imageops;
+use image::RgbaImage;
+
+use crate::encode::write_image;
+use crate::error::AppError;
+use crate::models::{PixelRect, SliceLayout, SliceManifest, SliceResult, SliceTile};
+
+// Tile names for two guides on each axis, the nine-patch layout.
+const NINE_PATCH: [[&str; 3]; 3] = [
+    ["top_left", "top", "top_right"],
+    ["left", "center", "right"],
+    ["bottom_left", "bottom", "bottom_right"],
+];
+
+// Edges of the spans along one axis, from 0 to `len`.
+fn edges(cuts: &[u32], len: u32) -> Result<Vec<u32>, AppError> {
+    let mut edges = vec![0];
+    for &cut in cuts {
+        if cut == 0 || cut >= len {
+            return Err(AppError::invalid(format!(
+                "Guide at {} is outside the image",
+                cut
+            )));
+        }
+        if cut <= *edges.last().unwrap() {
+            return Err(AppError::invalid("Guides must be in increasing order"));
+        }
+        edges.push(cut);
+    }
+    edges.push(len);
+    Ok(edges)
+}
+
+fn grid_cuts(count: u32, len: u32) -> Result<Vec<u32>, AppError> {
+    if count == 0 || count > len {
+        return Err(AppError::invalid(format!(
+            "Can't split {} pixels into {} tiles",
+            len, count
+        )));
+    }
+    Ok((1..count)
+        .map(|i| (i as u64 * len as u64 / count as u64) as u32)
+        .collect())
+}
+
+// Cuts the image into tiles saved as `<name>_<tile>.png` in `output_dir`,
+// with a `<name>.json` manifest listing where each came from. Tiles of a
+// nine-patch are named by position, others by row and column.
+pub fn slice_image(
+    img: &RgbaImage,
+    layout: &SliceLayout,
+    output_dir: &Path,
+    name: &str,
+) -> Result<SliceResult, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let (xs, ys) = match layout {
+        SliceLayout::Grid { rows, columns } => (
+            edges(&grid_cuts(*columns, width)?, width)?,
+            edges(&grid_cuts(*rows, height)?, height)?,
+        ),
+        SliceLayout::Guides { x, y } => (edges(x, width)?, edges(y, height)?),
+    };
+    let nine_patch = xs.len() == 4 && ys.len() == 4;
+
+    fs::create_dir_all(output_dir).map_err(|e| AppError::io(output_dir, e, true))?;
+    let mut tiles = Vec::new();
+    for (row, span_y) in ys.windows(2).enumerate() {
+        for (column, span_x) in xs.windows(2).enumerate() {
+            let tile_name = if nine_patch {
+                NINE_PATCH[row][column].to_string()
+            } else {
+                format!("r{}_c{}", row, column)
+            };
+            let bounds = PixelRect {
+                x: span_x[0],
+                y: span_y[0],
+                width: span_x[1] - span_x[0],
+                height: span_y[1] - span_y[0],
+            };
+            let file = format!("{}_{}.png", name, tile_name);
+            let tile =
+                imageops::crop_imm(img, bounds.x, bounds.y, bounds.width, bounds.height).to_image();
+            write_image(&tile, None, &output_dir.join(&file).display().to_string())?;
+            tiles.push(SliceTile {
+                name: tile_name,
+                file,
+                row: row as u32,
+                column: column as u32,
+                bounds,
+            });
+        }
+    }
+
+    let manifest = SliceManifest {
+        width,
+        height,
+        tiles,
+    };
+    let manifest_path = output_dir.join(format!("{}.json", name));
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    fs::write(&manifest_path, json).map_err(|e| AppError::io(&manifest_path, e, true))?;
+
+    Ok(SliceResult {
+        manifest_path: manifest_path.display().to_string(),
+        manifest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges_span_the_whole_axis() {
+        assert_eq!(edges(&[], 10).unwrap(), vec![0, 10]);
+        assert_eq!(edges(&[3, 7], 10).unwrap(), vec![0, 3, 7, 10]);
+    }
+
+    #[test]
+    fn edges_reject_guides_outside_or_out_of_order() {
+        assert!(edges(&[0], 10).is_err());
+        assert!(edges(&[10], 10).is_err());
+        assert!(edges(&[7, 3], 10).is_err());
+        assert!(edges(&[3, 3], 10).is_err());
+    }
+
+    #[test]
+    fn grid_cuts_stay_inside_and_increase() {
+        for len in 1..40 {
+            for count in 1..=len {
+                let cuts = grid_cuts(count, len).unwrap();
+                assert_eq!(cuts.len() as u32, count - 1);
+                // Every cut is a valid guide, so every tile is at least a pixel.
+                edges(&cuts, len).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn grid_cuts_reject_impossible_grids() {
+        assert!(grid_cuts(0, 10).is_err());
+        assert!(grid_cuts(11, 10).is_err());
+    }
+}
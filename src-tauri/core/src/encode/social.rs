@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::analysis::composition::frame_crop;
+use crate::analysis::saliency::saliency_map;
+use crate::encode::write_image;
+use crate::error::AppError;
+use crate::models::{ExportResult, SocialPreset};
+
+// Target sizes for the common social media placements.
+const BUNDLED_PRESETS: &str = include_str!("../../resources/social_presets.json");
+
+#[derive(Deserialize)]
+struct PresetLibrary {
+    presets: Vec<SocialPreset>,
+}
+
+fn preset_library() -> &'static PresetLibrary {
+    static PRESETS: OnceLock<PresetLibrary> = OnceLock::new();
+    PRESETS.get_or_init(|| {
+        serde_json::from_str(BUNDLED_PRESETS).expect("bundled social presets are valid JSON")
+    })
+}
+
+pub fn list_social_presets() -> Vec<SocialPreset> {
+    preset_library().presets.clone()
+}
+
+fn find_preset(name: &str) -> Result<&'static SocialPreset, AppError> {
+    preset_library()
+        .presets
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| AppError::invalid(format!("Unknown social preset: {}", name)))
+}
+
+// Crops each named preset's aspect ratio around the most salient part of the
+// image, scales it to the preset size and saves it into `output_dir` as
+// `<name>-<preset>.<format>`.
+pub fn export_social_presets(
+    img: &RgbaImage,
+    presets: &[String],
+    output_dir: &Path,
+    name: &str,
+    format: &str,
+) -> Result<Vec<ExportResult>, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if presets.is_empty() {
+        return Err(AppError::invalid("No presets given"));
+    }
+    let presets = presets
+        .iter()
+        .map(|p| find_preset(p))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    std::fs::create_dir_all(output_dir).map_err(|e| AppError::io(output_dir, e, true))?;
+    let map = saliency_map(img);
+    let mut results = Vec::with_capacity(presets.len());
+    for preset in presets {
+        let aspect = preset.width as f32 / preset.height as f32;
+        let crop = frame_crop(&map, width, height, aspect);
+        let cropped = imageops::crop_imm(img, crop.x, crop.y, crop.width, crop.height).to_image();
+        let sized = imageops::resize(&cropped, preset.width, preset.height, FilterType::Lanczos3);
+
+        let output = output_dir
+            .join(format!("{}-{}.{}", name, preset.name, format))
+            .display()
+            .to_string();
+        let file_size = write_image(&sized, None, &output)?;
+        results.push(ExportResult {
+            output,
+            width: preset.width,
+            height: preset.height,
+            file_size,
+        });
+    }
+    Ok(results)
+}
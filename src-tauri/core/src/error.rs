@@ -0,0 +1,304 @@
+//! Typed errors with stable codes. Commands serialize them as
+//! `{ code, message, params, actions }` so the frontend can look up a
+//! translated message with `describe_error` and fill in the params.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::models::ImageId;
+
+const CATALOG_JSON: &str = include_str!("../resources/errors.json");
+const FALLBACK_LOCALE: &str = "en";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    FileNotFound,
+    PermissionDenied,
+    ReadFailed,
+    WriteFailed,
+    DecodeFailed,
+    UnsupportedFormat,
+    InvalidArgument,
+    ImageNotFound,
+    ClipboardEmpty,
+    ClipboardUnavailable,
+    FfmpegNotFound,
+    FfmpegFailed,
+    Cancelled,
+    OperationFailed,
+}
+
+// Follow-up the UI can offer next to the message.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorAction {
+    Retry,
+    // Reveal the `folder` param in the file manager.
+    OpenFolder,
+    ChooseFile,
+}
+
+impl ErrorCode {
+    pub fn actions(self) -> &'static [ErrorAction] {
+        use ErrorAction::*;
+        match self {
+            ErrorCode::FileNotFound => &[ChooseFile, OpenFolder],
+            ErrorCode::PermissionDenied => &[OpenFolder],
+            ErrorCode::ReadFailed => &[Retry, OpenFolder],
+            ErrorCode::WriteFailed => &[Retry, OpenFolder],
+            ErrorCode::DecodeFailed => &[ChooseFile],
+            ErrorCode::ClipboardEmpty | ErrorCode::ClipboardUnavailable => &[Retry],
+            ErrorCode::FfmpegFailed | ErrorCode::OperationFailed => &[Retry],
+            ErrorCode::UnsupportedFormat
+            | ErrorCode::InvalidArgument
+            | ErrorCode::ImageNotFound
+            | ErrorCode::FfmpegNotFound
+            | ErrorCode::Cancelled => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AppError {
+    FileNotFound {
+        path: String,
+    },
+    PermissionDenied {
+        path: String,
+    },
+    ReadFailed {
+        path: String,
+        reason: String,
+    },
+    WriteFailed {
+        path: String,
+        reason: String,
+    },
+    DecodeFailed {
+        path: Option<String>,
+        reason: String,
+    },
+    UnsupportedFormat {
+        format: String,
+    },
+    InvalidArgument {
+        reason: String,
+    },
+    ImageNotFound {
+        id: ImageId,
+    },
+    ClipboardEmpty,
+    ClipboardUnavailable {
+        reason: String,
+    },
+    FfmpegNotFound,
+    FfmpegFailed {
+        reason: String,
+    },
+    // The user cancelled the job with `cancel_job`.
+    Cancelled,
+    // Anything that hasn't been given its own code yet.
+    OperationFailed {
+        message: String,
+    },
+}
+
+impl AppError {
+    // Maps the io error kinds users can act on to their own codes.
+    pub fn io(path: &Path, error: io::Error, writing: bool) -> Self {
+        let path = path.display().to_string();
+        match error.kind() {
+            io::ErrorKind::NotFound if !writing => AppError::FileNotFound { path },
+            io::ErrorKind::PermissionDenied => AppError::PermissionDenied { path },
+            _ if writing => AppError::WriteFailed {
+                path,
+                reason: error.to_string(),
+            },
+            _ => AppError::ReadFailed {
+                path,
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    pub fn invalid(reason: impl Into<String>) -> Self {
+        AppError::InvalidArgument {
+            reason: reason.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::FileNotFound { .. } => ErrorCode::FileNotFound,
+            AppError::PermissionDenied { .. } => ErrorCode::PermissionDenied,
+            AppError::ReadFailed { .. } => ErrorCode::ReadFailed,
+            AppError::WriteFailed { .. } => ErrorCode::WriteFailed,
+            AppError::DecodeFailed { .. } => ErrorCode::DecodeFailed,
+            AppError::UnsupportedFormat { .. } => ErrorCode::UnsupportedFormat,
+            AppError::InvalidArgument { .. } => ErrorCode::InvalidArgument,
+            AppError::ImageNotFound { .. } => ErrorCode::ImageNotFound,
+            AppError::ClipboardEmpty => ErrorCode::ClipboardEmpty,
+            AppError::ClipboardUnavailable { .. } => ErrorCode::ClipboardUnavailable,
+            AppError::FfmpegNotFound => ErrorCode::FfmpegNotFound,
+            AppError::FfmpegFailed { .. } => ErrorCode::FfmpegFailed,
+            AppError::Cancelled => ErrorCode::Cancelled,
+            AppError::OperationFailed { .. } => ErrorCode::OperationFailed,
+        }
+    }
+
+    // Values for the `{name}` placeholders in the catalog messages. Errors about
+    // a file also carry its `folder` for the open-folder action.
+    pub fn params(&self) -> BTreeMap<&'static str, String> {
+        let mut params = BTreeMap::new();
+        let mut path_param = |path: &str| {
+            params.insert("path", path.to_string());
+            if let Some(folder) = Path::new(path).parent() {
+                params.insert("folder", folder.display().to_string());
+            }
+        };
+        match self {
+            AppError::FileNotFound { path } | AppError::PermissionDenied { path } => {
+                path_param(path)
+            }
+            AppError::ReadFailed { path, reason } | AppError::WriteFailed { path, reason } => {
+                path_param(path);
+                params.insert("reason", reason.clone());
+            }
+            AppError::DecodeFailed { path, reason } => {
+                if let Some(path) = path {
+                    path_param(path);
+                }
+                params.insert("reason", reason.clone());
+            }
+            AppError::UnsupportedFormat { format } => {
+                params.insert("format", format.clone());
+            }
+            AppError::ImageNotFound { id } => {
+                params.insert("id", id.to_string());
+            }
+            AppError::InvalidArgument { reason }
+            | AppError::ClipboardUnavailable { reason }
+            | AppError::FfmpegFailed { reason } => {
+                params.insert("reason", reason.clone());
+            }
+            AppError::OperationFailed { message } => {
+                params.insert("message", message.clone());
+            }
+            AppError::ClipboardEmpty | AppError::FfmpegNotFound | AppError::Cancelled => {}
+        }
+        params
+    }
+}
+
+// English text, used in logs and by callers that still pass errors on as strings.
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::FileNotFound { path } => write!(f, "File not found: {}", path),
+            AppError::PermissionDenied { path } => write!(f, "Permission denied: {}", path),
+            AppError::ReadFailed { path, reason } => {
+                write!(f, "Failed to read {}: {}", path, reason)
+            }
+            AppError::WriteFailed { path, reason } => {
+                write!(f, "Failed to save {}: {}", path, reason)
+            }
+            AppError::DecodeFailed { reason, .. } => write!(f, "Failed to load image: {}", reason),
+            AppError::UnsupportedFormat { format } => write!(f, "Unsupported format: {}", format),
+            AppError::InvalidArgument { reason } => f.write_str(reason),
+            AppError::ImageNotFound { id } => write!(f, "No image loaded with id {}", id),
+            AppError::ClipboardEmpty => f.write_str("The clipboard does not contain an image"),
+            AppError::ClipboardUnavailable { reason } => {
+                write!(f, "Failed to open clipboard: {}", reason)
+            }
+            AppError::FfmpegNotFound => f.write_str("ffmpeg was not found"),
+            AppError::FfmpegFailed { reason } => write!(f, "ffmpeg failed: {}", reason),
+            AppError::Cancelled => f.write_str("Cancelled"),
+            AppError::OperationFailed { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("params", &self.params())?;
+        state.serialize_field("actions", self.code().actions())?;
+        state.end()
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::OperationFailed { message }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::OperationFailed {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorDescription {
+    pub code: ErrorCode,
+    // The catalog locale actually used after falling back.
+    pub locale: String,
+    pub title: String,
+    // May contain `{name}` placeholders for the error's params.
+    pub message: String,
+    pub actions: Vec<ErrorAction>,
+}
+
+#[derive(Deserialize)]
+struct CatalogEntry {
+    title: String,
+    message: String,
+}
+
+type Catalog = HashMap<String, HashMap<ErrorCode, CatalogEntry>>;
+
+fn catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| serde_json::from_str(CATALOG_JSON).expect("errors.json is valid"))
+}
+
+// Looks up `code` for a BCP 47 locale, trying e.g. "pt-BR", then "pt", then English.
+pub fn describe_error(code: ErrorCode, locale: &str) -> ErrorDescription {
+    let catalog = catalog();
+    let language = locale.split(['-', '_']).next().unwrap_or_default();
+    let (locale, entry) = [locale, language, FALLBACK_LOCALE]
+        .into_iter()
+        .find_map(|candidate| {
+            let entry = catalog.get(candidate)?.get(&code)?;
+            Some((candidate, entry))
+        })
+        .expect("every code has an English entry");
+
+    ErrorDescription {
+        code,
+        locale: locale.to_string(),
+        title: entry.title.clone(),
+        message: entry.message.clone(),
+        actions: code.actions().to_vec(),
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::analysis::blobs::{components, luminance};
+use crate::error::AppError;
+use crate::models::{CopyMoveMatch, PixelRect};
+
+// Matching runs at this size; cloned regions worth reporting survive it and
+// the block count stays manageable.
+const WORK_SIZE: u32 = 512;
+const BLOCK: usize = 8;
+// Low-frequency DCT coefficients per block, from the top-left 3x3.
+const FEATURES: usize = 9;
+// Neighbours in feature order each block is compared with.
+const NEIGHBOURS: usize = 16;
+// Feature distance below which two blocks count as copies, allowing for the
+// noise a re-save adds.
+const MATCH_DISTANCE: f32 = 5.0;
+// Features are rounded to this step for sorting, coarse enough that copies
+// blurred by a re-save still sort next to each other.
+const SORT_STEP: f32 = 8.0;
+// Blocks flatter than this (standard deviation in 0-255 luminance) match
+// everything similar, like sky, and are left out.
+const MIN_BLOCK_STD: f32 = 4.0;
+// Copies closer than this would be a block overlapping itself.
+const MIN_SHIFT: f32 = 2.0 * BLOCK as f32;
+// Matching blocks that a region needs to be reported, so chance matches
+// between single blocks don't count.
+const MIN_BLOCKS: usize = 40;
+
+fn dct_basis() -> Vec<[f32; BLOCK * BLOCK]> {
+    let n = BLOCK as f32;
+    let scale = |u: usize| {
+        if u == 0 {
+            (1.0 / n).sqrt()
+        } else {
+            (2.0 / n).sqrt()
+        }
+    };
+    let mut basis = Vec::with_capacity(FEATURES);
+    for v in 0..3 {
+        for u in 0..3 {
+            basis.push(std::array::from_fn(|i| {
+                let (x, y) = ((i % BLOCK) as f32, (i / BLOCK) as f32);
+                scale(u)
+                    * scale(v)
+                    * ((2.0 * x + 1.0) * u as f32 * std::f32::consts::PI / (2.0 * n)).cos()
+                    * ((2.0 * y + 1.0) * v as f32 * std::f32::consts::PI / (2.0 * n)).cos()
+            }));
+        }
+    }
+    basis
+}
+
+// Finds regions that appear twice in the image, as a cloned-over blemish or
+// a duplicated crowd does. Overlapping blocks are described by their low
+// DCT frequencies, sorted so similar blocks sit together, and pairs that
+// share an offset with many other pairs are grouped into regions. Each match
+// gives the two regions in the image's own pixels; which was the original
+// can't be told apart.
+pub fn detect_copy_move(img: &RgbaImage) -> Result<Vec<CopyMoveMatch>, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let factor = (width.max(height) as f32 / WORK_SIZE as f32).max(1.0);
+    let work = if factor > 1.0 {
+        let w = ((width as f32 / factor).round() as u32).max(1);
+        let h = ((height as f32 / factor).round() as u32).max(1);
+        imageops::resize(img, w, h, FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+    let (w, h) = (work.width() as usize, work.height() as usize);
+    if w < BLOCK || h < BLOCK {
+        return Ok(Vec::new());
+    }
+    let luma: Vec<f32> = work.pixels().map(luminance).collect();
+
+    // Blocks are indexed by their top-left corner on a (w - 7)x(h - 7) grid.
+    let (grid_w, grid_h) = (w - BLOCK + 1, h - BLOCK + 1);
+    let basis = dct_basis();
+    let mut blocks: Vec<(usize, [f32; FEATURES])> = Vec::new();
+    let mut pixels = [0f32; BLOCK * BLOCK];
+    for by in 0..grid_h {
+        for bx in 0..grid_w {
+            for (i, p) in pixels.iter_mut().enumerate() {
+                *p = luma[(by + i / BLOCK) * w + bx + i % BLOCK];
+            }
+            let mean = pixels.iter().sum::<f32>() / pixels.len() as f32;
+            let variance =
+                pixels.iter().map(|p| (p - mean).powi(2)).sum::<f32>() / pixels.len() as f32;
+            if variance.sqrt() < MIN_BLOCK_STD {
+                continue;
+            }
+            let features =
+                std::array::from_fn(|f| basis[f].iter().zip(&pixels).map(|(b, p)| b * p).sum());
+            blocks.push((by * grid_w + bx, features));
+        }
+    }
+    blocks.sort_by(|a, b| {
+        let key = |f: &[f32; FEATURES]| f.map(|c| (c / SORT_STEP).round() as i32);
+        key(&a.1).cmp(&key(&b.1))
+    });
+
+    // Pairs grouped by the offset from one copy to the other, pointing right
+    // (or down) so a pair is counted once whichever block came first.
+    let mut by_offset: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, (a, fa)) in blocks.iter().enumerate() {
+        for (b, fb) in blocks.iter().skip(i + 1).take(NEIGHBOURS) {
+            let distance = fa
+                .iter()
+                .zip(fb)
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f32>()
+                .sqrt();
+            if distance > MATCH_DISTANCE {
+                continue;
+            }
+            let (ax, ay) = ((a % grid_w) as i32, (a / grid_w) as i32);
+            let (bx, by) = ((b % grid_w) as i32, (b / grid_w) as i32);
+            let (mut dx, mut dy, mut source) = (bx - ax, by - ay, *a);
+            if dx < 0 || (dx == 0 && dy < 0) {
+                (dx, dy, source) = (-dx, -dy, *b);
+            }
+            if ((dx * dx + dy * dy) as f32).sqrt() < MIN_SHIFT {
+                continue;
+            }
+            by_offset.entry((dx, dy)).or_default().push(source);
+        }
+    }
+
+    // Re-saving blurs a clone's offset by a pixel, so offsets next to a
+    // stronger one join it.
+    let mut offsets: Vec<((i32, i32), usize)> =
+        by_offset.iter().map(|(&o, s)| (o, s.len())).collect();
+    offsets.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let to_image = |v: usize| (v as f32 * factor).round() as u32;
+    let mut matches = Vec::new();
+    for ((dx, dy), count) in offsets {
+        if count < MIN_BLOCKS {
+            break;
+        }
+        if !by_offset.contains_key(&(dx, dy)) {
+            continue;
+        }
+        let mut sources = Vec::new();
+        for ny in dy - 1..=dy + 1 {
+            for nx in dx - 1..=dx + 1 {
+                if let Some(blocks) = by_offset.remove(&(nx, ny)) {
+                    sources.extend(blocks);
+                }
+            }
+        }
+        let mut mask = vec![false; grid_w * grid_h];
+        for source in sources {
+            mask[source] = true;
+        }
+        for component in components(&mask, grid_w, grid_h) {
+            if component.len() < MIN_BLOCKS {
+                continue;
+            }
+            let xs = component.iter().map(|i| i % grid_w);
+            let ys = component.iter().map(|i| i / grid_w);
+            let (x0, x1) = (xs.clone().min().unwrap(), xs.max().unwrap() + BLOCK);
+            let (y0, y1) = (ys.clone().min().unwrap(), ys.max().unwrap() + BLOCK);
+            let rect = |x: usize, y: usize| PixelRect {
+                x: to_image(x).min(width - 1),
+                y: to_image(y).min(height - 1),
+                width: (to_image(x + x1 - x0).min(width) - to_image(x).min(width - 1)).max(1),
+                height: (to_image(y + y1 - y0).min(height) - to_image(y).min(height - 1)).max(1),
+            };
+            matches.push(CopyMoveMatch {
+                source: rect(x0, y0),
+                target: rect(x0 + dx as usize, (y0 as i32 + dy).max(0) as usize),
+                offset_x: (dx as f32 * factor).round() as i32,
+                offset_y: (dy as f32 * factor).round() as i32,
+                blocks: component.len(),
+            });
+        }
+    }
+    Ok(matches)
+}
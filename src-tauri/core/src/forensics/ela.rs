@@ -0,0 +1,46 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::error::AppError;
+
+// Re-saves the image as JPEG at `quality` and maps how much each pixel
+// changed, scaled so the largest change is white. Areas pasted in from
+// elsewhere or retouched have usually been compressed a different number of
+// times than their surroundings, so they stand out as brighter or darker.
+pub fn error_level_analysis(img: &RgbaImage, quality: u8) -> Result<RgbaImage, AppError> {
+    if !(1..=100).contains(&quality) {
+        return Err(AppError::invalid("JPEG quality must be between 1 and 100"));
+    }
+    let rgb = DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, quality)
+        .encode_image(&rgb)
+        .map_err(|e| format!("Failed to re-compress the image: {}", e))?;
+    let recompressed = image::load_from_memory(&encoded)
+        .map_err(|e| format!("Failed to decode the re-compressed image: {}", e))?
+        .to_rgb8();
+
+    let differences: Vec<[u8; 3]> = rgb
+        .pixels()
+        .zip(recompressed.pixels())
+        .map(|(a, b)| std::array::from_fn(|c| a[c].abs_diff(b[c])))
+        .collect();
+    let max = differences
+        .iter()
+        .flat_map(|d| d.iter().copied())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let scale = 255.0 / max as f32;
+
+    let mut map = RgbaImage::new(img.width(), img.height());
+    for (px, d) in map.pixels_mut().zip(&differences) {
+        *px = Rgba([
+            (d[0] as f32 * scale).round() as u8,
+            (d[1] as f32 * scale).round() as u8,
+            (d[2] as f32 * scale).round() as u8,
+            255,
+        ]);
+    }
+    Ok(map)
+}
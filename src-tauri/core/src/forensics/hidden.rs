@@ -0,0 +1,484 @@
+use std::fs;
+use std::path::Path;
+
+use image::ImageFormat;
+
+use crate::decode::{decode_bytes, format_name};
+use crate::error::AppError;
+use crate::models::{HiddenDataFinding, HiddenDataKind, HiddenDataReport};
+
+// Text chunks and comments shorter than this are the usual software and
+// copyright notes and aren't reported.
+const LONG_TEXT_BYTES: usize = 256;
+// Estimated fraction of least significant bits carrying a message above
+// which it is reported. Clean images estimate within a few percent of zero.
+const LSB_THRESHOLD: f64 = 0.1;
+
+const PNG_CHUNKS: &[&[u8; 4]] = &[
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"tRNS", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB",
+    b"cICP", b"mDCv", b"cLLi", b"tEXt", b"zTXt", b"iTXt", b"bKGD", b"hIST", b"pHYs", b"sPLT",
+    b"eXIf", b"tIME", b"acTL", b"fcTL", b"fdAT", b"oFFs", b"pCAL", b"sCAL", b"sTER", b"dSIG",
+];
+const WEBP_CHUNKS: &[&[u8; 4]] = &[
+    b"VP8 ", b"VP8L", b"VP8X", b"ALPH", b"ANIM", b"ANMF", b"ICCP", b"EXIF", b"XMP ",
+];
+// APPn identifiers written by cameras and common editors.
+const JPEG_APP_IDS: &[&[u8]] = &[
+    b"JFIF\0",
+    b"JFXX\0",
+    b"Exif\0",
+    b"http://ns.adobe.com/xap/1.0/\0",
+    b"http://ns.adobe.com/xmp/extension/\0",
+    b"ICC_PROFILE\0",
+    b"MPF\0",
+    b"FPXR\0",
+    b"Ducky",
+    b"Photoshop 3.0\0",
+    b"Adobe",
+];
+
+fn finding(
+    kind: HiddenDataKind,
+    offset: usize,
+    length: usize,
+    detail: String,
+) -> HiddenDataFinding {
+    HiddenDataFinding {
+        kind,
+        offset: Some(offset as u64),
+        length: Some(length as u64),
+        detail,
+    }
+}
+
+// What the bytes after the end of an image look like, from their signature.
+fn describe_payload(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"PK\x03\x04", "a ZIP archive"),
+        (b"Rar!", "a RAR archive"),
+        (b"7z\xBC\xAF", "a 7-Zip archive"),
+        (b"%PDF", "a PDF document"),
+        (b"\xFF\xD8\xFF", "a JPEG image"),
+        (b"\x89PNG", "a PNG image"),
+        (b"MZ", "a Windows executable"),
+        (b"\x7FELF", "an ELF executable"),
+        (b"\x1F\x8B", "gzip data"),
+    ];
+    if bytes.iter().all(|&b| b == 0) {
+        return "zero padding";
+    }
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+        .map_or("unrecognized data", |(_, name)| name)
+}
+
+fn trailing(bytes: &[u8], end: usize, note: &str) -> Option<HiddenDataFinding> {
+    let length = bytes.len().checked_sub(end).filter(|&n| n > 0)?;
+    Some(finding(
+        HiddenDataKind::TrailingData,
+        end,
+        length,
+        format!(
+            "{} bytes after the end of the image, which look like {}{}",
+            length,
+            describe_payload(&bytes[end..]),
+            note
+        ),
+    ))
+}
+
+fn printable(name: &[u8]) -> String {
+    name.iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+fn inspect_jpeg(bytes: &[u8], findings: &mut Vec<HiddenDataFinding>) {
+    let is_rst = |m: u8| (0xD0..=0xD7).contains(&m);
+    let mut multi_picture = false;
+    let mut i = 2;
+    while i + 1 < bytes.len() {
+        if bytes[i] != 0xFF {
+            i += 1;
+            continue;
+        }
+        let marker = bytes[i + 1];
+        match marker {
+            0xFF => i += 1,
+            0xD8 | 0x01 => i += 2,
+            m if is_rst(m) => i += 2,
+            0xD9 => {
+                let note = if multi_picture {
+                    " (the file declares extra pictures, as phones do for depth or HDR data)"
+                } else {
+                    ""
+                };
+                findings.extend(trailing(bytes, i + 2, note));
+                return;
+            }
+            _ => {
+                if i + 4 > bytes.len() {
+                    return;
+                }
+                let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+                let body = &bytes[(i + 4).min(bytes.len())..(i + 2 + length).min(bytes.len())];
+                if (0xE0..=0xEF).contains(&marker) {
+                    multi_picture |= body.starts_with(b"MPF\0");
+                    if !JPEG_APP_IDS.iter().any(|id| body.starts_with(id)) {
+                        let id_end = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+                        findings.push(finding(
+                            HiddenDataKind::UnknownChunk,
+                            i,
+                            length + 2,
+                            format!(
+                                "APP{} segment \"{}\" of {} bytes isn't a common format",
+                                marker - 0xE0,
+                                printable(&body[..id_end.min(32)]),
+                                length
+                            ),
+                        ));
+                    }
+                } else if marker == 0xFE && body.len() >= LONG_TEXT_BYTES {
+                    findings.push(finding(
+                        HiddenDataKind::Comment,
+                        i,
+                        length + 2,
+                        format!("{}-byte JPEG comment", body.len()),
+                    ));
+                }
+                if marker != 0xDA {
+                    i += 2 + length;
+                    continue;
+                }
+                // Entropy-coded data runs until the next non-RST marker.
+                let mut j = i + 2 + length;
+                while j + 1 < bytes.len()
+                    && !(bytes[j] == 0xFF && bytes[j + 1] != 0 && !is_rst(bytes[j + 1]))
+                {
+                    j += 1;
+                }
+                i = j;
+            }
+        }
+    }
+}
+
+fn inspect_png(bytes: &[u8], findings: &mut Vec<HiddenDataFinding>) {
+    let mut offset = 8;
+    while offset + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        let chunk_type: &[u8; 4] = bytes[offset + 4..offset + 8].try_into().unwrap();
+        let end = offset + 12 + length;
+        if end > bytes.len() {
+            return;
+        }
+        let name = printable(chunk_type);
+        if !PNG_CHUNKS.contains(&chunk_type) {
+            findings.push(finding(
+                HiddenDataKind::UnknownChunk,
+                offset,
+                length + 12,
+                format!("Unregistered {} chunk of {} bytes", name, length),
+            ));
+        } else if matches!(chunk_type, b"tEXt" | b"zTXt" | b"iTXt") && length >= LONG_TEXT_BYTES {
+            let data = &bytes[offset + 8..offset + 8 + length];
+            let keyword = &data[..data.iter().position(|&b| b == 0).unwrap_or(0)];
+            if keyword != b"XML:com.adobe.xmp" {
+                findings.push(finding(
+                    HiddenDataKind::Comment,
+                    offset,
+                    length + 12,
+                    format!(
+                        "{}-byte {} chunk \"{}\"",
+                        length,
+                        name,
+                        printable(&keyword[..keyword.len().min(79)])
+                    ),
+                ));
+            }
+        }
+        if chunk_type == b"IEND" {
+            findings.extend(trailing(bytes, end, ""));
+            return;
+        }
+        offset = end;
+    }
+}
+
+fn inspect_webp(bytes: &[u8], findings: &mut Vec<HiddenDataFinding>) {
+    if bytes.len() < 12 {
+        return;
+    }
+    let end = (u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize).saturating_add(8);
+    let mut offset = 12;
+    while offset + 8 <= end.min(bytes.len()) {
+        let fourcc: &[u8; 4] = bytes[offset..offset + 4].try_into().unwrap();
+        let length = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        if !WEBP_CHUNKS.contains(&fourcc) {
+            findings.push(finding(
+                HiddenDataKind::UnknownChunk,
+                offset,
+                length + 8,
+                format!("Unknown {} chunk of {} bytes", printable(fourcc), length),
+            ));
+        }
+        offset += 8 + length + (length & 1);
+    }
+    findings.extend(trailing(bytes, end, ""));
+}
+
+fn inspect_bmp(bytes: &[u8], findings: &mut Vec<HiddenDataFinding>) {
+    if bytes.len() >= 6 {
+        let size = u32::from_le_bytes(bytes[2..6].try_into().unwrap()) as usize;
+        findings.extend(trailing(bytes, size, ""));
+    }
+}
+
+// Walks the GIF blocks to the trailer byte.
+fn inspect_gif(bytes: &[u8], findings: &mut Vec<HiddenDataFinding>) {
+    let table_size = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 0x07) + 1)
+        } else {
+            0
+        }
+    };
+    let skip_sub_blocks = |mut i: usize| {
+        while i < bytes.len() && bytes[i] != 0 {
+            i += bytes[i] as usize + 1;
+        }
+        i + 1
+    };
+    if bytes.len() < 13 {
+        return;
+    }
+    let mut i = 13 + table_size(bytes[10]);
+    while i < bytes.len() {
+        match bytes[i] {
+            0x3B => {
+                findings.extend(trailing(bytes, i + 1, ""));
+                return;
+            }
+            0x21 => {
+                if i + 1 < bytes.len() && bytes[i + 1] == 0xFE {
+                    let end = skip_sub_blocks(i + 2);
+                    if end - i >= LONG_TEXT_BYTES {
+                        findings.push(finding(
+                            HiddenDataKind::Comment,
+                            i,
+                            end - i,
+                            format!("{}-byte GIF comment", end - i),
+                        ));
+                    }
+                }
+                i = skip_sub_blocks(i + 2);
+            }
+            0x2C => {
+                if i + 10 > bytes.len() {
+                    return;
+                }
+                i = skip_sub_blocks(i + 10 + table_size(bytes[i + 9]) + 1);
+            }
+            _ => return,
+        }
+    }
+}
+
+// Smoothness of a group of neighbouring values.
+fn variation(group: &[i32; 4]) -> i32 {
+    group.windows(2).map(|w| (w[1] - w[0]).abs()).sum()
+}
+
+// Counts of groups made rougher (regular) and smoother (singular) by
+// flipping the middle two values with `flip`, as fractions of all groups.
+fn regular_singular(values: &[i32], flip: impl Fn(i32) -> i32) -> (f64, f64) {
+    let (mut regular, mut singular) = (0usize, 0usize);
+    let groups = values.chunks_exact(4);
+    let total = groups.len().max(1) as f64;
+    for group in groups {
+        let group = [group[0], group[1], group[2], group[3]];
+        let flipped = [group[0], flip(group[1]), flip(group[2]), group[3]];
+        match variation(&flipped).cmp(&variation(&group)) {
+            std::cmp::Ordering::Greater => regular += 1,
+            std::cmp::Ordering::Less => singular += 1,
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+    (regular as f64 / total, singular as f64 / total)
+}
+
+// Fridrich, Goljan and Du's RS analysis of one channel, in row order.
+// Flipping least significant bits roughens natural images about as much as
+// shifting values by one does; replacing them with message bits breaks that
+// symmetry in a way that gives away how many were replaced. Returns the
+// estimated fraction of values carrying message bits.
+fn rs_embedding_rate(values: &[i32]) -> f64 {
+    let flip = |v: i32| v ^ 1;
+    let shifted_flip = |v: i32| ((v + 1) ^ 1) - 1;
+    let inverted: Vec<i32> = values.iter().map(|&v| flip(v)).collect();
+    let (r, s) = regular_singular(values, flip);
+    let (r_neg, s_neg) = regular_singular(values, shifted_flip);
+    let (r_inv, s_inv) = regular_singular(&inverted, flip);
+    let (r_inv_neg, s_inv_neg) = regular_singular(&inverted, shifted_flip);
+    let (d0, d1) = (r - s, r_inv - s_inv);
+    let (d0_neg, d1_neg) = (r_neg - s_neg, r_inv_neg - s_inv_neg);
+
+    let a = 2.0 * (d1 + d0);
+    let b = d0_neg - d1_neg - d1 - 3.0 * d0;
+    let c = d0 - d0_neg;
+    let z = if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return 0.0;
+        }
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return 0.0;
+        }
+        let roots = [
+            (-b + discriminant.sqrt()) / (2.0 * a),
+            (-b - discriminant.sqrt()) / (2.0 * a),
+        ];
+        if roots[0].abs() < roots[1].abs() {
+            roots[0]
+        } else {
+            roots[1]
+        }
+    };
+    (z / (z - 0.5)).clamp(0.0, 1.0)
+}
+
+fn inspect_lsb(bytes: &[u8], path: &Path, findings: &mut Vec<HiddenDataFinding>) -> Option<f32> {
+    let image = decode_bytes(bytes, Some(path), false).ok()?.image;
+    let (rate, channel) = ["red", "green", "blue"]
+        .into_iter()
+        .enumerate()
+        .map(|(c, channel)| {
+            let values: Vec<i32> = image.pixels().map(|px| px[c] as i32).collect();
+            (rs_embedding_rate(&values), channel)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))?;
+    if rate > LSB_THRESHOLD {
+        findings.push(HiddenDataFinding {
+            kind: HiddenDataKind::LsbAnomaly,
+            offset: None,
+            length: None,
+            detail: format!(
+                "About {:.0}% of the {} channel's least significant bits look replaced \
+                 by embedded data (RS analysis)",
+                rate * 100.0,
+                channel
+            ),
+        });
+    }
+    Some(rate as f32)
+}
+
+// Looks for data hidden in an image file: bytes after the end of the image,
+// chunks or segments no common software writes, long embedded text, and for
+// lossless formats, least significant bits that look like an embedded
+// message. Findings are leads, not proof; phones append depth maps and some
+// editors add private chunks.
+pub fn inspect_hidden_data(path: &Path) -> Result<HiddenDataReport, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let format = image::guess_format(&bytes).ok();
+    let mut findings = Vec::new();
+    match format {
+        Some(ImageFormat::Jpeg) => inspect_jpeg(&bytes, &mut findings),
+        Some(ImageFormat::Png) => inspect_png(&bytes, &mut findings),
+        Some(ImageFormat::WebP) => inspect_webp(&bytes, &mut findings),
+        Some(ImageFormat::Bmp) => inspect_bmp(&bytes, &mut findings),
+        Some(ImageFormat::Gif) => inspect_gif(&bytes, &mut findings),
+        Some(_) => {}
+        None => {
+            return Err(AppError::UnsupportedFormat {
+                format: path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "unknown".to_string()),
+            })
+        }
+    }
+    // Lossy compression rewrites the low bits, so pixel LSBs only carry a
+    // message in lossless files.
+    let lsb_embedding_rate = match format {
+        Some(ImageFormat::Png | ImageFormat::Bmp | ImageFormat::Tiff) => {
+            inspect_lsb(&bytes, path, &mut findings)
+        }
+        _ => None,
+    };
+
+    Ok(HiddenDataReport {
+        path: path.to_string_lossy().into_owned(),
+        format: format.map(format_name),
+        findings,
+        lsb_embedding_rate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A deterministic stand-in for a row of photo pixels: a smooth ramp with
+    // a little sensor noise.
+    fn natural(len: usize) -> Vec<i32> {
+        let mut state = 0x9e37_79b9u32;
+        (0..len)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let t = i as f32 / 40.0;
+                (128.0 + 60.0 * t.sin() + 20.0 * (t * 0.37).cos()) as i32 + (state % 5) as i32 - 2
+            })
+            .collect()
+    }
+
+    fn embed(values: &[i32], every: usize) -> Vec<i32> {
+        let mut state = 0x1234_5678u32;
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                if i % every == 0 {
+                    (v & !1) | (state & 1) as i32
+                } else {
+                    v
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clean_values_show_little_embedding() {
+        let rate = rs_embedding_rate(&natural(40_000));
+        assert!(rate < 0.1, "{}", rate);
+    }
+
+    #[test]
+    fn replaced_bits_raise_the_estimate() {
+        let clean = natural(40_000);
+        let half = rs_embedding_rate(&embed(&clean, 2));
+        let full = rs_embedding_rate(&embed(&clean, 1));
+        assert!(half > 0.3, "{}", half);
+        assert!(full > half, "{} <= {}", full, half);
+    }
+
+    #[test]
+    fn flat_values_are_not_flagged() {
+        assert_eq!(rs_embedding_rate(&[100; 4000]), 0.0);
+        assert_eq!(rs_embedding_rate(&[]), 0.0);
+    }
+}
@@ -0,0 +1,7 @@
+//! Checks for signs that an image was edited after capture.
+
+pub mod copy_move;
+pub mod ela;
+pub mod hidden;
+pub mod prnu;
+pub mod thumbnail;
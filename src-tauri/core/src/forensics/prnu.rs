@@ -0,0 +1,361 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{imageops, RgbaImage};
+use rayon::prelude::*;
+
+use crate::decode::decode_file;
+use crate::error::AppError;
+use crate::metadata::extract_metadata;
+use crate::models::{PrnuFingerprint, PrnuMatch};
+
+const MAGIC: &[u8; 4] = b"PRNU";
+// Fingerprints cover the centre of the frame up to this size, which is
+// plenty to identify a sensor and keeps them a few megabytes.
+const MAX_SIZE: u32 = 1024;
+// Noise the residual filter separates out, in 0-255 levels.
+const SIGMA: f64 = 3.0;
+// Local windows the filter tries, keeping whichever sees the least detail.
+const WINDOWS: [usize; 4] = [3, 5, 7, 9];
+// Saturated pixels have lost the pattern and are left out.
+const SATURATED: f32 = 250.0;
+// Peak-to-correlation energy above which a photo is taken to be from the
+// fingerprinted camera; unrelated cameras score in single figures.
+const PCE_THRESHOLD: f32 = 60.0;
+// Shifts the correlation is sampled at to measure its background, skipping
+// the few around zero where a match's peak can spread.
+const SHIFT_STEP: i64 = 8;
+const SHIFT_RANGE: i64 = 48;
+const PEAK_RADIUS: i64 = 5;
+
+// The centre of the frame, as luminance on 0-255.
+fn central_luma(img: &RgbaImage, width: u32, height: u32) -> Vec<f32> {
+    let (x, y) = ((img.width() - width) / 2, (img.height() - height) / 2);
+    imageops::crop_imm(img, x, y, width, height)
+        .to_image()
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect()
+}
+
+// Mean of `values` over the `size`x`size` window centred on each pixel,
+// shrinking at the edges.
+fn box_mean(values: &[f64], w: usize, h: usize, size: usize) -> Vec<f64> {
+    let mut sum = vec![0f64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0f64;
+        for x in 0..w {
+            row += values[y * w + x];
+            let i = (y + 1) * (w + 1) + x + 1;
+            sum[i] = sum[i - w - 1] + row;
+        }
+    }
+    let r = size / 2;
+    (0..w * h)
+        .map(|i| {
+            let (x, y) = (i % w, i / w);
+            let (x0, x1) = (x.saturating_sub(r), (x + r + 1).min(w));
+            let (y0, y1) = (y.saturating_sub(r), (y + r + 1).min(h));
+            let total = sum[y1 * (w + 1) + x1] + sum[y0 * (w + 1) + x0]
+                - sum[y0 * (w + 1) + x1]
+                - sum[y1 * (w + 1) + x0];
+            total / ((x1 - x0) * (y1 - y0)) as f64
+        })
+        .collect()
+}
+
+// The sensor's share of the image's fine detail. Detail is what a 3x3 mean
+// leaves behind; where the scene has texture of its own it is damped by how
+// far its local energy exceeds the noise, as in Lukáš, Fridrich and Goljan's
+// wavelet filter, leaving mostly the pattern behind.
+fn residual(luma: &[f32], w: usize, h: usize) -> Vec<f32> {
+    let values: Vec<f64> = luma.iter().map(|&v| v as f64).collect();
+    let mean = box_mean(&values, w, h, 3);
+    let detail: Vec<f64> = values.iter().zip(&mean).map(|(v, m)| v - m).collect();
+    let energy: Vec<f64> = detail.iter().map(|d| d * d).collect();
+    let mut signal = vec![f64::INFINITY; w * h];
+    for size in WINDOWS {
+        for (s, e) in signal.iter_mut().zip(box_mean(&energy, w, h, size)) {
+            *s = s.min((e - SIGMA * SIGMA).max(0.0));
+        }
+    }
+    let noise = SIGMA * SIGMA;
+    detail
+        .iter()
+        .zip(&signal)
+        .map(|(d, s)| (d * noise / (s + noise)) as f32)
+        .collect()
+}
+
+// Removes the row and column averages, which carry patterns that cameras
+// of the same model share, such as demosaicing and JPEG block artefacts.
+fn zero_mean(values: &mut [f32], w: usize, h: usize) {
+    for row in values.chunks_mut(w) {
+        let mean = row.iter().sum::<f32>() / w as f32;
+        row.iter_mut().for_each(|v| *v -= mean);
+    }
+    for x in 0..w {
+        let mean = (0..h).map(|y| values[y * w + x]).sum::<f32>() / h as f32;
+        (0..h).for_each(|y| values[y * w + x] -= mean);
+    }
+}
+
+fn crop_size(width: u32, height: u32) -> (u32, u32) {
+    (width.min(MAX_SIZE), height.min(MAX_SIZE))
+}
+
+fn write_fingerprint(
+    path: &Path,
+    fingerprint: &PrnuFingerprint,
+    values: &[f32],
+) -> Result<(), AppError> {
+    let mut bytes = Vec::with_capacity(24 + values.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    for field in [
+        fingerprint.width,
+        fingerprint.height,
+        fingerprint.crop_width,
+        fingerprint.crop_height,
+        fingerprint.images as u32,
+    ] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    for v in values {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    fs::write(path, bytes).map_err(|e| AppError::io(path, e, true))
+}
+
+struct Pattern {
+    // The photos' size and the central area the values cover.
+    size: (u32, u32),
+    crop: (u32, u32),
+    values: Vec<f32>,
+}
+
+fn read_fingerprint(path: &Path) -> Result<Pattern, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let invalid = || AppError::invalid(format!("{} is not a PRNU fingerprint", path.display()));
+    if bytes.len() < 24 || &bytes[..4] != MAGIC {
+        return Err(invalid());
+    }
+    let field = |i: usize| u32::from_le_bytes(bytes[4 + i * 4..8 + i * 4].try_into().unwrap());
+    let (width, height, crop_width, crop_height) = (field(0), field(1), field(2), field(3));
+    let values: Vec<f32> = bytes[24..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    if values.len() != crop_width as usize * crop_height as usize {
+        return Err(invalid());
+    }
+    Ok(Pattern {
+        size: (width, height),
+        crop: (crop_width, crop_height),
+        values,
+    })
+}
+
+// Estimates the camera's photo-response non-uniformity, the fixed pattern by
+// which each of its pixels reads slightly brighter or darker than the rest,
+// from photos it took, and writes it to `output`. Every photo must be at the
+// sensor's full size in the same orientation; a couple of dozen evenly lit,
+// unfocused shots such as sky give the cleanest pattern.
+pub fn extract_prnu(paths: &[PathBuf], output: &Path) -> Result<PrnuFingerprint, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::invalid("At least one image is needed"));
+    }
+    // Only the centre is kept, so a large set needn't fit in memory whole.
+    let images = paths
+        .par_iter()
+        .map(|path| {
+            let image = decode_file(path, false)?.image;
+            let (cw, ch) = crop_size(image.width(), image.height());
+            Ok((path, image.dimensions(), central_luma(&image, cw, ch)))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    let (width, height) = images[0].1;
+    if let Some((path, (w, h), _)) = images.iter().find(|(_, size, _)| *size != (width, height)) {
+        return Err(AppError::invalid(format!(
+            "{} is {}x{}, but the first image is {}x{}",
+            path.display(),
+            w,
+            h,
+            width,
+            height
+        )));
+    }
+    let (cw, ch) = crop_size(width, height);
+    let (w, h) = (cw as usize, ch as usize);
+
+    // K = Σ W·I / Σ I², the maximum-likelihood estimate under I·K noise.
+    let (numerator, denominator) = images
+        .par_iter()
+        .map(|(_, _, luma)| {
+            let noise = residual(luma, w, h);
+            let mut numerator = vec![0f32; w * h];
+            let mut denominator = vec![0f32; w * h];
+            for i in 0..w * h {
+                if luma[i] < SATURATED {
+                    numerator[i] = noise[i] * luma[i];
+                    denominator[i] = luma[i] * luma[i];
+                }
+            }
+            (numerator, denominator)
+        })
+        .reduce(
+            || (vec![0f32; w * h], vec![0f32; w * h]),
+            |(mut n, mut d), (n2, d2)| {
+                n.iter_mut().zip(n2).for_each(|(a, b)| *a += b);
+                d.iter_mut().zip(d2).for_each(|(a, b)| *a += b);
+                (n, d)
+            },
+        );
+    let mut values: Vec<f32> = numerator
+        .iter()
+        .zip(&denominator)
+        .map(|(n, d)| if *d > 0.0 { n / d } else { 0.0 })
+        .collect();
+    zero_mean(&mut values, w, h);
+
+    let camera = extract_metadata(images[0].0)
+        .ok()
+        .and_then(|m| m.camera_model);
+    let fingerprint = PrnuFingerprint {
+        path: output.to_string_lossy().into_owned(),
+        camera,
+        images: images.len(),
+        width,
+        height,
+        crop_width: cw,
+        crop_height: ch,
+    };
+    write_fingerprint(output, &fingerprint, &values)?;
+    Ok(fingerprint)
+}
+
+// Tests whether `img` carries the pattern in the fingerprint at `path`. The
+// residual is correlated with the pattern as this image would show it, and
+// the peak-to-correlation energy compares that with the correlation at
+// shifts where no match can be, so the score means the same at any size.
+pub fn match_prnu(img: &RgbaImage, path: &Path) -> Result<PrnuMatch, AppError> {
+    let Pattern {
+        size: (width, height),
+        crop: (cw, ch),
+        values: pattern,
+    } = read_fingerprint(path)?;
+    if img.dimensions() != (width, height) {
+        return Err(AppError::invalid(format!(
+            "The image is {}x{}, but the fingerprint is for {}x{} photos",
+            img.width(),
+            img.height(),
+            width,
+            height
+        )));
+    }
+    let (w, h) = (cw as usize, ch as usize);
+    let luma = central_luma(img, cw, ch);
+    let mut noise = residual(&luma, w, h);
+    let mut expected: Vec<f32> = luma.iter().zip(&pattern).map(|(l, k)| l * k).collect();
+    for values in [&mut noise, &mut expected] {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter_mut().for_each(|v| *v -= mean);
+    }
+
+    // Circular, so each shift covers every pixel.
+    let correlate = |dx: i64, dy: i64| -> f64 {
+        (0..h)
+            .into_par_iter()
+            .map(|y| {
+                let sy = (y as i64 + dy).rem_euclid(h as i64) as usize;
+                (0..w)
+                    .map(|x| {
+                        let sx = (x as i64 + dx).rem_euclid(w as i64) as usize;
+                        noise[y * w + x] as f64 * expected[sy * w + sx] as f64
+                    })
+                    .sum::<f64>()
+            })
+            .sum()
+    };
+    let norm = |values: &[f32]| {
+        values
+            .iter()
+            .map(|&v| v as f64 * v as f64)
+            .sum::<f64>()
+            .sqrt()
+    };
+    let peak = correlate(0, 0);
+    let mut background = Vec::new();
+    for dy in (-SHIFT_RANGE..=SHIFT_RANGE).step_by(SHIFT_STEP as usize) {
+        for dx in (-SHIFT_RANGE..=SHIFT_RANGE).step_by(SHIFT_STEP as usize) {
+            if dx.abs() <= PEAK_RADIUS && dy.abs() <= PEAK_RADIUS {
+                continue;
+            }
+            background.push(correlate(dx, dy));
+        }
+    }
+    let energy = background.iter().map(|c| c * c).sum::<f64>() / background.len() as f64;
+    let correlation = peak / (norm(&noise) * norm(&expected)).max(f64::MIN_POSITIVE);
+    let pce = (peak * peak.abs() / energy.max(f64::MIN_POSITIVE)) as f32;
+    Ok(PrnuMatch {
+        correlation: correlation as f32,
+        pce,
+        matches: pce > PCE_THRESHOLD,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint_file(name: &str) -> (PathBuf, Vec<f32>) {
+        let path = std::env::temp_dir().join(format!(
+            "image-analyzer-{}-{}.prnu",
+            name,
+            std::process::id()
+        ));
+        let fingerprint = PrnuFingerprint {
+            path: path.display().to_string(),
+            camera: None,
+            images: 3,
+            width: 40,
+            height: 30,
+            crop_width: 4,
+            crop_height: 2,
+        };
+        let values: Vec<f32> = (0..8).map(|i| i as f32 * 0.25 - 1.0).collect();
+        write_fingerprint(&path, &fingerprint, &values).unwrap();
+        (path, values)
+    }
+
+    #[test]
+    fn fingerprint_round_trips() {
+        let (path, values) = fingerprint_file("round-trip");
+        let pattern = read_fingerprint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(pattern.size, (40, 30));
+        assert_eq!(pattern.crop, (4, 2));
+        assert_eq!(pattern.values, values);
+    }
+
+    #[test]
+    fn truncated_fingerprint_is_rejected() {
+        let (path, _) = fingerprint_file("truncated");
+        let bytes = fs::read(&path).unwrap();
+        for len in [bytes.len() - 4, bytes.len() - 1, 23, 3] {
+            fs::write(&path, &bytes[..len]).unwrap();
+            assert!(read_fingerprint(&path).is_err(), "{} bytes", len);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let (path, _) = fingerprint_file("magic");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[..4].copy_from_slice(b"\x89PNG");
+        fs::write(&path, &bytes).unwrap();
+        let result = read_fingerprint(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use exif::{In, Tag};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use crate::decode::decode_bytes;
+use crate::error::AppError;
+use crate::models::{PixelRect, ThumbnailComparison};
+use crate::processing::transform::fit_within;
+
+// Side of the squares, in thumbnail pixels, whose average colours are
+// compared. Averaging hides the thumbnail's own JPEG noise and sharpening.
+const BLOCK: u32 = 4;
+// Mean and worst block differences, in 0-255 levels, above which the image
+// probably no longer shows what the camera saved. Untouched files stay well
+// under both.
+const MEAN_THRESHOLD: f32 = 10.0;
+const PEAK_THRESHOLD: f32 = 40.0;
+// Padding bars are near-black; anything brighter is picture.
+const BAR_LEVEL: f32 = 24.0;
+
+// The JPEG thumbnail in the file's EXIF data, if it has one.
+fn exif_thumbnail(bytes: &[u8]) -> Option<RgbaImage> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()?;
+    let field = |tag| exif.get_field(tag, In::THUMBNAIL)?.value.get_uint(0);
+    let offset = field(Tag::JPEGInterchangeFormat)? as usize;
+    let length = field(Tag::JPEGInterchangeFormatLength)? as usize;
+    let data = exif.buf().get(offset..offset.checked_add(length)?)?;
+    Some(image::load_from_memory(data).ok()?.to_rgba8())
+}
+
+fn mean_level(img: &RgbaImage, rect: PixelRect) -> f32 {
+    let pixels = imageops::crop_imm(img, rect.x, rect.y, rect.width, rect.height).to_image();
+    let sum: f32 = pixels
+        .pixels()
+        .map(|p| (p[0] as f32 + p[1] as f32 + p[2] as f32) / 3.0)
+        .sum();
+    sum / (rect.width * rect.height).max(1) as f32
+}
+
+// The part of the thumbnail showing the picture. Cameras pad thumbnails to a
+// fixed 4:3 or 16:9 with black bars; if what should be bars isn't black, the
+// image was cropped since and the whole thumbnail is compared.
+fn content_area(thumbnail: &RgbaImage, width: u32, height: u32) -> (PixelRect, bool) {
+    let (tw, th) = thumbnail.dimensions();
+    let full = PixelRect {
+        x: 0,
+        y: 0,
+        width: tw,
+        height: th,
+    };
+    let (cw, ch) = fit_within(width, height, tw, th);
+    if tw.abs_diff(cw) <= 1 && th.abs_diff(ch) <= 1 {
+        return (full, true);
+    }
+    let (x, y) = ((tw - cw) / 2, (th - ch) / 2);
+    let bars = if cw < tw {
+        [
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: x,
+                height: th,
+            },
+            PixelRect {
+                x: x + cw,
+                y: 0,
+                width: tw - x - cw,
+                height: th,
+            },
+        ]
+    } else {
+        [
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: tw,
+                height: y,
+            },
+            PixelRect {
+                x: 0,
+                y: y + ch,
+                width: tw,
+                height: th - y - ch,
+            },
+        ]
+    };
+    if bars
+        .iter()
+        .all(|&bar| mean_level(thumbnail, bar) < BAR_LEVEL)
+    {
+        let content = PixelRect {
+            x,
+            y,
+            width: cw,
+            height: ch,
+        };
+        (content, true)
+    } else {
+        (full, false)
+    }
+}
+
+fn block_mean(img: &RgbaImage, bx: u32, by: u32, size: u32) -> [f32; 3] {
+    let mut sum = [0f32; 3];
+    let mut count = 0;
+    for y in by..(by + size).min(img.height()) {
+        for x in bx..(bx + size).min(img.width()) {
+            let Rgba(p) = *img.get_pixel(x, y);
+            for c in 0..3 {
+                sum[c] += p[c] as f32;
+            }
+            count += 1;
+        }
+    }
+    sum.map(|s| s / count.max(1) as f32)
+}
+
+// Compares the EXIF thumbnail with the image it's meant to preview. Editors
+// often rewrite the pixels but carry the camera's metadata across untouched,
+// leaving a thumbnail of the image as it was shot. Returns `None` for files
+// without one.
+pub fn compare_thumbnail(path: &Path) -> Result<Option<ThumbnailComparison>, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let Some(thumbnail) = exif_thumbnail(&bytes) else {
+        return Ok(None);
+    };
+    let image = decode_bytes(&bytes, Some(path), false)?.image;
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || thumbnail.width() == 0 || thumbnail.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let (area, aspect_matches) = content_area(&thumbnail, width, height);
+    let thumbnail =
+        imageops::crop_imm(&thumbnail, area.x, area.y, area.width, area.height).to_image();
+    let scaled = imageops::resize(&image, area.width, area.height, FilterType::Triangle);
+
+    let (mut total, mut blocks, mut peak, mut peak_at) = (0f32, 0u32, 0f32, (0, 0));
+    for by in (0..area.height).step_by(BLOCK as usize) {
+        for bx in (0..area.width).step_by(BLOCK as usize) {
+            let (a, b) = (
+                block_mean(&thumbnail, bx, by, BLOCK),
+                block_mean(&scaled, bx, by, BLOCK),
+            );
+            let difference = (0..3).map(|c| (a[c] - b[c]).abs()).fold(0.0, f32::max);
+            total += difference;
+            blocks += 1;
+            if difference > peak {
+                (peak, peak_at) = (difference, (bx, by));
+            }
+        }
+    }
+    let difference = total / blocks.max(1) as f32;
+
+    let scale_x = width as f32 / area.width as f32;
+    let scale_y = height as f32 / area.height as f32;
+    let to_image = |v: u32, scale: f32, max: u32| ((v as f32 * scale).round() as u32).min(max);
+    let (x, y) = (
+        to_image(peak_at.0, scale_x, width - 1),
+        to_image(peak_at.1, scale_y, height - 1),
+    );
+    let peak_region = PixelRect {
+        x,
+        y,
+        width: (to_image(peak_at.0 + BLOCK, scale_x, width) - x).max(1),
+        height: (to_image(peak_at.1 + BLOCK, scale_y, height) - y).max(1),
+    };
+    Ok(Some(ThumbnailComparison {
+        thumbnail_width: area.width,
+        thumbnail_height: area.height,
+        aspect_matches,
+        difference,
+        peak_difference: peak,
+        peak_region,
+        likely_edited: !aspect_matches || difference > MEAN_THRESHOLD || peak > PEAK_THRESHOLD,
+    }))
+}
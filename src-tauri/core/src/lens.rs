@@ -0,0 +1,321 @@
+use std::sync::OnceLock;
+
+use std::path::Path;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::metadata::{read_camera_info, CameraInfo};
+
+// Lensfun-style calibration data: ptlens distortion, poly3 TCA and pa vignetting,
+// each sampled at one or more focal lengths.
+const BUNDLED_LENS_DB: &str = include_str!("../resources/lens_db.json");
+
+#[derive(Debug, Deserialize)]
+struct LensDatabase {
+    lenses: Vec<LensEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LensEntry {
+    model: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    // Bodies with a fixed lens (compacts) are matched by camera model instead.
+    #[serde(default)]
+    cameras: Vec<String>,
+    #[serde(default)]
+    distortion: Vec<DistortionCalibration>,
+    #[serde(default)]
+    tca: Vec<TcaCalibration>,
+    #[serde(default)]
+    vignetting: Vec<VignettingCalibration>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct DistortionCalibration {
+    focal: f64,
+    a: f64,
+    b: f64,
+    c: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct TcaCalibration {
+    focal: f64,
+    vr: f64,
+    vb: f64,
+    #[serde(default)]
+    cr: f64,
+    #[serde(default)]
+    cb: f64,
+    #[serde(default)]
+    br: f64,
+    #[serde(default)]
+    bb: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct VignettingCalibration {
+    focal: f64,
+    aperture: f64,
+    k1: f64,
+    k2: f64,
+    k3: f64,
+}
+
+// Calibration coefficients resolved for a single focal length / aperture.
+#[derive(Clone, Copy, Debug, Default)]
+struct LensCorrection {
+    distortion: Option<[f64; 3]>,
+    tca: Option<[f64; 6]>,
+    vignetting: Option<[f64; 3]>,
+}
+
+fn lens_database() -> &'static LensDatabase {
+    static DB: OnceLock<LensDatabase> = OnceLock::new();
+    DB.get_or_init(|| {
+        serde_json::from_str(BUNDLED_LENS_DB).expect("bundled lens database is valid JSON")
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '.')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn find_lens<'a>(db: &'a LensDatabase, camera: &CameraInfo) -> Option<&'a LensEntry> {
+    if let Some(lens_model) = camera.lens_model.as_deref() {
+        let wanted = normalize_name(lens_model);
+        let found = db.lenses.iter().find(|lens| {
+            normalize_name(&lens.model) == wanted
+                || lens
+                    .aliases
+                    .iter()
+                    .any(|alias| normalize_name(alias) == wanted)
+        });
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    let body = normalize_name(camera.model.as_deref()?);
+    db.lenses
+        .iter()
+        .find(|lens| lens.cameras.iter().any(|c| normalize_name(c) == body))
+}
+
+// Linearly interpolate calibration coefficients between the two nearest focal lengths.
+fn interpolate<T: Copy, const N: usize>(
+    samples: &[T],
+    focal: f64,
+    focal_of: impl Fn(&T) -> f64,
+    coeffs_of: impl Fn(&T) -> [f64; N],
+) -> Option<[f64; N]> {
+    let mut sorted: Vec<T> = samples.to_vec();
+    sorted.sort_by(|a, b| focal_of(a).total_cmp(&focal_of(b)));
+
+    let first = sorted.first()?;
+    let last = sorted.last()?;
+    if focal <= focal_of(first) {
+        return Some(coeffs_of(first));
+    }
+    if focal >= focal_of(last) {
+        return Some(coeffs_of(last));
+    }
+
+    let upper = sorted.iter().position(|s| focal_of(s) >= focal)?;
+    let (lo, hi) = (&sorted[upper - 1], &sorted[upper]);
+    let span = focal_of(hi) - focal_of(lo);
+    let t = if span > 0.0 {
+        (focal - focal_of(lo)) / span
+    } else {
+        0.0
+    };
+    let (a, b) = (coeffs_of(lo), coeffs_of(hi));
+    let mut out = [0.0; N];
+    for i in 0..N {
+        out[i] = a[i] + (b[i] - a[i]) * t;
+    }
+    Some(out)
+}
+
+fn resolve_correction(lens: &LensEntry, focal: f64, aperture: Option<f64>) -> LensCorrection {
+    let distortion = interpolate(&lens.distortion, focal, |d| d.focal, |d| [d.a, d.b, d.c]);
+    let tca = interpolate(
+        &lens.tca,
+        focal,
+        |t| t.focal,
+        |t| [t.vr, t.vb, t.cr, t.cb, t.br, t.bb],
+    );
+
+    // Vignetting depends on aperture as well; use the calibrated aperture closest
+    // (in stops) to the one the shot was taken at.
+    let vignetting = aperture
+        .and_then(|f_number| {
+            lens.vignetting.iter().map(|v| v.aperture).min_by(|a, b| {
+                let da = (a.log2() - f_number.log2()).abs();
+                let db = (b.log2() - f_number.log2()).abs();
+                da.total_cmp(&db)
+            })
+        })
+        .or_else(|| lens.vignetting.first().map(|v| v.aperture))
+        .and_then(|closest| {
+            let samples: Vec<VignettingCalibration> = lens
+                .vignetting
+                .iter()
+                .copied()
+                .filter(|v| v.aperture == closest)
+                .collect();
+            interpolate(&samples, focal, |v| v.focal, |v| [v.k1, v.k2, v.k3])
+        });
+
+    LensCorrection {
+        distortion,
+        tca,
+        vignetting,
+    }
+}
+
+fn sample_channel(img: &RgbaImage, x: f64, y: f64, channel: usize) -> Option<f32> {
+    let (w, h) = (img.width() as f64, img.height() as f64);
+    if x < 0.0 || y < 0.0 || x > w - 1.0 || y > h - 1.0 {
+        return None;
+    }
+
+    let x0 = x.floor() as u32;
+    let y0 = y.floor() as u32;
+    let x1 = (x0 + 1).min(img.width() - 1);
+    let y1 = (y0 + 1).min(img.height() - 1);
+    let fx = (x - x0 as f64) as f32;
+    let fy = (y - y0 as f64) as f32;
+
+    let p = |px: u32, py: u32| img.get_pixel(px, py)[channel] as f32;
+    let top = p(x0, y0) * (1.0 - fx) + p(x1, y0) * fx;
+    let bottom = p(x0, y1) * (1.0 - fx) + p(x1, y1) * fx;
+    Some(top * (1.0 - fy) + bottom * fy)
+}
+
+fn apply_correction(src: &RgbaImage, correction: &LensCorrection) -> RgbaImage {
+    let width = src.width();
+    let height = src.height();
+    let cx = (width as f64 - 1.0) / 2.0;
+    let cy = (height as f64 - 1.0) / 2.0;
+    // Lensfun normalizes distortion so the shorter half-side has radius 1, and
+    // vignetting so the half-diagonal does.
+    let dist_norm = width.min(height) as f64 / 2.0;
+    let vig_norm = ((width as f64).powi(2) + (height as f64).powi(2)).sqrt() / 2.0;
+
+    let mut out = RgbaImage::new(width, height);
+    out.par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width as usize {
+                let dx = (x as f64 - cx) / dist_norm;
+                let dy = (y as f64 - cy) / dist_norm;
+                let ru = (dx * dx + dy * dy).sqrt();
+
+                // ptlens: r_d = r_u * (a r_u^3 + b r_u^2 + c r_u + 1 - a - b - c)
+                let scale = match correction.distortion {
+                    Some([a, b, c]) => a * ru.powi(3) + b * ru.powi(2) + c * ru + 1.0 - a - b - c,
+                    None => 1.0,
+                };
+                let rd = ru * scale;
+
+                // poly3 TCA scales the red and blue planes relative to green.
+                let (red_scale, blue_scale) = match correction.tca {
+                    Some([vr, vb, cr, cb, br, bb]) => {
+                        (br * rd * rd + cr * rd + vr, bb * rd * rd + cb * rd + vb)
+                    }
+                    None => (1.0, 1.0),
+                };
+
+                let source = |channel_scale: f64| {
+                    let s = scale * channel_scale;
+                    (cx + dx * s * dist_norm, cy + dy * s * dist_norm)
+                };
+                let (gx, gy) = source(1.0);
+                let pixel = &mut row[x * 4..x * 4 + 4];
+
+                let (Some(g), Some(a)) = (
+                    sample_channel(src, gx, gy, 1),
+                    sample_channel(src, gx, gy, 3),
+                ) else {
+                    pixel.copy_from_slice(&[0, 0, 0, 0]);
+                    continue;
+                };
+                let (rx, ry) = source(red_scale);
+                let (bx, by) = source(blue_scale);
+                let r = sample_channel(src, rx, ry, 0).unwrap_or(g);
+                let b = sample_channel(src, bx, by, 2).unwrap_or(g);
+
+                // pa vignetting: C_d = C_s * (1 + k1 r^2 + k2 r^4 + k3 r^6)
+                let gain = match correction.vignetting {
+                    Some([k1, k2, k3]) => {
+                        let rv2 = ((gx - cx).powi(2) + (gy - cy).powi(2)) / (vig_norm * vig_norm);
+                        let falloff = 1.0 + k1 * rv2 + k2 * rv2.powi(2) + k3 * rv2.powi(3);
+                        if falloff > 0.0 {
+                            (1.0 / falloff) as f32
+                        } else {
+                            1.0
+                        }
+                    }
+                    None => 1.0,
+                };
+
+                pixel[0] = (r * gain).round().clamp(0.0, 255.0) as u8;
+                pixel[1] = (g * gain).round().clamp(0.0, 255.0) as u8;
+                pixel[2] = (b * gain).round().clamp(0.0, 255.0) as u8;
+                pixel[3] = a.round().clamp(0.0, 255.0) as u8;
+            }
+        });
+
+    out
+}
+
+// Result of correcting an image with the bundled profile for its lens.
+pub struct LensCorrectionOutput {
+    pub image: RgbaImage,
+    pub lens: String,
+    pub camera: Option<String>,
+    pub focal_length: f64,
+    pub distortion_corrected: bool,
+    pub tca_corrected: bool,
+    pub vignetting_corrected: bool,
+}
+
+// Looks up the lens that took `source` (from its EXIF data) and removes its
+// distortion, lateral chromatic aberration and vignetting from `img`.
+pub fn auto_correct(img: &RgbaImage, source: &Path) -> Result<LensCorrectionOutput, AppError> {
+    let camera = read_camera_info(source)
+        .ok_or_else(|| AppError::invalid("Image has no readable EXIF data"))?;
+    let focal_length = camera
+        .focal_length
+        .ok_or_else(|| AppError::invalid("EXIF data does not include a focal length"))?;
+
+    let lens = find_lens(lens_database(), &camera).ok_or_else(|| {
+        AppError::invalid(format!(
+            "No lens profile found for {}",
+            camera
+                .lens_model
+                .as_deref()
+                .or(camera.model.as_deref())
+                .unwrap_or("unknown lens")
+        ))
+    })?;
+
+    let correction = resolve_correction(lens, focal_length, camera.aperture);
+    Ok(LensCorrectionOutput {
+        image: apply_correction(img, &correction),
+        lens: lens.model.clone(),
+        camera: camera.model,
+        focal_length,
+        distortion_corrected: correction.distortion.is_some(),
+        tca_corrected: correction.tca.is_some(),
+        vignetting_corrected: correction.vignetting.is_some(),
+    })
+}
@@ -0,0 +1,20 @@
+//! Image decoding, analysis and processing shared by the desktop app and any
+//! other front end. Nothing in here depends on Tauri.
+
+pub mod analysis;
+pub mod audit;
+pub mod batch;
+pub mod checksum;
+pub mod color;
+pub mod cull;
+pub mod decode;
+pub mod encode;
+pub mod error;
+pub mod forensics;
+pub mod lens;
+pub mod library;
+pub mod metadata;
+pub mod models;
+pub mod processing;
+pub mod progress;
+pub mod recipe;
@@ -0,0 +1,408 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike};
+use image::DynamicImage;
+use rayon::prelude::*;
+
+use crate::analysis::sharpness::variance_of_laplacian;
+use crate::checksum::hash_path;
+use crate::decode::{decode_file, is_raw_file};
+use crate::error::AppError;
+use crate::metadata::{read_capture_time, read_exif, read_gps};
+use crate::models::{
+    Burst, BurstFrame, DateSource, DuplicateFileGroup, FileHash, HashAlgorithm, LocationCluster,
+    LocationClusterResult, OrganizeAction, OrganizeResult, SkippedFile, TransferMode,
+};
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "tga", "ico",
+];
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+const DEFAULT_DATE_PATTERN: &str = "YYYY/MM/DD";
+// Frames are scored on a downscaled copy so the metric is comparable across
+// resolutions and culling a burst of 50MP shots stays fast.
+pub(crate) const SHARPNESS_SAMPLE_SIZE: u32 = 1024;
+
+pub fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+        || is_raw_file(path)
+}
+
+pub fn list_images(dir: &str) -> Result<Vec<PathBuf>, AppError> {
+    let entries = fs::read_dir(dir).map_err(|e| AppError::io(Path::new(dir), e, false))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_image_file(path))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn haversine_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+fn to_unit_vector((lat, lon): (f64, f64)) -> [f64; 3] {
+    let (phi, lambda) = (lat.to_radians(), lon.to_radians());
+    [
+        phi.cos() * lambda.cos(),
+        phi.cos() * lambda.sin(),
+        phi.sin(),
+    ]
+}
+
+// Running cluster state; centroids are averaged on the unit sphere so clusters
+// spanning the antimeridian don't collapse to the wrong side of the globe.
+struct ClusterAccumulator {
+    sum: [f64; 3],
+    centroid: (f64, f64),
+    paths: Vec<String>,
+}
+
+impl ClusterAccumulator {
+    fn add(&mut self, point: (f64, f64), path: String) {
+        let v = to_unit_vector(point);
+        for (total, component) in self.sum.iter_mut().zip(v) {
+            *total += component;
+        }
+        let [x, y, z] = self.sum;
+        let hyp = (x * x + y * y).sqrt();
+        self.centroid = (z.atan2(hyp).to_degrees(), y.atan2(x).to_degrees());
+        self.paths.push(path);
+    }
+}
+
+pub fn cluster_by_location(dir: &str, radius_km: f64) -> Result<LocationClusterResult, AppError> {
+    if radius_km.is_nan() || radius_km <= 0.0 {
+        return Err(AppError::invalid(
+            "Cluster radius must be greater than zero",
+        ));
+    }
+
+    let located: Vec<(String, Option<(f64, f64)>)> = list_images(dir)?
+        .par_iter()
+        .map(|path| {
+            let gps = read_exif(path).as_ref().and_then(read_gps);
+            (path.to_string_lossy().into_owned(), gps)
+        })
+        .collect();
+
+    let mut clusters: Vec<ClusterAccumulator> = Vec::new();
+    let mut unlocated = Vec::new();
+    for (path, gps) in located {
+        let Some(point) = gps else {
+            unlocated.push(path);
+            continue;
+        };
+
+        let nearest = clusters
+            .iter_mut()
+            .map(|cluster| (haversine_km(cluster.centroid, point), cluster))
+            .filter(|(distance, _)| *distance <= radius_km)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        match nearest {
+            Some((_, cluster)) => cluster.add(point, path),
+            None => {
+                let mut cluster = ClusterAccumulator {
+                    sum: [0.0; 3],
+                    centroid: point,
+                    paths: Vec::new(),
+                };
+                cluster.add(point, path);
+                clusters.push(cluster);
+            }
+        }
+    }
+
+    let mut clusters: Vec<LocationCluster> = clusters
+        .into_iter()
+        .map(|cluster| LocationCluster {
+            latitude: cluster.centroid.0,
+            longitude: cluster.centroid.1,
+            count: cluster.paths.len(),
+            paths: cluster.paths,
+        })
+        .collect();
+    clusters.sort_by_key(|cluster| Reverse(cluster.count));
+
+    Ok(LocationClusterResult {
+        clusters,
+        unlocated,
+    })
+}
+
+pub fn capture_time(path: &Path) -> Option<(NaiveDateTime, DateSource)> {
+    if let Some(taken) = read_exif(path).as_ref().and_then(read_capture_time) {
+        return Some((taken, DateSource::Exif));
+    }
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let local: DateTime<Local> = modified.into();
+    Some((local.naive_local(), DateSource::FileModified))
+}
+
+// Expands YYYY, MM, DD, hh, mm and ss tokens, e.g. "YYYY/MM/DD" -> "2024/03/09".
+fn expand_date_pattern(pattern: &str, time: &NaiveDateTime) -> String {
+    pattern
+        .replace("YYYY", &format!("{:04}", time.year()))
+        .replace("MM", &format!("{:02}", time.month()))
+        .replace("DD", &format!("{:02}", time.day()))
+        .replace("hh", &format!("{:02}", time.hour()))
+        .replace("mm", &format!("{:02}", time.minute()))
+        .replace("ss", &format!("{:02}", time.second()))
+}
+
+// Picks a destination that doesn't collide with existing files or with files
+// already planned earlier in the same run.
+fn unique_destination(dir: &Path, file_name: &str, planned: &HashSet<PathBuf>) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() && !planned.contains(&candidate) {
+        return candidate;
+    }
+
+    let path = Path::new(file_name);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_name);
+    let ext = path.extension().and_then(|e| e.to_str());
+    (1..)
+        .map(|n| match ext {
+            Some(ext) => dir.join(format!("{}-{}.{}", stem, n, ext)),
+            None => dir.join(format!("{}-{}", stem, n)),
+        })
+        .find(|candidate| !candidate.exists() && !planned.contains(candidate))
+        .expect("an unused file name exists")
+}
+
+fn transfer_file(source: &Path, destination: &Path, mode: TransferMode) -> Result<(), AppError> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e, true))?;
+    }
+    // A missing source is the one failure that isn't about the destination.
+    if !source.exists() {
+        return Err(AppError::FileNotFound {
+            path: source.display().to_string(),
+        });
+    }
+    match mode {
+        TransferMode::Copy => fs::copy(source, destination).map(|_| ()),
+        // rename fails across filesystems, so fall back to copy + delete.
+        TransferMode::Move => fs::rename(source, destination).or_else(|_| {
+            fs::copy(source, destination)?;
+            fs::remove_file(source)
+        }),
+    }
+    .map_err(|e| AppError::io(destination, e, true))
+}
+
+pub fn organize_by_date(
+    paths: Vec<String>,
+    destination: String,
+    pattern: Option<String>,
+    mode: TransferMode,
+    dry_run: bool,
+) -> Result<OrganizeResult, AppError> {
+    let pattern = pattern.unwrap_or_else(|| DEFAULT_DATE_PATTERN.to_string());
+    let destination = PathBuf::from(destination);
+
+    let mut planned = HashSet::new();
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+
+    for path in paths {
+        let source = PathBuf::from(&path);
+        let Some(file_name) = source.file_name().and_then(|n| n.to_str()) else {
+            skipped.push(SkippedFile {
+                path,
+                reason: "Not a file path".to_string(),
+            });
+            continue;
+        };
+        let Some((time, date_source)) = capture_time(&source) else {
+            skipped.push(SkippedFile {
+                path,
+                reason: "Could not determine capture date".to_string(),
+            });
+            continue;
+        };
+
+        let folder = destination.join(expand_date_pattern(&pattern, &time));
+        let target = unique_destination(&folder, file_name, &planned);
+
+        if !dry_run {
+            if let Err(e) = transfer_file(&source, &target, mode) {
+                skipped.push(SkippedFile {
+                    path,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        }
+
+        planned.insert(target.clone());
+        actions.push(OrganizeAction {
+            source: path,
+            destination: target.to_string_lossy().into_owned(),
+            date_source,
+        });
+    }
+
+    Ok(OrganizeResult {
+        actions,
+        skipped,
+        dry_run,
+    })
+}
+
+// Decoded through `decode_file`, as culling does, so RAW bursts are scored too.
+fn sharpness_score(path: &Path) -> Option<f64> {
+    let decoded = decode_file(path, false).ok()?;
+    let gray = DynamicImage::ImageRgba8(decoded.image)
+        .thumbnail(SHARPNESS_SAMPLE_SIZE, SHARPNESS_SAMPLE_SIZE)
+        .to_luma8();
+    Some(variance_of_laplacian(&gray))
+}
+
+pub fn detect_bursts(
+    dir: &str,
+    max_gap_ms: i64,
+    pick_sharpest: bool,
+) -> Result<Vec<Burst>, AppError> {
+    let mut timed: Vec<(PathBuf, NaiveDateTime)> = list_images(dir)?
+        .into_par_iter()
+        .filter_map(|path| capture_time(&path).map(|(time, _)| (path, time)))
+        .collect();
+    timed.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    // Consecutive shots closer than max_gap_ms belong to the same sequence.
+    let mut groups: Vec<Vec<(PathBuf, NaiveDateTime)>> = Vec::new();
+    for shot in timed {
+        match groups.last_mut() {
+            Some(group) if (shot.1 - group.last().unwrap().1).num_milliseconds() <= max_gap_ms => {
+                group.push(shot)
+            }
+            _ => groups.push(vec![shot]),
+        }
+    }
+
+    let bursts = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let duration_ms = (group.last().unwrap().1 - group[0].1).num_milliseconds();
+            let frames: Vec<BurstFrame> = group
+                .par_iter()
+                .map(|(path, time)| BurstFrame {
+                    path: path.to_string_lossy().into_owned(),
+                    captured_at: time.format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+                    sharpness: if pick_sharpest {
+                        sharpness_score(path)
+                    } else {
+                        None
+                    },
+                })
+                .collect();
+            let sharpest = frames
+                .iter()
+                .filter_map(|frame| frame.sharpness.map(|score| (score, &frame.path)))
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, path)| path.clone());
+
+            Burst {
+                frames,
+                duration_ms,
+                sharpest,
+            }
+        })
+        .collect();
+
+    Ok(bursts)
+}
+
+pub fn hash_file(path: String, algorithm: HashAlgorithm) -> Result<FileHash, AppError> {
+    let size = fs::metadata(&path)
+        .map(|m| m.len())
+        .map_err(|e| AppError::io(Path::new(&path), e, false))?;
+    let hash = hash_path(Path::new(&path), algorithm)?;
+
+    Ok(FileHash {
+        path,
+        algorithm,
+        hash,
+        size,
+    })
+}
+
+pub fn find_exact_duplicates(dir: &str) -> Result<Vec<DuplicateFileGroup>, AppError> {
+    // Only files sharing a size can be identical, so most files never get hashed.
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in list_images(dir)? {
+        if let Ok(meta) = fs::metadata(&path) {
+            by_size.entry(meta.len()).or_default().push(path);
+        }
+    }
+
+    let candidates: Vec<(u64, PathBuf)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |path| (size, path)))
+        .collect();
+
+    let hashed: Vec<(u64, String, PathBuf)> = candidates
+        .into_par_iter()
+        .filter_map(|(size, path)| {
+            hash_path(&path, HashAlgorithm::Blake3)
+                .ok()
+                .map(|hash| (size, hash, path))
+        })
+        .collect();
+
+    let mut by_hash: HashMap<(u64, String), Vec<String>> = HashMap::new();
+    for (size, hash, path) in hashed {
+        by_hash
+            .entry((size, hash))
+            .or_default()
+            .push(path.to_string_lossy().into_owned());
+    }
+
+    let mut groups: Vec<DuplicateFileGroup> = by_hash
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, hash), mut paths)| {
+            paths.sort();
+            DuplicateFileGroup { hash, size, paths }
+        })
+        .collect();
+    // Largest wasted space first.
+    groups.sort_by_key(|g| Reverse(g.size * (g.paths.len() as u64 - 1)));
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_files_report_their_own_code() {
+        let missing =
+            std::env::temp_dir().join(format!("image-analyzer-missing-{}.png", std::process::id()));
+        let path = missing.to_string_lossy().into_owned();
+
+        let err = hash_file(path.clone(), HashAlgorithm::Sha256).unwrap_err();
+        assert!(matches!(err, AppError::FileNotFound { .. }), "{:?}", err);
+        let err = list_images(&path).unwrap_err();
+        assert!(matches!(err, AppError::FileNotFound { .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,256 @@
+//! Develop settings written by Lightroom (`crs:` properties) and darktable
+//! (its XMP history stack), mapped onto this app's edits where there is an
+//! equivalent.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::metadata::xmp;
+use crate::models::{DevelopTool, Edit, ImportedSettings, Operation, PixelRect};
+
+// Brightness multiplies gamma-encoded values, so one stop of exposure is
+// 2^(1/2.2) rather than 2.
+const GAMMA: f64 = 2.2;
+// Lightroom's ±100 contrast slider spans a factor of 0.5-1.5 here.
+const LIGHTROOM_CONTRAST_RANGE: f64 = 200.0;
+
+fn brightness(stops: f64) -> Edit {
+    Edit::Operation {
+        operation: Operation::Brightness {
+            amount: 2f64.powf(stops / GAMMA) as f32,
+        },
+    }
+}
+
+fn contrast(amount: f64) -> Edit {
+    Edit::Operation {
+        operation: Operation::Contrast {
+            amount: amount.max(0.0) as f32,
+        },
+    }
+}
+
+// A crop given as fractional edges of the image.
+fn crop(left: f64, top: f64, right: f64, bottom: f64, width: u32, height: u32) -> Option<Edit> {
+    let edge = |fraction: f64, size: u32| (fraction.clamp(0.0, 1.0) * size as f64).round() as u32;
+    let (x0, y0) = (edge(left, width), edge(top, height));
+    let (x1, y1) = (edge(right, width), edge(bottom, height));
+    if x1 <= x0 || y1 <= y0 || (x0, y0, x1, y1) == (0, 0, width, height) {
+        return None;
+    }
+    Some(Edit::Crop {
+        rect: PixelRect {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        },
+    })
+}
+
+fn number(xmp: &str, name: &str) -> Option<f64> {
+    xmp::property(xmp, name)?
+        .trim_start_matches('+')
+        .parse()
+        .ok()
+}
+
+fn lightroom(packet: &str, width: u32, height: u32) -> Option<(Vec<Edit>, Vec<String>)> {
+    if !packet.contains("crs:") {
+        return None;
+    }
+    let mut edits = Vec::new();
+    let mut unsupported = Vec::new();
+
+    let exposure = number(packet, "crs:Exposure2012").or_else(|| number(packet, "crs:Exposure"));
+    if let Some(stops) = exposure.filter(|&stops| stops != 0.0) {
+        edits.push(brightness(stops));
+    }
+    if let Some(amount) = number(packet, "crs:Contrast2012").filter(|&c| c != 0.0) {
+        edits.push(contrast(1.0 + amount / LIGHTROOM_CONTRAST_RANGE));
+    }
+    let white_balance = xmp::property(packet, "crs:WhiteBalance");
+    if white_balance.is_some_and(|wb| wb != "As Shot") {
+        unsupported.push("white balance".to_string());
+    }
+    if xmp::property(packet, "crs:HasCrop").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+        let edges = (
+            number(packet, "crs:CropLeft").unwrap_or(0.0),
+            number(packet, "crs:CropTop").unwrap_or(0.0),
+            number(packet, "crs:CropRight").unwrap_or(1.0),
+            number(packet, "crs:CropBottom").unwrap_or(1.0),
+        );
+        edits.extend(crop(edges.0, edges.1, edges.2, edges.3, width, height));
+        if number(packet, "crs:CropAngle").is_some_and(|angle| angle.abs() > 0.01) {
+            unsupported.push("crop angle".to_string());
+        }
+    }
+    Some((edits, unsupported))
+}
+
+fn hex_floats(params: &str) -> Option<Vec<f32>> {
+    if !params.len().is_multiple_of(8) {
+        return None;
+    }
+    (0..params.len())
+        .step_by(8)
+        .map(|i| {
+            let bytes = u32::from_str_radix(&params[i..i + 8], 16)
+                .ok()?
+                .to_be_bytes();
+            // The hex is the raw little-endian struct.
+            Some(f32::from_le_bytes(bytes))
+        })
+        .collect()
+}
+
+struct HistoryItem {
+    num: u32,
+    operation: String,
+    enabled: bool,
+    version: u32,
+    params: String,
+}
+
+// darktable's history stack, keeping only the entries up to `history_end`.
+fn darktable_history(packet: &str) -> Vec<HistoryItem> {
+    let Some(history) = xmp::element(packet, "darktable:history") else {
+        return Vec::new();
+    };
+    let end = number(packet, "darktable:history_end").map(|end| end as u32);
+    let mut items = Vec::new();
+    let mut rest = history;
+    while let Some(start) = rest.find("<rdf:li") {
+        rest = &rest[start..];
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        let field = |name: &str| xmp::attribute(tag, name);
+        if let (Some(num), Some(operation)) = (field("darktable:num"), field("darktable:operation"))
+        {
+            items.push(HistoryItem {
+                num: num.parse().unwrap_or(0),
+                operation,
+                enabled: field("darktable:enabled").as_deref() == Some("1"),
+                version: field("darktable:modversion")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                params: field("darktable:params").unwrap_or_default(),
+            });
+        }
+        rest = &rest[tag_end..];
+    }
+    items.retain(|item| end.is_none_or(|end| item.num < end));
+    items.sort_by_key(|item| item.num);
+    items
+}
+
+fn darktable(packet: &str, width: u32, height: u32) -> Option<(Vec<Edit>, Vec<String>)> {
+    if !packet.contains("darktable:history") {
+        return None;
+    }
+    let history = darktable_history(packet);
+    // Later entries for a module replace earlier ones.
+    let latest = |operation: &str| {
+        history
+            .iter()
+            .rev()
+            .find(|item| item.operation == operation)
+            .filter(|item| item.enabled)
+    };
+    let mut edits = Vec::new();
+    let mut unsupported = Vec::new();
+    let mut unreadable =
+        |name: &str| unsupported.push(format!("{} (unrecognised parameters)", name));
+
+    if let Some(item) = latest("exposure") {
+        // Versions 4 onwards start with the mode before black and exposure.
+        let offset = if item.version >= 4 { 2 } else { 1 };
+        match hex_floats(&item.params).and_then(|p| p.get(offset).copied()) {
+            Some(stops) if stops != 0.0 => edits.push(brightness(stops as f64)),
+            Some(_) => {}
+            None => unreadable("exposure"),
+        }
+    }
+    if let Some(item) = latest("colisa") {
+        match hex_floats(&item.params).and_then(|p| p.first().copied()) {
+            Some(amount) if amount != 0.0 => edits.push(contrast(1.0 + amount as f64)),
+            Some(_) => {}
+            None => unreadable("contrast"),
+        }
+    }
+    if let Some(item) = latest("crop") {
+        // Left, top, right and bottom edges as fractions of the image.
+        match hex_floats(&item.params).filter(|p| p.len() >= 4) {
+            Some(p) => edits.extend(crop(
+                p[0] as f64,
+                p[1] as f64,
+                p[2] as f64,
+                p[3] as f64,
+                width,
+                height,
+            )),
+            None => unreadable("crop"),
+        }
+    }
+    if latest("temperature").is_some() {
+        unsupported.push("white balance".to_string());
+    }
+    for module in ["clipping", "ashift", "filmicrgb", "colorbalancergb"] {
+        if latest(module).is_some() {
+            unsupported.push(module.to_string());
+        }
+    }
+    Some((edits, unsupported))
+}
+
+pub fn parse_develop_settings(
+    packet: &str,
+    width: u32,
+    height: u32,
+) -> Option<(DevelopTool, Vec<Edit>, Vec<String>)> {
+    if let Some((edits, unsupported)) = darktable(packet, width, height) {
+        return Some((DevelopTool::Darktable, edits, unsupported));
+    }
+    let (edits, unsupported) = lightroom(packet, width, height)?;
+    Some((DevelopTool::Lightroom, edits, unsupported))
+}
+
+// Reads develop settings for `image` from `sidecar`, or else the usual
+// sidecar locations, or else the XMP embedded in the image. Crops are
+// resolved against the `width`x`height` image they'll be applied to.
+pub fn read_develop_settings(
+    image: &Path,
+    sidecar: Option<&Path>,
+    width: u32,
+    height: u32,
+) -> Result<ImportedSettings, AppError> {
+    let candidates: Vec<PathBuf> = match sidecar {
+        Some(path) => vec![path.to_path_buf()],
+        None => xmp::sidecar_paths(image)
+            .into_iter()
+            .chain([image.to_path_buf()])
+            .collect(),
+    };
+    for path in &candidates {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if sidecar.is_some() => return Err(AppError::io(path, e, false)),
+            Err(_) => continue,
+        };
+        let Some(packet) = xmp::find_packet(&bytes) else {
+            continue;
+        };
+        if let Some((tool, edits, unsupported)) = parse_develop_settings(&packet, width, height) {
+            return Ok(ImportedSettings {
+                tool,
+                source: path.display().to_string(),
+                edits,
+                unsupported,
+            });
+        }
+    }
+    Err(AppError::invalid(
+        "No Lightroom or darktable settings were found for this image",
+    ))
+}
@@ -0,0 +1,250 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use exif::{Exif, In, Tag, Value};
+use image::ImageFormat;
+
+use crate::decode::{describe_icc_profile, format_name, read_icc_profile};
+use crate::error::AppError;
+use crate::models::{GpsPosition, ImageMetadata, Resolution, XmpMetadata};
+
+pub mod develop;
+pub mod xmp;
+
+pub fn read_exif(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    exif::Reader::new().read_from_container(&mut reader).ok()
+}
+
+pub fn exif_string(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    match &field.value {
+        Value::Ascii(parts) => {
+            let text = parts
+                .iter()
+                .map(|part| String::from_utf8_lossy(part).trim().to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let text = text.trim_matches(char::from(0)).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        _ => Some(field.display_value().to_string()),
+    }
+}
+
+pub fn exif_f64(exif: &Exif, tag: Tag) -> Option<f64> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    match &field.value {
+        Value::Rational(v) => v.first().map(|r| r.to_f64()),
+        Value::SRational(v) => v.first().map(|r| r.to_f64()),
+        Value::Short(v) => v.first().map(|&n| n as f64),
+        Value::Long(v) => v.first().map(|&n| n as f64),
+        _ => None,
+    }
+}
+
+// The subset of EXIF needed to look up lens calibration data.
+#[derive(Clone, Debug, Default)]
+pub struct CameraInfo {
+    pub model: Option<String>,
+    pub lens_model: Option<String>,
+    pub focal_length: Option<f64>,
+    pub aperture: Option<f64>,
+}
+
+pub fn read_camera_info(path: &Path) -> Option<CameraInfo> {
+    let exif = read_exif(path)?;
+    Some(CameraInfo {
+        model: exif_string(&exif, Tag::Model),
+        lens_model: exif_string(&exif, Tag::LensModel),
+        focal_length: exif_f64(&exif, Tag::FocalLength),
+        aperture: exif_f64(&exif, Tag::FNumber),
+    })
+}
+
+fn gps_coordinate(exif: &Exif, value_tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let field = exif.get_field(value_tag, In::PRIMARY)?;
+    let Value::Rational(parts) = &field.value else {
+        return None;
+    };
+    if parts.len() < 3 {
+        return None;
+    }
+    let degrees = parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0;
+    let negative = exif_string(exif, ref_tag)
+        .map(|r| r.eq_ignore_ascii_case(negative_ref))
+        .unwrap_or(false);
+    Some(if negative { -degrees } else { degrees })
+}
+
+// Returns (latitude, longitude) in signed decimal degrees.
+pub fn read_gps(exif: &Exif) -> Option<(f64, f64)> {
+    let lat = gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let lon = gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+    Some((lat, lon))
+}
+
+// EXIF stores local capture time as "YYYY:MM:DD HH:MM:SS" with no zone; the
+// fractional seconds live in a separate SubSecTime* tag.
+pub fn read_capture_time(exif: &Exif) -> Option<NaiveDateTime> {
+    [
+        (Tag::DateTimeOriginal, Tag::SubSecTimeOriginal),
+        (Tag::DateTimeDigitized, Tag::SubSecTimeDigitized),
+        (Tag::DateTime, Tag::SubSecTime),
+    ]
+    .into_iter()
+    .find_map(|(tag, subsec_tag)| {
+        let text = exif_string(exif, tag)?;
+        let time = NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S").ok()?;
+        let subsec = exif_string(exif, subsec_tag)
+            .and_then(|digits| format!("0.{}", digits.trim()).parse::<f64>().ok())
+            .unwrap_or(0.0);
+        Some(time + Duration::microseconds((subsec * 1_000_000.0) as i64))
+    })
+}
+
+fn exif_u32(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn read_altitude(exif: &Exif) -> Option<f64> {
+    let altitude = exif_f64(exif, Tag::GPSAltitude)?;
+    // Reference 1 means below sea level.
+    let below = exif_u32(exif, Tag::GPSAltitudeRef) == Some(1);
+    Some(if below { -altitude } else { altitude })
+}
+
+fn exif_dpi(exif: &Exif) -> Option<Resolution> {
+    let per_unit = match exif_u32(exif, Tag::ResolutionUnit).unwrap_or(2) {
+        2 => 1.0,
+        3 => 2.54,
+        _ => return None,
+    };
+    Some(Resolution {
+        x: exif_f64(exif, Tag::XResolution)? * per_unit,
+        y: exif_f64(exif, Tag::YResolution)? * per_unit,
+    })
+}
+
+// The density in a JPEG's JFIF header, when it has a physical unit.
+fn jfif_dpi(bytes: &[u8]) -> Option<Resolution> {
+    let app0 = bytes.get(..20)?;
+    if app0[..4] != [0xFF, 0xD8, 0xFF, 0xE0] || &app0[6..11] != b"JFIF\0" {
+        return None;
+    }
+    let per_unit = match app0[13] {
+        1 => 1.0,
+        2 => 2.54,
+        _ => return None,
+    };
+    let density = |i: usize| u16::from_be_bytes([app0[i], app0[i + 1]]) as f64 * per_unit;
+    Some(Resolution {
+        x: density(14),
+        y: density(16),
+    })
+}
+
+// A PNG's pHYs chunk, which is in pixels per metre.
+fn png_dpi(bytes: &[u8]) -> Option<Resolution> {
+    let mut offset = 8;
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let length = u32::from_be_bytes(header[..4].try_into().ok()?) as usize;
+        let data = bytes.get(offset + 8..offset + 8 + length)?;
+        match &header[4..8] {
+            b"pHYs" if length >= 9 && data[8] == 1 => {
+                let ppm = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap());
+                return Some(Resolution {
+                    x: ppm(0) as f64 * 0.0254,
+                    y: ppm(4) as f64 * 0.0254,
+                });
+            }
+            // pHYs must come before the image data.
+            b"IDAT" | b"IEND" => return None,
+            _ => offset += 12 + length,
+        }
+    }
+    None
+}
+
+fn read_xmp(bytes: &[u8], path: &Path) -> Option<XmpMetadata> {
+    if let Some(packet) = xmp::find_packet(bytes) {
+        return Some(xmp::parse(&packet));
+    }
+    xmp::sidecar_paths(path).into_iter().find_map(|sidecar| {
+        let packet = fs::read_to_string(&sidecar).ok()?;
+        Some(XmpMetadata {
+            sidecar: Some(sidecar.display().to_string()),
+            ..xmp::parse(&packet)
+        })
+    })
+}
+
+// Everything about a file short of its pixels: file-system details, EXIF,
+// XMP (embedded, or from a sidecar) and the embedded colour profile.
+pub fn extract_metadata(path: &Path) -> Result<ImageMetadata, AppError> {
+    let bytes = fs::read(path).map_err(|e| AppError::io(path, e, false))?;
+    let file = fs::metadata(path).map_err(|e| AppError::io(path, e, false))?;
+    let modified = file.modified().ok().map(|time| {
+        DateTime::<Local>::from(time)
+            .format("%Y-%m-%dT%H:%M:%S%.3f")
+            .to_string()
+    });
+
+    let format = image::guess_format(&bytes)
+        .ok()
+        .or_else(|| ImageFormat::from_path(path).ok());
+    let dimensions = image::io::Reader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(&bytes))
+        .ok();
+    let exif = exif.as_ref();
+    let string = |tag| exif.and_then(|exif| exif_string(exif, tag));
+    let number = |tag| exif.and_then(|exif| exif_f64(exif, tag));
+    let integer = |tag| exif.and_then(|exif| exif_u32(exif, tag));
+
+    let dpi = exif.and_then(exif_dpi).or_else(|| match format {
+        Some(ImageFormat::Jpeg) => jfif_dpi(&bytes),
+        Some(ImageFormat::Png) => png_dpi(&bytes),
+        _ => None,
+    });
+
+    Ok(ImageMetadata {
+        path: path.display().to_string(),
+        file_size: file.len(),
+        modified,
+        format: format.map(format_name),
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        camera_make: string(Tag::Make),
+        camera_model: string(Tag::Model),
+        lens_model: string(Tag::LensModel),
+        exposure_time: number(Tag::ExposureTime),
+        aperture: number(Tag::FNumber),
+        iso: integer(Tag::PhotographicSensitivity),
+        focal_length: number(Tag::FocalLength),
+        focal_length_35mm: integer(Tag::FocalLengthIn35mmFilm),
+        captured_at: exif
+            .and_then(read_capture_time)
+            .map(|time| time.format("%Y-%m-%dT%H:%M:%S%.3f").to_string()),
+        gps: exif.and_then(|exif| {
+            let (latitude, longitude) = read_gps(exif)?;
+            Some(GpsPosition {
+                latitude,
+                longitude,
+                altitude: read_altitude(exif),
+            })
+        }),
+        orientation: integer(Tag::Orientation).map(|o| o as u16),
+        dpi,
+        color_profile: format
+            .and_then(|format| read_icc_profile(&bytes, format))
+            .and_then(|icc| describe_icc_profile(&icc)),
+        xmp: read_xmp(&bytes, path),
+    })
+}
@@ -0,0 +1,129 @@
+//! Minimal reader for XMP packets: enough RDF to pull simple, language
+//! alternative and list properties, whether written as attributes or elements.
+
+use std::path::{Path, PathBuf};
+
+use crate::models::XmpMetadata;
+
+const PACKET_START: &[u8] = b"<x:xmpmeta";
+const PACKET_END: &[u8] = b"</x:xmpmeta>";
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// The XMP packet embedded in a file of any format. Every container stores it
+// as plain UTF-8 (JPEG APP1, PNG iTXt, TIFF tag 700, WebP XMP chunk).
+pub fn find_packet(bytes: &[u8]) -> Option<String> {
+    let start = find(bytes, PACKET_START)?;
+    let end = start + find(&bytes[start..], PACKET_END)? + PACKET_END.len();
+    Some(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+}
+
+// Lightroom writes `photo.xmp`; darktable and others write `photo.jpg.xmp`.
+pub fn sidecar_paths(path: &Path) -> [PathBuf; 2] {
+    let mut appended = path.as_os_str().to_owned();
+    appended.push(".xmp");
+    [PathBuf::from(appended), path.with_extension("xmp")]
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Offsets of `name` where it's a whole qualified name, not the tail of a longer one.
+fn occurrences<'a>(xmp: &'a str, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+    xmp.match_indices(name).map(|(i, _)| i).filter(move |&i| {
+        let before = xmp[..i].chars().next_back();
+        let after = xmp[i + name.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric() || c == ':' || c == '_')
+            && !after.is_some_and(|c| c.is_alphanumeric() || c == ':' || c == '_')
+    })
+}
+
+pub(crate) fn attribute(xmp: &str, name: &str) -> Option<String> {
+    occurrences(xmp, name).find_map(|i| {
+        let rest = xmp[i + name.len()..].trim_start().strip_prefix('=')?;
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value = &rest[1..];
+        Some(unescape(&value[..value.find(quote)?]))
+    })
+}
+
+// The content of the `<name>` element, if there is one.
+pub(crate) fn element<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    occurrences(xmp, name).find_map(|i| {
+        if !xmp[..i].ends_with('<') {
+            return None;
+        }
+        let open_end = i + xmp[i..].find('>')?;
+        if xmp[..open_end].ends_with('/') {
+            return None;
+        }
+        let close = format!("</{}>", name);
+        let content_end = open_end + xmp[open_end..].find(&close)?;
+        Some(&xmp[open_end + 1..content_end])
+    })
+}
+
+fn list_items(content: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("<rdf:li") {
+        rest = &rest[start..];
+        let Some(open_end) = rest.find('>') else {
+            break;
+        };
+        let Some(close) = rest.find("</rdf:li>") else {
+            break;
+        };
+        if close > open_end {
+            let text = unescape(rest[open_end + 1..close].trim());
+            if !text.is_empty() {
+                items.push(text);
+            }
+        }
+        rest = &rest[close + "</rdf:li>".len()..];
+    }
+    items
+}
+
+// A simple property, or the first entry of an alternative (e.g. the default
+// language of a title).
+pub fn property(xmp: &str, name: &str) -> Option<String> {
+    attribute(xmp, name)
+        .or_else(|| {
+            let content = element(xmp, name)?;
+            if content.contains("<rdf:li") {
+                list_items(content).into_iter().next()
+            } else {
+                Some(unescape(content.trim()))
+            }
+        })
+        .filter(|value| !value.is_empty())
+}
+
+pub fn list(xmp: &str, name: &str) -> Vec<String> {
+    element(xmp, name).map(list_items).unwrap_or_default()
+}
+
+pub fn parse(xmp: &str) -> XmpMetadata {
+    XmpMetadata {
+        rating: property(xmp, "xmp:Rating").and_then(|r| r.parse::<f64>().ok().map(|r| r as i32)),
+        label: property(xmp, "xmp:Label"),
+        title: property(xmp, "dc:title"),
+        description: property(xmp, "dc:description"),
+        creators: list(xmp, "dc:creator"),
+        rights: property(xmp, "dc:rights"),
+        keywords: list(xmp, "dc:subject"),
+        creator_tool: property(xmp, "xmp:CreatorTool"),
+        sidecar: None,
+    }
+}
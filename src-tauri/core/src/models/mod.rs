@@ -0,0 +1,1911 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub format: String,
+    // Set when a damaged file was only partially decoded.
+    #[serde(default)]
+    pub recovered: bool,
+    // How the pixels were stored on disk; `data` is always expanded to RGBA8.
+    #[serde(default)]
+    pub source_color_type: Option<SourceColorType>,
+}
+
+// The frames of an animated GIF, PNG or WebP, or the pages of a TIFF. Still
+// images have one frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageFrames {
+    pub path: String,
+    pub frame_count: usize,
+    // How long each frame is shown, in milliseconds; 0 for TIFF pages and
+    // still images.
+    pub delays: Vec<u32>,
+    // Of the first frame; TIFF pages can each have their own size.
+    pub width: u32,
+    pub height: u32,
+    pub animated: bool,
+}
+
+// A RAW file's sensor data before demosaicing, one value per photosite.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawMosaic {
+    // Each photosite in its filter colour, or in gray, in sensor orientation
+    // and cropped to the usable area.
+    pub image: ImageData,
+    // The repeating filter layout from the top left, one letter per site row
+    // by row, e.g. "RGGB".
+    pub pattern: String,
+    pub channels: Vec<CfaChannelStats>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CfaColor {
+    Red,
+    Green,
+    Blue,
+    // The fourth filter colour of a few four-colour sensors.
+    Emerald,
+}
+
+// The photosites under one filter colour.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CfaChannelStats {
+    pub color: CfaColor,
+    pub sites: u64,
+    pub black_level: u16,
+    pub white_level: u16,
+    // Mean level above black, as a fraction of the range up to clipping.
+    pub mean: f32,
+    // Share of sites at or above the white level.
+    pub clipped: f32,
+    // 256 bins over the same 0 to 1 range, linear in sensor values.
+    pub histogram: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorKind {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    Indexed,
+    Cmyk,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SourceColorType {
+    pub container: String,
+    pub kind: ColorKind,
+    pub bit_depth: u8,
+    pub interlaced: bool,
+    pub palette_size: Option<u16>,
+    // How non-RGB sources were brought into RGB for display.
+    #[serde(default)]
+    pub conversion: Option<ColorConversion>,
+    // Description of the embedded ICC profile, if the file carries one.
+    #[serde(default)]
+    pub icc_profile: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorConversion {
+    Icc,
+    Naive,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistogramData {
+    pub red: Vec<u32>,
+    pub green: Vec<u32>,
+    pub blue: Vec<u32>,
+    pub luminosity: Vec<u32>,
+    // HSL saturation and lightness, 256 bins each.
+    #[serde(default)]
+    pub saturation: Vec<u32>,
+    #[serde(default)]
+    pub lightness: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ColorSample {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub hex: String,
+    // Share of the visible pixels closest to this colour, 0-100. Zero for
+    // samples that don't come from a palette.
+    #[serde(default)]
+    pub population: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    pub histogram: HistogramData,
+    pub dominant_colors: Vec<ColorSample>,
+    pub average_brightness: f32,
+    pub contrast: f32,
+    #[serde(default)]
+    pub warnings: Vec<AnalysisWarning>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisWarningKind {
+    EmbeddedProfileIgnored,
+    MissingProfileWideGamut,
+    ColorCast,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalysisWarning {
+    pub kind: AnalysisWarningKind,
+    pub message: String,
+}
+
+pub type ImageId = u64;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageHandle {
+    pub id: ImageId,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LensCorrectionReport {
+    pub handle: ImageHandle,
+    pub lens: String,
+    pub camera: Option<String>,
+    pub focal_length: f64,
+    pub distortion_corrected: bool,
+    pub tca_corrected: bool,
+    pub vignetting_corrected: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocationCluster {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: usize,
+    pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocationClusterResult {
+    pub clusters: Vec<LocationCluster>,
+    pub unlocated: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+    Move,
+    Copy,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    Exif,
+    FileModified,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrganizeAction {
+    pub source: String,
+    pub destination: String,
+    pub date_source: DateSource,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrganizeResult {
+    pub actions: Vec<OrganizeAction>,
+    pub skipped: Vec<SkippedFile>,
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BurstFrame {
+    pub path: String,
+    pub captured_at: String,
+    pub sharpness: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Burst {
+    pub frames: Vec<BurstFrame>,
+    pub duration_ms: i64,
+    pub sharpest: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    Brightness {
+        amount: f32,
+    },
+    Contrast {
+        amount: f32,
+    },
+    Grayscale,
+    // Hue shift in degrees, saturation as a multiplier and lightness from -1
+    // (black) to 1 (white).
+    Hsl {
+        hue_shift: f32,
+        saturation: f32,
+        lightness: f32,
+    },
+    // `temperature` is the Kelvin of the light to neutralize and `tint` runs
+    // from -100 (green) to 100 (magenta).
+    WhiteBalance {
+        temperature: f32,
+        tint: f32,
+    },
+    // Without a profile the noise level is estimated from the image itself.
+    Denoise {
+        strength: f32,
+        #[serde(default)]
+        profile: Option<NoiseProfile>,
+    },
+    // Gaussian, on the colour channels only.
+    Blur {
+        sigma: f32,
+        #[serde(default)]
+        edge: EdgeMode,
+    },
+    // Edge-preserving smoothing with a guided filter over windows `radius`
+    // pixels either side. `epsilon` is the variance, on 0-1 values, below
+    // which detail is smoothed away.
+    SmoothPreserveEdges {
+        radius: u32,
+        epsilon: f32,
+    },
+    // Unsharp mask with a blur of `radius` sigma. Differences of `threshold`
+    // levels or less are left alone.
+    Sharpen {
+        amount: f32,
+        radius: f32,
+        threshold: u8,
+        #[serde(default)]
+        edge: EdgeMode,
+    },
+}
+
+// What filters sample past the image border.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeMode {
+    // Repeats the edge pixels.
+    #[default]
+    Clamp,
+    // Reflects the image at its border, which keeps gradients running
+    // smoothly into the edge.
+    Mirror,
+    // Continues from the opposite side, for textures that tile.
+    Wrap,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalanceMethod {
+    // Assumes the scene averages to grey.
+    GrayWorld,
+    // Assumes the brightest tones are white.
+    WhitePatch,
+}
+
+// Sensor noise measured from dark frames, so a camera at a given ISO can be
+// denoised the same way across a whole shoot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseProfile {
+    pub camera: Option<String>,
+    pub iso: Option<u32>,
+    pub frames: usize,
+    // Per RGB channel, on the 0-255 scale.
+    pub black_level: [f32; 3],
+    pub sigma: [f32; 3],
+}
+
+// Noise measured from an image itself, with standard deviations on the
+// 0-255 scale like `NoiseProfile`'s.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseEstimate {
+    // Per RGB channel.
+    pub sigma: [f32; 3],
+    pub luminance_sigma: f32,
+    // Luminance noise as a percentage of the full range: under 1 looks
+    // clean, over 3 visibly grainy.
+    pub score: f32,
+    pub map: NoiseMap,
+}
+
+// Luminance noise over a grid of square cells, row by row from the top left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseMap {
+    pub columns: u32,
+    pub rows: u32,
+    // Cell side in image pixels; cells on the right and bottom edges may be
+    // cut short.
+    pub cell_size: u32,
+    pub sigma: Vec<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimelapseResult {
+    pub output: String,
+    pub frame_count: usize,
+    pub width: u32,
+    pub height: u32,
+    pub duration_seconds: f32,
+}
+
+// Animation frames can come from already-loaded images or straight from disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FrameSource {
+    Handle(ImageId),
+    Path(String),
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    Webp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnimationResult {
+    pub output: String,
+    pub frame_count: usize,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+}
+
+fn opaque() -> u8 {
+    255
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    #[serde(default = "opaque")]
+    pub a: u8,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonOrientation {
+    SideBySide,
+    TopBottom,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComparisonLayout {
+    pub orientation: ComparisonOrientation,
+    #[serde(default)]
+    pub divider_width: u32,
+    pub divider_color: Option<Color>,
+    pub background: Option<Color>,
+    pub before_label: Option<String>,
+    pub after_label: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub output: String,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Tiff,
+    Bmp,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    // 1-100, for JPEG and WebP. JPEG defaults to 90; WebP is written
+    // lossless unless a quality is given.
+    #[serde(default)]
+    pub quality: Option<u8>,
+    #[serde(default)]
+    pub png_compression: PngCompression,
+    // Flattens transparency onto white. JPEG and BMP are always flattened.
+    #[serde(default)]
+    pub strip_alpha: bool,
+    // Leaves out the EXIF data and ICC profile otherwise copied from the
+    // source file into PNG and JPEG output.
+    #[serde(default)]
+    pub strip_metadata: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AsciiCharset {
+    // Ten ASCII glyphs, readable at any size.
+    Standard,
+    // A 70-glyph ASCII ramp, for finer shading at wide sizes.
+    Detailed,
+    // Unicode shade blocks, for solid-looking output.
+    Blocks,
+    // Glyphs ordered from emptiest to fullest.
+    Custom { chars: String },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AsciiColorMode {
+    Plain,
+    // 24-bit ANSI escapes, for terminals.
+    Ansi,
+    // A `<pre>` of coloured spans.
+    Html,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitOrientation {
+    Vertical,
+    Horizontal,
+}
+
+// Missing red, green or blue cones, or, for achromatopsia, no colour vision
+// at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorVisionDeficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    Achromatopsia,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileHash {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    pub hash: String,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DuplicateFileGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+// 64-bit hashes of how an image looks rather than its bytes, so re-encoded,
+// resized or lightly edited copies hash alike. `Ahash` compares pixels with
+// the mean, `Dhash` neighbouring pixels, and `Phash`, the most robust, the
+// image's low frequencies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerceptualHashAlgorithm {
+    Ahash,
+    Dhash,
+    #[default]
+    Phash,
+}
+
+// `hash` is 16 hex digits, since JavaScript numbers can't hold 64 bits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PerceptualHash {
+    pub algorithm: PerceptualHashAlgorithm,
+    pub hash: String,
+}
+
+// Images within the threshold of another in the group, sorted by path.
+// `max_distance` is the largest Hamming distance between any two of them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NearDuplicateGroup {
+    pub paths: Vec<String>,
+    pub max_distance: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NearDuplicateReport {
+    pub algorithm: PerceptualHashAlgorithm,
+    pub groups: Vec<NearDuplicateGroup>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageVerification {
+    pub path: String,
+    pub format: Option<String>,
+    pub valid: bool,
+    pub decodes: bool,
+    pub truncated: bool,
+    pub recoverable: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub issues: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanPreview {
+    pub path: String,
+    pub scan: usize,
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub width: u32,
+    pub height: u32,
+    pub data_url: String,
+}
+
+// A downscaled copy of an image for display, as a PNG data URL.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImagePreview {
+    pub width: u32,
+    pub height: u32,
+    pub data_url: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PixelPoint {
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LookInfo {
+    pub name: String,
+    pub label: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub logical_cores: usize,
+    pub physical_cores: Option<usize>,
+    // Bytes.
+    pub total_memory: u64,
+    pub available_memory: u64,
+    // User-configured cap for batch jobs; `None` means one worker per core.
+    pub max_workers: Option<usize>,
+    pub current_workers: usize,
+    pub suggested_workers: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    // Bytes of pixel data held in memory.
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+    pub resident_images: usize,
+    pub spilled_images: usize,
+}
+
+// Payload of the `memory-pressure` event, sent whenever the store goes over
+// its budget.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryPressure {
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+    // Images moved to disk to make room; they reload on next use.
+    pub spilled: Vec<ImageId>,
+    // Still over budget after spilling everything that could be.
+    pub over_budget: bool,
+    pub message: String,
+}
+
+// Where an operation's pixel work ran.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerfBackend {
+    // Single-threaded on the calling thread.
+    Cpu,
+    // Split across the rayon thread pool.
+    Rayon,
+    // Handed off to an external ffmpeg process.
+    Ffmpeg,
+}
+
+// Payload of the `perf-metric` event emitted after each image operation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PerfMetric {
+    pub command: String,
+    pub width: u32,
+    pub height: u32,
+    pub megapixels: f64,
+    pub duration_ms: f64,
+    pub backend: PerfBackend,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutomationServerInfo {
+    pub url: String,
+    pub port: u16,
+    // Sent by clients as `Authorization: Bearer <token>`.
+    pub token: String,
+}
+
+// Payload of the `clipboard-image` event.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClipboardImage {
+    pub handle: ImageHandle,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenSource {
+    Clipboard,
+    RecentFile,
+    // Passed on the command line, e.g. by "Open With" or a double-click.
+    FileAssociation,
+    DeepLink,
+    // Arrived in a folder being watched.
+    WatchFolder,
+}
+
+// Payload of the `image-opened` event, sent when an image is loaded from
+// outside the main window (the tray menu, the OS, or a deep link).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenedImage {
+    pub handle: ImageHandle,
+    pub path: Option<String>,
+    pub source: OpenSource,
+}
+
+// The folders watched for new images, and whether the watch is paused.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatchFolders {
+    pub folders: Vec<String>,
+    pub paused: bool,
+}
+
+// Pixels removed from (or added to) each side of an image.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Margins {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrimResult {
+    pub handle: ImageHandle,
+    pub margins: Margins,
+}
+
+// How `extend_canvas` fills the added margins.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasFill {
+    Solid { color: Color },
+    Transparent,
+    // Repeats the outermost row or column.
+    Edge,
+    // Reflects the image about its edge, without repeating the edge pixel.
+    Mirror,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DitherPalette {
+    // Exactly these colours, e.g. a display's or a retro system's; alpha is
+    // ignored.
+    Fixed { colors: Vec<Color> },
+    // The image's own `size` most representative colours.
+    Extracted { size: usize },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherAlgorithm {
+    // Error diffusion: fine, organic grain.
+    FloydSteinberg,
+    // Error diffusion passing on only part of the error, with the crisper,
+    // higher-contrast look of early Macs.
+    Atkinson,
+    // Ordered 8x8 Bayer matrix: a regular crosshatch that stays stable
+    // frame to frame.
+    Bayer,
+    // Ordered with a blue-noise map: as stable as Bayer without its pattern.
+    BlueNoise,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnhanceMethod {
+    // Spreads the luminosity histogram evenly over the tonal range.
+    Equalize,
+    // Equalizes `tiles` x `tiles` regions separately, for local contrast,
+    // limiting each histogram bin to `clip_limit` times the average.
+    Clahe {
+        #[serde(default = "default_clahe_tiles")]
+        tiles: u32,
+        #[serde(default = "default_clahe_clip_limit")]
+        clip_limit: f32,
+    },
+    // Stretches the range so `clip_percent` of values clip at each end.
+    AutoContrast {
+        #[serde(default = "default_clip_percent")]
+        clip_percent: f32,
+    },
+}
+
+fn default_clahe_tiles() -> u32 {
+    8
+}
+
+fn default_clahe_clip_limit() -> f32 {
+    2.0
+}
+
+fn default_clip_percent() -> f32 {
+    0.5
+}
+
+// Input levels: `black` and `white` (0-255) map to 0 and 255, and `gamma`
+// bends the midtones between them, brightening above 1.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Levels {
+    pub black: f32,
+    pub white: f32,
+    pub gamma: f32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            black: 0.0,
+            white: 255.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+// `master` applies to all three channels, before each channel's own levels.
+// Any left out are unchanged.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ChannelLevels {
+    pub master: Levels,
+    pub red: Levels,
+    pub green: Levels,
+    pub blue: Levels,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveChannel {
+    // All three channels.
+    #[default]
+    Rgb,
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    // Keeps hard pixel edges, for pixel art and masks.
+    Nearest,
+    Bilinear,
+    Bicubic,
+    // Sharpest, at some ringing around hard edges.
+    #[default]
+    Lanczos3,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelArtAlgorithm {
+    // AdvMAME Scale2x/Scale3x: copies neighbours into corners, so the output
+    // keeps the source's exact colours.
+    Scale2x,
+    // Hyllian's xBR: finds edges at several angles and anti-aliases them,
+    // for smoother curves with new in-between colours.
+    Xbr,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlipAxis {
+    // Swaps left and right.
+    Horizontal,
+    // Swaps top and bottom.
+    Vertical,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// A position in image pixels, allowing fractions for guide geometry.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+// A brush stroke through `points`, covering `radius` pixels either side. A
+// single point is a dab.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scribble {
+    pub points: Vec<Point>,
+    pub radius: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Subject {
+    // Saliency-weighted centre of the subject.
+    pub center: Point,
+    pub bounds: PixelRect,
+    // Share of the frame the subject covers, 0-1.
+    pub coverage: f32,
+}
+
+// The guide point closest to the subject.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GuideOffset {
+    pub point: Point,
+    // Subject centre minus guide point, in pixels.
+    pub dx: f32,
+    pub dy: f32,
+    // Distance as a fraction of the image diagonal, so 0 is dead on.
+    pub distance: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GuideKind {
+    Thirds,
+    GoldenRatio,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GuideLine {
+    pub kind: GuideKind,
+    pub from: Point,
+    pub to: Point,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompositionAnalysis {
+    pub width: u32,
+    pub height: u32,
+    pub subject: Subject,
+    pub thirds_points: Vec<Point>,
+    pub golden_points: Vec<Point>,
+    pub nearest_third: GuideOffset,
+    pub nearest_golden: GuideOffset,
+    // Overlay lines for both grids, in image pixels.
+    pub guides: Vec<GuideLine>,
+}
+
+// A dominant straight-line direction. `angle` is the tilt in degrees,
+// counter-clockwise positive, away from exactly horizontal or vertical.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LineEstimate {
+    pub angle: f32,
+    // 0-1: how strongly edges agree on this direction.
+    pub confidence: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HorizonAnalysis {
+    pub horizon: Option<LineEstimate>,
+    pub vertical: Option<LineEstimate>,
+    // Counter-clockwise degrees that level the image, from whichever estimate
+    // is more confident; 0 when neither is usable.
+    pub rotation: f32,
+    pub confidence: f32,
+}
+
+// How evenly weight is spread between two halves of the frame.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct HalfBalance {
+    // Mean luminance of the first half (left or top) minus the second, -1 to 1.
+    pub luminance_difference: f32,
+    // CIE76 delta E between the halves' mean colours.
+    pub color_difference: f32,
+    // 1 for perfectly balanced halves, falling towards 0.
+    pub score: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BalanceAnalysis {
+    pub left_right: HalfBalance,
+    pub top_bottom: HalfBalance,
+    // Correlation between the image and its mirror image, 0-1.
+    pub left_right_symmetry: f32,
+    pub top_bottom_symmetry: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TextRegion {
+    pub bounds: PixelRect,
+    // Mean text probability inside the region, 0-1.
+    pub confidence: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FaceRegion {
+    pub bounds: PixelRect,
+    // The model's score for the face, 0-1.
+    pub confidence: f32,
+    // A heuristic guess; None when the face is too small or the eyes too
+    // featureless to judge.
+    pub eyes_open: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedactionStyle {
+    // Replaces each block with its average colour.
+    Pixelate { block_size: u32 },
+    Solid { color: Color },
+    // Blurs block averages rather than the pixels, so no detail survives to
+    // be deconvolved.
+    Blur { radius: f32 },
+}
+
+// What one-click redaction looks for.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionTarget {
+    Text,
+    Faces,
+}
+
+// The image after one-click redaction and the regions hidden, padding
+// included. With nothing found the image is left as it was.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutoRedaction {
+    pub handle: ImageHandle,
+    pub regions: Vec<PixelRect>,
+}
+
+// The image and its border set on a mat it casts a shadow onto.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DropShadow {
+    pub mat_width: u32,
+    pub mat_color: Color,
+    pub color: Color,
+    #[serde(default)]
+    pub offset_x: i32,
+    #[serde(default)]
+    pub offset_y: i32,
+    // Gaussian sigma in pixels; 0 for a hard shadow.
+    #[serde(default)]
+    pub blur: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BorderSpec {
+    pub width: u32,
+    pub color: Color,
+    // Rounds the outer corners of the bordered image.
+    #[serde(default)]
+    pub corner_radius: u32,
+    #[serde(default)]
+    pub shadow: Option<DropShadow>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaskShape {
+    RoundedRect { radius: u32 },
+    // Inscribed in the full frame.
+    Ellipse,
+    // Crops to the centred square first, as avatars expect.
+    Circle,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IconTarget {
+    // Windows and favicon icon. Empty sizes give 16 through 256.
+    Ico {
+        #[serde(default)]
+        sizes: Vec<u32>,
+    },
+    // macOS icon with every size from 16 to 1024, including @2x.
+    Icns,
+    Png {
+        size: u32,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocialPreset {
+    pub name: String,
+    pub label: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Where `slice_image` cuts. Guides are pixel positions of the cuts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SliceLayout {
+    Grid { rows: u32, columns: u32 },
+    Guides { x: Vec<u32>, y: Vec<u32> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SliceTile {
+    pub name: String,
+    // Relative to the manifest.
+    pub file: String,
+    pub row: u32,
+    pub column: u32,
+    pub bounds: PixelRect,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SliceManifest {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<SliceTile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SliceResult {
+    pub manifest_path: String,
+    pub manifest: SliceManifest,
+}
+
+// An eyedropper pick. `relative_luminance` is WCAG's, 0 for black to 1 for
+// white.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SampledColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub hex: String,
+    pub relative_luminance: f32,
+}
+
+// WCAG 2 contrast between a foreground and background colour. The ratio
+// runs from 1 to 21; AA asks for 4.5 for normal text and 3 for large text
+// and interface parts, AAA for 7 and 4.5.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContrastReport {
+    pub luminance_a: f32,
+    pub luminance_b: f32,
+    pub ratio: f32,
+    pub aa_normal: bool,
+    pub aa_large: bool,
+    pub aaa_normal: bool,
+    pub aaa_large: bool,
+}
+
+// Intensity cross-section along a line, one entry per sample in each list.
+// Channel values are on 0-255.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LineProfile {
+    // Line length in pixels.
+    pub length: f32,
+    pub distances: Vec<f32>,
+    pub points: Vec<Point>,
+    pub red: Vec<f32>,
+    pub green: Vec<f32>,
+    pub blue: Vec<f32>,
+    pub luminance: Vec<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlobStats {
+    // In pixels.
+    pub area: u32,
+    pub centroid: Point,
+    pub bounds: PixelRect,
+    // Mean luminance over the blob, 0-255.
+    pub mean_intensity: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlobAnalysis {
+    pub count: usize,
+    pub total_area: u64,
+    // Components dropped for being smaller than the minimum area.
+    pub rejected: usize,
+    pub blobs: Vec<BlobStats>,
+}
+
+// Two regions of an image that are copies of each other. `target` is
+// `source` moved by the offset; either may be the original.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CopyMoveMatch {
+    pub source: PixelRect,
+    pub target: PixelRect,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    // Matching 8x8 blocks at the working resolution, a rough measure of
+    // confidence.
+    pub blocks: usize,
+}
+
+// A camera's sensor pattern, as written by `extract_prnu`. The pattern itself
+// stays in the file at `path`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrnuFingerprint {
+    pub path: String,
+    pub camera: Option<String>,
+    pub images: usize,
+    // Size of the photos it was built from, which matched photos must share.
+    pub width: u32,
+    pub height: u32,
+    // The central area the pattern covers.
+    pub crop_width: u32,
+    pub crop_height: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrnuMatch {
+    // Normalized correlation between the photo's noise and the pattern.
+    pub correlation: f32,
+    // Peak-to-correlation energy: how far the correlation stands out from
+    // chance. Above about 60 the photo is taken to be from the camera.
+    pub pce: f32,
+    pub matches: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThumbnailComparison {
+    // The part of the EXIF thumbnail showing the picture, without padding.
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+    // False when the thumbnail shows more of the scene than the image does,
+    // as after a crop.
+    pub aspect_matches: bool,
+    // Mean and worst difference between the thumbnail and the downscaled
+    // image over small blocks, in 0-255 levels.
+    pub difference: f32,
+    pub peak_difference: f32,
+    // Where the worst block falls, in the image's pixels.
+    pub peak_region: PixelRect,
+    pub likely_edited: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HiddenDataKind {
+    // Bytes after the image's end marker.
+    TrailingData,
+    // A chunk or segment that common software doesn't write.
+    UnknownChunk,
+    // Long embedded text.
+    Comment,
+    // Pixel low bits that look like an embedded message.
+    LsbAnomaly,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiddenDataFinding {
+    pub kind: HiddenDataKind,
+    // Where in the file, for findings about its structure.
+    pub offset: Option<u64>,
+    pub length: Option<u64>,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiddenDataReport {
+    pub path: String,
+    pub format: Option<String>,
+    pub findings: Vec<HiddenDataFinding>,
+    // Estimated fraction of pixels carrying message bits in their least
+    // significant bit, in the most suspicious channel. Lossless formats only.
+    pub lsb_embedding_rate: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Star {
+    pub position: Point,
+    // Summed luminance above the sky.
+    pub flux: f32,
+    // Brightest pixel, 0-255.
+    pub peak: f32,
+    // Full width at half maximum, in pixels.
+    pub fwhm: f32,
+    // 0 for a round star, approaching 1 as it stretches into a line.
+    pub eccentricity: f32,
+    // Direction of the long axis, in degrees counter-clockwise from the
+    // positive x axis.
+    pub angle: f32,
+    // Clipped at the top, which makes its FWHM and shape unreliable.
+    pub saturated: bool,
+}
+
+// Focus scores on luminance. Both grow with fine detail and fall with blur,
+// but depend on content and size too, so they rank shots of one scene rather
+// than judge one shot alone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SharpnessReport {
+    pub laplacian_variance: f64,
+    // Mean squared Sobel gradient.
+    pub tenengrad: f64,
+    pub heatmap: Option<SharpnessMap>,
+}
+
+// Tenengrad over a grid of square cells, row by row from the top left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SharpnessMap {
+    pub columns: u32,
+    pub rows: u32,
+    // Cell side in image pixels; cells on the right and bottom edges may be
+    // cut short.
+    pub cell_size: u32,
+    pub tenengrad: Vec<f32>,
+}
+
+// `Sobel` is the raw gradient magnitude; `Canny` thins it to one-pixel lines
+// and keeps only those connected to a strong edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeMethod {
+    #[default]
+    Sobel,
+    Canny,
+}
+
+// Gradient magnitudes in levels: a hard step from black to white is 255.
+// Canny keeps edges reaching `high` and those down to `low` joined to them;
+// Sobel drops pixels below `low` and draws the rest more opaque up to `high`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeThresholds {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Default for EdgeThresholds {
+    fn default() -> Self {
+        Self {
+            low: 20.0,
+            high: 50.0,
+        }
+    }
+}
+
+// `Overlay` is a transparent layer with the edges in a colour, for drawing
+// over the image; `Mask` is white edges on black.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeOutput {
+    #[default]
+    Overlay,
+    Mask,
+}
+
+// Medians are over unsaturated stars where there are any. `stars` lists the
+// brightest first and is capped; `star_count` is every detection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StarAnalysis {
+    pub star_count: usize,
+    // Median sky luminance and its noise, 0-255.
+    pub background: f32,
+    pub noise: f32,
+    pub fwhm: Option<f32>,
+    pub eccentricity: Option<f32>,
+    // The prevailing elongation direction, in degrees; only meaningful when
+    // eccentricity is high, as with trailing from tracking error.
+    pub elongation_angle: Option<f32>,
+    pub stars: Vec<Star>,
+}
+
+// Real-world size of one pixel, e.g. 0.5 "mm".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpatialScale {
+    pub units_per_pixel: f64,
+    pub unit: String,
+}
+
+// Lengths and areas are in `unit`, or pixels when the image has no scale.
+// Angles are in degrees, counter-clockwise from the positive x axis.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Measurement {
+    pub unit: String,
+    pub calibrated: bool,
+    pub segment_lengths: Vec<f64>,
+    pub segment_angles: Vec<f64>,
+    pub total_length: f64,
+    // Interior angle at each point between two segments, 0-180.
+    pub vertex_angles: Vec<f64>,
+    // Of the closed polygon through three or more points.
+    pub area: Option<f64>,
+    pub perimeter: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverlayGuide {
+    // One line every `spacing` image pixels.
+    PixelGrid {
+        spacing: u32,
+    },
+    Thirds,
+    GoldenRatio,
+    // Quarter arcs through successive golden rectangles, stretched to the
+    // frame and turned to follow its longer side.
+    GoldenSpiral {
+        #[serde(default)]
+        flip_horizontal: bool,
+        #[serde(default)]
+        flip_vertical: bool,
+    },
+    // Lines at fractions (0-1) of the width and height.
+    Custom {
+        #[serde(default)]
+        vertical: Vec<f32>,
+        #[serde(default)]
+        horizontal: Vec<f32>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OverlaySpec {
+    pub guides: Vec<OverlayGuide>,
+    pub color: Color,
+    #[serde(default = "one")]
+    pub line_width: f32,
+    // Size to render at, e.g. the viewer's; defaults to the image's.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+// How far one channel's histogram moved, in levels (0-255).
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct HistogramShift {
+    // Earth mover's distance between the normalized histograms.
+    pub emd: f32,
+    // Change in the mean level; the sign gives the direction.
+    pub mean_shift: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChannelShifts {
+    pub red: HistogramShift,
+    pub green: HistogramShift,
+    pub blue: HistogramShift,
+    pub luminosity: HistogramShift,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaletteChange {
+    pub before: ColorSample,
+    pub after: ColorSample,
+    // CIE76 delta E.
+    pub delta_e: f32,
+}
+
+// Pixel-level differences between two images of the same size. Colour
+// statistics are over RGB, in levels; alpha is only counted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageComparison {
+    pub identical: bool,
+    // Pixels whose colour differs at all, and their share of the image.
+    pub differing_pixels: u64,
+    pub differing_percent: f32,
+    pub alpha_differing_pixels: u64,
+    // Largest difference in any one channel.
+    pub max_difference: u8,
+    // Mean absolute difference per channel, RGB.
+    pub mean_difference: [f32; 3],
+    pub rmse: f32,
+    // Peak signal-to-noise ratio in dB; `None` when the colours are
+    // identical, where it's infinite. Above about 40 dB differences are
+    // rarely visible.
+    pub psnr: Option<f32>,
+    // Mean structural similarity of the luminance, 1 for identical.
+    pub ssim: f32,
+    // Each pixel's largest channel difference, black through red and yellow
+    // to white at the largest in the image.
+    pub heatmap: Option<ImageData>,
+}
+
+// What changed from analysis `a` to analysis `b`; deltas are b minus a.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalysisDiff {
+    pub brightness_delta: f32,
+    pub contrast_delta: f32,
+    pub histogram_shift: ChannelShifts,
+    // Dominant colours paired closest first.
+    pub palette_changes: Vec<PaletteChange>,
+    // Mean delta E over the pairs.
+    pub palette_distance: f32,
+    // Colours only in `b` or only in `a`, when the palettes differ in size.
+    pub added_colors: Vec<ColorSample>,
+    pub removed_colors: Vec<ColorSample>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CropSuggestion {
+    // Width over height the crop was made for.
+    pub aspect_ratio: f32,
+    pub bounds: PixelRect,
+    // Overall rank score, 0-1.
+    pub score: f32,
+    // Share of the image's saliency the crop keeps.
+    pub saliency_coverage: f32,
+    // Distance from the crop's subject to its nearest thirds point, as a
+    // fraction of the crop diagonal.
+    pub thirds_distance: f32,
+}
+
+// A region of the image with an operation applied, at viewport resolution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OperationPreview {
+    // The requested region clipped to the image, in image pixels.
+    pub region: PixelRect,
+    pub image: ImageData,
+}
+
+// State of an image's low-resolution editing proxy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProxyInfo {
+    pub id: ImageId,
+    // Full-resolution size.
+    pub width: u32,
+    pub height: u32,
+    // Size of the proxy, equal to the full size for images small enough to edit directly.
+    pub proxy_width: u32,
+    pub proxy_height: u32,
+    // Edits shown on the proxy that haven't been applied at full resolution yet.
+    pub pending_edits: usize,
+}
+
+// Per-pixel arithmetic between two images, or an image and a constant.
+// Multiply and divide treat an image's values as 0-1 fractions, so
+// multiplying by white changes nothing; `Difference` is the absolute
+// difference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MathOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Min,
+    Max,
+    Difference,
+}
+
+// The right-hand side of `image_math`. A constant is in levels for every
+// operation but multiply and divide, where it's a plain factor.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MathOperand {
+    Image { id: ImageId },
+    Constant { value: f32 },
+}
+
+// `handle` is the result clamped to 0-255 as a new image. With float output
+// `values` also holds it unclamped, RGB by pixel, row by row, in levels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageMathResult {
+    pub handle: ImageHandle,
+    pub values: Option<Vec<f32>>,
+}
+
+// How an image's tone and colour adjustments are computed. At `U8` each
+// rounds its result to 8 bits; at `F32` the image keeps a float copy they
+// work on, so a chain of them rounds only once and doesn't band.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    #[default]
+    U8,
+    F32,
+}
+
+// A committed change to a stored image, with everything needed to redo it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Edit {
+    Operation {
+        operation: Operation,
+    },
+    AutoTrim {
+        tolerance: u8,
+    },
+    Crop {
+        rect: PixelRect,
+    },
+    // Counter-clockwise degrees.
+    Rotate {
+        degrees: f32,
+        fill: CanvasFill,
+    },
+    Flip {
+        axis: FlipAxis,
+    },
+    Resize {
+        width: u32,
+        height: u32,
+        filter: ResizeFilter,
+        #[serde(default)]
+        edge: EdgeMode,
+    },
+    ScalePixelArt {
+        algorithm: PixelArtAlgorithm,
+        factor: u32,
+    },
+    ExtendCanvas {
+        margins: Margins,
+        fill: CanvasFill,
+    },
+    AddBorder {
+        spec: BorderSpec,
+    },
+    ApplyShapeMask {
+        shape: MaskShape,
+    },
+    ApplyLook {
+        name: String,
+        intensity: f32,
+    },
+    AutoEnhance {
+        method: EnhanceMethod,
+    },
+    AutoWhiteBalance {
+        method: WhiteBalanceMethod,
+    },
+    Dither {
+        palette: DitherPalette,
+        algorithm: DitherAlgorithm,
+    },
+    Levels {
+        levels: ChannelLevels,
+    },
+    Curve {
+        points: Vec<[f32; 2]>,
+        channel: CurveChannel,
+    },
+    SetBlackWhitePoints {
+        black_sample: PixelPoint,
+        white_sample: PixelPoint,
+    },
+    RedactRegions {
+        regions: Vec<PixelRect>,
+        style: RedactionStyle,
+    },
+    // Reads the lens from the source file's metadata again.
+    AutoLensCorrect,
+    // Replays read the flat frame from `flat_frame` again.
+    FlatFieldCorrect {
+        flat_frame: String,
+    },
+    // Likewise `dark_frame`.
+    SubtractDarkFrame {
+        dark_frame: String,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoggedEdit {
+    // Starts at 1 and never repeats for an image.
+    pub sequence: u64,
+    pub edit: Edit,
+    // Image size after the edit.
+    pub width: u32,
+    pub height: u32,
+}
+
+// The committed edits an image's current state is made of, in order. Undone
+// edits leave the log until they are redone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OperationLog {
+    pub id: ImageId,
+    pub source_path: Option<String>,
+    // Size of the original the edits start from.
+    pub width: u32,
+    pub height: u32,
+    // Entries before this index are already part of the original (redactions
+    // replace it) and are skipped on replay.
+    pub baseline: usize,
+    pub entries: Vec<LoggedEdit>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub handle: ImageHandle,
+    pub replayed: usize,
+    // Whether replaying reproduced the image exactly. Anything else points to
+    // an edit that isn't deterministic or wasn't logged.
+    pub matches_current: bool,
+}
+
+// What was done to an image, for proving an export's provenance. Hashes are
+// lowercase hex in `algorithm`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub created_at: String,
+    pub algorithm: HashAlgorithm,
+    pub source_path: Option<String>,
+    // `None` when the image wasn't opened from a file or the file is gone.
+    pub source_hash: Option<String>,
+    pub source_width: u32,
+    pub source_height: u32,
+    pub edits: Vec<LoggedEdit>,
+    pub output_path: Option<String>,
+    pub output_hash: Option<String>,
+    // Hash of the edited RGBA pixels, which holds whatever format they are
+    // later saved in.
+    pub pixel_hash: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// `signature` is a keyed BLAKE3 hash of the record, so it shows the record
+// came from this installation unchanged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedAudit {
+    pub record: AuditRecord,
+    pub signature: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EditHistory {
+    pub handle: ImageHandle,
+    // Edits making up the current image, oldest first.
+    pub applied: Vec<LoggedEdit>,
+    // Edits `redo` would reapply, next first.
+    pub undone: Vec<LoggedEdit>,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    // Metres above sea level.
+    pub altitude: Option<f64>,
+}
+
+// Pixels per inch along each axis.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Resolution {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct XmpMetadata {
+    // -1 for rejected, otherwise 0-5 stars.
+    pub rating: Option<i32>,
+    pub label: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub creators: Vec<String>,
+    pub rights: Option<String>,
+    pub keywords: Vec<String>,
+    pub creator_tool: Option<String>,
+    // Set when the packet came from a sidecar file rather than the image.
+    pub sidecar: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub path: String,
+    pub file_size: u64,
+    // Local time, e.g. 2024-05-01T14:03:22.000.
+    pub modified: Option<String>,
+    pub format: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    // Seconds.
+    pub exposure_time: Option<f64>,
+    pub aperture: Option<f64>,
+    pub iso: Option<u32>,
+    // Millimetres, and the 35mm-equivalent when the camera records it.
+    pub focal_length: Option<f64>,
+    pub focal_length_35mm: Option<u32>,
+    pub captured_at: Option<String>,
+    pub gps: Option<GpsPosition>,
+    // EXIF orientation, 1-8; 1 is upright.
+    pub orientation: Option<u16>,
+    pub dpi: Option<Resolution>,
+    pub color_profile: Option<String>,
+    pub xmp: Option<XmpMetadata>,
+}
+
+// Thresholds for `cull_folder`. Sharpness is the variance of the Laplacian
+// on a 1024-pixel thumbnail, as burst detection scores frames; clipping is
+// the percentage of pixels pure black or pure white.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CullRules {
+    pub reject_sharpness: f64,
+    pub review_sharpness: f64,
+    pub reject_clipped_percent: f32,
+    pub review_clipped_percent: f32,
+    // Difference-hash bits two shots may differ by and still be grouped as
+    // near-duplicates; `None` turns grouping off.
+    pub duplicate_distance: Option<u32>,
+    // Sends shots where a face seems to have its eyes closed to review, when
+    // faces are being checked.
+    pub review_closed_eyes: bool,
+}
+
+impl Default for CullRules {
+    fn default() -> Self {
+        Self {
+            reject_sharpness: 20.0,
+            review_sharpness: 80.0,
+            reject_clipped_percent: 25.0,
+            review_clipped_percent: 5.0,
+            duplicate_distance: Some(6),
+            review_closed_eyes: true,
+        }
+    }
+}
+
+// Ordered from best to worst, so the strictest rule an image fails wins.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CullVerdict {
+    Keep,
+    Review,
+    Reject,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CullEntry {
+    pub path: String,
+    pub verdict: CullVerdict,
+    pub reasons: Vec<String>,
+    pub sharpness: f64,
+    pub clipped_shadows: f32,
+    pub clipped_highlights: f32,
+    // Faces found, or None when faces weren't checked.
+    pub faces: Option<usize>,
+    // Faces whose eyes look closed.
+    pub closed_eyes: usize,
+    // The best shot of this image's near-duplicate group, when that's another
+    // image.
+    pub duplicate_of: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CullReport {
+    pub keep: Vec<CullEntry>,
+    pub review: Vec<CullEntry>,
+    pub reject: Vec<CullEntry>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+// `gain` and `offset` map each channel value v to v * gain + offset. Means
+// are luminosity, 0-255, before and after.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExposureMatch {
+    pub path: String,
+    pub gain: f32,
+    pub offset: f32,
+    pub mean_before: f32,
+    pub mean_after: f32,
+    // Set when the corrected frame was written out.
+    pub output: Option<ExportResult>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExposureMatchReport {
+    pub reference: String,
+    pub reference_mean: f32,
+    pub matched: Vec<ExposureMatch>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+// An edit stack saved next to its original as `<file>.recipe.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recipe {
+    pub version: u32,
+    // File name of the original, for reference; the sidecar's location is what links them.
+    pub source: String,
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecipeExportReport {
+    pub exported: Vec<ExportResult>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+// Payload of the `analysis-progress` and `filter-progress` events.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub percent: f32,
+}
+
+// Sent as each image of a batch finishes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub path: String,
+    // Why the image was skipped, if it was.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub exported: Vec<ExportResult>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DevelopTool {
+    Lightroom,
+    Darktable,
+}
+
+// Another editor's adjustments translated into edits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportedSettings {
+    pub tool: DevelopTool,
+    // The XMP file read, or the image itself for embedded settings.
+    pub source: String,
+    pub edits: Vec<Edit>,
+    // Settings that were found but have no equivalent here.
+    pub unsupported: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsImport {
+    pub handle: ImageHandle,
+    pub settings: ImportedSettings,
+}
@@ -0,0 +1,97 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::analysis::PARALLEL_CHUNK;
+use crate::error::AppError;
+use crate::models::MathOp;
+
+pub enum Operand<'a> {
+    Image(&'a RgbaImage),
+    Constant(f32),
+}
+
+// `unit` is the `b` that multiplying or dividing by leaves `a` as it is: 255
+// for an image, 1 for a constant.
+fn combine(op: MathOp, a: f32, b: f32, unit: f32) -> f32 {
+    match op {
+        MathOp::Add => a + b,
+        MathOp::Subtract => a - b,
+        MathOp::Multiply => a * b / unit,
+        // Anything over zero is as bright as it gets.
+        MathOp::Divide if b == 0.0 => {
+            if a == 0.0 {
+                0.0
+            } else {
+                255.0
+            }
+        }
+        MathOp::Divide => a / b * unit,
+        MathOp::Min => a.min(b),
+        MathOp::Max => a.max(b),
+        MathOp::Difference => (a - b).abs(),
+    }
+}
+
+// Applies `op` to the colour channels of `a` and `b`, returning the results
+// unclamped, RGB by pixel, in levels. Alpha is ignored.
+pub fn image_math(a: &RgbaImage, b: Operand<'_>, op: MathOp) -> Result<Vec<f32>, AppError> {
+    let mut values: Vec<f32> = a
+        .as_raw()
+        .par_chunks(PARALLEL_CHUNK * 4)
+        .flat_map_iter(|chunk| {
+            chunk
+                .chunks_exact(4)
+                .flat_map(|px| px[..3].iter().map(|&v| v as f32))
+        })
+        .collect();
+    match b {
+        Operand::Image(b) => {
+            if b.dimensions() != a.dimensions() {
+                return Err(AppError::invalid(format!(
+                    "The images must be the same size, but one is {}x{} and the other {}x{}",
+                    a.width(),
+                    a.height(),
+                    b.width(),
+                    b.height()
+                )));
+            }
+            values
+                .par_chunks_mut(PARALLEL_CHUNK * 3)
+                .zip(b.as_raw().par_chunks(PARALLEL_CHUNK * 4))
+                .for_each(|(values, b)| {
+                    for (rgb, px) in values.chunks_exact_mut(3).zip(b.chunks_exact(4)) {
+                        for (v, &b) in rgb.iter_mut().zip(&px[..3]) {
+                            *v = combine(op, *v, b as f32, 255.0);
+                        }
+                    }
+                });
+        }
+        Operand::Constant(value) => {
+            if !value.is_finite() {
+                return Err(AppError::invalid("The constant must be a finite number"));
+            }
+            if op == MathOp::Divide && value == 0.0 {
+                return Err(AppError::invalid("Can't divide by zero"));
+            }
+            values
+                .par_iter_mut()
+                .for_each(|v| *v = combine(op, *v, value, 1.0));
+        }
+    }
+    Ok(values)
+}
+
+// `values` from `image_math` clamped to 8 bits, with `alpha_from`'s alpha.
+pub fn to_rgba(values: &[f32], alpha_from: &RgbaImage) -> RgbaImage {
+    let mut img = alpha_from.clone();
+    img.par_chunks_mut(PARALLEL_CHUNK * 4)
+        .zip(values.par_chunks(PARALLEL_CHUNK * 3))
+        .for_each(|(pixels, values)| {
+            for (px, rgb) in pixels.chunks_exact_mut(4).zip(values.chunks_exact(3)) {
+                for (p, v) in px.iter_mut().zip(rgb) {
+                    *p = v.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+    img
+}
@@ -0,0 +1,179 @@
+use image::{Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{CanvasFill, Margins, PixelRect};
+
+// Fully transparent pixels count as the same colour whatever their RGB, since
+// editors leave arbitrary values behind in padding.
+fn matches(px: &Rgba<u8>, border: &Rgba<u8>, tolerance: u8) -> bool {
+    if px[3] == 0 && border[3] == 0 {
+        return true;
+    }
+    px.0.iter()
+        .zip(border.0.iter())
+        .all(|(a, b)| a.abs_diff(*b) <= tolerance)
+}
+
+// The corner colour shared by the most other corners. A subject touching one
+// corner then doesn't stop the other three from deciding the border.
+fn border_color(img: &RgbaImage, tolerance: u8) -> Rgba<u8> {
+    let (w, h) = (img.width() - 1, img.height() - 1);
+    let corners = [
+        *img.get_pixel(0, 0),
+        *img.get_pixel(w, 0),
+        *img.get_pixel(0, h),
+        *img.get_pixel(w, h),
+    ];
+    *corners
+        .iter()
+        .rev()
+        .max_by_key(|corner| {
+            corners
+                .iter()
+                .filter(|other| matches(other, corner, tolerance))
+                .count()
+        })
+        .expect("four corners")
+}
+
+// Finds uniform borders (scanner margins, letterboxing, transparent padding)
+// within `tolerance` per channel of the border colour.
+pub fn trim_margins(img: &RgbaImage, tolerance: u8) -> Result<Margins, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let border = border_color(img, tolerance);
+    let row_is_border =
+        |y: u32| (0..width).all(|x| matches(img.get_pixel(x, y), &border, tolerance));
+
+    let Some(top) = (0..height).find(|&y| !row_is_border(y)) else {
+        return Err(AppError::invalid(
+            "The image is a single uniform colour; there is no content to trim to",
+        ));
+    };
+    let bottom = (0..height)
+        .rev()
+        .find(|&y| !row_is_border(y))
+        .unwrap_or(top);
+    let column_is_border =
+        |x: u32| (top..=bottom).all(|y| matches(img.get_pixel(x, y), &border, tolerance));
+    let left = (0..width).find(|&x| !column_is_border(x)).unwrap_or(0);
+    let right = (0..width)
+        .rev()
+        .find(|&x| !column_is_border(x))
+        .unwrap_or(width - 1);
+
+    Ok(Margins {
+        top,
+        right: width - 1 - right,
+        bottom: height - 1 - bottom,
+        left,
+    })
+}
+
+// Crops the borders found by `trim_margins` and reports what was removed.
+pub fn auto_trim(img: &mut RgbaImage, tolerance: u8) -> Result<Margins, AppError> {
+    let margins = trim_margins(img, tolerance)?;
+    if margins != Margins::default() {
+        let width = img.width() - margins.left - margins.right;
+        let height = img.height() - margins.top - margins.bottom;
+        *img = image::imageops::crop_imm(img, margins.left, margins.top, width, height).to_image();
+    }
+    Ok(margins)
+}
+
+// Maps a coordinate outside 0..len back into the image by reflection.
+pub(crate) fn mirror(i: i64, len: i64) -> i64 {
+    if len == 1 {
+        return 0;
+    }
+    let period = 2 * (len - 1);
+    let i = i.rem_euclid(period);
+    if i < len {
+        i
+    } else {
+        period - i
+    }
+}
+
+// Adds `margins` around the image, filling them with `fill`.
+pub fn extend_canvas(
+    img: &RgbaImage,
+    margins: Margins,
+    fill: CanvasFill,
+) -> Result<RgbaImage, AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let too_large = || AppError::invalid("The extended canvas is too large");
+    let new_width = width
+        .checked_add(margins.left)
+        .and_then(|w| w.checked_add(margins.right))
+        .ok_or_else(too_large)?;
+    let new_height = height
+        .checked_add(margins.top)
+        .and_then(|h| h.checked_add(margins.bottom))
+        .ok_or_else(too_large)?;
+
+    let (w, h) = (width as i64, height as i64);
+    let (left, top) = (margins.left as i64, margins.top as i64);
+    let solid = match fill {
+        CanvasFill::Solid { color } => Some(color.into()),
+        CanvasFill::Transparent => Some(Rgba([0, 0, 0, 0])),
+        CanvasFill::Edge | CanvasFill::Mirror => None,
+    };
+
+    Ok(RgbaImage::from_fn(new_width, new_height, |x, y| {
+        let sx = x as i64 - left;
+        let sy = y as i64 - top;
+        if (0..w).contains(&sx) && (0..h).contains(&sy) {
+            return *img.get_pixel(sx as u32, sy as u32);
+        }
+        let (sx, sy) = match (fill, solid) {
+            (_, Some(color)) => return color,
+            (CanvasFill::Mirror, _) => (mirror(sx, w), mirror(sy, h)),
+            _ => (sx.clamp(0, w - 1), sy.clamp(0, h - 1)),
+        };
+        *img.get_pixel(sx as u32, sy as u32)
+    }))
+}
+
+// Intersects a rectangle that may start or end off the image (a selection
+// dragged past the edge, say) with the image bounds.
+pub fn clip_rect(
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    image_width: u32,
+    image_height: u32,
+) -> Option<PixelRect> {
+    let x0 = x.clamp(0, image_width as i64);
+    let y0 = y.clamp(0, image_height as i64);
+    let x1 = (x + width as i64).clamp(0, image_width as i64);
+    let y1 = (y + height as i64).clamp(0, image_height as i64);
+    (x1 > x0 && y1 > y0).then(|| PixelRect {
+        x: x0 as u32,
+        y: y0 as u32,
+        width: (x1 - x0) as u32,
+        height: (y1 - y0) as u32,
+    })
+}
+
+// Crops to the part of the rectangle that lies on the image.
+pub fn crop(
+    img: &RgbaImage,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, AppError> {
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Crop size must be at least 1 pixel"));
+    }
+    let rect = clip_rect(x, y, width, height, img.width(), img.height())
+        .ok_or_else(|| AppError::invalid("The crop rectangle doesn't overlap the image"))?;
+    Ok(image::imageops::crop_imm(img, rect.x, rect.y, rect.width, rect.height).to_image())
+}
@@ -0,0 +1,60 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::analysis::PARALLEL_CHUNK;
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::models::ColorVisionDeficiency;
+
+// Viénot, Brettel and Mollon (1999): linear RGB to LMS cone responses and
+// back, and for each dichromacy the missing cone's response rebuilt from
+// the other two so that colours collapse onto the plane that viewer sees.
+// Their plane for protans and deutans runs through white, blue and yellow;
+// the tritan one through white and the red primary, which tritans see as
+// everyone else does.
+const RGB_TO_LMS: [[f32; 3]; 3] = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+const LMS_TO_RGB: [[f32; 3]; 3] = [
+    [0.080_944_45, -0.130_504_41, 0.116_721_07],
+    [-0.010_248_533, 0.054_019_33, -0.113_614_71],
+    [-0.000_365_296_94, -0.004_121_614_7, 0.693_511_4],
+];
+// Rec. 709 luminance, for achromatopsia.
+const LUMINANCE: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+fn multiply(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn simulate(rgb: [f32; 3], deficiency: ColorVisionDeficiency) -> [f32; 3] {
+    let [l, m, s] = multiply(&RGB_TO_LMS, rgb);
+    let lms = match deficiency {
+        ColorVisionDeficiency::Protanopia => [2.02344 * m - 2.52581 * s, m, s],
+        ColorVisionDeficiency::Deuteranopia => [l, 0.494207 * l + 1.24827 * s, s],
+        ColorVisionDeficiency::Tritanopia => [l, m, -0.012245 * l + 0.072035 * m],
+        ColorVisionDeficiency::Achromatopsia => {
+            let y = LUMINANCE.iter().zip(rgb).map(|(w, v)| w * v).sum();
+            return [y; 3];
+        }
+    };
+    multiply(&LMS_TO_RGB, lms)
+}
+
+// Renders the image as someone with `deficiency` would see it, for checking
+// that graphics don't rely on colours they can't tell apart. Works in
+// linear light; alpha is kept.
+pub fn simulate_color_vision(img: &RgbaImage, deficiency: ColorVisionDeficiency) -> RgbaImage {
+    let linear: Vec<f32> = (0..=255).map(srgb_to_linear).collect();
+    let mut out = img.clone();
+    out.par_chunks_mut(PARALLEL_CHUNK * 4).for_each(|chunk| {
+        for px in chunk.chunks_exact_mut(4) {
+            let rgb = [0, 1, 2].map(|c| linear[px[c] as usize]);
+            for (v, s) in px.iter_mut().zip(simulate(rgb, deficiency)) {
+                *v = linear_to_srgb(s);
+            }
+        }
+    });
+    out
+}
@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{Color, ComparisonLayout, ComparisonOrientation, Edit, SplitOrientation};
+use crate::processing::edits::apply_edit;
+use crate::processing::fill_rect;
+use crate::processing::text::{draw_text, text_size};
+
+const DEFAULT_DIVIDER: Color = Color {
+    r: 255,
+    g: 255,
+    b: 255,
+    a: 255,
+};
+
+// Draws a label in the panel's top-left corner on a translucent backing plate.
+fn draw_label(canvas: &mut RgbaImage, x: i64, y: i64, panel_width: u32, label: &str) {
+    let scale = (panel_width / 400).clamp(1, 6);
+    let padding = 4 * scale;
+    let (text_width, text_height) = text_size(label, scale);
+    let margin = 8 * scale as i64;
+
+    fill_rect(
+        canvas,
+        x + margin,
+        y + margin,
+        text_width + padding * 2,
+        text_height + padding * 2,
+        Rgba([0, 0, 0, 160]),
+    );
+    draw_text(
+        canvas,
+        x + margin + padding as i64,
+        y + margin + padding as i64,
+        label,
+        scale,
+        Rgba([255, 255, 255, 255]),
+    );
+}
+
+pub fn compose_comparison(
+    before: &RgbaImage,
+    after: &RgbaImage,
+    layout: &ComparisonLayout,
+) -> RgbaImage {
+    let divider = layout.divider_width;
+    let (width, height, after_offset) = match layout.orientation {
+        ComparisonOrientation::SideBySide => (
+            before.width() + divider + after.width(),
+            before.height().max(after.height()),
+            (before.width() + divider, 0),
+        ),
+        ComparisonOrientation::TopBottom => (
+            before.width().max(after.width()),
+            before.height() + divider + after.height(),
+            (0, before.height() + divider),
+        ),
+    };
+
+    let background = layout
+        .background
+        .map(Rgba::from)
+        .unwrap_or(Rgba([0, 0, 0, 0]));
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+
+    // Panels are centred across the axis the two images share.
+    let centre = |panel: &RgbaImage, offset: (u32, u32)| match layout.orientation {
+        ComparisonOrientation::SideBySide => (offset.0, (height - panel.height()) / 2),
+        ComparisonOrientation::TopBottom => ((width - panel.width()) / 2, offset.1),
+    };
+    let before_pos = centre(before, (0, 0));
+    let after_pos = centre(after, after_offset);
+    image::imageops::overlay(
+        &mut canvas,
+        before,
+        before_pos.0 as i64,
+        before_pos.1 as i64,
+    );
+    image::imageops::overlay(&mut canvas, after, after_pos.0 as i64, after_pos.1 as i64);
+
+    if divider > 0 {
+        let color = layout.divider_color.unwrap_or(DEFAULT_DIVIDER).into();
+        match layout.orientation {
+            ComparisonOrientation::SideBySide => fill_rect(
+                &mut canvas,
+                before.width() as i64,
+                0,
+                divider,
+                height,
+                color,
+            ),
+            ComparisonOrientation::TopBottom => fill_rect(
+                &mut canvas,
+                0,
+                before.height() as i64,
+                width,
+                divider,
+                color,
+            ),
+        }
+    }
+
+    if let Some(label) = layout.before_label.as_deref().filter(|l| !l.is_empty()) {
+        draw_label(
+            &mut canvas,
+            before_pos.0 as i64,
+            before_pos.1 as i64,
+            before.width(),
+            label,
+        );
+    }
+    if let Some(label) = layout.after_label.as_deref().filter(|l| !l.is_empty()) {
+        draw_label(
+            &mut canvas,
+            after_pos.0 as i64,
+            after_pos.1 as i64,
+            after.width(),
+            label,
+        );
+    }
+
+    canvas
+}
+
+// Edits that move pixels rather than change their colour. Redone on the
+// original, they bring it into line with the edited image.
+fn moves_pixels(edit: &Edit) -> bool {
+    matches!(
+        edit,
+        Edit::AutoTrim { .. }
+            | Edit::Crop { .. }
+            | Edit::Rotate { .. }
+            | Edit::Flip { .. }
+            | Edit::Resize { .. }
+            | Edit::ScalePixelArt { .. }
+            | Edit::ExtendCanvas { .. }
+            | Edit::AddBorder { .. }
+            | Edit::AutoLensCorrect
+    )
+}
+
+// `original` with the geometry among `edits` redone, so each of its pixels
+// sits where the same pixel sits after every edit.
+pub fn aligned_original<'a>(
+    original: &RgbaImage,
+    edits: impl IntoIterator<Item = &'a Edit>,
+    source: Option<&Path>,
+) -> Result<RgbaImage, AppError> {
+    let mut img = original.clone();
+    for edit in edits.into_iter().filter(|edit| moves_pixels(edit)) {
+        apply_edit(&mut img, edit, source)?;
+    }
+    Ok(img)
+}
+
+// Original pixels on the left/top of the split, edited pixels on the
+// right/bottom. The divider is left for the viewer to draw over it.
+fn compose_split(
+    before: &RgbaImage,
+    after: &RgbaImage,
+    split_position: f32,
+    orientation: SplitOrientation,
+) -> RgbaImage {
+    let (width, height) = after.dimensions();
+    let split = split_position.clamp(0.0, 1.0);
+    let line = match orientation {
+        SplitOrientation::Vertical => (width as f32 * split).round() as u32,
+        SplitOrientation::Horizontal => (height as f32 * split).round() as u32,
+    };
+
+    let mut out = after.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let along = match orientation {
+            SplitOrientation::Vertical => x,
+            SplitOrientation::Horizontal => y,
+        };
+        if along < line {
+            *pixel = *before.get_pixel(x, y);
+        }
+    }
+    out
+}
+
+// Before/after split of `before`, the original lined up with
+// `aligned_original`, and `current`, optionally downscaled so its longest
+// side is at most `max_dimension`. Fails when the two aren't the same size,
+// as when an auto-trim of the edited image cut differently from the original.
+pub fn split_preview(
+    before: &RgbaImage,
+    current: &RgbaImage,
+    split_position: f32,
+    orientation: SplitOrientation,
+    max_dimension: Option<u32>,
+) -> Result<RgbaImage, AppError> {
+    if before.dimensions() != current.dimensions() {
+        return Err(AppError::invalid(format!(
+            "The original is {}x{} after its geometry edits but the image is {}x{}, \
+             so they can't be compared pixel for pixel",
+            before.width(),
+            before.height(),
+            current.width(),
+            current.height()
+        )));
+    }
+    // Downscale both sides before compositing so the work scales with the
+    // preview, not the source.
+    Ok(match max_dimension {
+        Some(max) if current.width().max(current.height()) > max => {
+            let after = DynamicImage::ImageRgba8(current.clone())
+                .thumbnail(max, max)
+                .to_rgba8();
+            let before = image::imageops::resize(
+                before,
+                after.width(),
+                after.height(),
+                FilterType::Triangle,
+            );
+            compose_split(&before, &after, split_position, orientation)
+        }
+        _ => compose_split(before, current, split_position, orientation),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::{Operation, PixelRect};
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(20, 10, |x, y| Rgba([x as u8 * 10, y as u8 * 20, 0, 255]))
+    }
+
+    #[test]
+    fn split_lines_up_a_cropped_image_with_its_original() {
+        let original = gradient();
+        let edits = [
+            Edit::Operation {
+                operation: Operation::Grayscale,
+            },
+            Edit::Crop {
+                rect: PixelRect {
+                    x: 5,
+                    y: 2,
+                    width: 10,
+                    height: 6,
+                },
+            },
+        ];
+        let mut current = original.clone();
+        for edit in &edits {
+            apply_edit(&mut current, edit, None).unwrap();
+        }
+        let before = aligned_original(&original, &edits, None).unwrap();
+        let split =
+            split_preview(&before, &current, 0.5, SplitOrientation::Vertical, None).unwrap();
+
+        assert_eq!(split.dimensions(), (10, 6));
+        // Left of the split is the original pixel under the crop, right of it
+        // the edited one, and nothing is painted over either.
+        assert_eq!(split.get_pixel(4, 3), original.get_pixel(9, 5));
+        assert_eq!(split.get_pixel(5, 3), current.get_pixel(5, 3));
+    }
+
+    #[test]
+    fn mismatched_sizes_are_rejected() {
+        let current = RgbaImage::new(10, 10);
+        assert!(
+            split_preview(&gradient(), &current, 0.5, SplitOrientation::Vertical, None).is_err()
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::decode::decode_file;
+use crate::error::AppError;
+
+// Subtracts `dark`, an exposure of the same length and ISO with the lens
+// capped, to remove hot pixels, amp glow and other fixed-pattern noise.
+// The pattern is tied to sensor positions, so the dark must be the same size
+// and orientation as the image. Subtraction is in linear light, where the
+// sensor's signal adds up.
+pub fn subtract_dark_frame(img: &mut RgbaImage, dark: &RgbaImage) -> Result<(), AppError> {
+    if img.dimensions() != dark.dimensions() {
+        return Err(AppError::invalid(format!(
+            "The dark frame is {}x{} but the image is {}x{}; they must come from the same sensor readout",
+            dark.width(),
+            dark.height(),
+            img.width(),
+            img.height()
+        )));
+    }
+    img.par_chunks_mut(4)
+        .zip(dark.par_chunks(4))
+        .for_each(|(px, dark)| {
+            for (value, dark) in px.iter_mut().zip(dark).take(3) {
+                *value = linear_to_srgb(srgb_to_linear(*value) - srgb_to_linear(*dark));
+            }
+        });
+    Ok(())
+}
+
+pub fn subtract_dark_frame_file(img: &mut RgbaImage, dark_path: &Path) -> Result<(), AppError> {
+    let dark = decode_file(dark_path, false)?.image;
+    subtract_dark_frame(img, &dark)
+}
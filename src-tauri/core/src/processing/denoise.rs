@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::decode::decode_file;
+use crate::error::AppError;
+use crate::metadata::extract_metadata;
+use crate::models::NoiseProfile;
+
+// The filter window is (2 * RADIUS + 1)^2 pixels.
+const RADIUS: u32 = 2;
+
+// Per-channel mean and noise level of one frame. Noise comes from the
+// difference between horizontal neighbours, which cancels gradual shading
+// such as vignetting or amp glow; that difference has twice the variance of
+// the noise itself.
+fn frame_noise(img: &RgbaImage) -> ([f64; 3], [f64; 3], u64) {
+    let mut sum = [0f64; 3];
+    let mut diff_sq = [0f64; 3];
+    let mut pairs = 0u64;
+    for row in img.rows() {
+        let row: Vec<_> = row.collect();
+        for px in &row {
+            for c in 0..3 {
+                sum[c] += px[c] as f64;
+            }
+        }
+        for pair in row.windows(2) {
+            for c in 0..3 {
+                diff_sq[c] += (pair[1][c] as f64 - pair[0][c] as f64).powi(2);
+            }
+            pairs += 1;
+        }
+    }
+    let pixels = img.width() as f64 * img.height() as f64;
+    let variance = diff_sq.map(|d| d / pairs.max(1) as f64 / 2.0);
+    (sum.map(|s| s / pixels.max(1.0)), variance, pairs)
+}
+
+// Builds a profile from dark frames: exposures taken with the lens capped
+// at the camera and ISO the profile is for. Frames that disagree on either
+// are rejected, since their noise isn't comparable.
+pub fn build_noise_profile(paths: &[PathBuf]) -> Result<NoiseProfile, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::invalid("At least one dark frame is needed"));
+    }
+    let frames = paths
+        .par_iter()
+        .map(|path| {
+            let metadata = extract_metadata(path).ok();
+            let camera = metadata.as_ref().and_then(|m| m.camera_model.clone());
+            let iso = metadata.as_ref().and_then(|m| m.iso);
+            let image = decode_file(path, false)?.image;
+            Ok((path, camera, iso, frame_noise(&image)))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let (_, camera, iso, _) = &frames[0];
+    if let Some((path, ..)) = frames.iter().find(|(_, c, i, _)| c != camera || i != iso) {
+        return Err(AppError::invalid(format!(
+            "{} was not taken with the same camera and ISO as the other dark frames",
+            path.display()
+        )));
+    }
+
+    let mut black_level = [0f64; 3];
+    let mut variance = [0f64; 3];
+    let mut weight = 0f64;
+    for (.., (mean, var, pairs)) in &frames {
+        let w = *pairs as f64;
+        for c in 0..3 {
+            black_level[c] += mean[c] * w;
+            variance[c] += var[c] * w;
+        }
+        weight += w;
+    }
+    let weight = weight.max(1.0);
+    Ok(NoiseProfile {
+        camera: camera.clone(),
+        iso: *iso,
+        frames: frames.len(),
+        black_level: black_level.map(|b| (b / weight) as f32),
+        sigma: variance.map(|v| (v / weight).sqrt() as f32),
+    })
+}
+
+// Estimates the noise in an image with no profile, from the median absolute
+// neighbour difference. Edges and texture are a minority of differences, so
+// the median mostly sees noise.
+fn estimate_sigma(img: &RgbaImage) -> [f32; 3] {
+    let mut diffs: [Vec<u8>; 3] = Default::default();
+    for row in img.rows() {
+        let row: Vec<_> = row.collect();
+        for pair in row.windows(2) {
+            for (c, diff) in diffs.iter_mut().enumerate() {
+                diff.push(pair[1][c].abs_diff(pair[0][c]));
+            }
+        }
+    }
+    diffs.map(|mut d| {
+        if d.is_empty() {
+            return 0.0;
+        }
+        let mid = d.len() / 2;
+        let median = *d.select_nth_unstable(mid).1 as f32;
+        // A median absolute deviation of a normal distribution is 0.6745
+        // sigma, and the difference of two samples has sqrt(2) times the
+        // noise.
+        median / 0.6745 / std::f32::consts::SQRT_2
+    })
+}
+
+// Summed-area table of values and squared values for one channel.
+fn integrals(img: &RgbaImage, channel: usize) -> (Vec<f64>, Vec<f64>) {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let mut sum = vec![0f64; (w + 1) * (h + 1)];
+    let mut sum_sq = vec![0f64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0f64;
+        let mut row_sq = 0f64;
+        for x in 0..w {
+            let v = img.get_pixel(x as u32, y as u32)[channel] as f64;
+            row += v;
+            row_sq += v * v;
+            let i = (y + 1) * (w + 1) + x + 1;
+            sum[i] = sum[i - w - 1] + row;
+            sum_sq[i] = sum_sq[i - w - 1] + row_sq;
+        }
+    }
+    (sum, sum_sq)
+}
+
+// Adaptive Wiener (Lee) filtering: each pixel moves towards its local mean
+// by how much of the local variance the noise accounts for, so flat areas
+// are smoothed and edges, whose variance is far above the noise, are kept.
+// `strength` scales the noise level, 1 being the profile or estimate as is.
+pub fn denoise(img: &mut RgbaImage, strength: f32, profile: Option<&NoiseProfile>) {
+    let sigma = match profile {
+        Some(profile) => profile.sigma,
+        None => estimate_sigma(img),
+    };
+    let (w, h) = (img.width(), img.height());
+    let stride = w as usize + 1;
+    for (c, sigma) in sigma.iter().enumerate() {
+        let noise = (sigma * strength.max(0.0)) as f64;
+        let noise_var = noise * noise;
+        if noise_var <= 0.0 {
+            continue;
+        }
+        let (sum, sum_sq) = integrals(img, c);
+        let area = |table: &[f64], x0: usize, y0: usize, x1: usize, y1: usize| {
+            table[y1 * stride + x1] - table[y0 * stride + x1] - table[y1 * stride + x0]
+                + table[y0 * stride + x0]
+        };
+        for y in 0..h {
+            let y0 = y.saturating_sub(RADIUS) as usize;
+            let y1 = (y + RADIUS + 1).min(h) as usize;
+            for x in 0..w {
+                let x0 = x.saturating_sub(RADIUS) as usize;
+                let x1 = (x + RADIUS + 1).min(w) as usize;
+                let n = ((x1 - x0) * (y1 - y0)) as f64;
+                let mean = area(&sum, x0, y0, x1, y1) / n;
+                let variance = (area(&sum_sq, x0, y0, x1, y1) / n - mean * mean).max(0.0);
+                let gain = if variance > noise_var {
+                    (variance - noise_var) / variance
+                } else {
+                    0.0
+                };
+                let px = img.get_pixel_mut(x, y);
+                let value = mean + gain * (px[c] as f64 - mean);
+                px[c] = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
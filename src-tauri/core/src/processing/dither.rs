@@ -0,0 +1,367 @@
+use std::sync::OnceLock;
+
+use image::RgbaImage;
+
+use crate::analysis::palette::{dominant_colors, MAX_PALETTE_SIZE};
+use crate::error::AppError;
+use crate::models::{DitherAlgorithm, DitherPalette};
+
+const MAX_FIXED_COLORS: usize = 256;
+// Side of the generated blue-noise tile.
+const BLUE_NOISE_SIZE: usize = 64;
+// Spread of the energy function void-and-cluster packs points by.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+// (dx, dy, weight) error-diffusion kernels, for rows scanned left to right.
+const FLOYD_STEINBERG: &[(i32, i32, f32)] = &[
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+// Passes on only three quarters of the error, which keeps highlights and
+// shadows clean at the cost of some detail there.
+const ATKINSON: &[(i32, i32, f32)] = &[
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+
+fn nearest(palette: &[[f32; 3]], color: [f32; 3]) -> [f32; 3] {
+    *palette
+        .iter()
+        .min_by(|a, b| distance(a, &color).total_cmp(&distance(b, &color)))
+        .expect("palette is not empty")
+}
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn resolve_palette(img: &RgbaImage, palette: &DitherPalette) -> Result<Vec<[f32; 3]>, AppError> {
+    match palette {
+        DitherPalette::Fixed { colors } => {
+            if colors.is_empty() || colors.len() > MAX_FIXED_COLORS {
+                return Err(AppError::invalid(format!(
+                    "A palette needs 1 to {} colours",
+                    MAX_FIXED_COLORS
+                )));
+            }
+            Ok(colors
+                .iter()
+                .map(|c| [c.r as f32, c.g as f32, c.b as f32])
+                .collect())
+        }
+        DitherPalette::Extracted { size } => {
+            if !(2..=MAX_PALETTE_SIZE).contains(size) {
+                return Err(AppError::invalid(format!(
+                    "An extracted palette needs 2 to {} colours",
+                    MAX_PALETTE_SIZE
+                )));
+            }
+            let colors = dominant_colors(img.as_raw(), *size);
+            if colors.is_empty() {
+                return Err(AppError::invalid("The image has no visible pixels"));
+            }
+            Ok(colors
+                .iter()
+                .map(|c| [c.r as f32, c.g as f32, c.b as f32])
+                .collect())
+        }
+    }
+}
+
+fn diffuse(img: &mut RgbaImage, palette: &[[f32; 3]], kernel: &[(i32, i32, f32)]) {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let rows = kernel.iter().map(|k| k.1).max().unwrap_or(0) as usize + 1;
+    // Error carried into the next few rows, indexed by row modulo `rows`.
+    let mut error = vec![vec![[0f32; 3]; w]; rows];
+    let data: &mut [u8] = img;
+    for y in 0..h {
+        // Alternate rows run right to left so the error doesn't streak.
+        let reverse = y % 2 == 1;
+        for i in 0..w {
+            let x = if reverse { w - 1 - i } else { i };
+            let px = &mut data[(y * w + x) * 4..(y * w + x) * 4 + 4];
+            let carried = error[y % rows][x];
+            let color: [f32; 3] =
+                std::array::from_fn(|c| (px[c] as f32 + carried[c]).clamp(0.0, 255.0));
+            let chosen = nearest(palette, color);
+            for c in 0..3 {
+                px[c] = chosen[c].round() as u8;
+            }
+            for &(dx, dy, weight) in kernel {
+                let dx = if reverse { -dx } else { dx };
+                let (nx, ny) = (x as i32 + dx, y + dy as usize);
+                if nx < 0 || nx >= w as i32 || ny >= h {
+                    continue;
+                }
+                let target = &mut error[ny % rows][nx as usize];
+                for c in 0..3 {
+                    target[c] += (color[c] - chosen[c]) * weight;
+                }
+            }
+        }
+        error[y % rows].iter_mut().for_each(|e| *e = [0.0; 3]);
+    }
+}
+
+// Each pixel is drawn as a mix of the two palette colours nearest it, in
+// proportion to where it falls between them; the threshold map decides which
+// of the two each pixel gets, so the mix comes out evenly spread.
+fn ordered(img: &mut RgbaImage, palette: &[[f32; 3]], threshold: impl Fn(u32, u32) -> f32) {
+    for (x, y, px) in img.enumerate_pixels_mut() {
+        let color = [px[0] as f32, px[1] as f32, px[2] as f32];
+        let (mut a, mut b) = ((f32::INFINITY, palette[0]), (f32::INFINITY, palette[0]));
+        for candidate in palette {
+            let d = distance(candidate, &color);
+            if d < a.0 {
+                (a, b) = ((d, *candidate), a);
+            } else if d < b.0 && *candidate != a.1 {
+                b = (d, *candidate);
+            }
+        }
+        let (a, b) = (a.1, b.1);
+        let chosen = if a == b {
+            a
+        } else {
+            let along: f32 = (0..3).map(|c| (color[c] - a[c]) * (b[c] - a[c])).sum();
+            let share = (along / distance(&a, &b)).clamp(0.0, 1.0);
+            if threshold(x, y) < share {
+                b
+            } else {
+                a
+            }
+        };
+        for c in 0..3 {
+            px[c] = chosen[c].round() as u8;
+        }
+    }
+}
+
+// A tileable threshold map whose values are spread with no low-frequency
+// structure, by Ulichney's void-and-cluster method. Generated once; the
+// starting pattern comes from a fixed seed so it's the same every run.
+fn blue_noise() -> &'static [f32] {
+    static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+    NOISE.get_or_init(|| {
+        let n = BLUE_NOISE_SIZE;
+        let weight = |a: usize, b: usize| {
+            let d = |p: usize, q: usize| {
+                let d = p.abs_diff(q);
+                d.min(n - d) as f32
+            };
+            let (dx, dy) = (d(a % n, b % n), d(a / n, b / n));
+            (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+        };
+        // Energy falls off fast enough that a small window covers it.
+        let reach = (BLUE_NOISE_SIGMA * 4.0).ceil() as usize;
+        let update = |energy: &mut [f32], point: usize, sign: f32| {
+            let (px, py) = (point % n, point / n);
+            for dy in 0..=2 * reach {
+                for dx in 0..=2 * reach {
+                    let x = (px + n + dx - reach) % n;
+                    let y = (py + n + dy - reach) % n;
+                    energy[y * n + x] += sign * weight(point, y * n + x);
+                }
+            }
+        };
+        let pick = |energy: &[f32], ones: &[bool], want: bool, tightest: bool| {
+            (0..n * n)
+                .filter(|&i| ones[i] == want)
+                .max_by(|&a, &b| {
+                    let order = energy[a].total_cmp(&energy[b]);
+                    if tightest {
+                        order
+                    } else {
+                        order.reverse()
+                    }
+                })
+                .expect("a candidate remains")
+        };
+
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut ones = vec![false; n * n];
+        let initial = n * n / 10;
+        let mut placed = 0;
+        while placed < initial {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let i = (state % (n * n) as u64) as usize;
+            if !ones[i] {
+                ones[i] = true;
+                placed += 1;
+            }
+        }
+        let mut energy = vec![0f32; n * n];
+        for i in (0..n * n).filter(|&i| ones[i]) {
+            update(&mut energy, i, 1.0);
+        }
+        // Relax the random start: move the tightest cluster's point into the
+        // largest void until it's already there.
+        for _ in 0..n * n {
+            let cluster = pick(&energy, &ones, true, true);
+            ones[cluster] = false;
+            update(&mut energy, cluster, -1.0);
+            let void = pick(&energy, &ones, false, false);
+            if void == cluster {
+                ones[cluster] = true;
+                update(&mut energy, cluster, 1.0);
+                break;
+            }
+            ones[void] = true;
+            update(&mut energy, void, 1.0);
+        }
+
+        let mut rank = vec![0usize; n * n];
+        // Ranks below the start come from removing its tightest clusters...
+        let (mut start_ones, mut start_energy) = (ones.clone(), energy.clone());
+        for r in (0..initial).rev() {
+            let cluster = pick(&start_energy, &start_ones, true, true);
+            start_ones[cluster] = false;
+            update(&mut start_energy, cluster, -1.0);
+            rank[cluster] = r;
+        }
+        // ...and those above from filling its largest voids.
+        for r in initial..n * n {
+            let void = pick(&energy, &ones, false, false);
+            ones[void] = true;
+            update(&mut energy, void, 1.0);
+            rank[void] = r;
+        }
+        rank.iter()
+            .map(|&r| (r as f32 + 0.5) / (n * n) as f32)
+            .collect()
+    })
+}
+
+// Reduces the image to `palette`, keeping alpha as it is.
+pub fn dither(
+    img: &mut RgbaImage,
+    palette: &DitherPalette,
+    algorithm: DitherAlgorithm,
+) -> Result<(), AppError> {
+    let palette = resolve_palette(img, palette)?;
+    match algorithm {
+        DitherAlgorithm::FloydSteinberg => diffuse(img, &palette, FLOYD_STEINBERG),
+        DitherAlgorithm::Atkinson => diffuse(img, &palette, ATKINSON),
+        DitherAlgorithm::Bayer => ordered(img, &palette, |x, y| {
+            (BAYER[y as usize % 8][x as usize % 8] as f32 + 0.5) / 64.0
+        }),
+        DitherAlgorithm::BlueNoise => {
+            let noise = blue_noise();
+            let n = BLUE_NOISE_SIZE;
+            ordered(img, &palette, |x, y| {
+                noise[(y as usize % n) * n + x as usize % n]
+            })
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::Rgba;
+
+    use crate::models::Color;
+
+    const BLACK_WHITE: [[f32; 3]; 2] = [[0.0; 3], [255.0; 3]];
+
+    fn color(v: u8) -> Color {
+        Color {
+            r: v,
+            g: v,
+            b: v,
+            a: 255,
+        }
+    }
+
+    fn white_share(img: &RgbaImage) -> f32 {
+        let white = img.pixels().filter(|px| px[0] == 255).count();
+        white as f32 / (img.width() * img.height()) as f32
+    }
+
+    #[test]
+    fn kernels_pass_on_their_share_of_the_error() {
+        let total: f32 = FLOYD_STEINBERG.iter().map(|k| k.2).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+        let total: f32 = ATKINSON.iter().map(|k| k.2).sum();
+        assert!((total - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn every_algorithm_keeps_mid_grey_mean() {
+        for algorithm in [
+            DitherAlgorithm::FloydSteinberg,
+            DitherAlgorithm::Bayer,
+            DitherAlgorithm::BlueNoise,
+        ] {
+            let mut img = RgbaImage::from_pixel(64, 64, Rgba([128, 128, 128, 200]));
+            let palette = DitherPalette::Fixed {
+                colors: vec![color(0), color(255)],
+            };
+            dither(&mut img, &palette, algorithm).unwrap();
+            assert!(img
+                .pixels()
+                .all(|px| matches!(px[0], 0 | 255) && px[3] == 200));
+            let share = white_share(&img);
+            assert!(
+                (share - 128.0 / 255.0).abs() < 0.02,
+                "{:?}: {}",
+                algorithm,
+                share
+            );
+        }
+    }
+
+    #[test]
+    fn palette_colours_are_left_alone() {
+        for kernel in [FLOYD_STEINBERG, ATKINSON] {
+            let mut img = RgbaImage::from_fn(8, 8, |x, _| {
+                Rgba(if x % 2 == 0 {
+                    [0, 0, 0, 255]
+                } else {
+                    [255, 255, 255, 255]
+                })
+            });
+            let before = img.clone();
+            diffuse(&mut img, &BLACK_WHITE, kernel);
+            assert_eq!(img, before);
+        }
+    }
+
+    #[test]
+    fn blue_noise_ranks_every_cell_once() {
+        let n = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        let mut ranks: Vec<usize> = blue_noise()
+            .iter()
+            .map(|&t| (t * n as f32 - 0.5).round() as usize)
+            .collect();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+    }
+
+    #[test]
+    fn fixed_palette_needs_colours() {
+        let mut img = RgbaImage::new(2, 2);
+        let empty = DitherPalette::Fixed { colors: Vec::new() };
+        assert!(dither(&mut img, &empty, DitherAlgorithm::Bayer).is_err());
+    }
+}
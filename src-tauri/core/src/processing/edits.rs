@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::error::AppError;
+use crate::lens;
+use crate::models::Edit;
+use crate::processing::{
+    self, canvas, dark_frame, dither, enhance, flat_field, frame, looks, pixel_art, redact, tone,
+    transform, white_balance,
+};
+
+// Redoes a logged edit. `source` is the file the image was opened from, which
+// lens correction reads its metadata from.
+pub fn apply_edit(img: &mut RgbaImage, edit: &Edit, source: Option<&Path>) -> Result<(), AppError> {
+    match edit {
+        Edit::Operation { operation } => processing::apply_operation(img, operation),
+        Edit::AutoTrim { tolerance } => {
+            canvas::auto_trim(img, *tolerance)?;
+        }
+        Edit::Crop { rect } => {
+            *img = canvas::crop(img, rect.x as i64, rect.y as i64, rect.width, rect.height)?
+        }
+        Edit::Rotate { degrees, fill } => *img = transform::rotate(img, *degrees, *fill)?,
+        Edit::Flip { axis } => transform::flip(img, *axis),
+        Edit::Resize {
+            width,
+            height,
+            filter,
+            edge,
+        } => *img = transform::resize(img, *width, *height, *filter, *edge)?,
+        Edit::ScalePixelArt { algorithm, factor } => {
+            *img = pixel_art::scale_pixel_art(img, *algorithm, *factor)?
+        }
+        Edit::ExtendCanvas { margins, fill } => *img = canvas::extend_canvas(img, *margins, *fill)?,
+        Edit::AddBorder { spec } => *img = frame::add_border(img, *spec)?,
+        Edit::ApplyShapeMask { shape } => frame::apply_shape_mask(img, *shape)?,
+        Edit::ApplyLook { name, intensity } => looks::apply_look(img, name, *intensity)?,
+        Edit::AutoEnhance { method } => enhance::auto_enhance(img, *method)?,
+        Edit::Dither { palette, algorithm } => dither::dither(img, palette, *algorithm)?,
+        Edit::AutoWhiteBalance { method } => {
+            white_balance::auto_white_balance(img, *method);
+        }
+        Edit::Levels { levels } => tone::apply_levels(img, levels)?,
+        Edit::Curve { points, channel } => tone::apply_curve(img, points, *channel)?,
+        Edit::SetBlackWhitePoints {
+            black_sample,
+            white_sample,
+        } => tone::set_black_white_points(img, *black_sample, *white_sample)?,
+        Edit::RedactRegions { regions, style } => redact::redact_regions(img, regions, *style)?,
+        Edit::AutoLensCorrect => {
+            let source = source.ok_or_else(|| {
+                AppError::invalid("Image has no source file to read lens data from")
+            })?;
+            *img = lens::auto_correct(img, source)?.image;
+        }
+        Edit::FlatFieldCorrect { flat_frame } => {
+            flat_field::flat_field_correct_file(img, Path::new(flat_frame))?
+        }
+        Edit::SubtractDarkFrame { dark_frame } => {
+            dark_frame::subtract_dark_frame_file(img, Path::new(dark_frame))?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use image::RgbaImage;
+
+use crate::analysis::{histogram, luminosity};
+use crate::error::AppError;
+use crate::models::{EnhanceMethod, Levels};
+use crate::processing::lut::{apply_luts, levels_lut};
+
+// Cumulative mapping that spreads `counts` evenly over 0-255.
+fn equalize_lut(counts: &[u32]) -> [u8; 256] {
+    let total: u64 = counts.iter().map(|&c| c as u64).sum();
+    let mut lut = [0u8; 256];
+    if total == 0 {
+        return std::array::from_fn(|v| v as u8);
+    }
+    // Anchoring at the first occupied level keeps the darkest tone black
+    // instead of lifting it by its own share of the pixels.
+    let first = counts
+        .iter()
+        .map(|&c| c as u64)
+        .find(|&c| c > 0)
+        .unwrap_or(0);
+    let mut cumulative = 0u64;
+    for (v, &count) in counts.iter().enumerate() {
+        cumulative += count as u64;
+        let t = cumulative.saturating_sub(first) as f64 / (total - first).max(1) as f64;
+        lut[v] = (t * 255.0).round() as u8;
+    }
+    lut
+}
+
+// Remaps each pixel's luminosity through `map`, scaling its RGB together so
+// hue and saturation are kept. Where that would push a channel past white,
+// the scale is capped rather than letting the colour shift.
+fn remap_luminosity(img: &mut RgbaImage, map: impl Fn(u32, u32, usize) -> f32) {
+    let width = img.width();
+    for (i, px) in img.pixels_mut().enumerate() {
+        if px[3] == 0 {
+            continue;
+        }
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let old = luminosity(px[0], px[1], px[2]);
+        let new = map(x, y, old);
+        if old == 0 {
+            let v = new.round().clamp(0.0, 255.0) as u8;
+            px[0] = v;
+            px[1] = v;
+            px[2] = v;
+            continue;
+        }
+        let brightest = px[0].max(px[1]).max(px[2]) as f32;
+        let scale = (new / old as f32).min(255.0 / brightest);
+        for c in 0..3 {
+            px[c] = (px[c] as f32 * scale).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+fn equalize(img: &mut RgbaImage) {
+    let lut = equalize_lut(&histogram(img).luminosity);
+    remap_luminosity(img, |_, _, l| lut[l] as f32);
+}
+
+// Contrast-limited adaptive equalization. Each of `tiles` x `tiles` regions
+// gets its own equalization, with bins clipped at `clip_limit` times the
+// average count so flat areas don't have their noise stretched; pixels blend
+// the mappings of the four nearest tiles.
+fn clahe(img: &mut RgbaImage, tiles: u32, clip_limit: f32) {
+    let (width, height) = img.dimensions();
+    let tiles_x = tiles.clamp(1, width);
+    let tiles_y = tiles.clamp(1, height);
+    let tile_w = width.div_ceil(tiles_x);
+    let tile_h = height.div_ceil(tiles_y);
+
+    let mut counts = vec![[0u32; 256]; (tiles_x * tiles_y) as usize];
+    for (x, y, px) in img.enumerate_pixels() {
+        if px[3] > 0 {
+            let tile = ((y / tile_h) * tiles_x + x / tile_w) as usize;
+            counts[tile][luminosity(px[0], px[1], px[2])] += 1;
+        }
+    }
+    let luts: Vec<[u8; 256]> = counts
+        .iter_mut()
+        .map(|bins| {
+            let total: u32 = bins.iter().sum();
+            let limit = ((clip_limit * total as f32 / 256.0).ceil() as u32).max(1);
+            let mut excess = 0u32;
+            for bin in bins.iter_mut() {
+                excess += bin.saturating_sub(limit);
+                *bin = (*bin).min(limit);
+            }
+            let (share, remainder) = (excess / 256, (excess % 256) as usize);
+            for (v, bin) in bins.iter_mut().enumerate() {
+                *bin += share + (v < remainder) as u32;
+            }
+            equalize_lut(bins)
+        })
+        .collect();
+
+    // Position in tile-centre coordinates, clamped at the outer half tiles.
+    let axis = |p: u32, size: u32, count: u32| {
+        let t = ((p as f32 + 0.5) / size as f32 - 0.5).clamp(0.0, (count - 1) as f32);
+        let i = t.floor() as u32;
+        (i, (i + 1).min(count - 1), t - i as f32)
+    };
+    remap_luminosity(img, |x, y, l| {
+        let (x0, x1, tx) = axis(x, tile_w, tiles_x);
+        let (y0, y1, ty) = axis(y, tile_h, tiles_y);
+        let at = |tx: u32, ty: u32| luts[(ty * tiles_x + tx) as usize][l] as f32;
+        let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    });
+}
+
+// The level below which `fraction` of the counts fall.
+fn percentile(counts: &[u32], fraction: f64) -> usize {
+    let total: u64 = counts.iter().map(|&c| c as u64).sum();
+    let target = (total as f64 * fraction).ceil() as u64;
+    let mut cumulative = 0u64;
+    for (v, &count) in counts.iter().enumerate() {
+        cumulative += count as u64;
+        if cumulative >= target.max(1) {
+            return v;
+        }
+    }
+    255
+}
+
+// Stretches all three channels by the same amount so that `clip_percent` of
+// the channel values at each end clip to black and white. Using one stretch
+// keeps the colour balance; see `set_black_white_points` to neutralize it.
+fn auto_contrast(img: &mut RgbaImage, clip_percent: f32) {
+    let histogram = histogram(img);
+    let fraction = (clip_percent as f64 / 100.0).clamp(0.0, 0.5);
+    let channels = [&histogram.red, &histogram.green, &histogram.blue];
+    let black = channels
+        .iter()
+        .map(|c| percentile(c, fraction))
+        .min()
+        .unwrap_or(0);
+    let white = channels
+        .iter()
+        .map(|c| percentile(c, 1.0 - fraction))
+        .max()
+        .unwrap_or(255);
+    if white <= black {
+        return;
+    }
+    let lut = levels_lut(Levels {
+        black: black as f32,
+        white: white as f32,
+        gamma: 1.0,
+    });
+    apply_luts(img, &[lut; 3]);
+}
+
+pub fn auto_enhance(img: &mut RgbaImage, method: EnhanceMethod) -> Result<(), AppError> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    match method {
+        EnhanceMethod::Equalize => equalize(img),
+        EnhanceMethod::Clahe { tiles, clip_limit } => {
+            if tiles == 0 {
+                return Err(AppError::invalid("CLAHE needs at least one tile"));
+            }
+            if !clip_limit.is_finite() || clip_limit < 1.0 {
+                return Err(AppError::invalid("CLAHE clip limit must be at least 1"));
+            }
+            clahe(img, tiles, clip_limit)
+        }
+        EnhanceMethod::AutoContrast { clip_percent } => {
+            if !(0.0..50.0).contains(&clip_percent) {
+                return Err(AppError::invalid(
+                    "Clip percentage must be at least 0 and under 50",
+                ));
+            }
+            auto_contrast(img, clip_percent)
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_histogram_equalizes_to_identity() {
+        let lut = equalize_lut(&[100; 256]);
+        for (v, &mapped) in lut.iter().enumerate() {
+            assert!((mapped as i32 - v as i32).abs() <= 1, "{} -> {}", v, mapped);
+        }
+    }
+
+    #[test]
+    fn empty_histogram_is_identity() {
+        let lut = equalize_lut(&[0; 256]);
+        assert!(lut.iter().enumerate().all(|(v, &m)| m as usize == v));
+    }
+
+    #[test]
+    fn narrow_range_is_stretched_to_full_range() {
+        let mut counts = [0u32; 256];
+        counts[100..=110].iter_mut().for_each(|c| *c = 50);
+        let lut = equalize_lut(&counts);
+        assert_eq!(lut[100], 0);
+        assert_eq!(lut[110], 255);
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
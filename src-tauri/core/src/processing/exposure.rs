@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::analysis::histogram;
+use crate::decode::decode_file;
+use crate::encode::write_image;
+use crate::error::AppError;
+use crate::models::{ExportResult, ExposureMatch, ExposureMatchReport, SkippedFile};
+use crate::processing::lut::apply_luts;
+
+// The luminosity distribution is compared at these fractions. Leaving out
+// the extremes keeps clipped highlights and a few stray pixels from skewing
+// the fit.
+const QUANTILES: [f64; 10] = [0.05, 0.15, 0.25, 0.35, 0.45, 0.55, 0.65, 0.75, 0.85, 0.95];
+// Frames needing more than this are probably a different scene, not flicker.
+const MAX_GAIN: f64 = 4.0;
+
+fn quantiles(img: &RgbaImage) -> Option<[f64; 10]> {
+    let counts = histogram(img).luminosity;
+    let total: u64 = counts.iter().map(|&c| c as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    Some(QUANTILES.map(|q| {
+        let target = total as f64 * q;
+        let mut cumulative = 0u64;
+        for (v, &count) in counts.iter().enumerate() {
+            cumulative += count as u64;
+            if cumulative as f64 >= target {
+                return v as f64;
+            }
+        }
+        255.0
+    }))
+}
+
+fn mean_luminosity(img: &RgbaImage) -> f32 {
+    let counts = histogram(img).luminosity;
+    let total: u64 = counts.iter().map(|&c| c as u64).sum();
+    let sum: u64 = counts
+        .iter()
+        .enumerate()
+        .map(|(v, &c)| v as u64 * c as u64)
+        .sum();
+    sum as f32 / total.max(1) as f32
+}
+
+// Least-squares gain and offset taking `frame`'s quantiles onto the
+// reference's.
+fn fit(frame: &[f64; 10], reference: &[f64; 10]) -> (f64, f64) {
+    let n = frame.len() as f64;
+    let mean_x = frame.iter().sum::<f64>() / n;
+    let mean_y = reference.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0f64, 0f64);
+    for (x, y) in frame.iter().zip(reference) {
+        sxy += (x - mean_x) * (y - mean_y);
+        sxx += (x - mean_x).powi(2);
+    }
+    // A flat frame has no spread to scale, only a level to shift.
+    let gain = if sxx > 0.0 {
+        (sxy / sxx).clamp(1.0 / MAX_GAIN, MAX_GAIN)
+    } else {
+        1.0
+    };
+    (gain, mean_y - gain * mean_x)
+}
+
+pub fn apply_gain_offset(img: &mut RgbaImage, gain: f32, offset: f32) {
+    let lut: [u8; 256] =
+        std::array::from_fn(|v| (v as f32 * gain + offset).round().clamp(0.0, 255.0) as u8);
+    apply_luts(img, &[lut; 3]);
+}
+
+fn match_frame(
+    path: &Path,
+    reference: &[f64; 10],
+    output_dir: Option<&Path>,
+) -> Result<ExposureMatch, AppError> {
+    let mut decoded = decode_file(path, false)?;
+    let frame = quantiles(&decoded.image)
+        .ok_or_else(|| AppError::invalid("Image has no visible pixels"))?;
+    let (gain, offset) = fit(&frame, reference);
+    let mean_before = mean_luminosity(&decoded.image);
+    apply_gain_offset(&mut decoded.image, gain as f32, offset as f32);
+
+    let output = match output_dir {
+        Some(dir) => {
+            let name = path
+                .file_name()
+                .ok_or_else(|| AppError::invalid("Path has no file name"))?;
+            let output = dir.join(name);
+            if output.exists() && fs::canonicalize(&output).ok() == fs::canonicalize(path).ok() {
+                return Err(AppError::invalid("Exporting would overwrite the original"));
+            }
+            let output = output.to_string_lossy().into_owned();
+            let file_size = write_image(&decoded.image, decoded.source.as_ref(), &output)?;
+            Some(ExportResult {
+                output,
+                width: decoded.image.width(),
+                height: decoded.image.height(),
+                file_size,
+            })
+        }
+        None => None,
+    };
+    Ok(ExposureMatch {
+        path: path.to_string_lossy().into_owned(),
+        gain: gain as f32,
+        offset: offset as f32,
+        mean_before,
+        mean_after: mean_luminosity(&decoded.image),
+        output,
+    })
+}
+
+// Works out the gain and offset that bring each frame's brightness
+// distribution in line with `reference`, so a timelapse or bracketed series
+// doesn't flicker. With `output_dir` the corrected frames are written there
+// under their own names.
+pub fn match_exposure(
+    paths: &[PathBuf],
+    reference: &Path,
+    output_dir: Option<&Path>,
+) -> Result<ExposureMatchReport, AppError> {
+    let reference_image = decode_file(reference, false)?.image;
+    let target = quantiles(&reference_image)
+        .ok_or_else(|| AppError::invalid("The reference image has no visible pixels"))?;
+    if let Some(dir) = output_dir {
+        fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e, true))?;
+    }
+
+    let results: Vec<(&PathBuf, Result<ExposureMatch, AppError>)> = paths
+        .par_iter()
+        .map(|path| (path, match_frame(path, &target, output_dir)))
+        .collect();
+    let mut report = ExposureMatchReport {
+        reference: reference.to_string_lossy().into_owned(),
+        reference_mean: mean_luminosity(&reference_image),
+        matched: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (path, result) in results {
+        match result {
+            Ok(matched) => report.matched.push(matched),
+            Err(e) => report.skipped.push(SkippedFile {
+                path: path.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok(report)
+}
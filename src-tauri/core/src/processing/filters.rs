@@ -0,0 +1,314 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::EdgeMode;
+use crate::processing::edge_index;
+use crate::progress::Progress;
+
+// The kernel is cut off at this many sigmas, past which the weights are
+// below 8-bit precision.
+const KERNEL_SIGMAS: f32 = 3.0;
+
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * KERNEL_SIGMAS).ceil() as i64;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+// Convolves each row of the interleaved premultiplied RGBA planes, reading
+// past the border as `edge` says.
+fn convolve_rows(planes: &[f32], width: usize, kernel: &[f32], edge: EdgeMode) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as i64;
+    let mut out = vec![0f32; planes.len()];
+    out.par_chunks_mut(width * 4)
+        .zip(planes.par_chunks(width * 4))
+        .for_each(|(out_row, row)| {
+            for (x, px) in out_row.chunks_exact_mut(4).enumerate() {
+                for (k, weight) in kernel.iter().enumerate() {
+                    let sx = edge_index(edge, x as i64 + k as i64 - radius, width);
+                    for c in 0..4 {
+                        px[c] += row[sx * 4 + c] * weight;
+                    }
+                }
+            }
+        });
+    out
+}
+
+// The same down the columns, a whole row at a time so memory is read in
+// order.
+fn convolve_columns(
+    planes: &[f32],
+    width: usize,
+    height: usize,
+    kernel: &[f32],
+    edge: EdgeMode,
+) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as i64;
+    let row_len = width * 4;
+    let mut out = vec![0f32; planes.len()];
+    out.par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            for (k, weight) in kernel.iter().enumerate() {
+                let sy = edge_index(edge, y as i64 + k as i64 - radius, height);
+                let row = &planes[sy * row_len..(sy + 1) * row_len];
+                for (o, v) in out_row.iter_mut().zip(row) {
+                    *o += v * weight;
+                }
+            }
+        });
+    out
+}
+
+// Colour weighted by alpha, so the colour hidden under transparent pixels
+// doesn't bleed into visible ones.
+fn premultiplied_planes(img: &RgbaImage) -> Vec<f32> {
+    img.pixels()
+        .flat_map(|px| {
+            let a = px[3] as f32 / 255.0;
+            [px[0] as f32 * a, px[1] as f32 * a, px[2] as f32 * a, a]
+        })
+        .collect()
+}
+
+// Blurred RGB, three values per pixel, from the alpha-weighted planes.
+// Where nothing visible is in reach the pixel keeps its own colour.
+// The two passes are most of the work, so each counts for close to half.
+fn blurred_planes(
+    img: &RgbaImage,
+    sigma: f32,
+    edge: EdgeMode,
+    progress: &dyn Progress,
+) -> Result<Vec<f32>, AppError> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let kernel = gaussian_kernel(sigma);
+    let rows = convolve_rows(&premultiplied_planes(img), width, &kernel, edge);
+    progress.report(0.45);
+    progress.check()?;
+    let columns = convolve_columns(&rows, width, height, &kernel, edge);
+    progress.report(0.9);
+    progress.check()?;
+    Ok(columns
+        .chunks_exact(4)
+        .zip(img.pixels())
+        .flat_map(|(px, own)| {
+            if px[3] <= f32::EPSILON {
+                [own[0] as f32, own[1] as f32, own[2] as f32]
+            } else {
+                [px[0] / px[3], px[1] / px[3], px[2] / px[3]]
+            }
+        })
+        .collect())
+}
+
+// Gaussian blur of any four interleaved float planes, for statistics that
+// need local means.
+pub fn blur_planes(
+    planes: &[f32],
+    width: usize,
+    height: usize,
+    sigma: f32,
+    edge: EdgeMode,
+) -> Vec<f32> {
+    let kernel = gaussian_kernel(sigma);
+    let rows = convolve_rows(planes, width, &kernel, edge);
+    convolve_columns(&rows, width, height, &kernel, edge)
+}
+
+pub fn check_sigma(sigma: f32) -> Result<(), AppError> {
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return Err(AppError::invalid("Blur sigma must be greater than 0"));
+    }
+    Ok(())
+}
+
+pub fn check_sharpen(amount: f32, radius: f32) -> Result<(), AppError> {
+    check_sigma(radius)?;
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(AppError::invalid("Sharpen amount must be 0 or more"));
+    }
+    Ok(())
+}
+
+// Gaussian blur of the colour channels; alpha is left as it is. Colours are
+// averaged weighted by their alpha, so transparent areas don't fringe the
+// edges of what's visible. A sigma `check_sigma` rejects leaves the image
+// unchanged.
+pub fn gaussian_blur(img: &mut RgbaImage, sigma: f32, edge: EdgeMode) {
+    let _ = gaussian_blur_tracked(img, sigma, edge, &());
+}
+
+// `gaussian_blur`, reporting progress. A cancelled blur leaves the image as
+// it was.
+pub fn gaussian_blur_tracked(
+    img: &mut RgbaImage,
+    sigma: f32,
+    edge: EdgeMode,
+    progress: &dyn Progress,
+) -> Result<(), AppError> {
+    check_sigma(sigma)?;
+    let blurred = blurred_planes(img, sigma, edge, progress)?;
+    for (px, rgb) in img.pixels_mut().zip(blurred.chunks_exact(3)) {
+        for c in 0..3 {
+            px[c] = rgb[c].round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    progress.report(1.0);
+    Ok(())
+}
+
+// Unsharp mask: adds `amount` times the difference from a blur of `radius`
+// sigma. Differences of `threshold` levels or less are left alone, so flat
+// areas and fine noise aren't sharpened. Alpha is untouched, and the blur
+// is alpha-weighted like `gaussian_blur`'s.
+pub fn unsharp_mask(img: &mut RgbaImage, amount: f32, radius: f32, threshold: u8, edge: EdgeMode) {
+    let _ = unsharp_mask_tracked(img, amount, radius, threshold, edge, &());
+}
+
+// `unsharp_mask`, reporting progress. A cancelled sharpen leaves the image
+// as it was.
+pub fn unsharp_mask_tracked(
+    img: &mut RgbaImage,
+    amount: f32,
+    radius: f32,
+    threshold: u8,
+    edge: EdgeMode,
+    progress: &dyn Progress,
+) -> Result<(), AppError> {
+    check_sharpen(amount, radius)?;
+    let blurred = blurred_planes(img, radius, edge, progress)?;
+    for (px, rgb) in img.pixels_mut().zip(blurred.chunks_exact(3)) {
+        for c in 0..3 {
+            let detail = px[c] as f32 - rgb[c];
+            if detail.abs() > threshold as f32 {
+                px[c] = (px[c] as f32 + amount * detail).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    progress.report(1.0);
+    Ok(())
+}
+
+pub fn check_guided(radius: u32, epsilon: f32) -> Result<(), AppError> {
+    if radius == 0 {
+        return Err(AppError::invalid("Smoothing radius must be at least 1"));
+    }
+    if !epsilon.is_finite() || epsilon <= 0.0 {
+        return Err(AppError::invalid(
+            "Smoothing epsilon must be greater than 0",
+        ));
+    }
+    Ok(())
+}
+
+// Mean over the square of side 2 * radius + 1 around each value of three
+// interleaved planes, the window cut short at the borders. Running sums
+// make it as fast at any radius.
+fn box_mean(planes: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let window = |i: usize, len: usize| (i.saturating_sub(radius), (i + radius + 1).min(len));
+    let mut rows = vec![0f32; planes.len()];
+    rows.par_chunks_mut(width * 3)
+        .zip(planes.par_chunks(width * 3))
+        .for_each(|(out, row)| {
+            let mut prefix = vec![0f32; (width + 1) * 3];
+            for x in 0..width {
+                for c in 0..3 {
+                    prefix[(x + 1) * 3 + c] = prefix[x * 3 + c] + row[x * 3 + c];
+                }
+            }
+            for x in 0..width {
+                let (x0, x1) = window(x, width);
+                for c in 0..3 {
+                    out[x * 3 + c] = (prefix[x1 * 3 + c] - prefix[x0 * 3 + c]) / (x1 - x0) as f32;
+                }
+            }
+        });
+
+    // Column sums a row at a time, so memory is read in order.
+    let row_len = width * 3;
+    let mut prefix = vec![0f32; (height + 1) * row_len];
+    for y in 0..height {
+        let (done, rest) = prefix.split_at_mut((y + 1) * row_len);
+        let previous = &done[y * row_len..];
+        for ((p, above), v) in rest[..row_len]
+            .iter_mut()
+            .zip(previous)
+            .zip(&rows[y * row_len..])
+        {
+            *p = above + v;
+        }
+    }
+    let mut out = vec![0f32; planes.len()];
+    out.par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            let (y0, y1) = window(y, height);
+            let (top, bottom) = (&prefix[y0 * row_len..], &prefix[y1 * row_len..]);
+            for (i, v) in out_row.iter_mut().enumerate() {
+                *v = (bottom[i] - top[i]) / (y1 - y0) as f32;
+            }
+        });
+    out
+}
+
+// He, Sun and Tang's guided filter, each colour channel guiding itself.
+// Within every window the output is a linear function of the input, flat
+// where the variance is small next to `epsilon` and following the input
+// where it's large, so texture and noise are smoothed but edges keep their
+// shape. `epsilon` is a variance on 0-1 values: around 0.01 smooths skin,
+// 0.001 only fine noise. Alpha is left as it is.
+pub fn guided_filter(img: &mut RgbaImage, radius: u32, epsilon: f32) {
+    let _ = guided_filter_tracked(img, radius, epsilon, &());
+}
+
+// `guided_filter`, reporting progress. A cancelled run leaves the image as
+// it was.
+pub fn guided_filter_tracked(
+    img: &mut RgbaImage,
+    radius: u32,
+    epsilon: f32,
+    progress: &dyn Progress,
+) -> Result<(), AppError> {
+    check_guided(radius, epsilon)?;
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let radius = radius as usize;
+    let input: Vec<f32> = img
+        .pixels()
+        .flat_map(|px| [0, 1, 2].map(|c| px[c] as f32 / 255.0))
+        .collect();
+    let squares: Vec<f32> = input.par_iter().map(|v| v * v).collect();
+
+    let mean = box_mean(&input, width, height, radius);
+    let mean_sq = box_mean(&squares, width, height, radius);
+    progress.report(0.4);
+    progress.check()?;
+    // Per window, the output is a * input + b.
+    let (a, b): (Vec<f32>, Vec<f32>) = mean
+        .par_iter()
+        .zip(&mean_sq)
+        .map(|(m, sq)| {
+            let variance = (sq - m * m).max(0.0);
+            let a = variance / (variance + epsilon);
+            (a, m - a * m)
+        })
+        .unzip();
+    let mean_a = box_mean(&a, width, height, radius);
+    let mean_b = box_mean(&b, width, height, radius);
+    progress.report(0.9);
+    progress.check()?;
+
+    for (i, px) in img.pixels_mut().enumerate() {
+        for c in 0..3 {
+            let k = i * 3 + c;
+            let q = mean_a[k] * input[k] + mean_b[k];
+            px[c] = (q * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    progress.report(1.0);
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::decode::decode_file;
+use crate::error::AppError;
+
+// Illumination falloff is smooth, so the flat is reduced to a coarse grid of
+// gains. That averages away its grain and dust, which would otherwise be
+// stamped onto every corrected image.
+const GRID_SIZE: u32 = 128;
+// Corners darker than this fraction of the mean are treated as this dark,
+// rather than amplifying what little signal they have without limit.
+const MIN_RELATIVE_LEVEL: f32 = 0.125;
+// Flats must match the image's shape to this tolerance, either way round.
+const ASPECT_TOLERANCE: f64 = 0.02;
+
+// Per-channel gains that bring each cell of the flat up to its mean.
+fn gain_grid(flat: &RgbaImage, width: u32, height: u32) -> (Vec<[f32; 3]>, u32, u32) {
+    let scale = GRID_SIZE as f64 / width.max(height) as f64;
+    let gw = ((width as f64 * scale).round() as u32).clamp(1, width);
+    let gh = ((height as f64 * scale).round() as u32).clamp(1, height);
+    let small = imageops::resize(flat, gw, gh, FilterType::Triangle);
+    let levels: Vec<[f32; 3]> = small
+        .pixels()
+        .map(|px| [0, 1, 2].map(|c| srgb_to_linear(px[c])))
+        .collect();
+    let mut mean = [0f32; 3];
+    for level in &levels {
+        for c in 0..3 {
+            mean[c] += level[c] / levels.len() as f32;
+        }
+    }
+    let gains = levels
+        .iter()
+        .map(|level| {
+            [0, 1, 2].map(|c| {
+                if mean[c] <= 0.0 {
+                    1.0
+                } else {
+                    mean[c] / level[c].max(mean[c] * MIN_RELATIVE_LEVEL)
+                }
+            })
+        })
+        .collect();
+    (gains, gw, gh)
+}
+
+// Divides out the vignetting and uneven lighting recorded in `flat`, a frame
+// of an evenly lit blank target (an empty slide, a sheet of white card)
+// taken on the same rig. The flat is resized to the image, or rotated first
+// if it was shot the other way round.
+pub fn flat_field_correct(img: &mut RgbaImage, flat: &RgbaImage) -> Result<(), AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 || flat.width() == 0 || flat.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    let aspect = |w: u32, h: u32| w as f64 / h as f64;
+    let matches = |a: f64, b: f64| (a / b - 1.0).abs() <= ASPECT_TOLERANCE;
+    let rotated;
+    let flat = if matches(aspect(flat.width(), flat.height()), aspect(width, height)) {
+        flat
+    } else if matches(aspect(flat.height(), flat.width()), aspect(width, height)) {
+        rotated = imageops::rotate90(flat);
+        &rotated
+    } else {
+        return Err(AppError::invalid(format!(
+            "The flat frame is {}x{}, which isn't the same shape as the {}x{} image",
+            flat.width(),
+            flat.height(),
+            width,
+            height
+        )));
+    };
+
+    let (gains, gw, gh) = gain_grid(flat, width, height);
+    let gain_at = |gx: usize, gy: usize| &gains[gy * gw as usize + gx];
+    let (sx, sy) = (gw as f32 / width as f32, gh as f32 / height as f32);
+    img.par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            // Bilinear between grid cell centres.
+            let fy = ((y as f32 + 0.5) * sy - 0.5).clamp(0.0, (gh - 1) as f32);
+            let (y0, ty) = (fy.floor() as usize, fy.fract());
+            let y1 = (y0 + 1).min(gh as usize - 1);
+            for (x, px) in row.chunks_exact_mut(4).enumerate() {
+                let fx = ((x as f32 + 0.5) * sx - 0.5).clamp(0.0, (gw - 1) as f32);
+                let (x0, tx) = (fx.floor() as usize, fx.fract());
+                let x1 = (x0 + 1).min(gw as usize - 1);
+                for (c, value) in px.iter_mut().take(3).enumerate() {
+                    let top = gain_at(x0, y0)[c] * (1.0 - tx) + gain_at(x1, y0)[c] * tx;
+                    let bottom = gain_at(x0, y1)[c] * (1.0 - tx) + gain_at(x1, y1)[c] * tx;
+                    let gain = top * (1.0 - ty) + bottom * ty;
+                    *value = linear_to_srgb(srgb_to_linear(*value) * gain);
+                }
+            }
+        });
+    Ok(())
+}
+
+pub fn flat_field_correct_file(img: &mut RgbaImage, flat_path: &Path) -> Result<(), AppError> {
+    let flat = decode_file(flat_path, false)?.image;
+    flat_field_correct(img, &flat)
+}
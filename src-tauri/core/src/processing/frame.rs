@@ -0,0 +1,151 @@
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{BorderSpec, CanvasFill, Margins, MaskShape};
+use crate::processing::{blend_pixel, canvas};
+
+// Larger blurs are slow and no longer read as a shadow.
+const MAX_SHADOW_BLUR: f32 = 500.0;
+
+// Anti-aliased coverage of the pixel at (x, y) by a `width`x`height` rounded
+// rectangle, from the signed distance of the pixel centre to its outline.
+fn rounded_rect_coverage(x: u32, y: u32, width: u32, height: u32, radius: f32) -> f32 {
+    let (hw, hh) = (width as f32 / 2.0, height as f32 / 2.0);
+    let r = radius.min(hw).min(hh);
+    let qx = (x as f32 + 0.5 - hw).abs() - (hw - r);
+    let qy = (y as f32 + 0.5 - hh).abs() - (hh - r);
+    let outside = qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - r;
+    (0.5 - outside).clamp(0.0, 1.0)
+}
+
+// Same for the ellipse inscribed in the rectangle, using the first-order
+// distance estimate |f| / |grad f|.
+fn ellipse_coverage(x: u32, y: u32, width: u32, height: u32) -> f32 {
+    let (a, b) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (px, py) = (x as f32 + 0.5 - a, y as f32 + 0.5 - b);
+    let f = (px / a).powi(2) + (py / b).powi(2) - 1.0;
+    let grad = 2.0 * (px * px / a.powi(4) + py * py / b.powi(4)).sqrt();
+    if grad <= f32::EPSILON {
+        return 1.0;
+    }
+    (0.5 - f / grad).clamp(0.0, 1.0)
+}
+
+fn scale_alpha(img: &mut RgbaImage, coverage: impl Fn(u32, u32) -> f32) {
+    for (x, y, px) in img.enumerate_pixels_mut() {
+        let c = coverage(x, y);
+        if c < 1.0 {
+            px[3] = (px[3] as f32 * c).round() as u8;
+        }
+    }
+}
+
+// Frames the image in a solid border, optionally rounding the outer corners
+// and setting the result on a mat with a drop shadow.
+pub fn add_border(img: &RgbaImage, spec: BorderSpec) -> Result<RgbaImage, AppError> {
+    let width = spec.width;
+    let mut framed = canvas::extend_canvas(
+        img,
+        Margins {
+            top: width,
+            right: width,
+            bottom: width,
+            left: width,
+        },
+        CanvasFill::Solid { color: spec.color },
+    )?;
+    if spec.corner_radius > 0 {
+        let (w, h) = framed.dimensions();
+        let radius = spec.corner_radius as f32;
+        scale_alpha(&mut framed, |x, y| {
+            rounded_rect_coverage(x, y, w, h, radius)
+        });
+    }
+
+    let Some(shadow) = spec.shadow else {
+        return Ok(framed);
+    };
+    if !(0.0..=MAX_SHADOW_BLUR).contains(&shadow.blur) {
+        return Err(AppError::invalid(format!(
+            "Shadow blur must be between 0 and {}",
+            MAX_SHADOW_BLUR
+        )));
+    }
+
+    let mat = shadow.mat_width;
+    let too_large = || AppError::invalid("The matted image is too large");
+    let grow = |v: u32| {
+        mat.checked_mul(2)
+            .and_then(|m| v.checked_add(m))
+            .ok_or_else(too_large)
+    };
+    let (out_w, out_h) = (grow(framed.width())?, grow(framed.height())?);
+    // The mat is opaque whatever colour it was given, or the shadow would
+    // show through to the framed image's corners.
+    let mut out = RgbaImage::from_pixel(
+        out_w,
+        out_h,
+        Rgba([
+            shadow.mat_color.r,
+            shadow.mat_color.g,
+            shadow.mat_color.b,
+            255,
+        ]),
+    );
+
+    // Transparent shadow pixels keep the shadow colour so blurring doesn't
+    // pull in black at the fringe.
+    let (sr, sg, sb, sa) = (
+        shadow.color.r,
+        shadow.color.g,
+        shadow.color.b,
+        shadow.color.a as f32 / 255.0,
+    );
+    let mut layer = RgbaImage::from_pixel(out_w, out_h, Rgba([sr, sg, sb, 0]));
+    let (dx, dy) = (
+        mat as i64 + shadow.offset_x as i64,
+        mat as i64 + shadow.offset_y as i64,
+    );
+    for (x, y, px) in framed.enumerate_pixels() {
+        let (lx, ly) = (x as i64 + dx, y as i64 + dy);
+        if (0..out_w as i64).contains(&lx) && (0..out_h as i64).contains(&ly) {
+            layer.get_pixel_mut(lx as u32, ly as u32)[3] = (px[3] as f32 * sa).round() as u8;
+        }
+    }
+    if shadow.blur > 0.0 {
+        layer = imageops::blur(&layer, shadow.blur);
+    }
+
+    for (dst, &src) in out.pixels_mut().zip(layer.pixels()) {
+        blend_pixel(dst, src);
+    }
+    for (x, y, &px) in framed.enumerate_pixels() {
+        blend_pixel(out.get_pixel_mut(x + mat, y + mat), px);
+    }
+    Ok(out)
+}
+
+// Makes everything outside `shape` transparent, with anti-aliased edges.
+pub fn apply_shape_mask(img: &mut RgbaImage, shape: MaskShape) -> Result<(), AppError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    match shape {
+        MaskShape::RoundedRect { radius } => {
+            let radius = radius as f32;
+            scale_alpha(img, |x, y| {
+                rounded_rect_coverage(x, y, width, height, radius)
+            });
+        }
+        MaskShape::Ellipse => scale_alpha(img, |x, y| ellipse_coverage(x, y, width, height)),
+        MaskShape::Circle => {
+            let side = width.min(height);
+            *img = imageops::crop_imm(img, (width - side) / 2, (height - side) / 2, side, side)
+                .to_image();
+            scale_alpha(img, |x, y| ellipse_coverage(x, y, side, side));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::sync::OnceLock;
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::models::LookInfo;
+use crate::processing::lut::{apply_luts, compose_lut, curve_lut, ChannelLuts};
+use crate::processing::tone::{apply_saturation, blend_towards};
+
+// Film-emulation looks: a master tone curve, optional per-channel curves
+// applied after it, and a saturation multiplier.
+const BUNDLED_LOOKS: &str = include_str!("../../resources/looks.json");
+
+#[derive(Debug, Deserialize)]
+struct LookLibrary {
+    looks: Vec<Look>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Look {
+    name: String,
+    label: String,
+    description: String,
+    #[serde(default)]
+    master: Vec<[f32; 2]>,
+    #[serde(default)]
+    red: Vec<[f32; 2]>,
+    #[serde(default)]
+    green: Vec<[f32; 2]>,
+    #[serde(default)]
+    blue: Vec<[f32; 2]>,
+    #[serde(default = "unit_saturation")]
+    saturation: f32,
+}
+
+fn unit_saturation() -> f32 {
+    1.0
+}
+
+fn look_library() -> &'static LookLibrary {
+    static LOOKS: OnceLock<LookLibrary> = OnceLock::new();
+    LOOKS.get_or_init(|| {
+        serde_json::from_str(BUNDLED_LOOKS).expect("bundled look library is valid JSON")
+    })
+}
+
+impl Look {
+    fn luts(&self) -> ChannelLuts {
+        let master = curve_lut(&self.master);
+        [&self.red, &self.green, &self.blue]
+            .map(|channel| compose_lut(&master, &curve_lut(channel)))
+    }
+}
+
+pub fn list_looks() -> Vec<LookInfo> {
+    look_library()
+        .looks
+        .iter()
+        .map(|look| LookInfo {
+            name: look.name.clone(),
+            label: look.label.clone(),
+            description: look.description.clone(),
+        })
+        .collect()
+}
+
+// Grades `img` with the named look; `intensity` 0 leaves it untouched and 1
+// applies the look fully.
+pub fn apply_look(img: &mut RgbaImage, name: &str, intensity: f32) -> Result<(), AppError> {
+    if !(0.0..=1.0).contains(&intensity) {
+        return Err(AppError::invalid("Look intensity must be between 0 and 1"));
+    }
+    let look = look_library()
+        .looks
+        .iter()
+        .find(|look| look.name == name)
+        .ok_or_else(|| AppError::invalid(format!("Unknown look: {}", name)))?;
+
+    let mut graded = img.clone();
+    apply_luts(&mut graded, &look.luts());
+    if look.saturation != 1.0 {
+        apply_saturation(&mut graded, look.saturation);
+    }
+    blend_towards(img, &mut graded, intensity);
+    *img = graded;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use image::RgbaImage;
+
+use crate::models::{ChannelLevels, CurveChannel, Levels};
+
+// Per-channel 8-bit lookup tables, indexed red, green, blue.
+pub type ChannelLuts = [[u8; 256]; 3];
+
+pub fn apply_luts(img: &mut RgbaImage, luts: &ChannelLuts) {
+    for px in img.pixels_mut() {
+        for (c, lut) in luts.iter().enumerate() {
+            px[c] = lut[px[c] as usize];
+        }
+    }
+}
+
+// Maps each channel's `black` level to 0 and `white` level to 255. Stretching
+// all three channels independently also neutralizes any tint in the samples.
+pub fn black_white_luts(black: [f32; 3], white: [f32; 3]) -> ChannelLuts {
+    std::array::from_fn(|c| {
+        let range = (white[c] - black[c]).max(1.0);
+        std::array::from_fn(|v| {
+            ((v as f32 - black[c]) / range * 255.0)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        })
+    })
+}
+
+// Builds a tone curve through `points` (input, output pairs on 0-255) using
+// monotone cubic interpolation, so the curve never overshoots between control
+// points. Inputs outside the first/last point are held flat.
+pub fn curve_lut(points: &[[f32; 2]]) -> [u8; 256] {
+    let curve = tone_curve(points);
+    std::array::from_fn(|v| curve(v as f32).round().clamp(0.0, 255.0) as u8)
+}
+
+// The curve `curve_lut` tabulates, for inputs between the table's entries.
+pub fn tone_curve(points: &[[f32; 2]]) -> impl Fn(f32) -> f32 {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    points.dedup_by(|a, b| a[0] == b[0]);
+
+    let n = points.len();
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|w| (w[1][1] - w[0][1]) / (w[1][0] - w[0][0]))
+        .collect();
+
+    // Fritsch-Carlson tangents.
+    let mut tangents = vec![0f32; n];
+    if n >= 2 {
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+    }
+    for i in 1..n.saturating_sub(1) {
+        tangents[i] = if secants[i - 1] * secants[i] <= 0.0 {
+            0.0
+        } else {
+            (secants[i - 1] + secants[i]) / 2.0
+        };
+    }
+    for i in 0..n.saturating_sub(1) {
+        if secants[i] == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let a = tangents[i] / secants[i];
+        let b = tangents[i + 1] / secants[i];
+        let norm = a * a + b * b;
+        if norm > 9.0 {
+            let t = 3.0 / norm.sqrt();
+            tangents[i] = t * a * secants[i];
+            tangents[i + 1] = t * b * secants[i];
+        }
+    }
+
+    move |x: f32| match n {
+        0 => x,
+        1 => points[0][1],
+        _ if x <= points[0][0] => points[0][1],
+        _ if x >= points[n - 1][0] => points[n - 1][1],
+        _ => {
+            let i = points.windows(2).position(|w| x < w[1][0]).unwrap_or(n - 2);
+            let (p0, p1) = (points[i], points[i + 1]);
+            let h = p1[0] - p0[0];
+            let t = (x - p0[0]) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * p0[1]
+                + (t3 - 2.0 * t2 + t) * h * tangents[i]
+                + (-2.0 * t3 + 3.0 * t2) * p1[1]
+                + (t3 - t2) * h * tangents[i + 1]
+        }
+    }
+}
+
+// Composes `first` followed by `second` into a single table.
+pub fn compose_lut(first: &[u8; 256], second: &[u8; 256]) -> [u8; 256] {
+    std::array::from_fn(|v| second[first[v] as usize])
+}
+
+// Input levels: `black` and `white` map to 0 and 255, and `gamma` bends the
+// midtones in between, brightening them above 1.
+pub fn levels_lut(levels: Levels) -> [u8; 256] {
+    std::array::from_fn(|v| levels_value(levels, v as f32).round() as u8)
+}
+
+// One value through `levels`, on 0-255.
+pub fn levels_value(levels: Levels, v: f32) -> f32 {
+    let range = (levels.white - levels.black).max(1.0);
+    let t = ((v - levels.black) / range).clamp(0.0, 1.0);
+    t.powf(1.0 / levels.gamma.max(0.01)) * 255.0
+}
+
+pub fn identity_lut() -> [u8; 256] {
+    std::array::from_fn(|v| v as u8)
+}
+
+// The master levels followed by each channel's own.
+pub fn channel_levels_luts(levels: &ChannelLevels) -> ChannelLuts {
+    let master = levels_lut(levels.master);
+    [levels.red, levels.green, levels.blue]
+        .map(|channel| compose_lut(&master, &levels_lut(channel)))
+}
+
+// A curve on one channel, or on all three for `Rgb`.
+pub fn channel_curve_luts(points: &[[f32; 2]], channel: CurveChannel) -> ChannelLuts {
+    let curve = curve_lut(points);
+    let index = match channel {
+        CurveChannel::Rgb => return [curve; 3],
+        CurveChannel::Red => 0,
+        CurveChannel::Green => 1,
+        CurveChannel::Blue => 2,
+    };
+    std::array::from_fn(|c| if c == index { curve } else { identity_lut() })
+}
@@ -0,0 +1,175 @@
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::analysis::PARALLEL_CHUNK;
+use crate::color::{hsl_to_rgb, rgb_to_hsl};
+use crate::models::{Color, EdgeMode, Operation};
+
+pub mod arithmetic;
+pub mod canvas;
+pub mod color_vision;
+pub mod comparison;
+pub mod dark_frame;
+pub mod denoise;
+pub mod dither;
+pub mod edits;
+pub mod enhance;
+pub mod exposure;
+pub mod filters;
+pub mod flat_field;
+pub mod frame;
+pub mod looks;
+pub mod lut;
+pub mod overlay;
+pub mod pixel_art;
+pub mod preview;
+pub mod redact;
+pub mod text;
+pub mod tone;
+pub mod transform;
+pub mod white_balance;
+pub mod working;
+
+impl From<Color> for Rgba<u8> {
+    fn from(color: Color) -> Self {
+        Rgba([color.r, color.g, color.b, color.a])
+    }
+}
+
+// Source-over compositing of `color` onto `dst`.
+pub fn blend_pixel(dst: &mut Rgba<u8>, color: Rgba<u8>) {
+    let src_a = color[3] as f32 / 255.0;
+    if src_a >= 1.0 {
+        *dst = color;
+        return;
+    }
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a <= 0.0 {
+        *dst = Rgba([0, 0, 0, 0]);
+        return;
+    }
+    for c in 0..3 {
+        let blended = (color[c] as f32 * src_a + dst[c] as f32 * dst_a * (1.0 - src_a)) / out_a;
+        dst[c] = blended.round().clamp(0.0, 255.0) as u8;
+    }
+    dst[3] = (out_a * 255.0).round() as u8;
+}
+
+pub fn fill_rect(img: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, color: Rgba<u8>) {
+    let x0 = x.max(0) as u32;
+    let y0 = y.max(0) as u32;
+    let x1 = ((x + width as i64).max(0) as u32).min(img.width());
+    let y1 = ((y + height as i64).max(0) as u32).min(img.height());
+    for py in y0..y1 {
+        for px in x0..x1 {
+            blend_pixel(img.get_pixel_mut(px, py), color);
+        }
+    }
+}
+
+// The pixel a filter reads for index `i` along an axis of `len` pixels,
+// which may be past either end.
+pub fn edge_index(edge: EdgeMode, i: i64, len: usize) -> usize {
+    let len = len as i64;
+    let i = match edge {
+        EdgeMode::Clamp => i.clamp(0, len - 1),
+        EdgeMode::Mirror => {
+            let m = i.rem_euclid(2 * len);
+            if m >= len {
+                2 * len - 1 - m
+            } else {
+                m
+            }
+        }
+        EdgeMode::Wrap => i.rem_euclid(len),
+    };
+    i as usize
+}
+
+// Runs `adjust` on each pixel, spread across the rayon pool in chunks.
+fn for_each_pixel(data: &mut [u8], adjust: impl Fn(&mut [u8]) + Sync) {
+    data.par_chunks_mut(PARALLEL_CHUNK * 4)
+        .for_each(|chunk| chunk.chunks_exact_mut(4).for_each(&adjust));
+}
+
+pub fn adjust_brightness(data: &mut [u8], amount: f32) {
+    for_each_pixel(data, |px| {
+        px[0] = (px[0] as f32 * amount).min(255.0) as u8;
+        px[1] = (px[1] as f32 * amount).min(255.0) as u8;
+        px[2] = (px[2] as f32 * amount).min(255.0) as u8;
+    });
+}
+
+pub fn adjust_contrast(data: &mut [u8], amount: f32) {
+    let center = 128.0;
+
+    for_each_pixel(data, |px| {
+        px[0] = ((px[0] as f32 - center) * amount + center).min(255.0) as u8;
+        px[1] = ((px[1] as f32 - center) * amount + center).min(255.0) as u8;
+        px[2] = ((px[2] as f32 - center) * amount + center).min(255.0) as u8;
+    });
+}
+
+pub fn convert_to_grayscale(data: &mut [u8]) {
+    for_each_pixel(data, |px| {
+        let gray = ((0.299 * px[0] as f32) + (0.587 * px[1] as f32) + (0.114 * px[2] as f32)) as u8;
+        px[0] = gray;
+        px[1] = gray;
+        px[2] = gray;
+    });
+}
+
+// Rotates hue by `hue_shift` degrees and scales saturation by `saturation`
+// (0 grey, 1 unchanged). `lightness` from -1 to 1 moves each pixel that
+// fraction of the way to black or white.
+pub fn adjust_hsl(data: &mut [u8], hue_shift: f32, saturation: f32, lightness: f32) {
+    let lightness = lightness.clamp(-1.0, 1.0);
+    for chunk in data.chunks_exact_mut(4) {
+        let [h, s, l] = rgb_to_hsl(chunk[0], chunk[1], chunk[2]);
+        let l = if lightness >= 0.0 {
+            l + (1.0 - l) * lightness
+        } else {
+            l * (1.0 + lightness)
+        };
+        let [r, g, b] = hsl_to_rgb(h + hue_shift, s * saturation.max(0.0), l);
+        chunk[0] = r;
+        chunk[1] = g;
+        chunk[2] = b;
+    }
+}
+
+pub fn apply_operation(img: &mut RgbaImage, operation: &Operation) {
+    match operation {
+        Operation::Brightness { amount } => adjust_brightness(img, *amount),
+        Operation::Contrast { amount } => adjust_contrast(img, *amount),
+        Operation::Grayscale => convert_to_grayscale(img),
+        Operation::Hsl {
+            hue_shift,
+            saturation,
+            lightness,
+        } => adjust_hsl(img, *hue_shift, *saturation, *lightness),
+        Operation::WhiteBalance { temperature, tint } => {
+            white_balance::adjust_white_balance(img, *temperature, *tint)
+        }
+        Operation::Denoise { strength, profile } => {
+            denoise::denoise(img, *strength, profile.as_ref())
+        }
+        Operation::Blur { sigma, edge } => filters::gaussian_blur(img, *sigma, *edge),
+        Operation::SmoothPreserveEdges { radius, epsilon } => {
+            filters::guided_filter(img, *radius, *epsilon)
+        }
+        Operation::Sharpen {
+            amount,
+            radius,
+            threshold,
+            edge,
+        } => filters::unsharp_mask(img, *amount, *radius, *threshold, *edge),
+    }
+}
+
+pub fn apply_operations(img: &mut RgbaImage, operations: &[Operation]) {
+    for operation in operations {
+        apply_operation(img, operation);
+    }
+}
@@ -0,0 +1,200 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use image::{Rgba, RgbaImage};
+
+use crate::error::AppError;
+use crate::models::{OverlayGuide, OverlaySpec};
+
+const MAX_OVERLAY_SIDE: u32 = 16_384;
+const PHI: f32 = 1.618_034;
+const SPIRAL_TURNS: usize = 10;
+const ARC_SEGMENTS: usize = 24;
+
+type Segment = ([f32; 2], [f32; 2]);
+
+// Coverage of line strokes, kept as the maximum over all strokes so joints
+// and crossings don't draw darker than the lines themselves.
+struct Strokes {
+    width: u32,
+    height: u32,
+    half_width: f32,
+    coverage: Vec<f32>,
+}
+
+impl Strokes {
+    fn segment(&mut self, [ax, ay]: [f32; 2], [bx, by]: [f32; 2]) {
+        let reach = self.half_width + 1.0;
+        let x0 = (ax.min(bx) - reach).floor().max(0.0) as u32;
+        let y0 = (ay.min(by) - reach).floor().max(0.0) as u32;
+        let x1 = ((ax.max(bx) + reach).ceil().max(0.0) as u32).min(self.width);
+        let y1 = ((ay.max(by) + reach).ceil().max(0.0) as u32).min(self.height);
+        let (dx, dy) = (bx - ax, by - ay);
+        let len_sq = dx * dx + dy * dy;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let t = if len_sq > 0.0 {
+                    (((px - ax) * dx + (py - ay) * dy) / len_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let dist = (px - ax - t * dx).hypot(py - ay - t * dy);
+                let c = (self.half_width + 0.5 - dist).clamp(0.0, 1.0);
+                let cell = &mut self.coverage[(y * self.width + x) as usize];
+                *cell = cell.max(c);
+            }
+        }
+    }
+}
+
+fn grid(fractions: &[f32], w: f32, h: f32) -> Vec<Segment> {
+    fractions
+        .iter()
+        .flat_map(|&f| [([f * w, 0.0], [f * w, h]), ([0.0, f * h], [w, f * h])])
+        .collect()
+}
+
+// A golden spiral in the unit square, as if drawn in a landscape golden
+// rectangle. Each turn cuts a square off the left, top, right then bottom
+// and draws a quarter arc inside it.
+fn golden_spiral() -> Vec<[f32; 2]> {
+    let (mut x, mut y, mut w, mut h) = (0.0f32, 0.0f32, PHI, 1.0f32);
+    let mut points = Vec::new();
+    for turn in 0..SPIRAL_TURNS {
+        let (centre, start, side) = match turn % 4 {
+            0 => {
+                let s = h;
+                x += s;
+                w -= s;
+                ([x, y + s], PI, s)
+            }
+            1 => {
+                let s = w;
+                y += s;
+                h -= s;
+                ([x, y], 1.5 * PI, s)
+            }
+            2 => {
+                let s = h;
+                w -= s;
+                ([x + w, y], 0.0, s)
+            }
+            _ => {
+                let s = w;
+                h -= s;
+                ([x + s, y + h], FRAC_PI_2, s)
+            }
+        };
+        for i in 0..=ARC_SEGMENTS {
+            let a = start + FRAC_PI_2 * i as f32 / ARC_SEGMENTS as f32;
+            points.push([
+                (centre[0] + side * a.cos()) / PHI,
+                centre[1] + side * a.sin(),
+            ]);
+        }
+    }
+    points
+}
+
+fn guide_segments(guide: &OverlayGuide, w: f32, h: f32, sx: f32) -> Vec<Segment> {
+    match guide {
+        OverlayGuide::PixelGrid { spacing } => {
+            // `sx` converts image pixels to overlay pixels.
+            let step = *spacing as f32 * sx;
+            let mut segments = Vec::new();
+            let mut v = step;
+            while v < w {
+                segments.push(([v, 0.0], [v, h]));
+                v += step;
+            }
+            let mut v = step;
+            while v < h {
+                segments.push(([0.0, v], [w, v]));
+                v += step;
+            }
+            segments
+        }
+        OverlayGuide::Thirds => grid(&[1.0 / 3.0, 2.0 / 3.0], w, h),
+        OverlayGuide::GoldenRatio => grid(&[1.0 - 1.0 / PHI, 1.0 / PHI], w, h),
+        OverlayGuide::GoldenSpiral {
+            flip_horizontal,
+            flip_vertical,
+        } => {
+            let portrait = h > w;
+            let points: Vec<[f32; 2]> = golden_spiral()
+                .into_iter()
+                .map(|[u, v]| {
+                    let [u, v] = if portrait { [v, u] } else { [u, v] };
+                    let u = if *flip_horizontal { 1.0 - u } else { u };
+                    let v = if *flip_vertical { 1.0 - v } else { v };
+                    [u * w, v * h]
+                })
+                .collect();
+            points.windows(2).map(|p| (p[0], p[1])).collect()
+        }
+        OverlayGuide::Custom {
+            vertical,
+            horizontal,
+        } => vertical
+            .iter()
+            .map(|&f| ([f * w, 0.0], [f * w, h]))
+            .chain(horizontal.iter().map(|&f| ([0.0, f * h], [w, f * h])))
+            .collect(),
+    }
+}
+
+// Draws the guides for an `image_width`x`image_height` image onto a
+// transparent layer, at the spec's size if it gives one.
+pub fn render_grid_overlay(
+    image_width: u32,
+    image_height: u32,
+    spec: &OverlaySpec,
+) -> Result<RgbaImage, AppError> {
+    let width = spec.width.unwrap_or(image_width);
+    let height = spec.height.unwrap_or(image_height);
+    if width == 0 || height == 0 || width > MAX_OVERLAY_SIDE || height > MAX_OVERLAY_SIDE {
+        return Err(AppError::invalid(format!(
+            "Overlay size must be between 1 and {} pixels a side",
+            MAX_OVERLAY_SIDE
+        )));
+    }
+    if !(spec.line_width > 0.0 && spec.line_width <= 100.0) {
+        return Err(AppError::invalid("Line width must be between 0 and 100"));
+    }
+    if spec
+        .guides
+        .iter()
+        .any(|g| matches!(g, OverlayGuide::PixelGrid { spacing: 0 }))
+    {
+        return Err(AppError::invalid("Grid spacing must be at least 1 pixel"));
+    }
+
+    let mut strokes = Strokes {
+        width,
+        height,
+        half_width: spec.line_width / 2.0,
+        coverage: vec![0.0; (width * height) as usize],
+    };
+    let (w, h) = (width as f32, height as f32);
+    let sx = w / image_width.max(1) as f32;
+    for guide in &spec.guides {
+        for (a, b) in guide_segments(guide, w, h, sx) {
+            strokes.segment(a, b);
+        }
+    }
+
+    let color = spec.color;
+    let mut layer = RgbaImage::new(width, height);
+    for (px, &c) in layer.pixels_mut().zip(&strokes.coverage) {
+        if c > 0.0 {
+            *px = Rgba([
+                color.r,
+                color.g,
+                color.b,
+                (color.a as f32 * c).round() as u8,
+            ]);
+        }
+    }
+    Ok(layer)
+}
@@ -0,0 +1,263 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::PixelArtAlgorithm;
+
+// Pixel art is small; this keeps the output within what other edits accept.
+const MAX_OUTPUT_SIDE: u32 = 16_384;
+// Samples per side of each output pixel when measuring how much of it lies
+// past an xBR edge.
+const COVERAGE_SAMPLES: usize = 16;
+
+type Px = [u8; 4];
+
+// The pixel at `(x, y)`, repeating the border for neighbours off the edge.
+fn at(img: &RgbaImage, x: i64, y: i64) -> Px {
+    let x = x.clamp(0, img.width() as i64 - 1) as u32;
+    let y = y.clamp(0, img.height() as i64 - 1) as u32;
+    img.get_pixel(x, y).0
+}
+
+// Writes each source pixel's `factor`x`factor` block, computed by `block`
+// into a row-major buffer of that many pixels.
+fn scale_by(img: &RgbaImage, factor: u32, block: impl Fn(i64, i64, &mut [Px]) + Sync) -> RgbaImage {
+    let n = factor as usize;
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let out_w = w * n;
+    let mut out = RgbaImage::new(out_w as u32, (h * n) as u32);
+    let data: &mut [u8] = &mut out;
+    data.par_chunks_mut(out_w * n * 4)
+        .enumerate()
+        .for_each(|(y, rows)| {
+            let mut cells = vec![[0u8; 4]; n * n];
+            for x in 0..w {
+                block(x as i64, y as i64, &mut cells);
+                for (i, cell) in cells.iter().enumerate() {
+                    let (sx, sy) = (i % n, i / n);
+                    let offset = (sy * out_w + x * n + sx) * 4;
+                    rows[offset..offset + 4].copy_from_slice(cell);
+                }
+            }
+        });
+    out
+}
+
+// AdvMAME2x: each quarter takes the neighbour colour when two neighbours
+// meeting at that corner agree, rounding off diagonal staircases.
+fn scale2x(img: &RgbaImage) -> RgbaImage {
+    scale_by(img, 2, |x, y, cells| {
+        let e = at(img, x, y);
+        let (b, d) = (at(img, x, y - 1), at(img, x - 1, y));
+        let (f, h) = (at(img, x + 1, y), at(img, x, y + 1));
+        cells.fill(e);
+        if b != h && d != f {
+            if d == b {
+                cells[0] = d;
+            }
+            if b == f {
+                cells[1] = f;
+            }
+            if d == h {
+                cells[2] = d;
+            }
+            if h == f {
+                cells[3] = f;
+            }
+        }
+    })
+}
+
+// AdvMAME3x, the same rules carried to a 3x3 block.
+fn scale3x(img: &RgbaImage) -> RgbaImage {
+    scale_by(img, 3, |x, y, cells| {
+        let p = |dx, dy| at(img, x + dx, y + dy);
+        let (a, b, c) = (p(-1, -1), p(0, -1), p(1, -1));
+        let (d, e, f) = (p(-1, 0), p(0, 0), p(1, 0));
+        let (g, h, i) = (p(-1, 1), p(0, 1), p(1, 1));
+        cells.fill(e);
+        if b != h && d != f {
+            if d == b {
+                cells[0] = d;
+            }
+            if (d == b && e != c) || (b == f && e != a) {
+                cells[1] = b;
+            }
+            if b == f {
+                cells[2] = f;
+            }
+            if (d == b && e != g) || (d == h && e != a) {
+                cells[3] = d;
+            }
+            if (b == f && e != i) || (h == f && e != c) {
+                cells[5] = f;
+            }
+            if d == h {
+                cells[6] = d;
+            }
+            if (d == h && e != i) || (h == f && e != g) {
+                cells[7] = h;
+            }
+            if h == f {
+                cells[8] = f;
+            }
+        }
+    })
+}
+
+// Perceptual distance between two colours, weighting luma over chroma as
+// xBR does.
+fn distance(a: Px, b: Px) -> f32 {
+    let d: [f32; 4] = std::array::from_fn(|c| a[c] as f32 - b[c] as f32);
+    let y = 0.299 * d[0] + 0.587 * d[1] + 0.114 * d[2];
+    let u = -0.169 * d[0] - 0.331 * d[1] + 0.5 * d[2];
+    let v = 0.5 * d[0] - 0.419 * d[1] - 0.081 * d[2];
+    48.0 * y.abs() + 7.0 * u.abs() + 6.0 * v.abs() + 48.0 * d[3].abs()
+}
+
+// Mixes `t` of `b` into `a` in premultiplied alpha, so transparent pixels'
+// colour doesn't bleed in.
+fn blend(a: Px, b: Px, t: f32) -> Px {
+    let (wa, wb) = (a[3] as f32 * (1.0 - t), b[3] as f32 * t);
+    let alpha = wa + wb;
+    if alpha <= 0.0 {
+        return [0; 4];
+    }
+    let mut out = [0u8; 4];
+    for c in 0..3 {
+        out[c] = ((a[c] as f32 * wa + b[c] as f32 * wb) / alpha).round() as u8;
+    }
+    out[3] = alpha.round() as u8;
+    out
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    // 45°, cutting off the corner.
+    Diagonal,
+    // Shallow, running off to the left of the corner.
+    Left,
+    // Steep, running up from the corner.
+    Up,
+    Both,
+}
+
+// For an edge across the bottom-right corner of an `n`x`n` block, the share
+// of each output pixel lying beyond it. The edges are the lines xBR's blend
+// weights approximate, so at 2x these reproduce them.
+fn coverage(n: usize, edge: Edge) -> Vec<f32> {
+    let beyond = |x: f32, y: f32| match edge {
+        Edge::Diagonal => x + y >= 1.5,
+        Edge::Left => x + 2.0 * y >= 2.0,
+        Edge::Up => 2.0 * x + y >= 2.0,
+        Edge::Both => x + 2.0 * y >= 2.0 || 2.0 * x + y >= 2.0,
+    };
+    let s = COVERAGE_SAMPLES;
+    (0..n * n)
+        .map(|i| {
+            let (cx, cy) = (i % n, i / n);
+            let mut hits = 0;
+            for j in 0..s * s {
+                let x = (cx as f32 + ((j % s) as f32 + 0.5) / s as f32) / n as f32;
+                let y = (cy as f32 + ((j / s) as f32 + 0.5) / s as f32) / n as f32;
+                hits += beyond(x, y) as usize;
+            }
+            hits as f32 / (s * s) as f32
+        })
+        .collect()
+}
+
+// Hyllian's xBR: looks for edges through each corner of a pixel by comparing
+// colour differences along and across them, then blends the colour beyond
+// the edge into the part of the block it covers. Smooth curves and diagonal
+// lines come out anti-aliased, while flat areas and dithering stay sharp.
+fn xbr(img: &RgbaImage, factor: u32) -> RgbaImage {
+    let n = factor as usize;
+    let tables = [Edge::Diagonal, Edge::Left, Edge::Up, Edge::Both].map(|edge| coverage(n, edge));
+    scale_by(img, factor, |x, y, cells| {
+        let center = at(img, x, y);
+        cells.fill(center);
+        // The kernel is written for the bottom-right corner and turned a
+        // quarter at a time for the others.
+        for turn in 0..4 {
+            let rotate = |(dx, dy): (i64, i64)| (0..turn).fold((dx, dy), |(dx, dy), _| (-dy, dx));
+            let p = |dx, dy| {
+                let (dx, dy) = rotate((dx, dy));
+                at(img, x + dx, y + dy)
+            };
+            let (b, c) = (p(0, -1), p(1, -1));
+            let (d, e, f) = (p(-1, 0), center, p(1, 0));
+            let (g, h, i) = (p(-1, 1), p(0, 1), p(1, 1));
+            let (f4, i4, h5, i5) = (p(2, 0), p(2, 1), p(0, 2), p(1, 2));
+            if e == h || e == f {
+                continue;
+            }
+            let along = distance(e, c)
+                + distance(e, g)
+                + distance(i, h5)
+                + distance(i, f4)
+                + 4.0 * distance(h, f);
+            let across = distance(h, d)
+                + distance(h, i5)
+                + distance(f, i4)
+                + distance(f, b)
+                + 4.0 * distance(e, i);
+            if along >= across {
+                continue;
+            }
+            let beyond = if distance(e, f) <= distance(e, h) {
+                f
+            } else {
+                h
+            };
+            let shaped = (f != b && h != d) || (e == i && f != i4 && h != i5) || e == g || e == c;
+            let (ke, ki) = (distance(f, g), distance(h, c));
+            let left = shaped && 2.0 * ke <= ki && e != g && d != g;
+            let up = shaped && ke >= 2.0 * ki && e != c && b != c;
+            let table = match (left, up) {
+                (false, false) => &tables[0],
+                (true, false) => &tables[1],
+                (false, true) => &tables[2],
+                (true, true) => &tables[3],
+            };
+            for (k, &share) in table.iter().enumerate() {
+                if share > 0.0 {
+                    // The block turns with the kernel.
+                    let (mut sx, mut sy) = (k % n, k / n);
+                    for _ in 0..turn {
+                        (sx, sy) = (n - 1 - sy, sx);
+                    }
+                    let cell = &mut cells[sy * n + sx];
+                    *cell = blend(*cell, beyond, share);
+                }
+            }
+        }
+    })
+}
+
+// Enlarges pixel art by `factor`, keeping its hard edges where smooth
+// resampling would blur them.
+pub fn scale_pixel_art(
+    img: &RgbaImage,
+    algorithm: PixelArtAlgorithm,
+    factor: u32,
+) -> Result<RgbaImage, AppError> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if !(2..=4).contains(&factor) {
+        return Err(AppError::invalid("Pixel art scales by 2, 3 or 4"));
+    }
+    if img.width().max(img.height()) * factor > MAX_OUTPUT_SIDE {
+        return Err(AppError::invalid(format!(
+            "The result would be over {} pixels wide or tall",
+            MAX_OUTPUT_SIDE
+        )));
+    }
+    Ok(match (algorithm, factor) {
+        (PixelArtAlgorithm::Scale2x, 2) => scale2x(img),
+        (PixelArtAlgorithm::Scale2x, 3) => scale3x(img),
+        (PixelArtAlgorithm::Scale2x, _) => scale2x(&scale2x(img)),
+        (PixelArtAlgorithm::Xbr, factor) => xbr(img, factor),
+    })
+}
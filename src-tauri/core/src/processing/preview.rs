@@ -0,0 +1,59 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::error::AppError;
+use crate::models::{Operation, PixelRect};
+use crate::processing::{apply_operation, apply_operations};
+
+// Applies `operation` to just `region` of the image, first scaled down to fit
+// `max_width`x`max_height`, so slider scrubbing on huge images stays cheap.
+// Returns the clipped region and the rendered preview.
+pub fn preview_operation(
+    img: &RgbaImage,
+    operation: &Operation,
+    region: PixelRect,
+    max_width: u32,
+    max_height: u32,
+) -> Result<(PixelRect, RgbaImage), AppError> {
+    if max_width == 0 || max_height == 0 {
+        return Err(AppError::invalid("Preview size must be at least 1 pixel"));
+    }
+    let (width, height) = img.dimensions();
+    let x1 = region.x.saturating_add(region.width).min(width);
+    let y1 = region.y.saturating_add(region.height).min(height);
+    if region.x >= x1 || region.y >= y1 {
+        return Err(AppError::invalid("Preview region is outside the image"));
+    }
+    let region = PixelRect {
+        x: region.x,
+        y: region.y,
+        width: x1 - region.x,
+        height: y1 - region.y,
+    };
+
+    let view = imageops::crop_imm(img, region.x, region.y, region.width, region.height);
+    let scale = (max_width as f32 / region.width as f32)
+        .min(max_height as f32 / region.height as f32)
+        .min(1.0);
+    let mut preview = if scale < 1.0 {
+        let w = ((region.width as f32 * scale).round() as u32).max(1);
+        let h = ((region.height as f32 * scale).round() as u32).max(1);
+        imageops::resize(&*view, w, h, FilterType::Triangle)
+    } else {
+        view.to_image()
+    };
+    apply_operation(&mut preview, operation);
+    Ok((region, preview))
+}
+
+// The stand-in interactive edits are shown on for a large image: `img`
+// scaled so its longer side is `max_dimension`, with the `pending`
+// operations applied.
+pub fn render_proxy(img: &RgbaImage, max_dimension: u32, pending: &[Operation]) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let factor = max_dimension as f64 / width.max(height) as f64;
+    let scaled = |side: u32| ((side as f64 * factor).round() as u32).max(1);
+    let mut proxy = imageops::resize(img, scaled(width), scaled(height), FilterType::Triangle);
+    apply_operations(&mut proxy, pending);
+    proxy
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use crate::analysis::{faces, text_regions};
+use crate::error::AppError;
+use crate::models::{PixelRect, RedactionStyle};
+
+// Smaller blocks leave text legible.
+const MIN_BLOCK_SIZE: u32 = 4;
+
+// Clips `rect` to the image, or None if they don't overlap.
+fn clip(rect: PixelRect, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let right = rect.x.saturating_add(rect.width).min(width);
+    let bottom = rect.y.saturating_add(rect.height).min(height);
+    (rect.x < right && rect.y < bottom).then_some((rect.x, rect.y, right, bottom))
+}
+
+// Averages `block`-sized tiles of the region in place, aligned to its corner.
+// The result is opaque so nothing can hide in the alpha channel.
+fn pixelate(img: &mut RgbaImage, (x0, y0, x1, y1): (u32, u32, u32, u32), block: u32) {
+    for by in (y0..y1).step_by(block as usize) {
+        for bx in (x0..x1).step_by(block as usize) {
+            let (ex, ey) = ((bx + block).min(x1), (by + block).min(y1));
+            let mut sum = [0u64; 3];
+            for y in by..ey {
+                for x in bx..ex {
+                    let px = img.get_pixel(x, y);
+                    for c in 0..3 {
+                        sum[c] += px[c] as u64;
+                    }
+                }
+            }
+            let count = ((ex - bx) * (ey - by)) as u64;
+            let avg = Rgba([
+                (sum[0] / count) as u8,
+                (sum[1] / count) as u8,
+                (sum[2] / count) as u8,
+                255,
+            ]);
+            for y in by..ey {
+                for x in bx..ex {
+                    img.put_pixel(x, y, avg);
+                }
+            }
+        }
+    }
+}
+
+// Destroys the content of each region. Regions are clipped to the image; at
+// least one must overlap it.
+pub fn redact_regions(
+    img: &mut RgbaImage,
+    regions: &[PixelRect],
+    style: RedactionStyle,
+) -> Result<(), AppError> {
+    match style {
+        RedactionStyle::Pixelate { block_size } if block_size < MIN_BLOCK_SIZE => {
+            return Err(AppError::invalid(format!(
+                "Block size must be at least {}",
+                MIN_BLOCK_SIZE
+            )));
+        }
+        RedactionStyle::Blur { radius } if radius.is_nan() || radius < 1.0 => {
+            return Err(AppError::invalid("Blur radius must be at least 1"));
+        }
+        _ => {}
+    }
+
+    let (width, height) = img.dimensions();
+    let clipped: Vec<_> = regions
+        .iter()
+        .filter_map(|&r| clip(r, width, height))
+        .collect();
+    if clipped.is_empty() {
+        return Err(AppError::invalid("No region overlaps the image"));
+    }
+
+    for region in clipped {
+        let (x0, y0, x1, y1) = region;
+        match style {
+            RedactionStyle::Pixelate { block_size } => pixelate(img, region, block_size),
+            RedactionStyle::Solid { color } => {
+                let fill = Rgba([color.r, color.g, color.b, 255]);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        img.put_pixel(x, y, fill);
+                    }
+                }
+            }
+            RedactionStyle::Blur { radius } => {
+                let block = (radius.round() as u32).max(MIN_BLOCK_SIZE);
+                pixelate(img, region, block);
+                let patch = imageops::crop_imm(img, x0, y0, x1 - x0, y1 - y0).to_image();
+                let blurred = imageops::blur(&patch, radius);
+                imageops::replace(img, &blurred, x0 as i64, y0 as i64);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Grows each region by `padding` on every side, clipped to the image, since
+// detectors box text and faces tightly and the edges of glyphs, hair and
+// ears would otherwise show.
+pub fn pad_regions(regions: &[PixelRect], padding: u32, width: u32, height: u32) -> Vec<PixelRect> {
+    regions
+        .iter()
+        .filter_map(|&r| {
+            let x = r.x.saturating_sub(padding);
+            let y = r.y.saturating_sub(padding);
+            let right =
+                r.x.saturating_add(r.width)
+                    .saturating_add(padding)
+                    .min(width);
+            let bottom =
+                r.y.saturating_add(r.height)
+                    .saturating_add(padding)
+                    .min(height);
+            (x < right && y < bottom).then_some(PixelRect {
+                x,
+                y,
+                width: right - x,
+                height: bottom - y,
+            })
+        })
+        .collect()
+}
+
+// What one-click redaction hides: the text found by the model at
+// `text_model` and the faces found by the one at `face_model`, each padded.
+// Either model can be left out to skip what it finds.
+pub fn find_private_regions(
+    img: &RgbaImage,
+    text_model: Option<&Path>,
+    face_model: Option<&Path>,
+    padding: u32,
+) -> Result<Vec<PixelRect>, AppError> {
+    let mut regions = Vec::new();
+    if let Some(model) = text_model {
+        let text = text_regions::detect_text_regions(img, model)?;
+        regions.extend(text.iter().map(|region| region.bounds));
+    }
+    if let Some(model) = face_model {
+        let found = faces::detect_faces(img, model)?;
+        regions.extend(found.iter().map(|face| face.bounds));
+    }
+    Ok(pad_regions(&regions, padding, img.width(), img.height()))
+}
@@ -0,0 +1,46 @@
+use font8x8::legacy::BASIC_LEGACY;
+use image::{Rgba, RgbaImage};
+
+use crate::processing::blend_pixel;
+
+const GLYPH_SIZE: u32 = 8;
+
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    (
+        text.chars().count() as u32 * GLYPH_SIZE * scale,
+        GLYPH_SIZE * scale,
+    )
+}
+
+// Draws ASCII text with the built-in 8x8 bitmap font, each font pixel becoming a
+// scale x scale block. Characters outside ASCII render as '?'.
+pub fn draw_text(img: &mut RgbaImage, x: i64, y: i64, text: &str, scale: u32, color: Rgba<u8>) {
+    let scale = scale.max(1) as i64;
+    for (index, ch) in text.chars().enumerate() {
+        let code = if ch.is_ascii() {
+            ch as usize
+        } else {
+            '?' as usize
+        };
+        let glyph = BASIC_LEGACY[code];
+        let origin_x = x + index as i64 * GLYPH_SIZE as i64 * scale;
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..GLYPH_SIZE as i64 {
+                if bits & (1 << col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = origin_x + col * scale + dx;
+                        let py = y + row as i64 * scale + dy;
+                        if px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64
+                        {
+                            blend_pixel(img.get_pixel_mut(px as u32, py as u32), color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
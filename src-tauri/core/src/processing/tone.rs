@@ -0,0 +1,138 @@
+use image::RgbaImage;
+
+use crate::error::AppError;
+use crate::models::{ChannelLevels, CurveChannel, Levels, PixelPoint};
+use crate::processing::lut::{
+    apply_luts, black_white_luts, channel_curve_luts, channel_levels_luts,
+};
+
+// Eyedropper picks average a 5x5 neighbourhood.
+const SAMPLE_RADIUS: u32 = 2;
+
+// Mean RGB over a (2 * radius + 1)^2 box, clipped to the image, which keeps an
+// eyedropper pick from landing on a single noisy pixel.
+pub fn sample_average(img: &RgbaImage, x: u32, y: u32, radius: u32) -> [f32; 3] {
+    let x0 = x.saturating_sub(radius);
+    let y0 = y.saturating_sub(radius);
+    let x1 = (x + radius).min(img.width() - 1);
+    let y1 = (y + radius).min(img.height() - 1);
+
+    let mut sums = [0f32; 3];
+    let mut count = 0f32;
+    for py in y0..=y1 {
+        for px in x0..=x1 {
+            let p = img.get_pixel(px, py);
+            for c in 0..3 {
+                sums[c] += p[c] as f32;
+            }
+            count += 1.0;
+        }
+    }
+    sums.map(|sum| sum / count)
+}
+
+// Scales each pixel's distance from its own luma; 0 is monochrome, 1 unchanged.
+pub fn apply_saturation(img: &mut RgbaImage, factor: f32) {
+    for px in img.pixels_mut() {
+        let luma = 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32;
+        for c in 0..3 {
+            px[c] = (luma + (px[c] as f32 - luma) * factor)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// Linear mix of `graded` back towards `original`; `amount` 1 keeps the grade.
+pub fn blend_towards(original: &RgbaImage, graded: &mut RgbaImage, amount: f32) {
+    for (out, src) in graded.pixels_mut().zip(original.pixels()) {
+        for c in 0..3 {
+            let (a, b) = (src[c] as f32, out[c] as f32);
+            out[c] = (a + (b - a) * amount).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// Stretches each channel so the pixels picked at `black` and `white` become
+// pure black and white.
+pub fn set_black_white_points(
+    img: &mut RgbaImage,
+    black_sample: PixelPoint,
+    white_sample: PixelPoint,
+) -> Result<(), AppError> {
+    for point in [black_sample, white_sample] {
+        if point.x >= img.width() || point.y >= img.height() {
+            return Err(AppError::invalid(format!(
+                "Sample point ({}, {}) is outside the {}x{} image",
+                point.x,
+                point.y,
+                img.width(),
+                img.height()
+            )));
+        }
+    }
+
+    let black = sample_average(img, black_sample.x, black_sample.y, SAMPLE_RADIUS);
+    let white = sample_average(img, white_sample.x, white_sample.y, SAMPLE_RADIUS);
+    if (0..3).any(|c| white[c] <= black[c]) {
+        return Err(AppError::invalid(
+            "White point sample must be brighter than the black point sample in every channel",
+        ));
+    }
+
+    apply_luts(img, &black_white_luts(black, white));
+    Ok(())
+}
+
+fn check_levels(name: &str, levels: Levels) -> Result<(), AppError> {
+    let in_range = |v: f32| (0.0..=255.0).contains(&v);
+    if !in_range(levels.black) || !in_range(levels.white) || levels.white <= levels.black {
+        return Err(AppError::invalid(format!(
+            "{} levels need 0 <= black < white <= 255",
+            name
+        )));
+    }
+    if !levels.gamma.is_finite() || levels.gamma <= 0.0 {
+        return Err(AppError::invalid(format!(
+            "{} gamma must be greater than 0",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_levels(levels: &ChannelLevels) -> Result<(), AppError> {
+    for (name, channel) in [
+        ("Master", levels.master),
+        ("Red", levels.red),
+        ("Green", levels.green),
+        ("Blue", levels.blue),
+    ] {
+        check_levels(name, channel)?;
+    }
+    Ok(())
+}
+
+pub fn apply_levels(img: &mut RgbaImage, levels: &ChannelLevels) -> Result<(), AppError> {
+    validate_levels(levels)?;
+    apply_luts(img, &channel_levels_luts(levels));
+    Ok(())
+}
+
+pub fn validate_curve(points: &[[f32; 2]]) -> Result<(), AppError> {
+    if points.iter().flatten().any(|v| !(0.0..=255.0).contains(v)) {
+        return Err(AppError::invalid("Curve points must be between 0 and 255"));
+    }
+    Ok(())
+}
+
+// `points` are (input, output) pairs on 0-255; see `curve_lut`.
+pub fn apply_curve(
+    img: &mut RgbaImage,
+    points: &[[f32; 2]],
+    channel: CurveChannel,
+) -> Result<(), AppError> {
+    validate_curve(points)?;
+    apply_luts(img, &channel_curve_luts(points, channel));
+    Ok(())
+}
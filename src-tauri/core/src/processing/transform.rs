@@ -0,0 +1,314 @@
+use std::f32::consts::PI;
+
+use image::imageops::FilterType;
+use image::{imageops, Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::models::{CanvasFill, EdgeMode, FlipAxis, ResizeFilter};
+use crate::processing::canvas::mirror;
+use crate::processing::edge_index;
+
+// Angles this close to a multiple of 90 degrees use the exact, lossless
+// rotation instead of resampling.
+const RIGHT_ANGLE_TOLERANCE: f64 = 1e-3;
+
+// Rotates counter-clockwise by `degrees`, the same sense as the rotation
+// `detect_horizon` suggests. Right angles swap or keep the dimensions
+// exactly; other angles grow the canvas to hold the whole rotated image and
+// fill the corners with `fill`.
+pub fn rotate(img: &RgbaImage, degrees: f32, fill: CanvasFill) -> Result<RgbaImage, AppError> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if !degrees.is_finite() {
+        return Err(AppError::invalid("Rotation angle must be a number"));
+    }
+    let degrees = (degrees as f64).rem_euclid(360.0);
+    let quarter = (degrees / 90.0).round();
+    if (degrees - quarter * 90.0).abs() < RIGHT_ANGLE_TOLERANCE {
+        // imageops rotates clockwise.
+        return Ok(match quarter as u32 % 4 {
+            0 => img.clone(),
+            1 => imageops::rotate270(img),
+            2 => imageops::rotate180(img),
+            _ => imageops::rotate90(img),
+        });
+    }
+    Ok(rotate_resampled(img, degrees.to_radians(), fill))
+}
+
+// Premultiplied, so transparent pixels' arbitrary RGB doesn't bleed into
+// their neighbours.
+fn premultiplied(px: Rgba<u8>) -> [f64; 4] {
+    let a = px[3] as f64 / 255.0;
+    [
+        px[0] as f64 * a,
+        px[1] as f64 * a,
+        px[2] as f64 * a,
+        px[3] as f64,
+    ]
+}
+
+fn rotate_resampled(img: &RgbaImage, radians: f64, fill: CanvasFill) -> RgbaImage {
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let (sin, cos) = radians.sin_cos();
+    let out_w = (w as f64 * cos.abs() + h as f64 * sin.abs())
+        .round()
+        .max(1.0) as u32;
+    let out_h = (w as f64 * sin.abs() + h as f64 * cos.abs())
+        .round()
+        .max(1.0) as u32;
+    let (cx, cy) = (w as f64 / 2.0, h as f64 / 2.0);
+    let (ox, oy) = (out_w as f64 / 2.0, out_h as f64 / 2.0);
+    let solid = match fill {
+        CanvasFill::Solid { color } => Some(color.into()),
+        CanvasFill::Transparent => Some(Rgba([0, 0, 0, 0])),
+        CanvasFill::Edge | CanvasFill::Mirror => None,
+    };
+    // Pixels off the image come from `fill`, so edges against a solid fill
+    // are blended rather than jagged.
+    let texel = |x: i64, y: i64| -> [f64; 4] {
+        if (0..w).contains(&x) && (0..h).contains(&y) {
+            return premultiplied(*img.get_pixel(x as u32, y as u32));
+        }
+        let (x, y) = match (fill, solid) {
+            (_, Some(color)) => return premultiplied(color),
+            (CanvasFill::Mirror, _) => (mirror(x, w), mirror(y, h)),
+            _ => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+        };
+        premultiplied(*img.get_pixel(x as u32, y as u32))
+    };
+
+    RgbaImage::from_fn(out_w, out_h, |x, y| {
+        // Image y points down, so a counter-clockwise turn on screen maps each
+        // output pixel back through the clockwise rotation in these axes.
+        let dx = x as f64 + 0.5 - ox;
+        let dy = y as f64 + 0.5 - oy;
+        let sx = cos * dx - sin * dy + cx - 0.5;
+        let sy = sin * dx + cos * dy + cy - 0.5;
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let mut sum = [0f64; 4];
+        for (tx, ty, weight) in [
+            (x0, y0, (1.0 - fx) * (1.0 - fy)),
+            (x0 + 1, y0, fx * (1.0 - fy)),
+            (x0, y0 + 1, (1.0 - fx) * fy),
+            (x0 + 1, y0 + 1, fx * fy),
+        ] {
+            for (s, v) in sum.iter_mut().zip(texel(tx, ty)) {
+                *s += v * weight;
+            }
+        }
+        let alpha = sum[3];
+        if alpha <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let unpremultiply = |v: f64| (v * 255.0 / alpha).round().clamp(0.0, 255.0) as u8;
+        Rgba([
+            unpremultiply(sum[0]),
+            unpremultiply(sum[1]),
+            unpremultiply(sum[2]),
+            alpha.round().clamp(0.0, 255.0) as u8,
+        ])
+    })
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Bilinear => FilterType::Triangle,
+            ResizeFilter::Bicubic => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+// The largest size with the image's aspect ratio that fits within
+// `max_width`x`max_height`, never less than a pixel each way.
+pub fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+    let fit = |size: u32, max: u32| ((size as f64 * scale).round() as u32).clamp(1, max.max(1));
+    (fit(width, max_width), fit(height, max_height))
+}
+
+// Resampling works on premultiplied colour so transparent pixels' RGB
+// doesn't fringe the edges of what they border. `edge` picks what the
+// kernel reads past the image's sides.
+pub fn resize(
+    img: &RgbaImage,
+    width: u32,
+    height: u32,
+    filter: ResizeFilter,
+    edge: EdgeMode,
+) -> Result<RgbaImage, AppError> {
+    if img.width() == 0 || img.height() == 0 {
+        return Err(AppError::invalid("Image is empty"));
+    }
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Width and height must be at least 1"));
+    }
+    if (width, height) == img.dimensions() {
+        return Ok(img.clone());
+    }
+    // `imageops` clamps at the border; other edges need our own resampler.
+    // Nearest-neighbour never reads past the border either way.
+    if let Some((support, kernel)) = kernel(filter).filter(|_| edge != EdgeMode::Clamp) {
+        return Ok(resample(img, width, height, support, kernel, edge));
+    }
+    let has_alpha = img.pixels().any(|px| px[3] < 255);
+    if !has_alpha || filter == ResizeFilter::Nearest {
+        return Ok(imageops::resize(img, width, height, filter.into()));
+    }
+
+    // Float images resample in 0-1.
+    let (w, h) = img.dimensions();
+    let premultiplied = image::Rgba32FImage::from_fn(w, h, |x, y| {
+        Rgba(premultiplied(*img.get_pixel(x, y)).map(|v| (v / 255.0) as f32))
+    });
+    let resized = imageops::resize(&premultiplied, width, height, filter.into());
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = resized.get_pixel(x, y).0;
+        if a <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let unpremultiply = |v: f32| (v / a * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgba([
+            unpremultiply(r),
+            unpremultiply(g),
+            unpremultiply(b),
+            (a * 255.0).round().clamp(0.0, 255.0) as u8,
+        ])
+    }))
+}
+
+// Mirrors the image: `Horizontal` swaps left and right, `Vertical` top and
+// bottom.
+pub fn flip(img: &mut RgbaImage, axis: FlipAxis) {
+    match axis {
+        FlipAxis::Horizontal => imageops::flip_horizontal_in_place(img),
+        FlipAxis::Vertical => imageops::flip_vertical_in_place(img),
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (x * PI).sin() / (x * PI)
+    }
+}
+
+type Kernel = fn(f32) -> f32;
+
+// Support radius and weights of the kernel `imageops` uses for `filter`.
+// `None` for nearest-neighbour, which isn't a weighted sum.
+fn kernel(filter: ResizeFilter) -> Option<(f32, Kernel)> {
+    match filter {
+        ResizeFilter::Nearest => None,
+        ResizeFilter::Bilinear => Some((1.0, |x| (1.0 - x.abs()).max(0.0))),
+        ResizeFilter::Bicubic => Some((2.0, |x| {
+            // Catmull-Rom.
+            let x = x.abs();
+            if x < 1.0 {
+                1.5 * x * x * x - 2.5 * x * x + 1.0
+            } else if x < 2.0 {
+                -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+            } else {
+                0.0
+            }
+        })),
+        ResizeFilter::Lanczos3 => Some((3.0, |x| {
+            if x.abs() < 3.0 {
+                sinc(x) * sinc(x / 3.0)
+            } else {
+                0.0
+            }
+        })),
+    }
+}
+
+// For each of `dst` output pixels, the first source pixel it reads and the
+// weights of it and those after. Downscaling widens the kernel to cover
+// every source pixel.
+fn taps(src: usize, dst: usize, support: f32, kernel: Kernel) -> Vec<(i64, Vec<f32>)> {
+    let scale = src as f32 / dst as f32;
+    let stretch = scale.max(1.0);
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let start = (center - support * stretch).floor() as i64;
+            let end = (center + support * stretch).ceil() as i64;
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| kernel((j as f32 + 0.5 - center) / stretch))
+                .collect();
+            let total: f32 = weights.iter().sum();
+            if total != 0.0 {
+                weights.iter_mut().for_each(|w| *w /= total);
+            }
+            (start, weights)
+        })
+        .collect()
+}
+
+// Separable resampling in premultiplied alpha, reading past the border as
+// `edge` says.
+fn resample(
+    img: &RgbaImage,
+    width: u32,
+    height: u32,
+    support: f32,
+    kernel: Kernel,
+    edge: EdgeMode,
+) -> RgbaImage {
+    let (src_w, src_h) = (img.width() as usize, img.height() as usize);
+    let (dst_w, dst_h) = (width as usize, height as usize);
+    let planes: Vec<f32> = img
+        .pixels()
+        .flat_map(|&px| premultiplied(px).map(|v| v as f32))
+        .collect();
+
+    let columns = taps(src_w, dst_w, support, kernel);
+    let mut wide = vec![0f32; dst_w * src_h * 4];
+    wide.par_chunks_mut(dst_w * 4)
+        .zip(planes.par_chunks(src_w * 4))
+        .for_each(|(out, row)| {
+            for (px, (start, weights)) in out.chunks_exact_mut(4).zip(&columns) {
+                for (k, weight) in weights.iter().enumerate() {
+                    let sx = edge_index(edge, start + k as i64, src_w);
+                    for c in 0..4 {
+                        px[c] += row[sx * 4 + c] * weight;
+                    }
+                }
+            }
+        });
+
+    let rows = taps(src_h, dst_h, support, kernel);
+    let mut out = vec![0u8; dst_w * dst_h * 4];
+    out.par_chunks_mut(dst_w * 4)
+        .zip(&rows)
+        .for_each(|(out_row, (start, weights))| {
+            let mut sums = vec![0f32; dst_w * 4];
+            for (k, weight) in weights.iter().enumerate() {
+                let sy = edge_index(edge, start + k as i64, src_h);
+                let row = &wide[sy * dst_w * 4..(sy + 1) * dst_w * 4];
+                for (sum, v) in sums.iter_mut().zip(row) {
+                    *sum += v * weight;
+                }
+            }
+            for (px, sum) in out_row.chunks_exact_mut(4).zip(sums.chunks_exact(4)) {
+                let alpha = sum[3].clamp(0.0, 255.0);
+                if alpha <= 0.0 {
+                    continue;
+                }
+                let a = alpha / 255.0;
+                for c in 0..3 {
+                    px[c] = (sum[c] / a).round().clamp(0.0, 255.0) as u8;
+                }
+                px[3] = alpha.round() as u8;
+            }
+        });
+    RgbaImage::from_raw(width, height, out).expect("buffer matches dimensions")
+}
@@ -0,0 +1,118 @@
+use crate::analysis::histogram;
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::models::WhiteBalanceMethod;
+
+// Neutral temperature: an illuminant at this many Kelvin needs no correction.
+const NEUTRAL_KELVIN: f32 = 6500.0;
+// Full tint (±100) moves green by this fraction.
+const TINT_RANGE: f32 = 0.3;
+// White-patch takes the brightest tone below this fraction of each channel,
+// so specular highlights and hot pixels don't decide it.
+const WHITE_PATCH_PERCENTILE: f64 = 0.99;
+// Corrections beyond this per channel mean the estimate has failed, e.g. on
+// a frame that really is all one colour.
+const MAX_GAIN: f32 = 4.0;
+
+// The linear RGB of a black body at `kelvin`, after Tanner Helland's fit to
+// the CIE data, with green at 1.
+fn blackbody(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    let [r, g, b] = [red, green, blue].map(|v| srgb_to_linear(v.clamp(1.0, 255.0) as u8));
+    [r / g, 1.0, b / g]
+}
+
+// Channel gains that neutralize light of `temperature` Kelvin, so a low
+// value cools a tungsten-lit shot and a high one warms a shaded one. `tint`
+// from -100 to 100 shifts from green towards magenta.
+pub fn temperature_gains(temperature: f32, tint: f32) -> [f32; 3] {
+    let neutral = blackbody(NEUTRAL_KELVIN);
+    let light = blackbody(temperature);
+    let mut gains: [f32; 3] = std::array::from_fn(|c| neutral[c] / light[c]);
+    gains[1] *= 1.0 - TINT_RANGE * tint.clamp(-100.0, 100.0) / 100.0;
+    normalized(gains)
+}
+
+// Scales gains so mid-grey keeps its luminance.
+fn normalized(gains: [f32; 3]) -> [f32; 3] {
+    let luminance = 0.2126 * gains[0] + 0.7152 * gains[1] + 0.0722 * gains[2];
+    gains.map(|g| (g / luminance).clamp(1.0 / MAX_GAIN, MAX_GAIN))
+}
+
+// Multiplies each channel in linear light.
+pub fn apply_gains(data: &mut [u8], gains: [f32; 3]) {
+    let luts: [[u8; 256]; 3] = std::array::from_fn(|c| {
+        std::array::from_fn(|v| linear_to_srgb(srgb_to_linear(v as u8) * gains[c]))
+    });
+    for chunk in data.chunks_exact_mut(4) {
+        for c in 0..3 {
+            chunk[c] = luts[c][chunk[c] as usize];
+        }
+    }
+}
+
+pub fn adjust_white_balance(data: &mut [u8], temperature: f32, tint: f32) {
+    apply_gains(data, temperature_gains(temperature, tint));
+}
+
+// Estimates gains from the channel histograms. Grey-world assumes the scene
+// averages to grey; white-patch that its brightest tones are white, which
+// suits scenes dominated by one colour as long as something is near white.
+pub fn estimate_gains(data: &[u8], method: WhiteBalanceMethod) -> [f32; 3] {
+    let histogram = histogram(data);
+    let channels = [&histogram.red, &histogram.green, &histogram.blue];
+    let levels: [f32; 3] = channels.map(|counts| {
+        let total: u64 = counts.iter().map(|&c| c as u64).sum();
+        if total == 0 {
+            return 1.0;
+        }
+        match method {
+            WhiteBalanceMethod::GrayWorld => {
+                let sum: f64 = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(v, &c)| srgb_to_linear(v as u8) as f64 * c as f64)
+                    .sum();
+                (sum / total as f64) as f32
+            }
+            WhiteBalanceMethod::WhitePatch => {
+                let target = (total as f64 * WHITE_PATCH_PERCENTILE).ceil() as u64;
+                let mut cumulative = 0u64;
+                let level = counts
+                    .iter()
+                    .position(|&c| {
+                        cumulative += c as u64;
+                        cumulative >= target
+                    })
+                    .unwrap_or(255);
+                srgb_to_linear(level as u8)
+            }
+        }
+    });
+    if levels.iter().any(|&l| l <= 0.0) {
+        return [1.0; 3];
+    }
+    normalized(levels.map(|l| levels[1] / l))
+}
+
+pub fn auto_white_balance(data: &mut [u8], method: WhiteBalanceMethod) -> [f32; 3] {
+    let gains = estimate_gains(data, method);
+    apply_gains(data, gains);
+    gains
+}
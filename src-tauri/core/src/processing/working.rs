@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use crate::analysis::PARALLEL_CHUNK;
+use crate::color::{decode_srgb, encode_srgb, hsl_to_rgb_f32, rgb_to_hsl_f32};
+use crate::error::AppError;
+use crate::models::{ChannelLevels, CurveChannel, Edit, Operation, Precision};
+use crate::processing::lut::{levels_value, tone_curve};
+use crate::processing::{self, edits, tone, white_balance};
+
+// An image held as 0-1 floats, so a chain of tone and colour adjustments
+// rounds once at the end instead of after every step. Spatial edits still
+// run on 8-bit pixels; see `apply_edit`.
+#[derive(Clone)]
+pub struct WorkingImage {
+    width: u32,
+    height: u32,
+    // RGBA, row by row.
+    data: Vec<f32>,
+}
+
+impl WorkingImage {
+    pub fn from_rgba(img: &RgbaImage) -> Self {
+        Self {
+            width: img.width(),
+            height: img.height(),
+            data: img.as_raw().iter().map(|&v| v as f32 / 255.0).collect(),
+        }
+    }
+
+    pub fn to_rgba(&self) -> RgbaImage {
+        let data = self
+            .data
+            .par_iter()
+            .map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8)
+            .collect();
+        RgbaImage::from_raw(self.width, self.height, data).expect("buffer matches dimensions")
+    }
+
+    pub fn byte_size(&self) -> u64 {
+        (self.data.len() * std::mem::size_of::<f32>()) as u64
+    }
+
+    fn for_each_pixel(&mut self, adjust: impl Fn(&mut [f32]) + Sync) {
+        self.data
+            .par_chunks_mut(PARALLEL_CHUNK * 4)
+            .for_each(|chunk| chunk.chunks_exact_mut(4).for_each(&adjust));
+    }
+
+    fn apply_gains(&mut self, gains: [f32; 3]) {
+        self.for_each_pixel(|px| {
+            for c in 0..3 {
+                px[c] = encode_srgb(decode_srgb(px[c]) * gains[c]);
+            }
+        });
+    }
+
+    // Runs `edit` the way `edits::apply_edit` does. Those `supports_edit`
+    // accepts stay in floats; any other is applied to the image rounded to
+    // 8 bits, which it then continues from.
+    pub fn apply_edit(&mut self, edit: &Edit, source: Option<&Path>) -> Result<(), AppError> {
+        match edit {
+            Edit::Operation { operation } => self.apply_operation(operation),
+            Edit::Levels { levels } => {
+                tone::validate_levels(levels)?;
+                self.apply_levels(levels);
+            }
+            Edit::Curve { points, channel } => {
+                tone::validate_curve(points)?;
+                self.apply_curve(points, *channel);
+            }
+            Edit::AutoWhiteBalance { method } => {
+                let gains = white_balance::estimate_gains(&self.to_rgba(), *method);
+                self.apply_gains(gains);
+            }
+            _ => {
+                let mut img = self.to_rgba();
+                edits::apply_edit(&mut img, edit, source)?;
+                *self = Self::from_rgba(&img);
+            }
+        }
+        Ok(())
+    }
+
+    // As `processing::apply_operation`. Blur, smoothing, sharpening and
+    // denoising run on the image rounded to 8 bits.
+    pub fn apply_operation(&mut self, operation: &Operation) {
+        match *operation {
+            Operation::Brightness { amount } => self.for_each_pixel(|px| {
+                for v in &mut px[..3] {
+                    *v = (*v * amount).clamp(0.0, 1.0);
+                }
+            }),
+            Operation::Contrast { amount } => self.for_each_pixel(|px| {
+                // The 8-bit path pivots on level 128.
+                let center = 128.0 / 255.0;
+                for v in &mut px[..3] {
+                    *v = ((*v - center) * amount + center).clamp(0.0, 1.0);
+                }
+            }),
+            Operation::Grayscale => self.for_each_pixel(|px| {
+                let gray = 0.299 * px[0] + 0.587 * px[1] + 0.114 * px[2];
+                px[..3].fill(gray);
+            }),
+            Operation::Hsl {
+                hue_shift,
+                saturation,
+                lightness,
+            } => {
+                let lightness = lightness.clamp(-1.0, 1.0);
+                self.for_each_pixel(|px| {
+                    let [h, s, l] = rgb_to_hsl_f32(px[0], px[1], px[2]);
+                    let l = if lightness >= 0.0 {
+                        l + (1.0 - l) * lightness
+                    } else {
+                        l * (1.0 + lightness)
+                    };
+                    let rgb = hsl_to_rgb_f32(h + hue_shift, s * saturation.max(0.0), l);
+                    px[..3].copy_from_slice(&rgb);
+                });
+            }
+            Operation::WhiteBalance { temperature, tint } => {
+                self.apply_gains(white_balance::temperature_gains(temperature, tint))
+            }
+            Operation::Denoise { .. }
+            | Operation::Blur { .. }
+            | Operation::SmoothPreserveEdges { .. }
+            | Operation::Sharpen { .. } => {
+                let mut img = self.to_rgba();
+                processing::apply_operation(&mut img, operation);
+                *self = Self::from_rgba(&img);
+            }
+        }
+    }
+
+    fn apply_levels(&mut self, levels: &ChannelLevels) {
+        let channels = [levels.red, levels.green, levels.blue];
+        self.for_each_pixel(|px| {
+            for c in 0..3 {
+                let master = levels_value(levels.master, px[c] * 255.0);
+                px[c] = levels_value(channels[c], master) / 255.0;
+            }
+        });
+    }
+
+    fn apply_curve(&mut self, points: &[[f32; 2]], channel: CurveChannel) {
+        let curve = tone_curve(points);
+        let channels = match channel {
+            CurveChannel::Rgb => 0..3,
+            CurveChannel::Red => 0..1,
+            CurveChannel::Green => 1..2,
+            CurveChannel::Blue => 2..3,
+        };
+        self.for_each_pixel(|px| {
+            for v in &mut px[channels.clone()] {
+                *v = (curve(*v * 255.0) / 255.0).clamp(0.0, 1.0);
+            }
+        });
+    }
+}
+
+fn supports_operation(operation: &Operation) -> bool {
+    !matches!(
+        operation,
+        Operation::Denoise { .. }
+            | Operation::Blur { .. }
+            | Operation::SmoothPreserveEdges { .. }
+            | Operation::Sharpen { .. }
+    )
+}
+
+// Whether `WorkingImage::apply_edit` keeps `edit` in floats. These are the
+// per-pixel tone and colour adjustments, where rounding between steps is
+// what causes banding.
+pub fn supports_edit(edit: &Edit) -> bool {
+    match edit {
+        Edit::Operation { operation } => supports_operation(operation),
+        Edit::Levels { .. } | Edit::Curve { .. } | Edit::AutoWhiteBalance { .. } => true,
+        _ => false,
+    }
+}
+
+// Redoes `steps` on `img` in order. At `Precision::F32` they run in floats and
+// are rounded to 8 bits once, after the last.
+pub fn apply_edits<'a>(
+    img: &mut RgbaImage,
+    steps: impl IntoIterator<Item = &'a Edit>,
+    source: Option<&Path>,
+    precision: Precision,
+) -> Result<(), AppError> {
+    match precision {
+        Precision::U8 => {
+            for edit in steps {
+                edits::apply_edit(img, edit, source)?;
+            }
+        }
+        Precision::F32 => {
+            let mut working = WorkingImage::from_rgba(img);
+            for edit in steps {
+                working.apply_edit(edit, source)?;
+            }
+            *img = working.to_rgba();
+        }
+    }
+    Ok(())
+}
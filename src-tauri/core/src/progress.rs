@@ -0,0 +1,62 @@
+//! Progress reporting and cancellation for work that can take a while on
+//! large images. Callers that don't need either pass `&()`.
+
+use crate::error::AppError;
+
+// Bands processed between reports by `for_each_band`.
+const BANDS: usize = 32;
+
+pub trait Progress: Sync {
+    // `fraction` of the work, from 0 to 1, is done.
+    fn report(&self, fraction: f32);
+
+    fn is_cancelled(&self) -> bool;
+
+    // Stops the work with `AppError::Cancelled` once cancelled.
+    fn check(&self) -> Result<(), AppError> {
+        if self.is_cancelled() {
+            Err(AppError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Progress for () {
+    fn report(&self, _fraction: f32) {}
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl<P: Progress> Progress for Option<P> {
+    fn report(&self, fraction: f32) {
+        if let Some(progress) = self {
+            progress.report(fraction);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.as_ref().is_some_and(Progress::is_cancelled)
+    }
+}
+
+// Runs a per-pixel adjustment over an RGBA buffer a band at a time,
+// reporting after each and stopping between bands if cancelled. Pixels
+// already adjusted when that happens stay adjusted.
+pub fn for_each_band(
+    data: &mut [u8],
+    progress: &dyn Progress,
+    adjust: impl Fn(&mut [u8]),
+) -> Result<(), AppError> {
+    let pixels = data.len() / 4;
+    let band = pixels.div_ceil(BANDS).max(1) * 4;
+    let bands = data.len().div_ceil(band);
+    for (i, chunk) in data.chunks_mut(band).enumerate() {
+        progress.check()?;
+        adjust(chunk);
+        progress.report((i + 1) as f32 / bands as f32);
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Edit stacks stored as sidecars next to the originals, so a whole folder
+//! can be re-rendered after tweaking one parameter.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::batch::output_paths;
+use crate::decode::{decode_file, DecodedImage};
+use crate::encode::write_image;
+use crate::error::AppError;
+use crate::library::list_images;
+use crate::models::{Edit, ExportResult, Recipe, RecipeExportReport, SkippedFile};
+use crate::processing::edits::apply_edit;
+
+pub const RECIPE_VERSION: u32 = 1;
+const RECIPE_SUFFIX: &str = ".recipe.json";
+
+// `photo.jpg` keeps its recipe in `photo.jpg.recipe.json`.
+pub fn recipe_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(RECIPE_SUFFIX);
+    PathBuf::from(path)
+}
+
+pub fn save_recipe(image: &Path, edits: Vec<Edit>) -> Result<PathBuf, AppError> {
+    let recipe = Recipe {
+        version: RECIPE_VERSION,
+        source: image
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        edits,
+    };
+    let path = recipe_path(image);
+    let json = serde_json::to_string_pretty(&recipe)
+        .map_err(|e| format!("Failed to write recipe: {}", e))?;
+    fs::write(&path, json).map_err(|e| AppError::io(&path, e, true))?;
+    Ok(path)
+}
+
+pub fn load_recipe(image: &Path) -> Result<Recipe, AppError> {
+    let path = recipe_path(image);
+    let json = fs::read_to_string(&path).map_err(|e| AppError::io(&path, e, false))?;
+    let recipe: Recipe = serde_json::from_str(&json).map_err(|e| {
+        AppError::invalid(format!("{} is not a valid recipe: {}", path.display(), e))
+    })?;
+    if recipe.version > RECIPE_VERSION {
+        return Err(AppError::invalid(format!(
+            "{} was written by a newer version (recipe version {})",
+            path.display(),
+            recipe.version
+        )));
+    }
+    Ok(recipe)
+}
+
+// Decodes the original and redoes its recipe on it.
+pub fn render(image: &Path, recipe: &Recipe) -> Result<DecodedImage, AppError> {
+    let mut decoded = decode_file(image, false)?;
+    for edit in &recipe.edits {
+        apply_edit(&mut decoded.image, edit, Some(image))?;
+    }
+    Ok(decoded)
+}
+
+fn reexport(image: &Path, output: &Path) -> Result<ExportResult, AppError> {
+    let recipe = load_recipe(image)?;
+
+    let rendered = render(image, &recipe)?;
+    let output = output.to_string_lossy().into_owned();
+    let file_size = write_image(&rendered.image, rendered.source.as_ref(), &output)?;
+    Ok(ExportResult {
+        output,
+        width: rendered.image.width(),
+        height: rendered.image.height(),
+        file_size,
+    })
+}
+
+// Re-renders every image in `dir` that has a recipe into `output_dir`, under
+// the same name with `format` (or the original's) as the extension. Images
+// without a recipe are left out; ones whose names clash are skipped.
+pub fn reexport_with_recipes(
+    dir: &str,
+    output_dir: &Path,
+    format: Option<&str>,
+) -> Result<RecipeExportReport, AppError> {
+    let images: Vec<PathBuf> = list_images(dir)?
+        .into_iter()
+        .filter(|image| recipe_path(image).is_file())
+        .collect();
+    fs::create_dir_all(output_dir).map_err(|e| AppError::io(output_dir, e, true))?;
+
+    let outputs = output_paths(&images, output_dir, format);
+    let results: Vec<(PathBuf, Result<ExportResult, AppError>)> = images
+        .into_par_iter()
+        .zip(outputs)
+        .map(|(image, output)| {
+            let result = output.and_then(|output| reexport(&image, &output));
+            (image, result)
+        })
+        .collect();
+
+    let mut report = RecipeExportReport {
+        exported: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (image, result) in results {
+        match result {
+            Ok(exported) => report.exported.push(exported),
+            Err(e) => report.skipped.push(SkippedFile {
+                path: image.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,110 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use arboard::Clipboard;
+use image::RgbaImage;
+use tauri::{AppHandle, Emitter, Manager};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::ClipboardImage;
+
+use crate::store::ImageStore;
+
+// Clipboards don't announce changes portably, so the watcher polls.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Watch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct ClipboardWatcher {
+    watch: Mutex<Option<Watch>>,
+}
+
+pub fn read_image(clipboard: &mut Clipboard) -> Option<RgbaImage> {
+    let data = clipboard.get_image().ok()?;
+    RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+}
+
+// Loads whatever image is on the clipboard right now into the store.
+pub fn load_clipboard_image(store: &ImageStore) -> Result<ClipboardImage, AppError> {
+    let mut clipboard = open_clipboard()?;
+    let image = read_image(&mut clipboard).ok_or(AppError::ClipboardEmpty)?;
+    Ok(ClipboardImage {
+        handle: store.insert(None, image),
+    })
+}
+
+fn open_clipboard() -> Result<Clipboard, AppError> {
+    Clipboard::new().map_err(|e| AppError::ClipboardUnavailable {
+        reason: e.to_string(),
+    })
+}
+
+fn fingerprint(img: &RgbaImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    img.dimensions().hash(&mut hasher);
+    img.as_raw().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ClipboardWatcher {
+    // Each newly copied image is loaded into the store and announced with a
+    // `clipboard-image` event. Whatever is already on the clipboard when the
+    // watch starts is ignored.
+    pub fn start(&self, app: AppHandle) -> Result<(), AppError> {
+        let mut watch = self.watch.lock().unwrap();
+        if watch.is_some() {
+            return Ok(());
+        }
+
+        let mut clipboard = open_clipboard()?;
+        let mut last_seen = read_image(&mut clipboard).as_ref().map(fingerprint);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("clipboard-watcher".to_string())
+            .spawn(move || {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL);
+                    let Some(image) = read_image(&mut clipboard) else {
+                        continue;
+                    };
+                    let seen = fingerprint(&image);
+                    if last_seen == Some(seen) {
+                        continue;
+                    }
+                    last_seen = Some(seen);
+
+                    let handle = app.state::<ImageStore>().insert(None, image);
+                    let _ = app.emit("clipboard-image", ClipboardImage { handle });
+                }
+            })
+            .map_err(|e| format!("Failed to start clipboard watcher: {}", e))?;
+
+        *watch = Some(Watch { stop, thread });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(watch) = self.watch.lock().unwrap().take() {
+            watch.stop.store(true, Ordering::Relaxed);
+            let _ = watch.thread.join();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.watch.lock().unwrap().is_some()
+    }
+}
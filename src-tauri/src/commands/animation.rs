@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use image::RgbaImage;
+use tauri::State;
+
+use image_analyzer_core::decode::decode_file;
+use image_analyzer_core::encode::animation::write_animation;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{AnimationFormat, AnimationResult, FrameSource, PerfBackend};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+fn resolve_frame(store: &ImageStore, source: &FrameSource) -> Result<RgbaImage, AppError> {
+    match source {
+        FrameSource::Handle(id) => store.with_image(*id, |stored| Ok((*stored.current).clone())),
+        FrameSource::Path(path) => decode_file(Path::new(path), false).map(|d| d.image),
+    }
+}
+
+#[tauri::command]
+pub async fn create_animation(
+    store: State<'_, ImageStore>,
+    sources: Vec<FrameSource>,
+    delays: Vec<u32>,
+    loop_count: u32,
+    format: AnimationFormat,
+    output: String,
+) -> Result<AnimationResult, AppError> {
+    if sources.is_empty() {
+        return Err(AppError::invalid("No frames to animate"));
+    }
+    let timer = OperationTimer::start("create_animation", PerfBackend::Cpu);
+
+    let frames = sources
+        .iter()
+        .map(|source| resolve_frame(&store, source))
+        .collect::<Result<Vec<_>, _>>()?;
+    let frame_count = frames.len();
+    let (width, height) = frames[0].dimensions();
+
+    let file_size = write_animation(frames, &delays, loop_count, format, &output)?;
+    timer.finish(width, height);
+
+    Ok(AnimationResult {
+        output,
+        frame_count,
+        width,
+        height,
+        file_size,
+    })
+}
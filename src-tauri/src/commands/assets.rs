@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use tauri::State;
+
+use image_analyzer_core::encode::{ascii, icons, slices};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    AsciiCharset, AsciiColorMode, ExportResult, IconTarget, ImageId, PerfBackend, SliceLayout,
+    SliceResult,
+};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn export_icon_set(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    targets: Vec<IconTarget>,
+    output_dir: String,
+    name: String,
+    padding: Option<f32>,
+) -> Result<Vec<ExportResult>, AppError> {
+    let timer = OperationTimer::start("export_icon_set", PerfBackend::Cpu);
+    let image = store.with_image(id, |stored| Ok(stored.current.clone()))?;
+    let results = icons::export_icon_set(
+        &image,
+        &targets,
+        Path::new(&output_dir),
+        &name,
+        padding.unwrap_or(0.0),
+    )?;
+    timer.finish(image.width(), image.height());
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn slice_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    layout: SliceLayout,
+    output_dir: String,
+    name: String,
+) -> Result<SliceResult, AppError> {
+    let timer = OperationTimer::start("slice_image", PerfBackend::Cpu);
+    let image = store.with_image(id, |stored| Ok(stored.current.clone()))?;
+    let result = slices::slice_image(&image, &layout, Path::new(&output_dir), &name)?;
+    timer.finish(image.width(), image.height());
+    Ok(result)
+}
+
+// Returns the text rather than writing it, so it can be copied or saved.
+#[tauri::command]
+pub async fn export_ascii(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    columns: u32,
+    charset: AsciiCharset,
+    color_mode: AsciiColorMode,
+) -> Result<String, AppError> {
+    let timer = OperationTimer::start("export_ascii", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let text = ascii::export_ascii(&stored.current, columns, &charset, color_mode)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(text)
+    })
+}
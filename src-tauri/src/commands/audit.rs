@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, State};
+
+use image_analyzer_core::audit;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{ImageId, PerfBackend};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+const SIGNING_KEY_FILE: &str = "audit.key";
+
+// The installation's audit signing key, created on first use. Losing it only
+// means older audit files can no longer be verified here.
+fn signing_key(app: &AppHandle) -> Result<[u8; 32], AppError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to locate the app data folder: {}", e))?;
+    let path = dir.join(SIGNING_KEY_FILE);
+    if let Ok(existing) = fs::read(&path) {
+        return existing
+            .try_into()
+            .map_err(|_| AppError::invalid("The audit signing key is corrupt"));
+    }
+    let mut key = [0u8; 32];
+    getrandom::getrandom(&mut key)
+        .map_err(|e| format!("Failed to generate a signing key: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| AppError::io(&dir, e, true))?;
+    fs::write(&path, key).map_err(|e| AppError::io(&path, e, true))?;
+    Ok(key)
+}
+
+// Writes a signed record of the source hash, every edit with its parameters
+// and the result's hashes, as CSV for a `.csv` path and JSON otherwise.
+// `output_path` is the exported file to vouch for, if there is one.
+#[tauri::command]
+pub async fn export_audit(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    path: String,
+    output_path: Option<String>,
+) -> Result<(), AppError> {
+    let key = signing_key(&app)?;
+    let timer = OperationTimer::start("export_audit", PerfBackend::Cpu);
+    let record = store.with_image(id, |stored| {
+        let record = audit::build_audit(
+            stored.operation_log(id),
+            &stored.current,
+            output_path.as_deref().map(Path::new),
+        )?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(record)
+    })?;
+    audit::write_audit(record, &key, Path::new(&path))
+}
+
+// Whether an audit file exported by this installation is unaltered.
+#[tauri::command]
+pub async fn verify_audit(app: AppHandle, path: String) -> Result<bool, AppError> {
+    let key = signing_key(&app)?;
+    audit::verify_audit(Path::new(&path), &key)
+}
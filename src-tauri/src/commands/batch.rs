@@ -0,0 +1,35 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, State};
+
+use image_analyzer_core::batch;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{BatchReport, Edit};
+
+use crate::jobs::WorkerPool;
+
+// Applies `operations` in order to every image in `input_dir` and writes the
+// results to `output_dir`, as `format` (an extension such as "jpg") or each
+// original's format. A "batch-progress" event follows each image; images that
+// fail are skipped and listed rather than stopping the batch.
+#[tauri::command]
+pub async fn batch_process(
+    app: AppHandle,
+    workers: State<'_, WorkerPool>,
+    input_dir: String,
+    output_dir: String,
+    operations: Vec<Edit>,
+    format: Option<String>,
+) -> Result<BatchReport, AppError> {
+    workers.install(|| {
+        batch::batch_process(
+            &input_dir,
+            Path::new(&output_dir),
+            &operations,
+            format.as_deref(),
+            |progress| {
+                let _ = app.emit("batch-progress", progress);
+            },
+        )
+    })
+}
@@ -0,0 +1,111 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    BorderSpec, CanvasFill, Edit, ImageData, ImageHandle, ImageId, Margins, MaskShape, PerfBackend,
+    TrimResult,
+};
+use image_analyzer_core::processing::{canvas, frame};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// `tolerance` is the largest per-channel difference from the border colour
+// still treated as border; a few levels absorbs scanner and JPEG noise.
+#[tauri::command]
+pub async fn auto_trim(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    tolerance: Option<u8>,
+) -> Result<TrimResult, AppError> {
+    let timer = OperationTimer::start("auto_trim", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        let tolerance = tolerance.unwrap_or(0);
+        let margins = canvas::auto_trim(stored.current_mut(), tolerance)?;
+        stored.record(Edit::AutoTrim { tolerance });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(TrimResult {
+            handle: stored.handle(id),
+            margins,
+        })
+    })
+}
+
+#[tauri::command]
+pub async fn extend_canvas(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    left: u32,
+    fill: CanvasFill,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("extend_canvas", PerfBackend::Cpu);
+    let margins = Margins {
+        top,
+        right,
+        bottom,
+        left,
+    };
+    store.with_image_mut(id, |stored| {
+        stored.current = canvas::extend_canvas(&stored.current, margins, fill)?.into();
+        stored.record(Edit::ExtendCanvas { margins, fill });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn add_border(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    spec: BorderSpec,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("add_border", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.current = frame::add_border(&stored.current, spec)?.into();
+        stored.record(Edit::AddBorder { spec });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn apply_shape_mask(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    shape: MaskShape,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("apply_shape_mask", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        frame::apply_shape_mask(stored.current_mut(), shape)?;
+        stored.record(Edit::ApplyShapeMask { shape });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// `x` and `y` may be negative and the rectangle may run past the image; only
+// the overlapping part is kept. No overlap at all is an error.
+#[tauri::command]
+pub async fn crop_image(
+    image_data: ImageData,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("crop_image", PerfBackend::Cpu);
+    let img = image::RgbaImage::from_raw(image_data.width, image_data.height, image_data.data)
+        .ok_or_else(|| AppError::invalid("Failed to create image from data"))?;
+    let cropped = canvas::crop(&img, x, y, width, height)?;
+    timer.finish(cropped.width(), cropped.height());
+
+    Ok(ImageData {
+        width: cropped.width(),
+        height: cropped.height(),
+        data: cropped.into_raw(),
+        ..image_data
+    })
+}
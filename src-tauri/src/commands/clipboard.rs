@@ -0,0 +1,38 @@
+use tauri::{AppHandle, State};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::ClipboardImage;
+
+use crate::clipboard::{self, ClipboardWatcher};
+use crate::store::ImageStore;
+use crate::tray;
+
+#[tauri::command]
+pub async fn load_clipboard_image(
+    store: State<'_, ImageStore>,
+) -> Result<ClipboardImage, AppError> {
+    clipboard::load_clipboard_image(&store)
+}
+
+// Listen for `clipboard-image` events while the watch is on.
+#[tauri::command]
+pub async fn set_clipboard_watch(
+    app: AppHandle,
+    watcher: State<'_, ClipboardWatcher>,
+    enabled: bool,
+) -> Result<bool, AppError> {
+    if enabled {
+        watcher.start(app.clone())?;
+    } else {
+        watcher.stop();
+    }
+    tray::sync_clipboard_watch(&app);
+    Ok(watcher.is_running())
+}
+
+#[tauri::command]
+pub async fn is_clipboard_watch_enabled(
+    watcher: State<'_, ClipboardWatcher>,
+) -> Result<bool, AppError> {
+    Ok(watcher.is_running())
+}
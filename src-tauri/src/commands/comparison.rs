@@ -0,0 +1,124 @@
+use tauri::State;
+
+use image_analyzer_core::analysis::similarity;
+use image_analyzer_core::encode::{png_data_url, write_image};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    ColorVisionDeficiency, ComparisonLayout, ExportResult, ImageComparison, ImageData, ImageId,
+    ImagePreview, PerfBackend, SplitOrientation,
+};
+use image_analyzer_core::processing::color_vision;
+use image_analyzer_core::processing::comparison::{
+    aligned_original, compose_comparison, split_preview,
+};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn export_comparison(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    layout: ComparisonLayout,
+    output: String,
+) -> Result<ExportResult, AppError> {
+    let timer = OperationTimer::start("export_comparison", PerfBackend::Cpu);
+    let canvas = store.with_image(id, |stored| {
+        Ok(compose_comparison(
+            &stored.original,
+            &stored.current,
+            &layout,
+        ))
+    })?;
+
+    let file_size = write_image(&canvas, None, &output)?;
+    timer.finish(canvas.width(), canvas.height());
+
+    Ok(ExportResult {
+        output,
+        width: canvas.width(),
+        height: canvas.height(),
+        file_size,
+    })
+}
+
+// The original and the edited image either side of a split at
+// `split_position`, a fraction across the image, as a PNG for display like
+// `get_preview`. The original has the image's crops, rotations and resizes
+// applied so the halves line up; the viewer draws the split line.
+#[tauri::command]
+pub async fn get_split_preview(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    split_position: f32,
+    orientation: SplitOrientation,
+    max_dimension: Option<u32>,
+) -> Result<ImagePreview, AppError> {
+    let timer = OperationTimer::start("get_split_preview", PerfBackend::Cpu);
+    let preview = store.with_image(id, |stored| {
+        let log = stored.operation_log(id);
+        let before = aligned_original(
+            &stored.original,
+            log.entries[log.baseline..].iter().map(|entry| &entry.edit),
+            stored.source_path.as_deref(),
+        )?;
+        split_preview(
+            &before,
+            &stored.current,
+            split_position,
+            orientation,
+            max_dimension,
+        )
+    })?;
+    timer.finish(preview.width(), preview.height());
+
+    Ok(ImagePreview {
+        width: preview.width(),
+        height: preview.height(),
+        data_url: png_data_url(&preview)?,
+    })
+}
+
+// Difference statistics, PSNR and SSIM between two open images of the same
+// size, with a heatmap of where they differ when `heatmap` is set.
+#[tauri::command]
+pub async fn compare_images(
+    store: State<'_, ImageStore>,
+    a: ImageId,
+    b: ImageId,
+    heatmap: Option<bool>,
+) -> Result<ImageComparison, AppError> {
+    let timer = OperationTimer::start("compare_images", PerfBackend::Rayon);
+    let first = store.with_image(a, |stored| Ok(stored.current.clone()))?;
+    let second = store.with_image(b, |stored| Ok(stored.current.clone()))?;
+    let comparison = similarity::compare_images(&first, &second, heatmap.unwrap_or(false))?;
+    timer.finish(first.width(), first.height());
+    Ok(comparison)
+}
+
+// The image as seen with `deficiency`, for checking graphics are readable
+// without full colour vision. Returned as a separate image; the stored one
+// is left alone.
+#[tauri::command]
+pub async fn simulate_color_vision(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    deficiency: ColorVisionDeficiency,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("simulate_color_vision", PerfBackend::Rayon);
+    let simulated = store.with_image(id, |stored| {
+        Ok(color_vision::simulate_color_vision(
+            &stored.current,
+            deficiency,
+        ))
+    })?;
+    timer.finish(simulated.width(), simulated.height());
+    Ok(ImageData {
+        width: simulated.width(),
+        height: simulated.height(),
+        data: simulated.into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
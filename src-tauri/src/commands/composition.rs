@@ -0,0 +1,91 @@
+use tauri::State;
+
+use image_analyzer_core::analysis::{balance, composition, horizon};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    BalanceAnalysis, CompositionAnalysis, CropSuggestion, HorizonAnalysis, ImageData, ImageId,
+    OverlaySpec, PerfBackend,
+};
+use image_analyzer_core::processing::overlay;
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn analyze_composition(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<CompositionAnalysis, AppError> {
+    let timer = OperationTimer::start("analyze_composition", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let analysis = composition::analyze_composition(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(analysis)
+    })
+}
+
+// `rotation` in the result is the counter-clockwise turn that levels the image.
+// `count` caps the suggestions per aspect ratio, 3 by default.
+#[tauri::command]
+pub async fn suggest_crops(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    aspect_ratios: Vec<f32>,
+    count: Option<usize>,
+) -> Result<Vec<CropSuggestion>, AppError> {
+    let timer = OperationTimer::start("suggest_crops", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let suggestions =
+            composition::suggest_crops(&stored.current, &aspect_ratios, count.unwrap_or(3))?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(suggestions)
+    })
+}
+
+#[tauri::command]
+pub async fn detect_horizon(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<HorizonAnalysis, AppError> {
+    let timer = OperationTimer::start("detect_horizon", PerfBackend::Rayon);
+    store.with_image(id, |stored| {
+        let analysis = horizon::detect_horizon(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(analysis)
+    })
+}
+
+#[tauri::command]
+pub async fn analyze_balance(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<BalanceAnalysis, AppError> {
+    let timer = OperationTimer::start("analyze_balance", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let analysis = balance::analyze_balance(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(analysis)
+    })
+}
+
+// The overlay is a separate transparent layer for the viewer to draw over the
+// image; the image itself is left alone.
+#[tauri::command]
+pub async fn render_grid_overlay(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    spec: OverlaySpec,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("render_grid_overlay", PerfBackend::Cpu);
+    let (width, height) = store.with_image(id, |stored| Ok(stored.current.dimensions()))?;
+    let layer = overlay::render_grid_overlay(width, height, &spec)?;
+    timer.finish(layer.width(), layer.height());
+    Ok(ImageData {
+        width: layer.width(),
+        height: layer.height(),
+        data: layer.into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
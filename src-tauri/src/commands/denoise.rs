@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use tauri::State;
+
+use image_analyzer_core::analysis::noise;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    Edit, ImageHandle, ImageId, NoiseEstimate, NoiseProfile, PerfBackend,
+};
+use image_analyzer_core::processing::{dark_frame, denoise};
+
+use crate::jobs::WorkerPool;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// The profile goes in a `denoise` operation, so every image in a batch from
+// the same camera and ISO gets the same calibrated noise reduction.
+#[tauri::command]
+pub async fn build_noise_profile(
+    workers: State<'_, WorkerPool>,
+    dark_frame_paths: Vec<String>,
+) -> Result<NoiseProfile, AppError> {
+    let paths: Vec<PathBuf> = dark_frame_paths.into_iter().map(PathBuf::from).collect();
+    workers.install(|| denoise::build_noise_profile(&paths))
+}
+
+// Measures the noise in the image as it is now, for comparing shots taken
+// at different ISOs or checking what a denoise did.
+#[tauri::command]
+pub async fn estimate_noise(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<NoiseEstimate, AppError> {
+    let timer = OperationTimer::start("estimate_noise", PerfBackend::Rayon);
+    store.with_image(id, |stored| {
+        let estimate = noise::estimate_noise(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(estimate)
+    })
+}
+
+// Removes fixed-pattern noise (hot pixels, amp glow) using a dark frame shot
+// at the same exposure, ISO and temperature, typically before stacking.
+#[tauri::command]
+pub async fn subtract_dark_frame(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    dark_frame_path: String,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("subtract_dark_frame", PerfBackend::Rayon);
+    store.with_image_mut(id, |stored| {
+        dark_frame::subtract_dark_frame_file(stored.current_mut(), Path::new(&dark_frame_path))?;
+        stored.record(Edit::SubtractDarkFrame {
+            dark_frame: dark_frame_path,
+        });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
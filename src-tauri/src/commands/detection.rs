@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, State};
+
+use image_analyzer_core::analysis::{edges, faces, grabcut, selection, text_regions};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    Color, EdgeMethod, EdgeOutput, EdgeThresholds, FaceRegion, ImageData, ImageId, PerfBackend,
+    PixelRect, Scribble, TextRegion,
+};
+
+use crate::jobs::Job;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+use super::ANALYSIS_PROGRESS;
+
+const DEFAULT_GRABCUT_ITERATIONS: u32 = 5;
+
+// Models aren't bundled; users drop one here or pass a path.
+pub(super) const TEXT_MODEL_FILE: &str = "models/text-detection.onnx";
+pub(super) const FACE_MODEL_FILE: &str = "models/face-detection.onnx";
+
+const DEFAULT_EDGE_COLOR: Color = Color {
+    r: 0,
+    g: 255,
+    b: 255,
+    a: 255,
+};
+
+pub(super) fn default_model_path(app: &AppHandle, file: &str) -> Result<PathBuf, AppError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to locate the app data folder: {}", e))?;
+    Ok(dir.join(file))
+}
+
+#[tauri::command]
+pub async fn detect_text_regions(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    model_path: Option<String>,
+) -> Result<Vec<TextRegion>, AppError> {
+    let model_path = match model_path {
+        Some(path) => PathBuf::from(path),
+        None => default_model_path(&app, TEXT_MODEL_FILE)?,
+    };
+    let timer = OperationTimer::start("detect_text_regions", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let regions = text_regions::detect_text_regions(&stored.current, &model_path)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(regions)
+    })
+}
+
+// Faces, most confident first, each with a guess at whether its eyes are
+// open.
+#[tauri::command]
+pub async fn detect_faces(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    model_path: Option<String>,
+) -> Result<Vec<FaceRegion>, AppError> {
+    let model_path = match model_path {
+        Some(path) => PathBuf::from(path),
+        None => default_model_path(&app, FACE_MODEL_FILE)?,
+    };
+    let timer = OperationTimer::start("detect_faces", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let faces = faces::detect_faces(&stored.current, &model_path)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(faces)
+    })
+}
+
+// Returns the edges as a separate image, an overlay in `color` (cyan by
+// default) or a black and white mask; the stored image is left alone.
+#[tauri::command]
+pub async fn detect_edges(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    method: Option<EdgeMethod>,
+    thresholds: Option<EdgeThresholds>,
+    output: Option<EdgeOutput>,
+    color: Option<Color>,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("detect_edges", PerfBackend::Rayon);
+    let map = store.with_image(id, |stored| {
+        edges::detect_edges(
+            &stored.current,
+            method.unwrap_or_default(),
+            thresholds.unwrap_or_default(),
+            output.unwrap_or_default(),
+            color.unwrap_or(DEFAULT_EDGE_COLOR),
+        )
+    })?;
+    timer.finish(map.width(), map.height());
+    Ok(ImageData {
+        width: map.width(),
+        height: map.height(),
+        data: map.into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
+
+// A mask of how closely each pixel matches the colours in `sample_region`,
+// from black for no match to white; the stored image is left alone.
+#[tauri::command]
+pub async fn select_by_histogram(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    sample_region: PixelRect,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("select_by_histogram", PerfBackend::Rayon);
+    let mask = store.with_image(id, |stored| {
+        selection::select_by_histogram(&stored.current, sample_region)
+    })?;
+    timer.finish(mask.width(), mask.height());
+    Ok(ImageData {
+        width: mask.width(),
+        height: mask.height(),
+        data: image::DynamicImage::ImageLuma8(mask)
+            .into_rgba8()
+            .into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
+
+// Separates the subject from the background without a model: `rect` bounds
+// the subject and strokes mark what's certainly subject or background.
+// Rerun with more strokes to correct it. Returns a mask, white for the
+// subject; the stored image is left alone.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn grabcut(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    rect: Option<PixelRect>,
+    fg_scribbles: Option<Vec<Scribble>>,
+    bg_scribbles: Option<Vec<Scribble>>,
+    iterations: Option<u32>,
+    job_id: Option<String>,
+) -> Result<ImageData, AppError> {
+    let job = Job::start(&app, job_id, ANALYSIS_PROGRESS)?;
+    let timer = OperationTimer::start("grabcut", PerfBackend::Cpu);
+    let mask = store.with_image(id, |stored| {
+        grabcut::grabcut(
+            &stored.current,
+            rect,
+            fg_scribbles.as_deref().unwrap_or_default(),
+            bg_scribbles.as_deref().unwrap_or_default(),
+            iterations.unwrap_or(DEFAULT_GRABCUT_ITERATIONS),
+            &job,
+        )
+    })?;
+    timer.finish(mask.width(), mask.height());
+    Ok(ImageData {
+        width: mask.width(),
+        height: mask.height(),
+        data: image::DynamicImage::ImageLuma8(mask)
+            .into_rgba8()
+            .into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
@@ -0,0 +1,14 @@
+use image_analyzer_core::error::{self, AppError, ErrorCode, ErrorDescription};
+
+// Catalog text for a command error's `code`. Placeholders in the message are
+// filled from the error's `params` on the frontend.
+#[tauri::command]
+pub async fn describe_error(
+    code: ErrorCode,
+    locale: Option<String>,
+) -> Result<ErrorDescription, AppError> {
+    Ok(error::describe_error(
+        code,
+        locale.as_deref().unwrap_or("en"),
+    ))
+}
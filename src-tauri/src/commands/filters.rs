@@ -0,0 +1,109 @@
+use tauri::{AppHandle, State};
+
+use image::RgbaImage;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{EdgeMode, Edit, ImageHandle, ImageId, Operation, PerfBackend};
+use image_analyzer_core::processing::filters;
+
+use crate::jobs::Job;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Default unsharp-mask blur, in pixels of sigma.
+const DEFAULT_SHARPEN_RADIUS: f32 = 1.0;
+
+use super::FILTER_PROGRESS;
+
+// `run` applies `operation` to the image; a cancelled run leaves it as it
+// was, with nothing recorded.
+fn apply(
+    store: &ImageStore,
+    id: ImageId,
+    name: &'static str,
+    operation: Operation,
+    run: impl FnOnce(&mut RgbaImage) -> Result<(), AppError>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start(name, PerfBackend::Rayon);
+    store.with_image_mut(id, |stored| {
+        run(stored.current_mut())?;
+        stored.record(Edit::Operation { operation });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// Gaussian blur of the colour channels, weighted by alpha so transparent
+// pixels don't bleed into their neighbours; alpha is kept as it is. `edge`
+// is what the kernel reads past the image's sides, clamped by default.
+#[tauri::command]
+pub async fn apply_blur(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    sigma: f32,
+    edge: Option<EdgeMode>,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    filters::check_sigma(sigma)?;
+    let edge = edge.unwrap_or_default();
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    apply(
+        &store,
+        id,
+        "apply_blur",
+        Operation::Blur { sigma, edge },
+        |img| filters::gaussian_blur_tracked(img, sigma, edge, &job),
+    )
+}
+
+// Edge-preserving smoothing for skin and noise: a guided filter over
+// windows `radius` pixels either side. Detail whose variance, on 0-1
+// values, is well under `epsilon` is smoothed; edges are kept.
+#[tauri::command]
+pub async fn smooth_preserve_edges(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    radius: u32,
+    epsilon: f32,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    filters::check_guided(radius, epsilon)?;
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    let operation = Operation::SmoothPreserveEdges { radius, epsilon };
+    apply(&store, id, "smooth_preserve_edges", operation, |img| {
+        filters::guided_filter_tracked(img, radius, epsilon, &job)
+    })
+}
+
+// Unsharp mask. `threshold` is the smallest difference from the blurred
+// image, in levels, that gets sharpened; raising it spares noise and skin.
+// `edge` works as for `apply_blur`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn apply_sharpen(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    amount: f32,
+    threshold: Option<u8>,
+    radius: Option<f32>,
+    edge: Option<EdgeMode>,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let radius = radius.unwrap_or(DEFAULT_SHARPEN_RADIUS);
+    let edge = edge.unwrap_or_default();
+    let threshold = threshold.unwrap_or(0);
+    filters::check_sharpen(amount, radius)?;
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    let operation = Operation::Sharpen {
+        amount,
+        radius,
+        threshold,
+        edge,
+    };
+    apply(&store, id, "apply_sharpen", operation, |img| {
+        filters::unsharp_mask_tracked(img, amount, radius, threshold, edge, &job)
+    })
+}
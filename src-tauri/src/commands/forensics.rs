@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::forensics::{copy_move, ela, hidden, prnu, thumbnail};
+use image_analyzer_core::models::{
+    CopyMoveMatch, HiddenDataReport, ImageData, ImageId, PerfBackend, PrnuFingerprint, PrnuMatch,
+    ThumbnailComparison,
+};
+
+use crate::jobs::WorkerPool;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+const DEFAULT_ELA_QUALITY: u8 = 90;
+
+// Returns the error level map as a separate image; the stored image is left
+// alone. `quality` defaults to 90, close to what most cameras save at.
+#[tauri::command]
+pub async fn error_level_analysis(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    quality: Option<u8>,
+) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("error_level_analysis", PerfBackend::Cpu);
+    let map = store.with_image(id, |stored| {
+        ela::error_level_analysis(&stored.current, quality.unwrap_or(DEFAULT_ELA_QUALITY))
+    })?;
+    timer.finish(map.width(), map.height());
+    Ok(ImageData {
+        width: map.width(),
+        height: map.height(),
+        data: map.into_raw(),
+        format: "rgba".to_string(),
+        recovered: false,
+        source_color_type: None,
+    })
+}
+
+// Regions that appear twice in the image, for review; matching textures such
+// as brickwork can show up too.
+#[tauri::command]
+pub async fn detect_copy_move(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<Vec<CopyMoveMatch>, AppError> {
+    let timer = OperationTimer::start("detect_copy_move", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let matches = copy_move::detect_copy_move(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(matches)
+    })
+}
+
+// Reads the file itself rather than an open image, since what it looks for
+// doesn't survive decoding.
+#[tauri::command]
+pub async fn inspect_hidden_data(path: String) -> Result<HiddenDataReport, AppError> {
+    hidden::inspect_hidden_data(Path::new(&path))
+}
+
+// Compares the file's EXIF thumbnail with its pixels. `None` when the file
+// has no thumbnail.
+#[tauri::command]
+pub async fn compare_exif_thumbnail(path: String) -> Result<Option<ThumbnailComparison>, AppError> {
+    let timer = OperationTimer::start("compare_exif_thumbnail", PerfBackend::Cpu);
+    let comparison = thumbnail::compare_thumbnail(Path::new(&path))?;
+    if let Some(comparison) = &comparison {
+        timer.finish(comparison.thumbnail_width, comparison.thumbnail_height);
+    }
+    Ok(comparison)
+}
+
+// Builds a camera's sensor fingerprint from photos it took and writes it to
+// `output_path`, for `match_prnu`.
+#[tauri::command]
+pub async fn extract_prnu(
+    workers: State<'_, WorkerPool>,
+    paths: Vec<String>,
+    output_path: String,
+) -> Result<PrnuFingerprint, AppError> {
+    let timer = OperationTimer::start("extract_prnu", PerfBackend::Rayon);
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let fingerprint = workers.install(|| prnu::extract_prnu(&paths, Path::new(&output_path)))?;
+    timer.finish(fingerprint.width, fingerprint.height);
+    Ok(fingerprint)
+}
+
+// Whether the open image was taken by the camera the fingerprint at
+// `fingerprint_path` came from. Crops, resizes and rotations since capture
+// hide the pattern, so a miss on an edited photo proves nothing.
+#[tauri::command]
+pub async fn match_prnu(
+    workers: State<'_, WorkerPool>,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    fingerprint_path: String,
+) -> Result<PrnuMatch, AppError> {
+    let timer = OperationTimer::start("match_prnu", PerfBackend::Rayon);
+    store.with_image(id, |stored| {
+        let result =
+            workers.install(|| prnu::match_prnu(&stored.current, Path::new(&fingerprint_path)))?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(result)
+    })
+}
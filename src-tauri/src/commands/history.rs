@@ -0,0 +1,37 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{EditHistory, ImageId, PerfBackend};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Edit history is kept per image in the store, so undoing or redoing only
+// returns the new state; fetch pixels with `get_image_data` as usual.
+#[tauri::command]
+pub async fn undo(store: State<'_, ImageStore>, id: ImageId) -> Result<EditHistory, AppError> {
+    let timer = OperationTimer::start("undo", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.undo()?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.history(id))
+    })
+}
+
+#[tauri::command]
+pub async fn redo(store: State<'_, ImageStore>, id: ImageId) -> Result<EditHistory, AppError> {
+    let timer = OperationTimer::start("redo", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.redo()?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.history(id))
+    })
+}
+
+#[tauri::command]
+pub async fn get_history(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<EditHistory, AppError> {
+    store.with_image(id, |stored| Ok(stored.history(id)))
+}
@@ -0,0 +1,15 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::OpenedImage;
+
+use crate::launch::PendingOpens;
+
+// Images passed at launch (double-clicked files, deep links). Call once the
+// window is listening for `image-opened`.
+#[tauri::command]
+pub async fn take_pending_opens(
+    pending: State<'_, PendingOpens>,
+) -> Result<Vec<OpenedImage>, AppError> {
+    Ok(pending.take())
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::lens;
+use image_analyzer_core::models::{Edit, ImageHandle, ImageId, LensCorrectionReport, PerfBackend};
+use image_analyzer_core::processing::flat_field;
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn auto_lens_correct(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<LensCorrectionReport, AppError> {
+    let timer = OperationTimer::start("auto_lens_correct", PerfBackend::Rayon);
+    store.with_image_mut(id, |stored| {
+        let path = stored
+            .source_path
+            .as_deref()
+            .ok_or_else(|| AppError::invalid("Image has no source file to read lens data from"))?;
+        let corrected = lens::auto_correct(&stored.current, path)?;
+        stored.current = corrected.image.into();
+        stored.record(Edit::AutoLensCorrect);
+        timer.finish(stored.current.width(), stored.current.height());
+
+        Ok(LensCorrectionReport {
+            handle: stored.handle(id),
+            lens: corrected.lens,
+            camera: corrected.camera,
+            focal_length: corrected.focal_length,
+            distortion_corrected: corrected.distortion_corrected,
+            tca_corrected: corrected.tca_corrected,
+            vignetting_corrected: corrected.vignetting_corrected,
+        })
+    })
+}
+
+// Divides out the falloff recorded in a flat frame: a shot of an evenly lit
+// blank target taken on the same scanner or microscope setup.
+#[tauri::command]
+pub async fn flat_field_correct(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    flat_frame_path: String,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("flat_field_correct", PerfBackend::Rayon);
+    store.with_image_mut(id, |stored| {
+        flat_field::flat_field_correct_file(stored.current_mut(), Path::new(&flat_frame_path))?;
+        stored.record(Edit::FlatFieldCorrect {
+            flat_frame: flat_frame_path,
+        });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
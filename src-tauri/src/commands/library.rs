@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State};
+
+use image::DynamicImage;
+
+use image_analyzer_core::analysis::{faces, hashing};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    Burst, CullReport, CullRules, DuplicateFileGroup, ExposureMatchReport, FileHash, HashAlgorithm,
+    ImageId, ImageMetadata, LocationClusterResult, NearDuplicateReport, OrganizeResult,
+    PerceptualHash, PerceptualHashAlgorithm, TransferMode,
+};
+use image_analyzer_core::processing::exposure;
+use image_analyzer_core::{cull, library, metadata};
+
+use crate::jobs::WorkerPool;
+use crate::store::ImageStore;
+
+use super::detection::{default_model_path, FACE_MODEL_FILE};
+
+#[tauri::command]
+pub async fn cluster_by_location(
+    workers: State<'_, WorkerPool>,
+    dir: String,
+    radius_km: f64,
+) -> Result<LocationClusterResult, AppError> {
+    workers.install(|| library::cluster_by_location(&dir, radius_km))
+}
+
+#[tauri::command]
+pub async fn organize_by_date(
+    paths: Vec<String>,
+    destination: String,
+    pattern: Option<String>,
+    mode: TransferMode,
+    dry_run: bool,
+) -> Result<OrganizeResult, AppError> {
+    library::organize_by_date(paths, destination, pattern, mode, dry_run)
+}
+
+#[tauri::command]
+pub async fn detect_bursts(
+    workers: State<'_, WorkerPool>,
+    dir: String,
+    max_gap_ms: i64,
+    pick_sharpest: bool,
+) -> Result<Vec<Burst>, AppError> {
+    workers.install(|| library::detect_bursts(&dir, max_gap_ms, pick_sharpest))
+}
+
+#[tauri::command]
+pub async fn hash_file(path: String, algorithm: HashAlgorithm) -> Result<FileHash, AppError> {
+    library::hash_file(path, algorithm)
+}
+
+// Camera, exposure, location and XMP details without decoding the pixels.
+#[tauri::command]
+pub async fn extract_metadata(path: String) -> Result<ImageMetadata, AppError> {
+    metadata::extract_metadata(Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn find_exact_duplicates(
+    workers: State<'_, WorkerPool>,
+    dir: String,
+) -> Result<Vec<DuplicateFileGroup>, AppError> {
+    workers.install(|| library::find_exact_duplicates(&dir))
+}
+
+// A hash of how the open image looks, pHash by default, for comparing with
+// others by Hamming distance.
+#[tauri::command]
+pub async fn compute_phash(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    algorithm: Option<PerceptualHashAlgorithm>,
+) -> Result<PerceptualHash, AppError> {
+    let algorithm = algorithm.unwrap_or_default();
+    store.with_image(id, |stored| {
+        let img = DynamicImage::ImageRgba8((*stored.current).clone());
+        Ok(PerceptualHash {
+            algorithm,
+            hash: format!("{:016x}", hashing::image_hash(&img, algorithm)),
+        })
+    })
+}
+
+// Groups near-duplicate images among `paths`, which may be files or folders,
+// by perceptual hash, pHash by default. Files that fail to decode are listed
+// as skipped.
+#[tauri::command]
+pub async fn find_duplicates(
+    workers: State<'_, WorkerPool>,
+    paths: Vec<String>,
+    threshold: u32,
+    algorithm: Option<PerceptualHashAlgorithm>,
+) -> Result<NearDuplicateReport, AppError> {
+    let algorithm = algorithm.unwrap_or_default();
+    workers.install(|| hashing::find_duplicates(&paths, threshold, algorithm))
+}
+
+// Evens out the brightness of a series, such as timelapse frames, against
+// `reference`. Corrected frames are written to `output_dir` when given;
+// otherwise only the corrections are reported.
+#[tauri::command]
+pub async fn match_exposure(
+    workers: State<'_, WorkerPool>,
+    paths: Vec<String>,
+    reference: String,
+    output_dir: Option<String>,
+) -> Result<ExposureMatchReport, AppError> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    workers.install(|| {
+        exposure::match_exposure(
+            &paths,
+            Path::new(&reference),
+            output_dir.as_deref().map(Path::new),
+        )
+    })
+}
+
+// Sorts a folder into keep, review and reject lists by sharpness, clipping,
+// closed eyes and near-duplicates, with the reasons for each. Rules left out
+// use the defaults. Eyes are checked with the face model at
+// `face_model_path`, or the installed one if there is one.
+#[tauri::command]
+pub async fn cull_folder(
+    app: AppHandle,
+    workers: State<'_, WorkerPool>,
+    dir: String,
+    rules: Option<CullRules>,
+    face_model_path: Option<String>,
+) -> Result<CullReport, AppError> {
+    let rules = rules.unwrap_or_default();
+    let face_model = match face_model_path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_model_path(&app, FACE_MODEL_FILE)
+            .ok()
+            .filter(|path| faces::is_supported() && path.is_file()),
+    };
+    workers.install(|| cull::cull_folder(&dir, &rules, face_model.as_deref()))
+}
@@ -0,0 +1,129 @@
+use tauri::State;
+
+use image_analyzer_core::analysis::{blobs, contrast, measure, sampling, sharpness, stars};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    BlobAnalysis, Color, ContrastReport, ImageId, LineProfile, Measurement, PerfBackend, Point,
+    SampledColor, SharpnessReport, SpatialScale, StarAnalysis,
+};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn sample_line(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    from: Point,
+    to: Point,
+    samples: u32,
+    radius: Option<u32>,
+) -> Result<LineProfile, AppError> {
+    let timer = OperationTimer::start("sample_line", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let profile =
+            sampling::sample_line(&stored.current, from, to, samples, radius.unwrap_or(0))?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(profile)
+    })
+}
+
+#[tauri::command]
+pub async fn count_blobs(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    threshold: u8,
+    min_area: u32,
+    invert: Option<bool>,
+) -> Result<BlobAnalysis, AppError> {
+    let timer = OperationTimer::start("count_blobs", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let analysis = blobs::count_blobs(
+            &stored.current,
+            threshold,
+            min_area,
+            invert.unwrap_or(false),
+        )?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(analysis)
+    })
+}
+
+// Star count, focus (FWHM) and roundness for astrophotography frames.
+#[tauri::command]
+pub async fn analyze_stars(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<StarAnalysis, AppError> {
+    let timer = OperationTimer::start("analyze_stars", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let analysis = stars::analyze_stars(&stored.current)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(analysis)
+    })
+}
+
+// Calibrates the image from a reference of known size. The scale stays with
+// the image until it is set again.
+#[tauri::command]
+pub async fn set_scale(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    pixel_distance: f64,
+    real_distance: f64,
+    unit: String,
+) -> Result<SpatialScale, AppError> {
+    let scale = measure::calibrate(pixel_distance, real_distance, &unit)?;
+    store.with_image_mut(id, |stored| {
+        stored.scale = Some(scale.clone());
+        Ok(scale)
+    })
+}
+
+#[tauri::command]
+pub async fn measure(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    points: Vec<Point>,
+) -> Result<Measurement, AppError> {
+    store.with_image(id, |stored| {
+        measure::measure(&points, stored.scale.as_ref())
+    })
+}
+
+// Focus scores for picking the sharpest of a burst or flagging missed focus,
+// with a map of where the image is sharp when `heatmap` is set.
+#[tauri::command]
+pub async fn measure_sharpness(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    heatmap: Option<bool>,
+) -> Result<SharpnessReport, AppError> {
+    let timer = OperationTimer::start("measure_sharpness", PerfBackend::Rayon);
+    store.with_image(id, |stored| {
+        let report = sharpness::measure_sharpness(&stored.current, heatmap.unwrap_or(false))?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(report)
+    })
+}
+
+// Eyedropper: the colour at pixel (x, y), averaged over a box of `radius`
+// when given, with its WCAG relative luminance.
+#[tauri::command]
+pub async fn sample_color_at(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    x: u32,
+    y: u32,
+    radius: Option<u32>,
+) -> Result<SampledColor, AppError> {
+    store.with_image(id, |stored| {
+        sampling::sample_color_at(&stored.current, x, y, radius.unwrap_or(0))
+    })
+}
+
+// WCAG contrast of `color_a` as foreground over `color_b`.
+#[tauri::command]
+pub async fn check_contrast(color_a: Color, color_b: Color) -> Result<ContrastReport, AppError> {
+    Ok(contrast::check_contrast(color_a, color_b))
+}
@@ -1,152 +1,476 @@
-use crate::models::{AnalysisResult, ColorSample, HistogramData, ImageData};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
+use tauri::{AppHandle, State};
+
+use image_analyzer_core::decode::{
+    decode_file, decode_thumbnail, frames, load_raw_mosaic, verify_file,
+};
+use image_analyzer_core::encode::{export, png_data_url, write_image};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    AnalysisDiff, AnalysisResult, EdgeMode, Edit, ExportOptions, ExportResult, ImageData,
+    ImageFrames, ImageHandle, ImageId, ImagePreview, ImageVerification, Operation, PerfBackend,
+    RawMosaic, ResizeFilter, WhiteBalanceMethod,
+};
+use image_analyzer_core::processing::white_balance;
+use image_analyzer_core::progress::for_each_band;
+use image_analyzer_core::{analysis, processing};
+
+use crate::jobs::Job;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Events long-running commands report progress on when given a job id.
+const ANALYSIS_PROGRESS: &str = "analysis-progress";
+const FILTER_PROGRESS: &str = "filter-progress";
+
+mod animation;
+mod assets;
+mod audit;
+mod batch;
+mod canvas;
+mod clipboard;
+mod comparison;
+mod composition;
+mod denoise;
+mod detection;
+mod errors;
+mod filters;
+mod forensics;
+mod history;
+mod launch;
+mod lens;
+mod library;
+mod measurement;
+mod operations;
+mod progressive;
+mod proxy;
+mod recipes;
+mod redaction;
+mod server;
+mod social;
+mod system;
+mod tone;
+mod transform;
+mod tray;
+mod video;
+mod watch;
+
+pub use animation::*;
+pub use assets::*;
+pub use audit::*;
+pub use batch::*;
+pub use canvas::*;
+pub use clipboard::*;
+pub use comparison::*;
+pub use composition::*;
+pub use denoise::*;
+pub use detection::*;
+pub use errors::*;
+pub use filters::*;
+pub use forensics::*;
+pub use history::*;
+pub use launch::*;
+pub use lens::*;
+pub use library::*;
+pub use measurement::*;
+pub use operations::*;
+pub use progressive::*;
+pub use proxy::*;
+pub use recipes::*;
+pub use redaction::*;
+pub use server::*;
+pub use social::*;
+pub use system::*;
+pub use tone::*;
+pub use transform::*;
+pub use tray::*;
+pub use video::*;
+pub use watch::*;
+
+// Decodes straight to a pixel buffer without storing it, for the views that
+// work on pixels in the page. Everything else opens a handle with `open_image`.
 #[tauri::command]
-pub async fn load_image(path: String) -> Result<ImageData, String> {
-    let img = image::open(&path).map_err(|e| format!("Failed to load image: {}", e))?;
-    let rgba_img = img.to_rgba8();
+pub async fn load_image(path: String, recover: Option<bool>) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("load_image", PerfBackend::Cpu);
+    let decoded = decode_file(Path::new(&path), recover.unwrap_or(false))?;
+    let rgba_img = decoded.image;
+    timer.finish(rgba_img.width(), rgba_img.height());
 
     Ok(ImageData {
         width: rgba_img.width(),
         height: rgba_img.height(),
         data: rgba_img.to_vec(),
         format: "rgba".to_string(),
+        recovered: decoded.recovered,
+        source_color_type: decoded.source,
     })
 }
 
 #[tauri::command]
-pub async fn analyze_image(image_data: ImageData) -> Result<AnalysisResult, String> {
-    let width = image_data.width as usize;
-    let height = image_data.height as usize;
-    let data = &image_data.data;
+pub async fn load_image_frames(path: String) -> Result<ImageFrames, AppError> {
+    frames::frame_info(Path::new(&path))
+}
 
-    // Calculate histogram
-    let mut histogram = HistogramData {
-        red: vec![0; 256],
-        green: vec![0; 256],
-        blue: vec![0; 256],
-        luminosity: vec![0; 256],
-    };
+// One frame of an animation as it's shown at that point, or one page of a
+// multi-page TIFF, as a pixel buffer like `load_image`'s.
+#[tauri::command]
+pub async fn get_image_frame(path: String, index: usize) -> Result<ImageData, AppError> {
+    let timer = OperationTimer::start("get_image_frame", PerfBackend::Cpu);
+    let decoded = frames::decode_frame(Path::new(&path), index)?;
+    let frame = decoded.image;
+    timer.finish(frame.width(), frame.height());
+    Ok(ImageData {
+        width: frame.width(),
+        height: frame.height(),
+        data: frame.to_vec(),
+        format: "rgba".to_string(),
+        recovered: decoded.recovered,
+        source_color_type: decoded.source,
+    })
+}
 
-    let chunk_size = 4; // RGBA
-    for chunk in data.chunks(chunk_size) {
-        if chunk.len() == chunk_size {
-            let r = chunk[0] as usize;
-            let g = chunk[1] as usize;
-            let b = chunk[2] as usize;
-            let a = chunk[3];
-
-            if a > 0 {
-                histogram.red[r] += 1;
-                histogram.green[g] += 1;
-                histogram.blue[b] += 1;
-
-                // Calculate luminosity (perceptual brightness)
-                let lum = ((0.299 * r as f32) + (0.587 * g as f32) + (0.114 * b as f32)) as usize;
-                histogram.luminosity[lum.min(255)] += 1;
-            }
-        }
-    }
+// The sensor data of a RAW file before demosaicing, for checking exposure
+// per filter colour. `colorize` tints each site in its colour; on by default.
+#[tauri::command]
+pub async fn get_raw_mosaic(path: String, colorize: Option<bool>) -> Result<RawMosaic, AppError> {
+    let timer = OperationTimer::start("get_raw_mosaic", PerfBackend::Cpu);
+    let mosaic = load_raw_mosaic(Path::new(&path), colorize.unwrap_or(true))?;
+    timer.finish(mosaic.image.width, mosaic.image.height);
+    Ok(mosaic)
+}
 
-    // Calculate average brightness
-    let total_pixels = (width * height) as f32;
-    let avg_brightness = histogram.luminosity.iter().enumerate()
-        .map(|(i, &count)| (i as f32) * (count as f32))
-        .sum::<f32>() / total_pixels / 255.0;
-
-    // Calculate contrast (using standard deviation)
-    let mean = avg_brightness * 255.0;
-    let variance = histogram.luminosity.iter().enumerate()
-        .map(|(i, &count)| {
-            let diff = (i as f32) - mean;
-            (diff * diff) * (count as f32)
+#[tauri::command]
+pub async fn open_image(
+    store: State<'_, ImageStore>,
+    path: String,
+    recover: Option<bool>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("open_image", PerfBackend::Cpu);
+    let decoded = decode_file(Path::new(&path), recover.unwrap_or(false))?;
+    timer.finish(decoded.image.width(), decoded.image.height());
+    Ok(store.insert_with_source(Some(PathBuf::from(path)), decoded.source, decoded.image))
+}
+
+#[tauri::command]
+pub async fn verify_image(path: String) -> Result<ImageVerification, AppError> {
+    verify_file(Path::new(&path))
+}
+
+// A PNG of the file fitting within `max_size`, for browsing folders without
+// decoding every file at full size.
+#[tauri::command]
+pub async fn generate_thumbnail(path: String, max_size: u32) -> Result<ImagePreview, AppError> {
+    let timer = OperationTimer::start("generate_thumbnail", PerfBackend::Cpu);
+    let thumbnail = decode_thumbnail(Path::new(&path), max_size)?;
+    timer.finish(thumbnail.width(), thumbnail.height());
+    Ok(ImagePreview {
+        width: thumbnail.width(),
+        height: thumbnail.height(),
+        data_url: png_data_url(&thumbnail)?,
+    })
+}
+
+// The full pixel buffer, for the few views that work on it directly. Anything
+// just showing the image should use `get_preview`, which is far smaller.
+#[tauri::command]
+pub async fn get_image_data(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ImageData, AppError> {
+    store.with_image(id, |stored| {
+        Ok(ImageData {
+            width: stored.current.width(),
+            height: stored.current.height(),
+            data: stored.current.to_vec(),
+            format: "rgba".to_string(),
+            recovered: false,
+            source_color_type: stored.source_color.clone(),
         })
-        .sum::<f32>() / total_pixels;
-    let contrast = variance.sqrt() / 255.0;
-
-    // Extract dominant colors (simplified)
-    let dominant_colors = vec![
-        ColorSample {
-            r: 128,
-            g: 128,
-            b: 128,
-            hex: "#808080".to_string(),
-        },
-    ];
-
-    Ok(AnalysisResult {
-        histogram,
-        dominant_colors,
-        average_brightness: avg_brightness,
-        contrast,
     })
 }
 
+// A PNG of the image scaled to fit within `max_dimension`, for display. Never
+// scales up.
 #[tauri::command]
-pub async fn adjust_brightness(
-    image_data: ImageData,
-    amount: f32,
-) -> Result<ImageData, String> {
-    let mut adjusted = image_data.data.clone();
-    let mut chunks = adjusted.chunks_exact_mut(4);
-
-    for chunk in &mut chunks {
-        chunk[0] = ((chunk[0] as f32 * amount).min(255.0) as u8).max(0);
-        chunk[1] = ((chunk[1] as f32 * amount).min(255.0) as u8).max(0);
-        chunk[2] = ((chunk[2] as f32 * amount).min(255.0) as u8).max(0);
+pub async fn get_preview(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    max_dimension: u32,
+) -> Result<ImagePreview, AppError> {
+    if max_dimension == 0 {
+        return Err(AppError::invalid("Preview size must be at least 1 pixel"));
     }
+    let timer = OperationTimer::start("get_preview", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let (width, height) = stored.current.dimensions();
+        let preview = if width.max(height) > max_dimension {
+            let (w, h) =
+                processing::transform::fit_within(width, height, max_dimension, max_dimension);
+            Cow::Owned(processing::transform::resize(
+                &stored.current,
+                w,
+                h,
+                ResizeFilter::Bilinear,
+                EdgeMode::Clamp,
+            )?)
+        } else {
+            Cow::Borrowed(&*stored.current)
+        };
+        timer.finish(width, height);
+        Ok(ImagePreview {
+            width: preview.width(),
+            height: preview.height(),
+            data_url: png_data_url(&preview)?,
+        })
+    })
+}
 
-    Ok(ImageData {
-        data: adjusted,
-        ..image_data
+#[tauri::command]
+pub async fn close_image(store: State<'_, ImageStore>, id: ImageId) -> Result<(), AppError> {
+    if store.remove(id) {
+        Ok(())
+    } else {
+        Err(AppError::ImageNotFound { id })
+    }
+}
+
+#[tauri::command]
+pub async fn analyze_image(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    palette_size: Option<usize>,
+    job_id: Option<String>,
+) -> Result<AnalysisResult, AppError> {
+    let job = Job::start(&app, job_id, ANALYSIS_PROGRESS)?;
+    let timer = OperationTimer::start("analyze_image", PerfBackend::Rayon);
+    store.with_image(id, |stored| {
+        let result = analysis::analyze_tracked(
+            &stored.current,
+            stored.source_color.as_ref(),
+            palette_size.unwrap_or(analysis::palette::DEFAULT_PALETTE_SIZE),
+            &job,
+        )?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(result)
+    })
+}
+
+#[tauri::command]
+pub async fn diff_analysis(
+    result_a: AnalysisResult,
+    result_b: AnalysisResult,
+) -> Result<AnalysisDiff, AppError> {
+    Ok(analysis::diff::diff_analysis(&result_a, &result_b))
+}
+
+// Runs a per-pixel adjustment over the stored image band by band and records
+// it; a cancelled job leaves the image as it was.
+fn adjust(
+    store: &ImageStore,
+    id: ImageId,
+    name: &'static str,
+    backend: PerfBackend,
+    job: &Option<Job>,
+    operation: Operation,
+    run: impl Fn(&mut [u8]),
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start(name, backend);
+    store.with_image_mut(id, |stored| {
+        let edit = Edit::Operation { operation };
+        // The working copy is adjusted in one pass.
+        if stored.works_in_float(&edit) {
+            stored.apply_edit(edit)?;
+        } else {
+            for_each_band(stored.current_mut(), job, run)?;
+            stored.record(edit);
+        }
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
     })
 }
 
+#[tauri::command]
+pub async fn adjust_brightness(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    amount: f32,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    adjust(
+        &store,
+        id,
+        "adjust_brightness",
+        PerfBackend::Rayon,
+        &job,
+        Operation::Brightness { amount },
+        |band| processing::adjust_brightness(band, amount),
+    )
+}
+
 #[tauri::command]
 pub async fn adjust_contrast(
-    image_data: ImageData,
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
     amount: f32,
-) -> Result<ImageData, String> {
-    let mut adjusted = image_data.data.clone();
-    let center = 128.0;
-
-    for chunk in adjusted.chunks_exact_mut(4) {
-        chunk[0] = (((chunk[0] as f32 - center) * amount + center).min(255.0) as u8).max(0);
-        chunk[1] = (((chunk[1] as f32 - center) * amount + center).min(255.0) as u8).max(0);
-        chunk[2] = (((chunk[2] as f32 - center) * amount + center).min(255.0) as u8).max(0);
-    }
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    adjust(
+        &store,
+        id,
+        "adjust_contrast",
+        PerfBackend::Rayon,
+        &job,
+        Operation::Contrast { amount },
+        |band| processing::adjust_contrast(band, amount),
+    )
+}
 
-    Ok(ImageData {
-        data: adjusted,
-        ..image_data
-    })
+#[tauri::command]
+pub async fn convert_to_grayscale(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    adjust(
+        &store,
+        id,
+        "convert_to_grayscale",
+        PerfBackend::Rayon,
+        &job,
+        Operation::Grayscale,
+        processing::convert_to_grayscale,
+    )
 }
 
+// `hue_shift` is in degrees and `saturation` a multiplier, 1 leaving it
+// unchanged; `lightness` from -1 to 1 moves pixels towards black or white.
 #[tauri::command]
-pub async fn convert_to_grayscale(image_data: ImageData) -> Result<ImageData, String> {
-    let mut adjusted = image_data.data.clone();
+pub async fn adjust_hsl(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    hue_shift: f32,
+    saturation: f32,
+    lightness: f32,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    let operation = Operation::Hsl {
+        hue_shift,
+        saturation,
+        lightness,
+    };
+    adjust(
+        &store,
+        id,
+        "adjust_hsl",
+        PerfBackend::Cpu,
+        &job,
+        operation,
+        |band| processing::adjust_hsl(band, hue_shift, saturation, lightness),
+    )
+}
 
-    for chunk in adjusted.chunks_exact_mut(4) {
-        let gray = ((0.299 * chunk[0] as f32)
-            + (0.587 * chunk[1] as f32)
-            + (0.114 * chunk[2] as f32)) as u8;
-        chunk[0] = gray;
-        chunk[1] = gray;
-        chunk[2] = gray;
-    }
+// `temperature` is the Kelvin of the light the shot was taken under: lower
+// values cool the image and higher ones warm it, 6500 leaving it neutral.
+// `tint` runs from -100 (green) to 100 (magenta).
+#[tauri::command]
+pub async fn adjust_white_balance(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    temperature: f32,
+    tint: f32,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    let gains = white_balance::temperature_gains(temperature, tint);
+    adjust(
+        &store,
+        id,
+        "adjust_white_balance",
+        PerfBackend::Cpu,
+        &job,
+        Operation::WhiteBalance { temperature, tint },
+        |band| white_balance::apply_gains(band, gains),
+    )
+}
 
-    Ok(ImageData {
-        data: adjusted,
-        ..image_data
+#[tauri::command]
+pub async fn auto_white_balance(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    method: WhiteBalanceMethod,
+    job_id: Option<String>,
+) -> Result<ImageHandle, AppError> {
+    let job = Job::start(&app, job_id, FILTER_PROGRESS)?;
+    let timer = OperationTimer::start("auto_white_balance", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        let edit = Edit::AutoWhiteBalance { method };
+        if stored.works_in_float(&edit) {
+            stored.apply_edit(edit)?;
+        } else {
+            let gains = white_balance::estimate_gains(&stored.current, method);
+            for_each_band(stored.current_mut(), &job, |band| {
+                white_balance::apply_gains(band, gains)
+            })?;
+            stored.record(edit);
+        }
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
     })
 }
 
+// Writes back in the source's colour layout where the edits still fit it.
 #[tauri::command]
-pub async fn save_image(image_data: ImageData, path: String) -> Result<(), String> {
-    let img = image::RgbaImage::from_raw(image_data.width, image_data.height, image_data.data)
-        .ok_or("Failed to create image from data")?;
-
-    img.save(&path)
-        .map_err(|e| format!("Failed to save image: {}", e))?;
+pub async fn save_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    path: String,
+) -> Result<(), AppError> {
+    let timer = OperationTimer::start("save_image", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        write_image(&stored.current, stored.source_color.as_ref(), &path)?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(())
+    })
+}
 
-    Ok(())
+// Writes in the chosen format rather than by extension, with its quality and
+// compression settings.
+#[tauri::command]
+pub async fn export_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    path: String,
+    options: ExportOptions,
+) -> Result<ExportResult, AppError> {
+    let timer = OperationTimer::start("export_image", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let (width, height) = stored.current.dimensions();
+        let file_size = export::export_image(
+            &stored.current,
+            stored.source_color.as_ref(),
+            stored.source_path.as_deref(),
+            &options,
+            &path,
+        )?;
+        timer.finish(width, height);
+        Ok(ExportResult {
+            output: path.clone(),
+            width,
+            height,
+            file_size,
+        })
+    })
 }
@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    Edit, ImageData, ImageHandle, ImageId, ImageMathResult, MathOp, MathOperand, Operation,
+    OperationLog, OperationPreview, PerfBackend, PixelRect, Precision, ReplayResult,
+};
+use image_analyzer_core::processing::arithmetic::{self, Operand};
+use image_analyzer_core::processing::preview;
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Renders `operation` over just the visible `region`, scaled to the viewport,
+// without touching the stored image. `apply_operation` commits it.
+#[tauri::command]
+pub async fn preview_operation(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    operation: Operation,
+    region: PixelRect,
+    viewport_width: u32,
+    viewport_height: u32,
+) -> Result<OperationPreview, AppError> {
+    let timer = OperationTimer::start("preview_operation", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        let (region, image) = preview::preview_operation(
+            &stored.current,
+            &operation,
+            region,
+            viewport_width,
+            viewport_height,
+        )?;
+        timer.finish(image.width(), image.height());
+        Ok(OperationPreview {
+            region,
+            image: ImageData {
+                width: image.width(),
+                height: image.height(),
+                data: image.into_raw(),
+                format: "rgba".to_string(),
+                recovered: false,
+                source_color_type: None,
+            },
+        })
+    })
+}
+
+#[tauri::command]
+pub async fn apply_operation(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    operation: Operation,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("apply_operation", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.apply_edit(Edit::Operation { operation })?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// At `f32`, tone and colour adjustments from now on work on a float copy of
+// the image and round to 8 bits only for display and export, so chaining
+// them doesn't band. Blur, sharpening and geometry stay 8-bit. Replaying the
+// log uses the image's precision at that time.
+#[tauri::command]
+pub async fn set_precision(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    precision: Precision,
+) -> Result<(), AppError> {
+    store.with_image_mut(id, |stored| {
+        stored.set_precision(precision);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn get_operation_log(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<OperationLog, AppError> {
+    store.with_image(id, |stored| Ok(stored.operation_log(id)))
+}
+
+// Rebuilds the image from its original by redoing every logged edit, and
+// reports whether that reproduced it exactly.
+#[tauri::command]
+pub async fn replay_log(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ReplayResult, AppError> {
+    let timer = OperationTimer::start("replay_log", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        let result = stored.replay(id)?;
+        timer.finish(result.handle.width, result.handle.height);
+        Ok(result)
+    })
+}
+
+// Writes the log as JSON, e.g. to attach to a bug report.
+#[tauri::command]
+pub async fn export_log(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    path: String,
+) -> Result<(), AppError> {
+    let log = store.with_image(id, |stored| Ok(stored.operation_log(id)))?;
+    let json = serde_json::to_string_pretty(&log)
+        .map_err(|e| format!("Failed to serialize the operation log: {}", e))?;
+    let path = Path::new(&path);
+    fs::write(path, json).map_err(|e| AppError::io(path, e, true))
+}
+
+// Combines image `id` with `operand` pixel by pixel into a new image; both
+// inputs are left alone. `float_output` also returns the values before
+// they're clamped, for calibration maths that goes below black or past white.
+#[tauri::command]
+pub async fn image_math(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    operand: MathOperand,
+    op: MathOp,
+    float_output: Option<bool>,
+) -> Result<ImageMathResult, AppError> {
+    let timer = OperationTimer::start("image_math", PerfBackend::Rayon);
+    let a = store.with_image(id, |stored| Ok(stored.current.clone()))?;
+    let values = match operand {
+        MathOperand::Image { id } => {
+            let b = store.with_image(id, |stored| Ok(stored.current.clone()))?;
+            arithmetic::image_math(&a, Operand::Image(&b), op)?
+        }
+        MathOperand::Constant { value } => {
+            arithmetic::image_math(&a, Operand::Constant(value), op)?
+        }
+    };
+    let handle = store.insert(None, arithmetic::to_rgba(&values, &a));
+    timer.finish(a.width(), a.height());
+    Ok(ImageMathResult {
+        handle,
+        values: float_output.unwrap_or(false).then_some(values),
+    })
+}
@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Emitter, State};
+
+use image_analyzer_core::decode::{decode_bytes, decode_jpeg_prefix, jpeg_scan_layout};
+use image_analyzer_core::encode::png_data_url;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{ImageHandle, PerfBackend, ScanPreview};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+const READ_CHUNK: usize = 4 << 20;
+const DEFAULT_PREVIEW_SIZE: u32 = 512;
+// Each preview is a full (if DCT-scaled) decode, so only a few are worth doing.
+const MAX_PREVIEWS: usize = 3;
+
+// Reads a JPEG incrementally, emitting `jpeg-scan-preview` events as the
+// scans of a progressive file arrive, then stores the full-quality decode.
+#[tauri::command]
+pub async fn open_image_progressive(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    path: String,
+    preview_size: Option<u32>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("open_image_progressive", PerfBackend::Cpu);
+    let preview_size = preview_size.unwrap_or(DEFAULT_PREVIEW_SIZE).max(16);
+    let mut file = File::open(&path).map_err(|e| format!("Failed to load image: {}", e))?;
+    let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+    let mut bytes = Vec::with_capacity(total_bytes as usize);
+    let mut chunk = vec![0u8; READ_CHUNK];
+    let mut previews = 0;
+    let mut last_previewed_scan = 0;
+    let mut is_jpeg = None;
+
+    loop {
+        let read = file
+            .read(&mut chunk)
+            .map_err(|e| format!("Failed to load image: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+
+        let jpeg = *is_jpeg.get_or_insert_with(|| bytes.starts_with(&[0xFF, 0xD8]));
+        if !jpeg || previews >= MAX_PREVIEWS {
+            continue;
+        }
+
+        let layout = jpeg_scan_layout(&bytes);
+        if !layout.progressive {
+            // Baseline JPEGs have a single scan; there is nothing to preview early.
+            previews = MAX_PREVIEWS;
+            continue;
+        }
+        let completed = layout.scan_ends.len();
+        // The first scan is usually DC-only; wait for a couple more before refining.
+        let due = if previews == 0 {
+            1
+        } else {
+            last_previewed_scan + 2
+        };
+        if completed < due || (bytes.len() as u64) >= total_bytes {
+            continue;
+        }
+
+        let end = layout.scan_ends[completed - 1];
+        if let Some(preview) = decode_jpeg_prefix(&bytes, end, preview_size) {
+            let payload = ScanPreview {
+                path: path.clone(),
+                scan: completed,
+                bytes_read: bytes.len() as u64,
+                total_bytes,
+                width: preview.width(),
+                height: preview.height(),
+                data_url: png_data_url(&preview)?,
+            };
+            let _ = app.emit("jpeg-scan-preview", payload);
+            previews += 1;
+            last_previewed_scan = completed;
+        }
+    }
+
+    let path = PathBuf::from(path);
+    let decoded = decode_bytes(&bytes, Some(&path), false)?;
+    timer.finish(decoded.image.width(), decoded.image.height());
+    Ok(store.insert_with_source(Some(path), decoded.source, decoded.image))
+}
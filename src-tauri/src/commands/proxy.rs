@@ -0,0 +1,79 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    ImageData, ImageHandle, ImageId, Operation, PerfBackend, ProxyInfo,
+};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Applies `operation` to the image's low-resolution proxy so sliders stay
+// responsive. The full-resolution image catches up when it's next used, e.g.
+// on export, or explicitly via `commit_proxy_edits`.
+#[tauri::command]
+pub async fn edit_proxy(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    operation: Operation,
+) -> Result<ProxyInfo, AppError> {
+    let timer = OperationTimer::start("edit_proxy", PerfBackend::Cpu);
+    store.with_proxy(id, |stored| {
+        stored.edit_proxy(operation);
+        let info = stored.proxy_info(id);
+        timer.finish(info.proxy_width, info.proxy_height);
+        Ok(info)
+    })
+}
+
+#[tauri::command]
+pub async fn get_proxy_info(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ProxyInfo, AppError> {
+    store.with_proxy(id, |stored| Ok(stored.proxy_info(id)))
+}
+
+#[tauri::command]
+pub async fn get_proxy_data(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ImageData, AppError> {
+    store.with_proxy(id, |stored| {
+        let source_color_type = stored.source_color.clone();
+        let proxy = stored.proxy();
+        Ok(ImageData {
+            width: proxy.width(),
+            height: proxy.height(),
+            data: proxy.as_raw().clone(),
+            format: "rgba".to_string(),
+            recovered: false,
+            source_color_type,
+        })
+    })
+}
+
+// Replays pending proxy edits on the full-resolution image now rather than
+// on next use.
+#[tauri::command]
+pub async fn commit_proxy_edits(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("commit_proxy_edits", PerfBackend::Cpu);
+    store.with_image(id, |stored| {
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn discard_proxy_edits(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+) -> Result<ProxyInfo, AppError> {
+    store.with_proxy(id, |stored| {
+        stored.discard_proxy_edits();
+        Ok(stored.proxy_info(id))
+    })
+}
@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use tauri::State;
+
+use image_analyzer_core::decode::decode_file;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::metadata::develop::read_develop_settings;
+use image_analyzer_core::models::{ImageHandle, ImageId, RecipeExportReport, SettingsImport};
+use image_analyzer_core::recipe;
+
+use crate::jobs::WorkerPool;
+use crate::store::ImageStore;
+
+// Saves the image's edits as a sidecar next to the file it was opened from,
+// and returns the sidecar's path.
+#[tauri::command]
+pub async fn save_recipe(store: State<'_, ImageStore>, id: ImageId) -> Result<String, AppError> {
+    let (source, log) = store.with_image(id, |stored| {
+        let source = stored.source_path.clone().ok_or_else(|| {
+            AppError::invalid("Image has no source file to save a recipe next to")
+        })?;
+        Ok((source, stored.operation_log(id)))
+    })?;
+    // The recipe starts from the file on disk, so it includes edits the stored
+    // original already contains, such as redactions.
+    let edits = log.entries.into_iter().map(|entry| entry.edit).collect();
+    let path = recipe::save_recipe(&source, edits)?;
+    Ok(path.display().to_string())
+}
+
+// Opens `path` with its recipe applied. The edits are in the image's history
+// like any other, so they can be undone one by one.
+#[tauri::command]
+pub async fn apply_recipe(
+    store: State<'_, ImageStore>,
+    path: String,
+) -> Result<ImageHandle, AppError> {
+    let path = Path::new(&path);
+    let recipe = recipe::load_recipe(path)?;
+    let decoded = decode_file(path, false)?;
+    let handle = store.insert_with_source(Some(PathBuf::from(path)), decoded.source, decoded.image);
+
+    let applied = store.with_image_mut(handle.id, |stored| {
+        for edit in recipe.edits {
+            stored.apply_edit(edit)?;
+        }
+        Ok(stored.handle(handle.id))
+    });
+    if applied.is_err() {
+        store.remove(handle.id);
+    }
+    applied
+}
+
+// Applies the Lightroom or darktable develop settings saved for the image's
+// source file, from `sidecar_path` or the usual sidecar locations. Settings
+// with no equivalent here are listed rather than applied.
+#[tauri::command]
+pub async fn import_settings(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    sidecar_path: Option<String>,
+) -> Result<SettingsImport, AppError> {
+    store.with_image_mut(id, |stored| {
+        let source = stored
+            .source_path
+            .clone()
+            .ok_or_else(|| AppError::invalid("Image has no source file to read settings for"))?;
+        let settings = read_develop_settings(
+            &source,
+            sidecar_path.as_deref().map(Path::new),
+            stored.current.width(),
+            stored.current.height(),
+        )?;
+        for edit in &settings.edits {
+            stored.apply_edit(edit.clone())?;
+        }
+        Ok(SettingsImport {
+            handle: stored.handle(id),
+            settings,
+        })
+    })
+}
+
+#[tauri::command]
+pub async fn reexport_with_recipes(
+    workers: State<'_, WorkerPool>,
+    dir: String,
+    output_dir: String,
+    format: Option<String>,
+) -> Result<RecipeExportReport, AppError> {
+    workers
+        .install(|| recipe::reexport_with_recipes(&dir, Path::new(&output_dir), format.as_deref()))
+}
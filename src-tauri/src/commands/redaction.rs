@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, State};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    AutoRedaction, Edit, ImageHandle, ImageId, PerfBackend, PixelRect, RedactionStyle,
+    RedactionTarget,
+};
+use image_analyzer_core::processing::redact;
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+use super::detection::{default_model_path, FACE_MODEL_FILE, TEXT_MODEL_FILE};
+
+const DEFAULT_REDACTION_PADDING: u32 = 8;
+
+// The redacted pixels also replace the stored original, so resetting or
+// comparing against it can't bring the hidden content back.
+#[tauri::command]
+pub async fn redact_regions(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    regions: Vec<PixelRect>,
+    style: RedactionStyle,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("redact_regions", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        redact::redact_regions(stored.current_mut(), &regions, style)?;
+        stored.record(Edit::RedactRegions { regions, style });
+        stored.rebase();
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// One-click privacy redaction: finds the `targets`, text and faces unless
+// told otherwise, and redacts them grown by `padding` pixels. Models default
+// to the ones installed for `detect_text_regions` and `detect_faces`. The
+// regions found are recorded, so replaying the history redacts the same
+// pixels without the models.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn redact_detected(
+    app: AppHandle,
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    style: RedactionStyle,
+    targets: Option<Vec<RedactionTarget>>,
+    padding: Option<u32>,
+    text_model_path: Option<String>,
+    face_model_path: Option<String>,
+) -> Result<AutoRedaction, AppError> {
+    let targets = targets.unwrap_or_else(|| vec![RedactionTarget::Text, RedactionTarget::Faces]);
+    let model = |target: RedactionTarget, path: Option<String>, file: &str| {
+        if !targets.contains(&target) {
+            return Ok(None);
+        }
+        match path {
+            Some(path) => Ok(Some(PathBuf::from(path))),
+            None => default_model_path(&app, file).map(Some),
+        }
+    };
+    let text_model = model(RedactionTarget::Text, text_model_path, TEXT_MODEL_FILE)?;
+    let face_model = model(RedactionTarget::Faces, face_model_path, FACE_MODEL_FILE)?;
+
+    let timer = OperationTimer::start("redact_detected", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        let regions = redact::find_private_regions(
+            &stored.current,
+            text_model.as_deref(),
+            face_model.as_deref(),
+            padding.unwrap_or(DEFAULT_REDACTION_PADDING),
+        )?;
+        if !regions.is_empty() {
+            redact::redact_regions(stored.current_mut(), &regions, style)?;
+            stored.record(Edit::RedactRegions {
+                regions: regions.clone(),
+                style,
+            });
+            stored.rebase();
+        }
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(AutoRedaction {
+            handle: stored.handle(id),
+            regions,
+        })
+    })
+}
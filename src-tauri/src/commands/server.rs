@@ -0,0 +1,27 @@
+use tauri::{AppHandle, State};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::AutomationServerInfo;
+
+use crate::server::{AutomationServer, DEFAULT_PORT};
+
+#[tauri::command]
+pub async fn start_automation_server(
+    app: AppHandle,
+    server: State<'_, AutomationServer>,
+    port: Option<u16>,
+) -> Result<AutomationServerInfo, AppError> {
+    Ok(server.start(app, port.unwrap_or(DEFAULT_PORT))?)
+}
+
+#[tauri::command]
+pub async fn stop_automation_server(server: State<'_, AutomationServer>) -> Result<bool, AppError> {
+    Ok(server.stop())
+}
+
+#[tauri::command]
+pub async fn get_automation_server(
+    server: State<'_, AutomationServer>,
+) -> Result<Option<AutomationServerInfo>, AppError> {
+    Ok(server.info())
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use tauri::State;
+
+use image_analyzer_core::encode::social;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{ExportResult, ImageId, PerfBackend, SocialPreset};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn list_social_presets() -> Result<Vec<SocialPreset>, AppError> {
+    Ok(social::list_social_presets())
+}
+
+// `format` is the file extension to save as, JPEG by default.
+#[tauri::command]
+pub async fn export_social_presets(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    presets: Vec<String>,
+    output_dir: String,
+    name: String,
+    format: Option<String>,
+) -> Result<Vec<ExportResult>, AppError> {
+    let timer = OperationTimer::start("export_social_presets", PerfBackend::Cpu);
+    let image = store.with_image(id, |stored| Ok(stored.current.clone()))?;
+    let results = social::export_social_presets(
+        &image,
+        &presets,
+        Path::new(&output_dir),
+        &name,
+        format.as_deref().unwrap_or("jpg"),
+    )?;
+    timer.finish(image.width(), image.height());
+    Ok(results)
+}
@@ -0,0 +1,69 @@
+use sysinfo::System;
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{MemoryUsage, SystemInfo};
+
+use crate::jobs::{JobRegistry, WorkerPool};
+use crate::store::ImageStore;
+
+// Leave a core free for the UI and OS when suggesting a batch worker count.
+fn suggested_workers(logical_cores: usize, physical_cores: Option<usize>) -> usize {
+    physical_cores
+        .unwrap_or(logical_cores)
+        .saturating_sub(1)
+        .max(1)
+}
+
+#[tauri::command]
+pub async fn get_system_info(workers: State<'_, WorkerPool>) -> Result<SystemInfo, AppError> {
+    let mut system = System::new();
+    system.refresh_memory();
+
+    let logical_cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let physical_cores = system.physical_core_count();
+
+    Ok(SystemInfo {
+        logical_cores,
+        physical_cores,
+        total_memory: system.total_memory(),
+        available_memory: system.available_memory(),
+        max_workers: workers.max_workers(),
+        current_workers: workers.current_workers(),
+        suggested_workers: suggested_workers(logical_cores, physical_cores),
+    })
+}
+
+#[tauri::command]
+pub async fn set_max_workers(
+    workers: State<'_, WorkerPool>,
+    max_workers: Option<usize>,
+) -> Result<usize, AppError> {
+    workers.set_max_workers(max_workers)
+}
+
+// Asks the job started with `job_id` to stop; it fails with a `cancelled`
+// error at its next checkpoint. Returns false if no such job is running.
+#[tauri::command]
+pub async fn cancel_job(jobs: State<'_, JobRegistry>, job_id: String) -> Result<bool, AppError> {
+    Ok(jobs.cancel(&job_id))
+}
+
+#[tauri::command]
+pub async fn get_memory_usage(store: State<'_, ImageStore>) -> Result<MemoryUsage, AppError> {
+    Ok(store.usage())
+}
+
+// Lowering the budget spills inactive images straight away.
+#[tauri::command]
+pub async fn set_memory_budget(
+    store: State<'_, ImageStore>,
+    budget_bytes: u64,
+) -> Result<MemoryUsage, AppError> {
+    if budget_bytes == 0 {
+        return Err(AppError::invalid("Memory budget must be greater than zero"));
+    }
+    Ok(store.set_budget(budget_bytes))
+}
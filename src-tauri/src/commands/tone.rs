@@ -0,0 +1,117 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    ChannelLevels, CurveChannel, DitherAlgorithm, DitherPalette, Edit, EnhanceMethod, ImageHandle,
+    ImageId, LookInfo, PerfBackend, PixelPoint,
+};
+use image_analyzer_core::processing::{dither, enhance, looks, tone};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+#[tauri::command]
+pub async fn set_black_white_points(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    black_sample: PixelPoint,
+    white_sample: PixelPoint,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("set_black_white_points", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        tone::set_black_white_points(stored.current_mut(), black_sample, white_sample)?;
+        stored.record(Edit::SetBlackWhitePoints {
+            black_sample,
+            white_sample,
+        });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// Histogram equalization, CLAHE or a percentile auto-contrast stretch.
+#[tauri::command]
+pub async fn auto_enhance(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    method: EnhanceMethod,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("auto_enhance", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        enhance::auto_enhance(stored.current_mut(), method)?;
+        stored.record(Edit::AutoEnhance { method });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn apply_levels(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    levels: ChannelLevels,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("apply_levels", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.apply_edit(Edit::Levels { levels })?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// `points` are (input, output) pairs on 0-255, joined by a smooth curve that
+// doesn't overshoot them. `channel` defaults to all three.
+#[tauri::command]
+pub async fn apply_curve(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    points: Vec<[f32; 2]>,
+    channel: Option<CurveChannel>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("apply_curve", PerfBackend::Cpu);
+    let channel = channel.unwrap_or_default();
+    store.with_image_mut(id, |stored| {
+        stored.apply_edit(Edit::Curve { points, channel })?;
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn list_looks() -> Result<Vec<LookInfo>, AppError> {
+    Ok(looks::list_looks())
+}
+
+#[tauri::command]
+pub async fn apply_look(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    name: String,
+    intensity: f32,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("apply_look", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        looks::apply_look(stored.current_mut(), &name, intensity)?;
+        stored.record(Edit::ApplyLook { name, intensity });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// Reduces the image to a small palette with dithering standing in for the
+// missing colours, for retro looks and e-ink or other limited displays.
+#[tauri::command]
+pub async fn dither(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    palette: DitherPalette,
+    algorithm: DitherAlgorithm,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("dither", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        dither::dither(stored.current_mut(), &palette, algorithm)?;
+        stored.record(Edit::Dither { palette, algorithm });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
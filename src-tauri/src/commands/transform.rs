@@ -0,0 +1,101 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    CanvasFill, EdgeMode, Edit, FlipAxis, ImageHandle, ImageId, PerfBackend, PixelArtAlgorithm,
+    ResizeFilter,
+};
+use image_analyzer_core::processing::{pixel_art, transform};
+
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// `degrees` is counter-clockwise, so a `detect_horizon` rotation can be
+// passed straight through. Corners uncovered by angles other than right
+// angles take `fill`, transparent by default.
+#[tauri::command]
+pub async fn rotate_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    degrees: f32,
+    fill: Option<CanvasFill>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("rotate_image", PerfBackend::Cpu);
+    let fill = fill.unwrap_or(CanvasFill::Transparent);
+    store.with_image_mut(id, |stored| {
+        stored.current = transform::rotate(&stored.current, degrees, fill)?.into();
+        stored.record(Edit::Rotate { degrees, fill });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+#[tauri::command]
+pub async fn flip_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    axis: FlipAxis,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("flip_image", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        transform::flip(stored.current_mut(), axis);
+        stored.record(Edit::Flip { axis });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
+
+// With `fit_within` the image keeps its aspect ratio and is scaled to the
+// largest size inside `width`x`height`; otherwise it's stretched to exactly
+// that size. A calibrated scale is adjusted to match. `edge` is what the
+// resampling kernel reads past the image's sides, clamped by default.
+#[tauri::command]
+pub async fn resize_image(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    width: u32,
+    height: u32,
+    filter: Option<ResizeFilter>,
+    fit_within: Option<bool>,
+    edge: Option<EdgeMode>,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("resize_image", PerfBackend::Cpu);
+    let filter = filter.unwrap_or_default();
+    let edge = edge.unwrap_or_default();
+    store.with_image_mut(id, |stored| {
+        let (old_width, old_height) = stored.current.dimensions();
+        let (width, height) = if fit_within.unwrap_or(false) {
+            transform::fit_within(old_width, old_height, width, height)
+        } else {
+            (width, height)
+        };
+        stored.current = transform::resize(&stored.current, width, height, filter, edge)?.into();
+        stored.record(Edit::Resize {
+            width,
+            height,
+            filter,
+            edge,
+        });
+        timer.finish(width, height);
+        Ok(stored.handle(id))
+    })
+}
+
+// Enlarges by a whole `factor` of 2 to 4 with an upscaler made for pixel
+// art, where `resize_image` would blur or, with nearest-neighbour, keep every
+// staircase.
+#[tauri::command]
+pub async fn scale_pixel_art(
+    store: State<'_, ImageStore>,
+    id: ImageId,
+    algorithm: PixelArtAlgorithm,
+    factor: u32,
+) -> Result<ImageHandle, AppError> {
+    let timer = OperationTimer::start("scale_pixel_art", PerfBackend::Cpu);
+    store.with_image_mut(id, |stored| {
+        stored.current = pixel_art::scale_pixel_art(&stored.current, algorithm, factor)?.into();
+        stored.record(Edit::ScalePixelArt { algorithm, factor });
+        timer.finish(stored.current.width(), stored.current.height());
+        Ok(stored.handle(id))
+    })
+}
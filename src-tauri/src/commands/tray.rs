@@ -0,0 +1,21 @@
+use tauri::State;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::OpenedImage;
+
+use crate::store::ImageStore;
+use crate::tray;
+
+#[tauri::command]
+pub async fn open_last_file(store: State<'_, ImageStore>) -> Result<OpenedImage, AppError> {
+    tray::open_last_file(&store)
+}
+
+#[tauri::command]
+pub async fn get_last_opened_path(
+    store: State<'_, ImageStore>,
+) -> Result<Option<String>, AppError> {
+    Ok(store
+        .last_opened()
+        .map(|path| path.to_string_lossy().into_owned()))
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+use tauri::State;
+
+use image_analyzer_core::encode::animation::{fit_frame, load_frame};
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{ImageHandle, Operation, PerfBackend, TimelapseResult};
+
+use crate::ffmpeg::ffmpeg_command;
+use crate::jobs::WorkerPool;
+use crate::store::ImageStore;
+use crate::telemetry::OperationTimer;
+
+// Frames are decoded in parallel a batch at a time, then streamed to ffmpeg in order.
+const DECODE_BATCH: usize = 8;
+
+fn encoder_args(output: &Path) -> Result<&'static [&'static str], AppError> {
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "mp4" | "m4v" | "mov" => Ok(&[
+            "-c:v",
+            "libx264",
+            "-preset",
+            "medium",
+            "-crf",
+            "18",
+            "-pix_fmt",
+            "yuv420p",
+            "-movflags",
+            "+faststart",
+        ]),
+        "webm" => Ok(&[
+            "-c:v",
+            "libvpx-vp9",
+            "-b:v",
+            "0",
+            "-crf",
+            "30",
+            "-pix_fmt",
+            "yuv420p",
+        ]),
+        _ => Err(AppError::UnsupportedFormat {
+            format: format!(".{}", ext),
+        }),
+    }
+}
+
+fn ffmpeg_spawn_error(error: std::io::Error) -> AppError {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => AppError::FfmpegNotFound,
+        _ => AppError::FfmpegFailed {
+            reason: format!("Failed to start ffmpeg: {}", error),
+        },
+    }
+}
+
+#[tauri::command]
+pub async fn export_timelapse(
+    workers: State<'_, WorkerPool>,
+    paths: Vec<String>,
+    fps: f32,
+    output: String,
+    operations: Option<Vec<Operation>>,
+) -> Result<TimelapseResult, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::invalid("No frames to export"));
+    }
+    if fps.is_nan() || fps <= 0.0 {
+        return Err(AppError::invalid("Frame rate must be greater than zero"));
+    }
+    let timer = OperationTimer::start("export_timelapse", PerfBackend::Ffmpeg);
+    let operations = operations.unwrap_or_default();
+    let encoder = encoder_args(Path::new(&output))?;
+
+    // The first frame decides the video size; yuv420p needs even dimensions.
+    let first = workers.install(|| load_frame(Path::new(&paths[0]), &operations))?;
+    let width = first.width() & !1;
+    let height = first.height() & !1;
+    if width == 0 || height == 0 {
+        return Err(AppError::invalid("Frames are too small to encode"));
+    }
+
+    // Without -nostats ffmpeg reports progress on stderr as it encodes.
+    let mut child = ffmpeg_command()
+        .arg("-nostats")
+        .args(["-y", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-framerate", &fps.to_string()])
+        .args(["-i", "-"])
+        .args(encoder)
+        .arg(&output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ffmpeg_spawn_error)?;
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open ffmpeg input")?;
+    // Drained while frames are written: if the pipe filled, ffmpeg would block
+    // on it and stop reading frames, and the export would hang.
+    let stderr = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            let _ = pipe.read_to_string(&mut output);
+            output
+        })
+    });
+    let mut write = |frame: RgbaImage| -> Result<(), AppError> {
+        let frame = fit_frame(frame, width, height);
+        stdin
+            .write_all(frame.as_raw())
+            .map_err(|e| AppError::from(format!("ffmpeg stopped accepting frames: {}", e)))
+    };
+    let mut write_result = write(first);
+    for batch in paths[1..].chunks(DECODE_BATCH) {
+        if write_result.is_err() {
+            break;
+        }
+        let frames: Vec<Result<RgbaImage, AppError>> = workers.install(|| {
+            batch
+                .par_iter()
+                .map(|path| load_frame(Path::new(path), &operations))
+                .collect()
+        });
+        write_result = frames.into_iter().try_for_each(|frame| write(frame?));
+    }
+    drop(stdin);
+
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    let status = child.wait();
+
+    // ffmpeg's own failure explains a broken pipe better than the pipe does. A
+    // half-written video is removed either way.
+    let result = match status {
+        Ok(status) if !status.success() => Err(AppError::FfmpegFailed {
+            reason: stderr.trim().to_string(),
+        }),
+        Ok(_) => write_result,
+        Err(e) => Err(AppError::from(format!("Failed to wait for ffmpeg: {}", e))),
+    };
+    if let Err(e) = result {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    timer.finish(width, height);
+
+    Ok(TimelapseResult {
+        output,
+        frame_count: paths.len(),
+        width,
+        height,
+        duration_seconds: paths.len() as f32 / fps,
+    })
+}
+
+#[tauri::command]
+pub async fn extract_video_frame(
+    store: State<'_, ImageStore>,
+    path: String,
+    timestamp: f64,
+) -> Result<ImageHandle, AppError> {
+    if timestamp.is_nan() || timestamp < 0.0 {
+        return Err(AppError::invalid("Timestamp must not be negative"));
+    }
+
+    let timer = OperationTimer::start("extract_video_frame", PerfBackend::Ffmpeg);
+    // Seeking before -i uses keyframe seeking followed by an exact decode, which
+    // is fast even deep into long files.
+    let output = ffmpeg_command()
+        .arg("-nostdin")
+        .args(["-ss", &format!("{:.3}", timestamp)])
+        .args(["-i", &path])
+        .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(ffmpeg_spawn_error)?;
+
+    if !output.status.success() {
+        return Err(AppError::FfmpegFailed {
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    if output.stdout.is_empty() {
+        return Err(AppError::invalid(format!(
+            "No frame found at {:.3}s",
+            timestamp
+        )));
+    }
+
+    let frame = image::load_from_memory(&output.stdout)
+        .map_err(|e| format!("Failed to decode video frame: {}", e))?;
+    timer.finish(frame.width(), frame.height());
+    Ok(store.insert(None, frame.to_rgba8()))
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::WatchFolders;
+
+use crate::tray;
+use crate::watch::FolderWatcher;
+
+// New images in `path` are opened as they arrive and announced with
+// `image-opened` events whose source is `watch_folder`.
+#[tauri::command]
+pub async fn add_watch_folder(
+    app: AppHandle,
+    watcher: State<'_, FolderWatcher>,
+    path: String,
+) -> Result<WatchFolders, AppError> {
+    watcher.add(&app, PathBuf::from(path))?;
+    Ok(watcher.status())
+}
+
+#[tauri::command]
+pub async fn remove_watch_folder(
+    watcher: State<'_, FolderWatcher>,
+    path: String,
+) -> Result<WatchFolders, AppError> {
+    watcher.remove(Path::new(&path));
+    Ok(watcher.status())
+}
+
+// Images that arrive while paused are skipped, not opened on resuming.
+#[tauri::command]
+pub async fn set_watch_folders_paused(
+    app: AppHandle,
+    watcher: State<'_, FolderWatcher>,
+    paused: bool,
+) -> Result<WatchFolders, AppError> {
+    watcher.set_paused(paused);
+    tray::sync_watch_folders(&app);
+    Ok(watcher.status())
+}
+
+#[tauri::command]
+pub async fn get_watch_folders(
+    watcher: State<'_, FolderWatcher>,
+) -> Result<WatchFolders, AppError> {
+    Ok(watcher.status())
+}
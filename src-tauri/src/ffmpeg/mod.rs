@@ -0,0 +1,44 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Tauri places bundled sidecars next to the app executable, optionally suffixed
+// with the target triple; fall back to an ffmpeg found on PATH.
+pub fn ffmpeg_binary() -> PathBuf {
+    let exe_name = if cfg!(windows) {
+        "ffmpeg.exe"
+    } else {
+        "ffmpeg"
+    };
+
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+    {
+        let bundled = dir.join(exe_name);
+        if bundled.is_file() {
+            return bundled;
+        }
+        if let Ok(entries) = dir.read_dir() {
+            let sidecar = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .find(|path| {
+                    path.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.starts_with("ffmpeg-"))
+                        .unwrap_or(false)
+                });
+            if let Some(sidecar) = sidecar {
+                return sidecar;
+            }
+        }
+    }
+
+    PathBuf::from(exe_name)
+}
+
+pub fn ffmpeg_command() -> Command {
+    let mut command = Command::new(ffmpeg_binary());
+    command.args(["-hide_banner", "-loglevel", "error"]);
+    command
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tauri::{AppHandle, Emitter, Manager};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::JobProgress;
+use image_analyzer_core::progress::Progress;
+
+// Folder-level jobs run on this pool rather than rayon's global one, so the
+// worker count can be capped at runtime without restarting the app.
+pub struct WorkerPool {
+    pool: RwLock<Arc<ThreadPool>>,
+    max_workers: RwLock<Option<usize>>,
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self {
+            pool: RwLock::new(Arc::new(
+                build_pool(None).expect("default worker pool can be created"),
+            )),
+            max_workers: RwLock::new(None),
+        }
+    }
+}
+
+fn build_pool(max_workers: Option<usize>) -> Result<ThreadPool, String> {
+    ThreadPoolBuilder::new()
+        .num_threads(max_workers.unwrap_or(0))
+        .thread_name(|i| format!("image-worker-{}", i))
+        .build()
+        .map_err(|e| format!("Failed to start worker pool: {}", e))
+}
+
+impl WorkerPool {
+    // Runs `op` inside the pool so any rayon iterators it uses are bounded by
+    // the configured worker count. Jobs already running keep the pool they
+    // started on.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        let pool = self.pool.read().unwrap().clone();
+        pool.install(op)
+    }
+
+    // `None` restores the default of one worker per logical core.
+    pub fn set_max_workers(&self, max_workers: Option<usize>) -> Result<usize, AppError> {
+        if max_workers == Some(0) {
+            return Err(AppError::invalid("Worker count must be at least 1"));
+        }
+        let pool = build_pool(max_workers)?;
+        let threads = pool.current_num_threads();
+        *self.pool.write().unwrap() = Arc::new(pool);
+        *self.max_workers.write().unwrap() = max_workers;
+        Ok(threads)
+    }
+
+    pub fn max_workers(&self) -> Option<usize> {
+        *self.max_workers.read().unwrap()
+    }
+
+    pub fn current_workers(&self) -> usize {
+        self.pool.read().unwrap().current_num_threads()
+    }
+}
+
+// Cancellation flags of the jobs running now, by the id the frontend gave
+// them when starting the command.
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobRegistry {
+    // Returns false if no job with that id is running, e.g. because it has
+    // already finished.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.jobs.lock().unwrap().get(id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// A running job, reporting its progress as `event` and registered for
+// `cancel_job` until dropped.
+pub struct Job {
+    id: String,
+    event: &'static str,
+    app: AppHandle,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Job {
+    // No job id means the caller doesn't follow progress, and gets `None`.
+    pub fn start(
+        app: &AppHandle,
+        id: Option<String>,
+        event: &'static str,
+    ) -> Result<Option<Job>, AppError> {
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let registry = app.state::<JobRegistry>();
+        let mut jobs = registry.jobs.lock().unwrap();
+        if jobs.contains_key(&id) {
+            return Err(AppError::invalid(format!("Job {} is already running", id)));
+        }
+        jobs.insert(id.clone(), cancelled.clone());
+        Ok(Some(Job {
+            id,
+            event,
+            app: app.clone(),
+            cancelled,
+        }))
+    }
+}
+
+impl Progress for Job {
+    fn report(&self, fraction: f32) {
+        let progress = JobProgress {
+            job_id: self.id.clone(),
+            percent: (fraction * 100.0).clamp(0.0, 100.0),
+        };
+        let _ = self.app.emit(self.event, progress);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        self.app
+            .state::<JobRegistry>()
+            .jobs
+            .lock()
+            .unwrap()
+            .remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_count_can_be_capped_and_restored() {
+        let pool = WorkerPool::default();
+        assert_eq!(pool.max_workers(), None);
+
+        assert_eq!(pool.set_max_workers(Some(2)).unwrap(), 2);
+        assert_eq!(pool.install(rayon::current_num_threads), 2);
+        assert_eq!(pool.max_workers(), Some(2));
+
+        let err = pool.set_max_workers(Some(0)).unwrap_err();
+        assert!(matches!(err, AppError::InvalidArgument { .. }), "{:?}", err);
+        assert_eq!(pool.current_workers(), 2);
+
+        pool.set_max_workers(None).unwrap();
+        assert_eq!(pool.current_workers(), rayon::current_num_threads());
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+use image_analyzer_core::decode::decode_file;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{ImageHandle, OpenSource, OpenedImage};
+
+use crate::store::ImageStore;
+use crate::tray::show_main_window;
+
+// Registered with the OS so `imageanalyzer://open?path=<file>` links reach the app.
+pub const DEEP_LINK_SCHEME: &str = "imageanalyzer";
+
+// Images opened while the app was starting. The window isn't listening for
+// `image-opened` yet, so it collects these with `take_pending_opens` instead.
+#[derive(Default)]
+pub struct PendingOpens(Mutex<Vec<OpenedImage>>);
+
+impl PendingOpens {
+    pub fn take(&self) -> Vec<OpenedImage> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+pub fn open_file(store: &ImageStore, path: PathBuf) -> Result<ImageHandle, AppError> {
+    let decoded = decode_file(&path, false)?;
+    Ok(store.insert_with_source(Some(path), decoded.source, decoded.image))
+}
+
+// Shows the window and tells it to switch to an image opened from outside.
+pub fn announce(app: &AppHandle, opened: OpenedImage) {
+    show_main_window(app);
+    let _ = app.emit("image-opened", opened);
+}
+
+// Files named on the command line. Flags like `--serve` and URLs are skipped;
+// relative paths are resolved against `cwd`.
+pub fn paths_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-') && !arg.contains("://"))
+        .map(|arg| cwd.join(arg))
+        .collect()
+}
+
+// `file://` URLs come from macOS "Open With"; ours carry the path as a query parameter.
+pub fn path_from_url(url: &Url) -> Option<PathBuf> {
+    match url.scheme() {
+        "file" => url.to_file_path().ok(),
+        DEEP_LINK_SCHEME => url
+            .query_pairs()
+            .find(|(key, _)| key == "path")
+            .map(|(_, path)| PathBuf::from(path.into_owned())),
+        _ => None,
+    }
+}
+
+fn open(app: &AppHandle, path: PathBuf, source: OpenSource) -> Result<OpenedImage, AppError> {
+    let handle = open_file(&app.state::<ImageStore>(), path.clone())?;
+    Ok(OpenedImage {
+        handle,
+        path: Some(path.to_string_lossy().into_owned()),
+        source,
+    })
+}
+
+// Opens paths that arrived while the app is running, e.g. from a second
+// instance. With nothing to open, the existing window is still brought forward.
+pub fn open_paths(app: &AppHandle, paths: Vec<PathBuf>, source: OpenSource) {
+    if paths.is_empty() {
+        show_main_window(app);
+        return;
+    }
+    for path in paths {
+        match open(app, path, source) {
+            Ok(opened) => announce(app, opened),
+            Err(error) => {
+                show_main_window(app);
+                let _ = app.emit("open-error", error);
+            }
+        }
+    }
+}
+
+pub fn open_urls(app: &AppHandle, urls: &[Url]) {
+    let paths = urls.iter().filter_map(path_from_url).collect();
+    open_paths(app, paths, OpenSource::DeepLink);
+}
+
+// Opens paths handed over at launch, keeping them for the window to collect.
+pub fn queue_paths(app: &AppHandle, paths: Vec<PathBuf>, source: OpenSource) {
+    for path in paths {
+        match open(app, path, source) {
+            Ok(opened) => app.state::<PendingOpens>().0.lock().unwrap().push(opened),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
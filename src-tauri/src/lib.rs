@@ -1,21 +1,241 @@
+mod clipboard;
 mod commands;
-mod models;
+mod ffmpeg;
+mod jobs;
+mod launch;
+mod server;
+mod store;
+mod telemetry;
+mod tray;
+mod watch;
 
+use clipboard::ClipboardWatcher;
 use commands::*;
+use image_analyzer_core::models::OpenSource;
+use jobs::{JobRegistry, WorkerPool};
+use launch::PendingOpens;
+use server::AutomationServer;
+use store::ImageStore;
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use watch::FolderWatcher;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+    // Must be registered first. A second launch (e.g. double-clicking another
+    // file) hands its arguments to this instance and exits.
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            let paths = launch::paths_from_args(&argv, std::path::Path::new(&cwd));
+            launch::open_paths(app, paths, OpenSource::FileAssociation);
+        }));
+    }
+
+    builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(ImageStore::default())
+        .manage(WorkerPool::default())
+        .manage(JobRegistry::default())
+        .manage(AutomationServer::default())
+        .manage(ClipboardWatcher::default())
+        .manage(FolderWatcher::default())
+        .manage(PendingOpens::default())
+        .setup(|app| {
+            let store = app.state::<ImageStore>();
+            if let Ok(cache_dir) = app.path().app_cache_dir() {
+                store.set_spill_dir(cache_dir.join("spill"));
+            }
+            telemetry::init(app.handle().clone());
+            tray::create(app)?;
+
+            // Installed builds register the scheme at install time; this covers
+            // development and portable builds on Linux and Windows.
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                eprintln!("Failed to register {}:// links: {}", launch::DEEP_LINK_SCHEME, e);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                launch::open_urls(&handle, &event.urls());
+            });
+            let args: Vec<String> = std::env::args().collect();
+            let cwd = std::env::current_dir().unwrap_or_default();
+            launch::queue_paths(
+                app.handle(),
+                launch::paths_from_args(&args, &cwd),
+                OpenSource::FileAssociation,
+            );
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                let paths = urls.iter().filter_map(launch::path_from_url).collect();
+                launch::queue_paths(app.handle(), paths, OpenSource::DeepLink);
+            }
+            let handle = app.handle().clone();
+            store.on_memory_pressure(move |pressure| {
+                let _ = handle.emit("memory-pressure", pressure);
+            });
+
+            // `--serve` starts the automation API at launch and prints where to reach it.
+            if std::env::args().any(|arg| arg == "--serve") {
+                let info = app
+                    .state::<AutomationServer>()
+                    .start(app.handle().clone(), server::DEFAULT_PORT)?;
+                println!("Automation API listening on {} (token {})", info.url, info.token);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             load_image,
             analyze_image,
             adjust_brightness,
             adjust_contrast,
             convert_to_grayscale,
-            save_image
+            save_image,
+            open_image,
+            get_image_data,
+            close_image,
+            auto_lens_correct,
+            cluster_by_location,
+            organize_by_date,
+            detect_bursts,
+            export_timelapse,
+            extract_video_frame,
+            create_animation,
+            export_comparison,
+            get_split_preview,
+            hash_file,
+            find_exact_duplicates,
+            verify_image,
+            open_image_progressive,
+            set_black_white_points,
+            list_looks,
+            apply_look,
+            get_system_info,
+            set_max_workers,
+            get_memory_usage,
+            set_memory_budget,
+            start_automation_server,
+            stop_automation_server,
+            get_automation_server,
+            load_clipboard_image,
+            set_clipboard_watch,
+            is_clipboard_watch_enabled,
+            open_last_file,
+            get_last_opened_path,
+            add_watch_folder,
+            remove_watch_folder,
+            set_watch_folders_paused,
+            get_watch_folders,
+            take_pending_opens,
+            describe_error,
+            auto_trim,
+            extend_canvas,
+            analyze_composition,
+            detect_horizon,
+            analyze_balance,
+            detect_text_regions,
+            detect_faces,
+            redact_regions,
+            redact_detected,
+            add_border,
+            apply_shape_mask,
+            export_icon_set,
+            list_social_presets,
+            export_social_presets,
+            slice_image,
+            sample_line,
+            count_blobs,
+            set_scale,
+            measure,
+            render_grid_overlay,
+            diff_analysis,
+            suggest_crops,
+            preview_operation,
+            apply_operation,
+            edit_proxy,
+            get_proxy_info,
+            get_proxy_data,
+            commit_proxy_edits,
+            discard_proxy_edits,
+            get_operation_log,
+            replay_log,
+            export_log,
+            undo,
+            redo,
+            get_history,
+            extract_metadata,
+            save_recipe,
+            apply_recipe,
+            reexport_with_recipes,
+            crop_image,
+            import_settings,
+            build_noise_profile,
+            rotate_image,
+            flip_image,
+            flat_field_correct,
+            resize_image,
+            subtract_dark_frame,
+            apply_blur,
+            apply_sharpen,
+            apply_levels,
+            apply_curve,
+            analyze_stars,
+            auto_enhance,
+            match_exposure,
+            adjust_hsl,
+            cull_folder,
+            adjust_white_balance,
+            auto_white_balance,
+            export_audit,
+            verify_audit,
+            batch_process,
+            error_level_analysis,
+            detect_copy_move,
+            cancel_job,
+            inspect_hidden_data,
+            get_preview,
+            compare_exif_thumbnail,
+            extract_prnu,
+            match_prnu,
+            generate_thumbnail,
+            dither,
+            export_image,
+            export_ascii,
+            load_image_frames,
+            get_image_frame,
+            scale_pixel_art,
+            get_raw_mosaic,
+            estimate_noise,
+            set_precision,
+            measure_sharpness,
+            detect_edges,
+            image_math,
+            compare_images,
+            select_by_histogram,
+            compute_phash,
+            find_duplicates,
+            simulate_color_vision,
+            grabcut,
+            smooth_preserve_edges,
+            sample_color_at,
+            check_contrast
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, _event| {
+            // macOS delivers "Open With" and double-clicked files as an event
+            // rather than as arguments.
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let paths = urls
+                    .iter()
+                    .filter(|url| url.scheme() == "file")
+                    .filter_map(launch::path_from_url)
+                    .collect();
+                launch::open_paths(_app, paths, OpenSource::FileAssociation);
+            }
+        });
 }
@@ -0,0 +1,364 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use image_analyzer_core::decode::{decode_file, verify_file};
+use image_analyzer_core::encode::write_image;
+use image_analyzer_core::error::{AppError, ErrorCode};
+use image_analyzer_core::models::{AutomationServerInfo, Edit, ExportResult, ImageId, Operation};
+use image_analyzer_core::{analysis, processing};
+
+use crate::store::ImageStore;
+
+pub const DEFAULT_PORT: u16 = 47823;
+// Request bodies are small JSON documents; anything bigger is a mistake.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+struct RunningServer {
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+    info: AutomationServerInfo,
+}
+
+// Opt-in REST API on localhost so scripts can drive the app while it runs.
+// Every request must carry `Authorization: Bearer <token>`.
+#[derive(Default)]
+pub struct AutomationServer {
+    running: Mutex<Option<RunningServer>>,
+}
+
+impl AutomationServer {
+    pub fn start(&self, app: AppHandle, port: u16) -> Result<AutomationServerInfo, String> {
+        let mut running = self.running.lock().unwrap();
+        if let Some(current) = running.as_ref() {
+            return Ok(current.info.clone());
+        }
+
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to start automation server on port {}: {}", port, e))?;
+        let server = Arc::new(server);
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .unwrap_or(port);
+        let token = generate_token()?;
+        let info = AutomationServerInfo {
+            url: format!("http://127.0.0.1:{}", port),
+            port,
+            token: token.clone(),
+        };
+
+        let listener = server.clone();
+        let thread = std::thread::Builder::new()
+            .name("automation-server".to_string())
+            .spawn(move || {
+                for request in listener.incoming_requests() {
+                    handle(&app, &token, request);
+                }
+            })
+            .map_err(|e| format!("Failed to start automation server: {}", e))?;
+
+        *running = Some(RunningServer {
+            server,
+            thread,
+            info: info.clone(),
+        });
+        Ok(info)
+    }
+
+    pub fn stop(&self) -> bool {
+        let Some(running) = self.running.lock().unwrap().take() else {
+            return false;
+        };
+        running.server.unblock();
+        let _ = running.thread.join();
+        true
+    }
+
+    pub fn info(&self) -> Option<AutomationServerInfo> {
+        self.running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|running| running.info.clone())
+    }
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Compares without short-circuiting so response timing doesn't leak how much
+// of a guessed token was right.
+fn token_matches(request: &Request, token: &str) -> bool {
+    let Some(provided) = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    same_token(provided, token)
+}
+
+fn same_token(provided: &str, token: &str) -> bool {
+    provided.len() == token.len()
+        && provided
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+struct ApiError {
+    status: u16,
+    code: Option<ErrorCode>,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: 400,
+            code: None,
+            message: message.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self {
+            status: 404,
+            code: None,
+            message: "No such endpoint".to_string(),
+        }
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self::bad_request(message)
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        let status = match error.code() {
+            ErrorCode::FileNotFound | ErrorCode::ImageNotFound => 404,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::ReadFailed | ErrorCode::WriteFailed | ErrorCode::OperationFailed => 500,
+            _ => 400,
+        };
+        Self {
+            status,
+            code: Some(error.code()),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<ErrorCode>,
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Response<std::io::Cursor<Vec<u8>>> {
+    let data = serde_json::to_vec(body).unwrap_or_else(|_| b"{}".to_vec());
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    Response::from_data(data)
+        .with_status_code(status)
+        .with_header(content_type)
+}
+
+fn handle(app: &AppHandle, token: &str, mut request: Request) {
+    let response = if !token_matches(&request, token) {
+        json_response(
+            401,
+            &ErrorBody {
+                error: "Missing or invalid bearer token",
+                code: None,
+            },
+        )
+    } else {
+        match route(app, &mut request) {
+            Ok(body) => json_response(200, &body),
+            Err(e) => json_response(
+                e.status,
+                &ErrorBody {
+                    error: &e.message,
+                    code: e.code,
+                },
+            ),
+        }
+    };
+    let _ = request.respond(response);
+}
+
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, ApiError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::bad_request(format!("Failed to read request body: {}", e)))?;
+    serde_json::from_slice(&body)
+        .map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<serde_json::Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+#[derive(Deserialize)]
+struct PathRequest {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct ConvertRequest {
+    input: String,
+    output: String,
+    #[serde(default)]
+    operations: Vec<Operation>,
+}
+
+#[derive(Deserialize)]
+struct OperationsRequest {
+    operations: Vec<Operation>,
+}
+
+fn route(app: &AppHandle, request: &mut Request) -> Result<serde_json::Value, ApiError> {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let method = request.method().clone();
+    let store = app.state::<ImageStore>();
+
+    match (&method, segments.as_slice()) {
+        (Method::Get, ["health"]) => to_json(serde_json::json!({ "status": "ok" })),
+
+        // Stateless file operations.
+        (Method::Post, ["analyze"]) => {
+            let body: PathRequest = read_json(request)?;
+            let decoded = decode_file(Path::new(&body.path), false)?;
+            to_json(analysis::analyze(
+                decoded.image.as_raw(),
+                decoded.source.as_ref(),
+                analysis::palette::DEFAULT_PALETTE_SIZE,
+            ))
+        }
+        (Method::Post, ["verify"]) => {
+            let body: PathRequest = read_json(request)?;
+            to_json(verify_file(Path::new(&body.path))?)
+        }
+        (Method::Post, ["convert"]) => {
+            let body: ConvertRequest = read_json(request)?;
+            let decoded = decode_file(Path::new(&body.input), false)?;
+            let mut img = decoded.image;
+            processing::apply_operations(&mut img, &body.operations);
+            let file_size = write_image(&img, decoded.source.as_ref(), &body.output)?;
+            to_json(ExportResult {
+                output: body.output,
+                width: img.width(),
+                height: img.height(),
+                file_size,
+            })
+        }
+
+        // Images held in the app, shared with the UI's handles.
+        (Method::Post, ["images"]) => {
+            let body: PathRequest = read_json(request)?;
+            let decoded = decode_file(Path::new(&body.path), false)?;
+            to_json(store.insert_with_source(
+                Some(PathBuf::from(body.path)),
+                decoded.source,
+                decoded.image,
+            ))
+        }
+        (Method::Get, ["images", id, "analysis"]) => {
+            let id = parse_id(id)?;
+            let result = store.with_image(id, |stored| {
+                Ok(analysis::analyze(
+                    stored.current.as_raw(),
+                    stored.source_color.as_ref(),
+                    analysis::palette::DEFAULT_PALETTE_SIZE,
+                ))
+            })?;
+            to_json(result)
+        }
+        (Method::Post, ["images", id, "operations"]) => {
+            let id = parse_id(id)?;
+            let body: OperationsRequest = read_json(request)?;
+            let handle = store.with_image_mut(id, |stored| {
+                for operation in body.operations {
+                    stored.apply_edit(Edit::Operation { operation })?;
+                }
+                Ok(stored.handle(id))
+            })?;
+            to_json(handle)
+        }
+        (Method::Post, ["images", id, "save"]) => {
+            let id = parse_id(id)?;
+            let body: PathRequest = read_json(request)?;
+            let result = store.with_image(id, |stored| {
+                let file_size =
+                    write_image(&stored.current, stored.source_color.as_ref(), &body.path)?;
+                Ok(ExportResult {
+                    output: body.path.clone(),
+                    width: stored.current.width(),
+                    height: stored.current.height(),
+                    file_size,
+                })
+            })?;
+            to_json(result)
+        }
+        (Method::Delete, ["images", id]) => {
+            let id = parse_id(id)?;
+            to_json(serde_json::json!({ "closed": store.remove(id) }))
+        }
+
+        _ => Err(ApiError::not_found()),
+    }
+}
+
+fn parse_id(segment: &str) -> Result<ImageId, ApiError> {
+    segment
+        .parse()
+        .map_err(|_| ApiError::bad_request(format!("Invalid image id: {}", segment)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_hex() {
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 64);
+        assert!(token.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token().unwrap());
+    }
+
+    #[test]
+    fn only_the_exact_token_matches() {
+        let token = generate_token().unwrap();
+        assert!(same_token(&token, &token));
+        assert!(!same_token(&token[..63], &token));
+        assert!(!same_token("", &token));
+        let mut wrong = token.clone().into_bytes();
+        wrong[63] = if wrong[63] == b'0' { b'1' } else { b'0' };
+        assert!(!same_token(std::str::from_utf8(&wrong).unwrap(), &token));
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use image_analyzer_core::models::{Edit, LoggedEdit};
+
+// Steps per stack that keep a full image for instant undo or redo. Older
+// steps are recomputed from the original and the log instead.
+const MAX_SNAPSHOTS: usize = 4;
+
+#[derive(Clone)]
+pub(super) struct Step {
+    pub logged: LoggedEdit,
+    // The image on the other side of the step: from before it while applied,
+    // from after it once undone.
+    pub snapshot: Option<Arc<RgbaImage>>,
+    // What the step multiplied the calibrated size of a pixel by.
+    pub scale_change: f64,
+}
+
+// An image's edit history: the committed edits its current state is made of,
+// the undone ones available to redo, and recent snapshots of both.
+#[derive(Clone, Default)]
+pub struct EditSession {
+    pub(super) applied: Vec<Step>,
+    // The next edit to redo is last.
+    pub(super) undone: Vec<Step>,
+    // How many of `applied` the original already includes. They can't be undone.
+    pub(super) baseline: usize,
+    next_sequence: u64,
+}
+
+impl EditSession {
+    // A new edit forks the history, so whatever was undone can no longer be redone.
+    pub fn record(&mut self, edit: Edit, width: u32, height: u32, scale_change: f64) {
+        self.next_sequence += 1;
+        self.applied.push(Step {
+            logged: LoggedEdit {
+                sequence: self.next_sequence,
+                edit,
+                width,
+                height,
+            },
+            snapshot: None,
+            scale_change,
+        });
+        self.undone.clear();
+    }
+
+    // Keeps `before` as the undo snapshot of the step at `index`, if an edit
+    // recorded one there that can be undone.
+    pub fn keep_before(&mut self, index: usize, before: Arc<RgbaImage>) {
+        if index < self.baseline {
+            return;
+        }
+        if let Some(step) = self.applied.get_mut(index) {
+            step.snapshot = Some(before);
+        }
+        self.trim_snapshots();
+    }
+
+    pub fn trim_snapshots(&mut self) {
+        for stack in [&mut self.applied, &mut self.undone] {
+            let oldest_kept = stack.len().saturating_sub(MAX_SNAPSHOTS);
+            for step in &mut stack[..oldest_kept] {
+                step.snapshot = None;
+            }
+        }
+    }
+
+    pub fn drop_snapshots(&mut self) {
+        for step in self.applied.iter_mut().chain(&mut self.undone) {
+            step.snapshot = None;
+        }
+    }
+
+    pub fn snapshot_bytes(&self) -> u64 {
+        self.applied
+            .iter()
+            .chain(&self.undone)
+            .filter_map(|step| step.snapshot.as_ref())
+            .map(|img| img.as_raw().len() as u64)
+            .sum()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.applied.len() > self.baseline
+    }
+
+    pub fn applied(&self) -> Vec<LoggedEdit> {
+        self.applied
+            .iter()
+            .map(|step| step.logged.clone())
+            .collect()
+    }
+
+    // In redo order.
+    pub fn undone(&self) -> Vec<LoggedEdit> {
+        self.undone
+            .iter()
+            .rev()
+            .map(|step| step.logged.clone())
+            .collect()
+    }
+}
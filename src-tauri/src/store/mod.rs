@@ -0,0 +1,928 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+
+use image::RgbaImage;
+use sysinfo::System;
+
+use image_analyzer_core::analysis::measure;
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{
+    Edit, EditHistory, ImageHandle, ImageId, MemoryPressure, MemoryUsage, Operation, OperationLog,
+    Precision, ProxyInfo, ReplayResult, SourceColorType, SpatialScale,
+};
+use image_analyzer_core::processing::working::{self, WorkingImage};
+use image_analyzer_core::processing::{self, edits, preview};
+
+mod history;
+
+use history::EditSession;
+
+// Used when the amount of physical memory can't be determined.
+const FALLBACK_BUDGET: u64 = 4 * 1024 * 1024 * 1024;
+
+// Longest side of the editing proxy. Images that already fit are edited directly.
+const PROXY_MAX_DIMENSION: u32 = 2048;
+
+// Pixel buffers are shared copy-on-write: a snapshot for a reader costs a
+// reference count, and an edit copies a buffer only while readers still hold it.
+#[derive(Clone)]
+pub struct StoredImage {
+    pub source_path: Option<PathBuf>,
+    pub source_color: Option<SourceColorType>,
+    pub original: Arc<RgbaImage>,
+    pub current: Arc<RgbaImage>,
+    // Calibration set with `set_scale`, in units of `current`'s pixels. Kept
+    // in step with resampling edits as they're recorded, undone and redone.
+    pub scale: Option<SpatialScale>,
+    // Downscaled copy of `current` with `pending` applied, built on first use.
+    proxy: Option<Arc<RgbaImage>>,
+    // Edits made through the proxy that `current` doesn't have yet. They are
+    // replayed at full resolution the next time `current` is read.
+    pending: Vec<Operation>,
+    // Committed edits, recorded by the commands that make them, and undo state.
+    session: EditSession,
+    pub precision: Precision,
+    // At `Precision::F32`, the float copy of `current` that tone and colour
+    // edits work on, with the `current` it was last rounded to. Any other
+    // change to `current` leaves it stale, and it is rebuilt from `current`
+    // on the next edit that uses it.
+    working: Option<(Weak<RgbaImage>, Arc<WorkingImage>)>,
+}
+
+impl StoredImage {
+    pub fn handle(&self, id: ImageId) -> ImageHandle {
+        ImageHandle {
+            id,
+            width: self.current.width(),
+            height: self.current.height(),
+        }
+    }
+
+    pub fn current_mut(&mut self) -> &mut RgbaImage {
+        Arc::make_mut(&mut self.current)
+    }
+
+    fn byte_size(&self) -> u64 {
+        let proxy = self.proxy.as_ref().map_or(0, |proxy| proxy.as_raw().len());
+        let working = self
+            .working
+            .as_ref()
+            .map_or(0, |(_, working)| working.byte_size());
+        // Until the first edit, the original and current are one buffer.
+        let current = if Arc::ptr_eq(&self.original, &self.current) {
+            0
+        } else {
+            self.current.as_raw().len()
+        };
+        (self.original.as_raw().len() + current + proxy) as u64
+            + working
+            + self.session.snapshot_bytes()
+    }
+
+    // Whether `edit` goes through the float working copy rather than
+    // straight to `current`.
+    pub fn works_in_float(&self, edit: &Edit) -> bool {
+        self.precision == Precision::F32 && working::supports_edit(edit)
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+        if precision == Precision::U8 {
+            self.working = None;
+        }
+    }
+
+    // Applies `edit` to `current`, through the working copy when
+    // `works_in_float`.
+    fn apply_to_current(&mut self, edit: &Edit) -> Result<(), AppError> {
+        let source = self.source_path.as_deref();
+        if !self.works_in_float(edit) {
+            return edits::apply_edit(Arc::make_mut(&mut self.current), edit, source);
+        }
+        let mut working = match self.working.take() {
+            Some((rounded, working)) if Weak::ptr_eq(&rounded, &Arc::downgrade(&self.current)) => {
+                working
+            }
+            _ => Arc::new(WorkingImage::from_rgba(&self.current)),
+        };
+        Arc::make_mut(&mut working).apply_edit(edit, source)?;
+        self.current = Arc::new(working.to_rgba());
+        self.working = Some((Arc::downgrade(&self.current), working));
+        Ok(())
+    }
+
+    fn apply_operation(&mut self, operation: Operation) {
+        let edit = Edit::Operation { operation };
+        self.apply_to_current(&edit).expect("operations don't fail");
+        self.record(edit);
+    }
+
+    fn needs_proxy(&self) -> bool {
+        self.current.width().max(self.current.height()) > PROXY_MAX_DIMENSION
+    }
+
+    // The image interactive edits are shown on: the proxy for large images,
+    // otherwise `current` itself.
+    pub fn proxy(&mut self) -> &RgbaImage {
+        if !self.needs_proxy() {
+            return &self.current;
+        }
+        let (current, pending) = (&self.current, &self.pending);
+        self.proxy.get_or_insert_with(|| {
+            Arc::new(preview::render_proxy(current, PROXY_MAX_DIMENSION, pending))
+        })
+    }
+
+    // Applies `operation` to the proxy only, deferring the full-resolution work.
+    pub fn edit_proxy(&mut self, operation: Operation) {
+        if !self.needs_proxy() {
+            self.apply_operation(operation);
+            return;
+        }
+        self.proxy();
+        let proxy = self.proxy.as_mut().expect("proxy was just built");
+        processing::apply_operation(Arc::make_mut(proxy), &operation);
+        self.pending.push(operation);
+    }
+
+    // Drops edits that haven't reached `current`, reverting the proxy to match it.
+    pub fn discard_proxy_edits(&mut self) {
+        self.pending.clear();
+        self.proxy = None;
+    }
+
+    pub fn proxy_info(&mut self, id: ImageId) -> ProxyInfo {
+        let (width, height) = self.current.dimensions();
+        let (proxy_width, proxy_height) = self.proxy().dimensions();
+        ProxyInfo {
+            id,
+            width,
+            height,
+            proxy_width,
+            proxy_height,
+            pending_edits: self.pending.len(),
+        }
+    }
+
+    fn apply_pending(&mut self) {
+        for operation in std::mem::take(&mut self.pending) {
+            self.apply_operation(operation);
+        }
+    }
+
+    // Applies and records `edit` the way the command that makes it would.
+    pub fn apply_edit(&mut self, edit: Edit) -> Result<(), AppError> {
+        self.apply_to_current(&edit)?;
+        let redaction = matches!(edit, Edit::RedactRegions { .. });
+        self.record(edit);
+        if redaction {
+            self.rebase();
+        }
+        Ok(())
+    }
+
+    // Adds an edit just applied to `current` to the image's history.
+    pub fn record(&mut self, edit: Edit) {
+        let before = self.session.applied.last().map_or_else(
+            || self.original.dimensions(),
+            |step| (step.logged.width, step.logged.height),
+        );
+        let after = self.current.dimensions();
+        let change = measure::pixel_size_change(&edit, before, after);
+        self.rescale(change);
+        self.session.record(edit, after.0, after.1, change);
+    }
+
+    fn rescale(&mut self, change: f64) {
+        if let Some(scale) = &mut self.scale {
+            scale.units_per_pixel *= change;
+        }
+    }
+
+    // Makes `current` the new original, with the edits logged so far part of
+    // it and no longer undoable.
+    pub fn rebase(&mut self) {
+        self.original = self.current.clone();
+        self.session.baseline = self.session.applied.len();
+        self.session.drop_snapshots();
+    }
+
+    pub fn operation_log(&self, id: ImageId) -> OperationLog {
+        OperationLog {
+            id,
+            source_path: self
+                .source_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            width: self.original.width(),
+            height: self.original.height(),
+            baseline: self.session.baseline,
+            entries: self.session.applied(),
+        }
+    }
+
+    pub fn history(&self, id: ImageId) -> EditHistory {
+        EditHistory {
+            handle: self.handle(id),
+            applied: self.session.applied(),
+            undone: self.session.undone(),
+            can_undo: self.session.can_undo(),
+            can_redo: !self.session.undone.is_empty(),
+        }
+    }
+
+    // The original with every applied edit redone, at the image's precision.
+    fn rebuild(&self) -> Result<RgbaImage, AppError> {
+        let mut img = (*self.original).clone();
+        working::apply_edits(
+            &mut img,
+            self.session.applied[self.session.baseline..]
+                .iter()
+                .map(|step| &step.logged.edit),
+            self.source_path.as_deref(),
+            self.precision,
+        )?;
+        Ok(img)
+    }
+
+    // Rebuilds `current` by redoing the logged edits on the original.
+    pub fn replay(&mut self, id: ImageId) -> Result<ReplayResult, AppError> {
+        let img = self.rebuild()?;
+        let matches_current =
+            img.dimensions() == self.current.dimensions() && img.as_raw() == self.current.as_raw();
+        self.current = Arc::new(img);
+        Ok(ReplayResult {
+            handle: self.handle(id),
+            replayed: self.session.applied.len() - self.session.baseline,
+            matches_current,
+        })
+    }
+
+    pub fn undo(&mut self) -> Result<(), AppError> {
+        if !self.session.can_undo() {
+            return Err(AppError::invalid("Nothing to undo"));
+        }
+        let mut step = self.session.applied.pop().expect("an edit to undo");
+        let before = match step.snapshot.take() {
+            Some(before) => before,
+            None => Arc::new(self.rebuild()?),
+        };
+        step.snapshot = Some(std::mem::replace(&mut self.current, before));
+        self.rescale(1.0 / step.scale_change);
+        self.session.undone.push(step);
+        self.session.trim_snapshots();
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<(), AppError> {
+        let mut step = self
+            .session
+            .undone
+            .pop()
+            .ok_or_else(|| AppError::invalid("Nothing to redo"))?;
+        let after = match step.snapshot.take() {
+            Some(after) => after,
+            None => {
+                let mut img = (*self.current).clone();
+                edits::apply_edit(&mut img, &step.logged.edit, self.source_path.as_deref())?;
+                Arc::new(img)
+            }
+        };
+        step.snapshot = Some(std::mem::replace(&mut self.current, after));
+        self.rescale(step.scale_change);
+        self.session.applied.push(step);
+        self.session.trim_snapshots();
+        Ok(())
+    }
+}
+
+// An image whose pixel buffers were written out to free memory. Everything
+// but the pixels stays resident so it can be restored transparently.
+struct SpilledImage {
+    file: PathBuf,
+    source_path: Option<PathBuf>,
+    source_color: Option<SourceColorType>,
+    scale: Option<SpatialScale>,
+    pending: Vec<Operation>,
+    session: EditSession,
+    precision: Precision,
+    original_size: (u32, u32),
+    current_size: (u32, u32),
+    // Whether original and current were one buffer, written once.
+    shared: bool,
+}
+
+enum Slot {
+    Resident(StoredImage),
+    Spilled(SpilledImage),
+}
+
+struct Entry {
+    // Held only to take or replace a snapshot, and while spilling or restoring.
+    slot: Mutex<Slot>,
+    // Held by a writer for its whole edit, so edits to one image apply in
+    // order and the image isn't spilled underneath them.
+    edit: Mutex<()>,
+    last_used: AtomicU64,
+}
+
+type PressureListener = Box<dyn Fn(MemoryPressure) + Send + Sync>;
+
+// Images live in managed state so commands can pass small handles over IPC
+// instead of full RGBA buffers. Resident pixels are kept under a byte budget by
+// spilling the least recently used images to disk.
+//
+// Commands work on snapshots rather than under a store-wide lock: reads run
+// concurrently with each other and with edits, including on the same image.
+pub struct ImageStore {
+    next_id: AtomicU64,
+    entries: RwLock<HashMap<ImageId, Arc<Entry>>>,
+    clock: AtomicU64,
+    budget: AtomicU64,
+    spill_dir: Mutex<PathBuf>,
+    last_opened: Mutex<Option<PathBuf>>,
+    pressure_listener: OnceLock<PressureListener>,
+}
+
+impl Default for ImageStore {
+    fn default() -> Self {
+        let mut system = System::new();
+        system.refresh_memory();
+        let budget = match system.total_memory() {
+            0 => FALLBACK_BUDGET,
+            total => total / 2,
+        };
+
+        Self {
+            next_id: AtomicU64::new(0),
+            entries: RwLock::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            budget: AtomicU64::new(budget),
+            spill_dir: Mutex::new(
+                std::env::temp_dir().join(format!("image-analyzer-spill-{}", std::process::id())),
+            ),
+            last_opened: Mutex::new(None),
+            pressure_listener: OnceLock::new(),
+        }
+    }
+}
+
+impl ImageStore {
+    pub fn insert(&self, source_path: Option<PathBuf>, image: RgbaImage) -> ImageHandle {
+        self.insert_with_source(source_path, None, image)
+    }
+
+    pub fn insert_with_source(
+        &self,
+        source_path: Option<PathBuf>,
+        source_color: Option<SourceColorType>,
+        image: RgbaImage,
+    ) -> ImageHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(path) = &source_path {
+            *self.last_opened.lock().unwrap() = Some(path.clone());
+        }
+        let image = Arc::new(image);
+        let stored = StoredImage {
+            source_path,
+            source_color,
+            original: image.clone(),
+            current: image,
+            scale: None,
+            proxy: None,
+            pending: Vec::new(),
+            session: EditSession::default(),
+            precision: Precision::default(),
+            working: None,
+        };
+        let handle = stored.handle(id);
+
+        let entry = Entry {
+            slot: Mutex::new(Slot::Resident(stored)),
+            edit: Mutex::new(()),
+            last_used: AtomicU64::new(self.tick()),
+        };
+        self.entries.write().unwrap().insert(id, Arc::new(entry));
+        let pressure = self.enforce_budget(id);
+        self.notify(pressure);
+        handle
+    }
+
+    // Full-resolution access. Any edits still pending on the proxy are applied
+    // to `current` first. `f` runs on a snapshot without holding any lock.
+    pub fn with_image<T>(
+        &self,
+        id: ImageId,
+        f: impl FnOnce(&StoredImage) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let entry = self.entry(id)?;
+        let (mut stored, restored) = snapshot(&entry)?;
+        if !stored.pending.is_empty() {
+            // The replay changes `current`, so it goes through the edit path once.
+            stored = self.edit(id, |stored| {
+                stored.apply_pending();
+                Ok(stored.clone())
+            })?;
+        } else if restored {
+            let pressure = self.enforce_budget(id);
+            self.notify(pressure);
+        }
+        f(&stored)
+    }
+
+    pub fn with_image_mut<T>(
+        &self,
+        id: ImageId,
+        f: impl FnOnce(&mut StoredImage) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        self.edit(id, |stored| {
+            stored.apply_pending();
+            let result = f(stored);
+            // Rebuilt from the edited image when next needed.
+            stored.proxy = None;
+            result
+        })
+    }
+
+    // Access for proxy edits, which must not trigger the full-resolution replay.
+    pub fn with_proxy<T>(
+        &self,
+        id: ImageId,
+        f: impl FnOnce(&mut StoredImage) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        self.edit(id, f)
+    }
+
+    // Runs `f` on a snapshot and commits it back if `f` succeeds, so a failed
+    // edit leaves the image untouched and readers never see a half-done one.
+    fn edit<T>(
+        &self,
+        id: ImageId,
+        f: impl FnOnce(&mut StoredImage) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let entry = self.entry(id)?;
+        let (result, restored) = {
+            let _edit = entry.edit.lock().unwrap();
+            let (mut stored, restored) = snapshot(&entry)?;
+            let before = stored.current.clone();
+            let steps = stored.session.applied.len();
+            let result = f(&mut stored);
+            if result.is_ok() {
+                // Undoing what `f` recorded can then skip the replay.
+                stored.session.keep_before(steps, before);
+                *entry.slot.lock().unwrap() = Slot::Resident(stored);
+            }
+            (result, restored)
+        };
+
+        // Edits can grow the image, and restoring one may push others out.
+        if restored || result.is_ok() {
+            let pressure = self.enforce_budget(id);
+            self.notify(pressure);
+        }
+        result
+    }
+
+    fn entry(&self, id: ImageId) -> Result<Arc<Entry>, AppError> {
+        let entry = self
+            .entries
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(AppError::ImageNotFound { id })?;
+        entry.last_used.store(self.tick(), Ordering::Relaxed);
+        Ok(entry)
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn remove(&self, id: ImageId) -> bool {
+        let removed = self.entries.write().unwrap().remove(&id);
+        if let Some(entry) = &removed {
+            if let Slot::Spilled(spilled) = &*entry.slot.lock().unwrap() {
+                let _ = fs::remove_file(&spilled.file);
+            }
+        }
+        removed.is_some()
+    }
+
+    // The most recent file loaded into the store, even if it has since been closed.
+    pub fn last_opened(&self) -> Option<PathBuf> {
+        self.last_opened.lock().unwrap().clone()
+    }
+
+    pub fn on_memory_pressure(&self, listener: impl Fn(MemoryPressure) + Send + Sync + 'static) {
+        let _ = self.pressure_listener.set(Box::new(listener));
+    }
+
+    pub fn set_spill_dir(&self, dir: PathBuf) {
+        *self.spill_dir.lock().unwrap() = dir;
+    }
+
+    pub fn set_budget(&self, budget: u64) -> MemoryUsage {
+        self.budget.store(budget, Ordering::Relaxed);
+        // Ids start at 1, so no image is exempt from spilling here.
+        let pressure = self.enforce_budget(0);
+        self.notify(pressure);
+        self.usage()
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        let entries = self.entries.read().unwrap();
+        let spilled = entries
+            .values()
+            .filter(|entry| matches!(*entry.slot.lock().unwrap(), Slot::Spilled(_)))
+            .count();
+        MemoryUsage {
+            used_bytes: resident_bytes(&entries),
+            budget_bytes: self.budget.load(Ordering::Relaxed),
+            resident_images: entries.len() - spilled,
+            spilled_images: spilled,
+        }
+    }
+
+    // Spills least recently used images until resident pixels fit the budget.
+    // `keep` is the image the caller is working on and is never spilled.
+    fn enforce_budget(&self, keep: ImageId) -> Option<MemoryPressure> {
+        let entries = self.entries.read().unwrap();
+        let budget = self.budget.load(Ordering::Relaxed);
+        let mut used = resident_bytes(&entries);
+        if used <= budget {
+            return None;
+        }
+
+        let mut candidates: Vec<(u64, ImageId)> = entries
+            .iter()
+            .filter(|(id, _)| **id != keep)
+            .map(|(id, entry)| (entry.last_used.load(Ordering::Relaxed), *id))
+            .collect();
+        candidates.sort_unstable();
+
+        let spill_dir = self.spill_dir.lock().unwrap().clone();
+        let mut spilled = Vec::new();
+        let mut failed = None;
+        for (_, id) in candidates {
+            if used <= budget {
+                break;
+            }
+            // Skip images mid-edit; the committed edit would replace the spill.
+            let Ok(_edit) = entries[&id].edit.try_lock() else {
+                continue;
+            };
+            let mut slot = entries[&id].slot.lock().unwrap();
+            if !matches!(*slot, Slot::Resident(_)) {
+                continue;
+            }
+            match spill(&mut slot, &spill_dir, id) {
+                Ok(freed) => {
+                    used -= freed;
+                    spilled.push(id);
+                }
+                Err(e) => {
+                    failed = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let message = match (&failed, used > budget) {
+            (Some(e), _) => format!("Could not free memory by spilling images to disk: {}", e),
+            (None, true) => {
+                "The active image alone exceeds the memory budget; close other images or raise the budget"
+                    .to_string()
+            }
+            (None, false) => format!("Moved {} inactive image(s) to disk", spilled.len()),
+        };
+        Some(MemoryPressure {
+            used_bytes: used,
+            budget_bytes: budget,
+            spilled,
+            over_budget: used > budget,
+            message,
+        })
+    }
+
+    fn notify(&self, pressure: Option<MemoryPressure>) {
+        if let (Some(pressure), Some(listener)) = (pressure, self.pressure_listener.get()) {
+            listener(pressure);
+        }
+    }
+}
+
+impl Drop for ImageStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.spill_dir.get_mut().unwrap());
+    }
+}
+
+fn resident_bytes(entries: &HashMap<ImageId, Arc<Entry>>) -> u64 {
+    entries
+        .values()
+        .map(|entry| match &*entry.slot.lock().unwrap() {
+            Slot::Resident(stored) => stored.byte_size(),
+            Slot::Spilled(_) => 0,
+        })
+        .sum()
+}
+
+// Clones the image out of its slot, reading it back from disk first if it was
+// spilled. Also returns whether that restore happened.
+fn snapshot(entry: &Entry) -> Result<(StoredImage, bool), AppError> {
+    let mut slot = entry.slot.lock().unwrap();
+    let restored = restore(&mut slot)?;
+    let Slot::Resident(stored) = &*slot else {
+        unreachable!("restore leaves the image resident");
+    };
+    Ok((stored.clone(), restored))
+}
+
+// Writes the original and current buffers back to back as raw RGBA, or just
+// the one when they're shared, and returns the number of bytes freed. The
+// proxy, float working copy and undo snapshots are dropped; all can be
+// rebuilt from what's kept, the working copy at the 8-bit precision of
+// `current`.
+fn spill(slot: &mut Slot, dir: &std::path::Path, id: ImageId) -> Result<u64, String> {
+    let Slot::Resident(stored) = slot else {
+        return Ok(0);
+    };
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create spill directory: {}", e))?;
+    let file = dir.join(format!("{}.rgba", id));
+
+    let shared = Arc::ptr_eq(&stored.original, &stored.current);
+    let write = || -> std::io::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(&file)?);
+        out.write_all(stored.original.as_raw())?;
+        if !shared {
+            out.write_all(stored.current.as_raw())?;
+        }
+        out.flush()
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&file);
+        return Err(format!("Failed to write {}: {}", file.display(), e));
+    }
+
+    let freed = stored.byte_size();
+    let spilled = SpilledImage {
+        file,
+        source_path: stored.source_path.take(),
+        source_color: stored.source_color.take(),
+        scale: stored.scale.take(),
+        pending: std::mem::take(&mut stored.pending),
+        session: {
+            let mut session = std::mem::take(&mut stored.session);
+            session.drop_snapshots();
+            session
+        },
+        precision: stored.precision,
+        original_size: stored.original.dimensions(),
+        current_size: stored.current.dimensions(),
+        shared,
+    };
+    *slot = Slot::Spilled(spilled);
+    Ok(freed)
+}
+
+// Reads a spilled image back into memory. Returns whether anything was loaded.
+fn restore(slot: &mut Slot) -> Result<bool, AppError> {
+    let Slot::Spilled(spilled) = slot else {
+        return Ok(false);
+    };
+
+    let read_error = |e: std::io::Error| AppError::io(&spilled.file, e, false);
+    let mut input = BufReader::new(fs::File::open(&spilled.file).map_err(read_error)?);
+    let mut read_image = |(width, height): (u32, u32)| -> Result<RgbaImage, AppError> {
+        let mut buf = vec![0; width as usize * height as usize * 4];
+        input.read_exact(&mut buf).map_err(read_error)?;
+        RgbaImage::from_raw(width, height, buf)
+            .ok_or_else(|| AppError::from("Spilled image is corrupt"))
+    };
+    let original = Arc::new(read_image(spilled.original_size)?);
+    let current = if spilled.shared {
+        original.clone()
+    } else {
+        Arc::new(read_image(spilled.current_size)?)
+    };
+
+    let _ = fs::remove_file(&spilled.file);
+    *slot = Slot::Resident(StoredImage {
+        source_path: spilled.source_path.take(),
+        source_color: spilled.source_color.take(),
+        scale: spilled.scale.take(),
+        original,
+        current,
+        proxy: None,
+        pending: std::mem::take(&mut spilled.pending),
+        session: std::mem::take(&mut spilled.session),
+        precision: spilled.precision,
+        working: None,
+    });
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image_analyzer_core::models::{EdgeMode, ResizeFilter};
+
+    fn store_with(width: u32, height: u32) -> (ImageStore, ImageId) {
+        let store = ImageStore::default();
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x * 7) as u8, (y * 11) as u8, 90, 255])
+        });
+        let id = store.insert(None, img).id;
+        (store, id)
+    }
+
+    fn units_per_pixel(store: &ImageStore, id: ImageId) -> f64 {
+        store
+            .with_image(id, |stored| {
+                Ok(stored.scale.as_ref().unwrap().units_per_pixel)
+            })
+            .unwrap()
+    }
+
+    fn spill_dir(test: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("image-analyzer-{}-{}", test, std::process::id()))
+    }
+
+    fn pixels(store: &ImageStore, id: ImageId) -> (RgbaImage, RgbaImage) {
+        store
+            .with_image(id, |stored| {
+                Ok(((*stored.original).clone(), (*stored.current).clone()))
+            })
+            .unwrap()
+    }
+
+    fn resize(width: u32, height: u32) -> Edit {
+        Edit::Resize {
+            width,
+            height,
+            filter: ResizeFilter::default(),
+            edge: EdgeMode::default(),
+        }
+    }
+
+    #[test]
+    fn scale_follows_resize_through_undo_and_redo() {
+        let (store, id) = store_with(40, 20);
+        store
+            .with_image_mut(id, |stored| {
+                stored.scale = Some(measure::calibrate(10.0, 5.0, "mm")?);
+                stored.apply_edit(resize(20, 10))
+            })
+            .unwrap();
+        assert_eq!(units_per_pixel(&store, id), 1.0);
+
+        store.with_image_mut(id, |stored| stored.undo()).unwrap();
+        assert_eq!(units_per_pixel(&store, id), 0.5);
+        store.with_image_mut(id, |stored| stored.redo()).unwrap();
+        assert_eq!(units_per_pixel(&store, id), 1.0);
+    }
+
+    #[test]
+    fn unedited_image_is_one_buffer_counted_once() {
+        let (store, id) = store_with(64, 32);
+        let used = store.usage().used_bytes;
+        assert_eq!(used, 64 * 32 * 4);
+        store
+            .with_image(id, |stored| {
+                assert!(Arc::ptr_eq(&stored.original, &stored.current));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn calibration_after_a_resize_survives_its_undo() {
+        let (store, id) = store_with(40, 20);
+        store
+            .with_image_mut(id, |stored| {
+                stored.apply_edit(resize(80, 40))?;
+                stored.scale = Some(measure::calibrate(4.0, 1.0, "cm")?);
+                stored.undo()
+            })
+            .unwrap();
+        // Pixels of the undone image are twice the size of the resized ones.
+        assert_eq!(units_per_pixel(&store, id), 0.5);
+    }
+
+    #[test]
+    fn spilled_images_come_back_as_they_were() {
+        let (store, edited) = store_with(30, 20);
+        store.set_spill_dir(spill_dir("spill-round-trip"));
+        let unedited = store.insert(None, RgbaImage::new(8, 8)).id;
+        store
+            .with_image_mut(edited, |stored| {
+                stored.apply_edit(Edit::Operation {
+                    operation: Operation::Grayscale,
+                })
+            })
+            .unwrap();
+        let before = pixels(&store, edited);
+
+        let usage = store.set_budget(0);
+        assert_eq!(usage.spilled_images, 2);
+        assert_eq!(usage.used_bytes, 0);
+
+        assert_eq!(pixels(&store, edited), before);
+        assert_ne!(before.0, before.1);
+        store
+            .with_image(unedited, |stored| {
+                assert!(Arc::ptr_eq(&stored.original, &stored.current));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    fn brighten(amount: f32) -> Edit {
+        Edit::Operation {
+            operation: Operation::Brightness { amount },
+        }
+    }
+
+    #[test]
+    fn replay_rebuilds_current_from_the_log() {
+        let (store, id) = store_with(24, 16);
+        let result = store
+            .with_image_mut(id, |stored| {
+                stored.apply_edit(brighten(0.2))?;
+                stored.apply_edit(resize(12, 8))?;
+                stored.replay(id)
+            })
+            .unwrap();
+        assert_eq!(result.replayed, 2);
+        assert!(result.matches_current);
+        assert_eq!(
+            store
+                .with_image(id, |s| Ok(s.current.dimensions()))
+                .unwrap(),
+            (12, 8)
+        );
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let (store, id) = store_with(24, 16);
+        let (original, _) = pixels(&store, id);
+        store
+            .with_image_mut(id, |stored| {
+                stored.apply_edit(brighten(0.3))?;
+                stored.apply_edit(resize(12, 8))
+            })
+            .unwrap();
+        let (_, edited) = pixels(&store, id);
+
+        store
+            .with_image_mut(id, |stored| {
+                stored.undo()?;
+                stored.undo()
+            })
+            .unwrap();
+        assert_eq!(pixels(&store, id).1, original);
+        let err = store
+            .with_image_mut(id, |stored| stored.undo())
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidArgument { .. }), "{:?}", err);
+
+        store
+            .with_image_mut(id, |stored| {
+                stored.redo()?;
+                stored.redo()
+            })
+            .unwrap();
+        assert_eq!(pixels(&store, id).1, edited);
+        let history = store
+            .with_image(id, |stored| Ok(stored.history(id)))
+            .unwrap();
+        assert_eq!(history.applied.len(), 2);
+        assert!(!history.can_redo);
+    }
+
+    #[test]
+    fn undo_after_a_spill_rebuilds_from_the_log() {
+        let (store, id) = store_with(24, 16);
+        store.set_spill_dir(spill_dir("spill-undo"));
+        store
+            .with_image_mut(id, |stored| {
+                stored.apply_edit(brighten(0.3))?;
+                stored.apply_edit(brighten(-0.1))
+            })
+            .unwrap();
+        let mut expected = pixels(&store, id).0;
+        edits::apply_edit(&mut expected, &brighten(0.3), None).unwrap();
+
+        // Spilling drops the undo snapshots.
+        store.set_budget(0);
+        store.with_image_mut(id, |stored| stored.undo()).unwrap();
+        assert_eq!(pixels(&store, id).1, expected);
+    }
+}
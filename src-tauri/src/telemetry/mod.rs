@@ -0,0 +1,46 @@
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use tauri::{AppHandle, Emitter};
+
+use image_analyzer_core::models::{PerfBackend, PerfMetric};
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+// Metrics are dropped until the app handle is registered during setup.
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+// Times one command and emits a `perf-metric` event when it finishes
+// successfully. Failed operations are not reported.
+pub struct OperationTimer {
+    command: &'static str,
+    backend: PerfBackend,
+    started: Instant,
+}
+
+impl OperationTimer {
+    pub fn start(command: &'static str, backend: PerfBackend) -> Self {
+        Self {
+            command,
+            backend,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self, width: u32, height: u32) {
+        let Some(app) = APP.get() else {
+            return;
+        };
+        let metric = PerfMetric {
+            command: self.command.to_string(),
+            width,
+            height,
+            megapixels: width as f64 * height as f64 / 1_000_000.0,
+            duration_ms: self.started.elapsed().as_secs_f64() * 1000.0,
+            backend: self.backend,
+        };
+        let _ = app.emit("perf-metric", metric);
+    }
+}
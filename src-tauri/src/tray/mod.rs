@@ -0,0 +1,170 @@
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::models::{OpenSource, OpenedImage};
+
+use crate::clipboard::{self, ClipboardWatcher};
+use crate::launch::{self, announce};
+use crate::store::ImageStore;
+use crate::watch::FolderWatcher;
+
+const ANALYZE_CLIPBOARD: &str = "analyze_clipboard";
+const OPEN_LAST_FILE: &str = "open_last_file";
+const WATCH_CLIPBOARD: &str = "watch_clipboard";
+const PAUSE_WATCH_FOLDERS: &str = "pause_watch_folders";
+const SHOW_WINDOW: &str = "show_window";
+const QUIT: &str = "quit";
+
+// Kept in managed state so the check marks can follow watch changes made from
+// the main window.
+pub struct TrayMenu {
+    watch_clipboard: CheckMenuItem<Wry>,
+    pause_watch_folders: CheckMenuItem<Wry>,
+}
+
+pub fn create(app: &App) -> tauri::Result<()> {
+    let watching = app.state::<ClipboardWatcher>().is_running();
+    let watch_clipboard = CheckMenuItem::with_id(
+        app,
+        WATCH_CLIPBOARD,
+        "Watch clipboard",
+        true,
+        watching,
+        None::<&str>,
+    )?;
+    let pause_watch_folders = CheckMenuItem::with_id(
+        app,
+        PAUSE_WATCH_FOLDERS,
+        "Pause watch folders",
+        true,
+        app.state::<FolderWatcher>().is_paused(),
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(
+                app,
+                ANALYZE_CLIPBOARD,
+                "Analyze clipboard image",
+                true,
+                None::<&str>,
+            )?,
+            &MenuItem::with_id(app, OPEN_LAST_FILE, "Open last file", true, None::<&str>)?,
+            &pause_watch_folders,
+            &watch_clipboard,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, SHOW_WINDOW, "Show Image Analyzer", true, None::<&str>)?,
+            &MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .menu(&menu)
+        .tooltip("Image Analyzer")
+        .show_menu_on_left_click(true)
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayMenu {
+        watch_clipboard,
+        pause_watch_folders,
+    });
+    Ok(())
+}
+
+// Updates the tray check mark after the clipboard watch is toggled elsewhere.
+pub fn sync_clipboard_watch(app: &AppHandle) {
+    if let Some(tray) = app.try_state::<TrayMenu>() {
+        let running = app.state::<ClipboardWatcher>().is_running();
+        let _ = tray.watch_clipboard.set_checked(running);
+    }
+}
+
+// Updates the tray check mark after watch folders are paused or resumed
+// elsewhere.
+pub fn sync_watch_folders(app: &AppHandle) {
+    if let Some(tray) = app.try_state::<TrayMenu>() {
+        let paused = app.state::<FolderWatcher>().is_paused();
+        let _ = tray.pause_watch_folders.set_checked(paused);
+    }
+}
+
+// Reopens the most recently loaded file as a new image.
+pub fn open_last_file(store: &ImageStore) -> Result<OpenedImage, AppError> {
+    let path = store
+        .last_opened()
+        .ok_or_else(|| AppError::invalid("No file has been opened yet"))?;
+    let handle = launch::open_file(store, path.clone())?;
+    Ok(OpenedImage {
+        handle,
+        path: Some(path.to_string_lossy().into_owned()),
+        source: OpenSource::RecentFile,
+    })
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let result = match event.id().as_ref() {
+        ANALYZE_CLIPBOARD => {
+            clipboard::load_clipboard_image(&app.state::<ImageStore>()).map(|image| {
+                Some(OpenedImage {
+                    handle: image.handle,
+                    path: None,
+                    source: OpenSource::Clipboard,
+                })
+            })
+        }
+        OPEN_LAST_FILE => open_last_file(&app.state::<ImageStore>()).map(Some),
+        WATCH_CLIPBOARD => toggle_clipboard_watch(app).map(|_| None),
+        PAUSE_WATCH_FOLDERS => {
+            let watcher = app.state::<FolderWatcher>();
+            watcher.set_paused(!watcher.is_paused());
+            sync_watch_folders(app);
+            Ok(None)
+        }
+        SHOW_WINDOW => {
+            show_main_window(app);
+            Ok(None)
+        }
+        QUIT => {
+            app.exit(0);
+            Ok(None)
+        }
+        _ => Ok(None),
+    };
+
+    match result {
+        Ok(Some(opened)) => announce(app, opened),
+        Ok(None) => {}
+        Err(error) => {
+            show_main_window(app);
+            let _ = app.emit("tray-error", error);
+        }
+    }
+}
+
+fn toggle_clipboard_watch(app: &AppHandle) -> Result<(), AppError> {
+    let watcher = app.state::<ClipboardWatcher>();
+    let result = if watcher.is_running() {
+        watcher.stop();
+        Ok(())
+    } else {
+        watcher.start(app.clone())
+    };
+    // The menu flips its own check mark on click; make it match reality.
+    sync_clipboard_watch(app);
+    result
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use image_analyzer_core::error::AppError;
+use image_analyzer_core::library::list_images;
+use image_analyzer_core::models::{OpenSource, WatchFolders};
+
+use crate::launch;
+
+// Polled like the clipboard: change notifications differ on every platform
+// and are unreliable on network shares.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+struct Folder {
+    path: PathBuf,
+    // Images that were there when the watch started, or have been opened.
+    seen: HashSet<PathBuf>,
+    // New images and their size at the last poll. One is opened once its size
+    // holds for a poll, so a file still being copied isn't read half-written.
+    arriving: HashMap<PathBuf, u64>,
+}
+
+impl Folder {
+    fn new(path: PathBuf) -> Result<Self, AppError> {
+        let mut folder = Folder {
+            path,
+            seen: HashSet::new(),
+            arriving: HashMap::new(),
+        };
+        folder.rescan()?;
+        Ok(folder)
+    }
+
+    // Counts everything in the folder now as seen.
+    fn rescan(&mut self) -> Result<(), AppError> {
+        self.seen = list_images(&self.path.to_string_lossy())?
+            .into_iter()
+            .collect();
+        self.arriving.clear();
+        Ok(())
+    }
+
+    // Images that have finished arriving since the last poll. A folder that
+    // can't be read, e.g. an unmounted drive, is tried again next time.
+    fn poll(&mut self) -> Vec<PathBuf> {
+        let Ok(images) = list_images(&self.path.to_string_lossy()) else {
+            return Vec::new();
+        };
+        let mut arrived = Vec::new();
+        let mut arriving = HashMap::new();
+        for path in images {
+            if self.seen.contains(&path) {
+                continue;
+            }
+            let Ok(size) = fs::metadata(&path).map(|m| m.len()) else {
+                continue;
+            };
+            if size > 0 && self.arriving.get(&path) == Some(&size) {
+                self.seen.insert(path.clone());
+                arrived.push(path);
+            } else {
+                arriving.insert(path, size);
+            }
+        }
+        self.arriving = arriving;
+        arrived
+    }
+}
+
+struct Watch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+// One thread polls every folder, running while there is any to watch.
+#[derive(Default)]
+pub struct FolderWatcher {
+    folders: Arc<Mutex<Vec<Folder>>>,
+    paused: Arc<AtomicBool>,
+    watch: Mutex<Option<Watch>>,
+}
+
+impl FolderWatcher {
+    // Images that appear in `path` from now on are opened and announced with
+    // `image-opened`, as files opened from the OS are. Whatever is in the
+    // folder already is ignored.
+    pub fn add(&self, app: &AppHandle, path: PathBuf) -> Result<(), AppError> {
+        if !path.is_dir() {
+            return Err(AppError::invalid(format!(
+                "{} is not a folder",
+                path.display()
+            )));
+        }
+        {
+            let mut folders = self.folders.lock().unwrap();
+            if !folders.iter().any(|folder| folder.path == path) {
+                folders.push(Folder::new(path)?);
+            }
+        }
+        self.start(app)
+    }
+
+    // The thread stops with the last folder. That's decided under the watch
+    // lock `start` takes, so a folder added meanwhile isn't left unwatched.
+    pub fn remove(&self, path: &Path) {
+        let mut watch = self.watch.lock().unwrap();
+        let empty = {
+            let mut folders = self.folders.lock().unwrap();
+            folders.retain(|folder| folder.path != path);
+            folders.is_empty()
+        };
+        if !empty {
+            return;
+        }
+        if let Some(running) = watch.take() {
+            running.stop.store(true, Ordering::Relaxed);
+            let _ = running.thread.join();
+        }
+    }
+
+    // Images that arrive while paused are skipped rather than opened on
+    // resuming, so an import made meanwhile doesn't open all at once. The
+    // folders are rescanned under the lock the poll takes, so none slip in.
+    pub fn set_paused(&self, paused: bool) {
+        let mut folders = self.folders.lock().unwrap();
+        if self.paused.load(Ordering::Relaxed) && !paused {
+            for folder in folders.iter_mut() {
+                let _ = folder.rescan();
+            }
+        }
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> WatchFolders {
+        WatchFolders {
+            folders: self
+                .folders
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|folder| folder.path.to_string_lossy().into_owned())
+                .collect(),
+            paused: self.is_paused(),
+        }
+    }
+
+    fn start(&self, app: &AppHandle) -> Result<(), AppError> {
+        let mut watch = self.watch.lock().unwrap();
+        if watch.is_some() {
+            return Ok(());
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let folders = self.folders.clone();
+        let paused = self.paused.clone();
+        let app = app.clone();
+        let thread = std::thread::Builder::new()
+            .name("folder-watcher".to_string())
+            .spawn(move || {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL);
+                    let arrived: Vec<PathBuf> = {
+                        let mut folders = folders.lock().unwrap();
+                        if paused.load(Ordering::Relaxed) {
+                            continue;
+                        }
+                        folders.iter_mut().flat_map(Folder::poll).collect()
+                    };
+                    if !arrived.is_empty() {
+                        launch::open_paths(&app, arrived, OpenSource::WatchFolder);
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to start folder watcher: {}", e))?;
+
+        *watch = Some(Watch { stop, thread });
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! Browser bindings for fast local previews. Buffers are RGBA8 in the same
+//! layout as `ImageData.data` and canvas `ImageData`.
+
+use image::RgbaImage;
+use wasm_bindgen::prelude::*;
+
+use image_analyzer_core::analysis;
+use image_analyzer_core::models::AnalysisResult;
+use image_analyzer_core::processing::{self, looks};
+
+fn to_image(data: &[u8], width: u32, height: u32) -> Result<RgbaImage, JsError> {
+    RgbaImage::from_raw(width, height, data.to_vec())
+        .ok_or_else(|| JsError::new("Pixel buffer does not match the image size"))
+}
+
+// Resolves to an `AnalysisResult`, as returned by the `analyze_image` command.
+#[wasm_bindgen]
+pub fn analyze(data: &[u8], palette_size: Option<usize>) -> Result<JsValue, JsError> {
+    let palette_size = palette_size.unwrap_or(analysis::palette::DEFAULT_PALETTE_SIZE);
+    let result = analysis::analyze(data, None, palette_size);
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// Takes two `AnalysisResult`s and resolves to an `AnalysisDiff`.
+#[wasm_bindgen(js_name = diffAnalysis)]
+pub fn diff_analysis(a: JsValue, b: JsValue) -> Result<JsValue, JsError> {
+    let a: AnalysisResult =
+        serde_wasm_bindgen::from_value(a).map_err(|e| JsError::new(&e.to_string()))?;
+    let b: AnalysisResult =
+        serde_wasm_bindgen::from_value(b).map_err(|e| JsError::new(&e.to_string()))?;
+    let diff = analysis::diff::diff_analysis(&a, &b);
+    serde_wasm_bindgen::to_value(&diff).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = adjustBrightness)]
+pub fn adjust_brightness(data: &mut [u8], amount: f32) {
+    processing::adjust_brightness(data, amount);
+}
+
+#[wasm_bindgen(js_name = adjustContrast)]
+pub fn adjust_contrast(data: &mut [u8], amount: f32) {
+    processing::adjust_contrast(data, amount);
+}
+
+#[wasm_bindgen(js_name = adjustHsl)]
+pub fn adjust_hsl(data: &mut [u8], hue_shift: f32, saturation: f32, lightness: f32) {
+    processing::adjust_hsl(data, hue_shift, saturation, lightness);
+}
+
+#[wasm_bindgen(js_name = convertToGrayscale)]
+pub fn convert_to_grayscale(data: &mut [u8]) {
+    processing::convert_to_grayscale(data);
+}
+
+// Resolves to `LookInfo[]`.
+#[wasm_bindgen(js_name = listLooks)]
+pub fn list_looks() -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(&looks::list_looks()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = applyLook)]
+pub fn apply_look(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    name: &str,
+    intensity: f32,
+) -> Result<(), JsError> {
+    let mut img = to_image(data, width, height)?;
+    looks::apply_look(&mut img, name, intensity).map_err(|e| JsError::new(&e.to_string()))?;
+    data.copy_from_slice(img.as_raw());
+    Ok(())
+}
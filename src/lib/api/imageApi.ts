@@ -1,32 +1,1060 @@
-import type { ImageData, HistogramData, AnalysisResult } from './types';
+import type {
+  ImageData,
+  HistogramData,
+  AnalysisResult,
+  ImageHandle,
+  LensCorrectionReport,
+  LocationClusterResult,
+  OrganizeResult,
+  TransferMode,
+  Burst,
+  Operation,
+  TimelapseResult,
+  FrameSource,
+  AnimationFormat,
+  AnimationResult,
+  ComparisonLayout,
+  ExportResult,
+  SplitOrientation,
+  ColorVisionDeficiency,
+  HashAlgorithm,
+  FileHash,
+  DuplicateFileGroup,
+  PerceptualHashAlgorithm,
+  PerceptualHash,
+  NearDuplicateReport,
+  ImageVerification,
+  PixelPoint,
+  LookInfo,
+  SystemInfo,
+  MemoryUsage,
+  AutomationServerInfo,
+  ClipboardImage,
+  OpenedImage,
+  WatchFolders,
+  AppError,
+  ErrorCode,
+  ErrorDescription,
+  TrimResult,
+  CanvasFill,
+  Margins,
+  CompositionAnalysis,
+  HorizonAnalysis,
+  BalanceAnalysis,
+  TextRegion,
+  FaceRegion,
+  RedactionStyle,
+  RedactionTarget,
+  AutoRedaction,
+  PixelRect,
+  BorderSpec,
+  MaskShape,
+  IconTarget,
+  SocialPreset,
+  SliceLayout,
+  SliceResult,
+  LineProfile,
+  SampledColor,
+  ContrastReport,
+  Point,
+  Scribble,
+  BlobAnalysis,
+  SpatialScale,
+  Measurement,
+  OverlaySpec,
+  AnalysisDiff,
+  ImageComparison,
+  CropSuggestion,
+  OperationPreview,
+  ProxyInfo,
+  OperationLog,
+  ReplayResult,
+  EditHistory,
+  ImageMetadata,
+  RecipeExportReport,
+  SettingsImport,
+  NoiseProfile,
+  NoiseEstimate,
+  FlipAxis,
+  ResizeFilter,
+  EdgeMode,
+  PixelArtAlgorithm,
+  ChannelLevels,
+  CurveChannel,
+  StarAnalysis,
+  SharpnessReport,
+  EdgeMethod,
+  EdgeThresholds,
+  EdgeOutput,
+  Color,
+  EnhanceMethod,
+  ExposureMatchReport,
+  CullRules,
+  CullReport,
+  WhiteBalanceMethod,
+  BatchReport,
+  Edit,
+  CopyMoveMatch,
+  HiddenDataReport,
+  ImagePreview,
+  ThumbnailComparison,
+  PrnuFingerprint,
+  PrnuMatch,
+  DitherPalette,
+  DitherAlgorithm,
+  ExportOptions,
+  AsciiCharset,
+  AsciiColorMode,
+  ImageFrames,
+  RawMosaic,
+  Precision,
+  MathOp,
+  MathOperand,
+  ImageMathResult,
+} from './types';
 import { invoke } from '@tauri-apps/api/core';
 
-export async function loadImage(path: string): Promise<ImageData> {
-  return invoke('load_image', { path });
+// Commands that take a `jobId` report progress as 'analysis-progress' or
+// 'filter-progress' events (a `JobProgress`) and can be stopped with
+// `cancelJob`, after which they reject with a 'cancelled' error. Ids are
+// chosen by the caller and must be unique among running jobs.
+export async function cancelJob(jobId: string): Promise<boolean> {
+  return invoke('cancel_job', { jobId });
 }
 
-export async function analyzeImage(imageData: ImageData): Promise<AnalysisResult> {
-  return invoke('analyze_image', { imageData });
+export async function loadImage(path: string, recover = false): Promise<ImageData> {
+  return invoke('load_image', { path, recover });
+}
+
+// `paletteSize` is the number of dominant colours to extract (default 5, at most 32).
+export async function analyzeImage(
+  id: number,
+  paletteSize?: number,
+  jobId?: string
+): Promise<AnalysisResult> {
+  return invoke('analyze_image', { id, paletteSize, jobId });
 }
 
 export async function adjustBrightness(
-  imageData: ImageData,
-  amount: number
-): Promise<ImageData> {
-  return invoke('adjust_brightness', { imageData, amount });
+  id: number,
+  amount: number,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('adjust_brightness', { id, amount, jobId });
 }
 
 export async function adjustContrast(
+  id: number,
+  amount: number,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('adjust_contrast', { id, amount, jobId });
+}
+
+// `temperature` is the Kelvin of the light the shot was taken under: lower
+// values cool the image and higher ones warm it, 6500 leaving it neutral.
+// `tint` runs from -100 (green) to 100 (magenta).
+export async function adjustWhiteBalance(
+  id: number,
+  temperature: number,
+  tint: number,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('adjust_white_balance', { id, temperature, tint, jobId });
+}
+
+export async function autoWhiteBalance(
+  id: number,
+  method: WhiteBalanceMethod,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('auto_white_balance', { id, method, jobId });
+}
+
+// `hueShift` is in degrees and `saturation` a multiplier, 1 leaving it
+// unchanged; `lightness` from -1 to 1 moves pixels towards black or white.
+export async function adjustHsl(
+  id: number,
+  hueShift: number,
+  saturation: number,
+  lightness: number,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('adjust_hsl', { id, hueShift, saturation, lightness, jobId });
+}
+
+export async function convertToGrayscale(id: number, jobId?: string): Promise<ImageHandle> {
+  return invoke('convert_to_grayscale', { id, jobId });
+}
+
+export async function saveImage(id: number, path: string): Promise<void> {
+  return invoke('save_image', { id, path });
+}
+
+export async function openImage(path: string, recover = false): Promise<ImageHandle> {
+  return invoke('open_image', { path, recover });
+}
+
+// The full pixel buffer. Prefer `getPreview` for display; this copies every
+// pixel over IPC.
+export async function getImageData(id: number): Promise<ImageData> {
+  return invoke('get_image_data', { id });
+}
+
+// A PNG scaled to fit within `maxDimension`, never scaled up.
+export async function getPreview(id: number, maxDimension: number): Promise<ImagePreview> {
+  return invoke('get_preview', { id, maxDimension });
+}
+
+export async function closeImage(id: number): Promise<void> {
+  return invoke('close_image', { id });
+}
+
+export async function autoLensCorrect(id: number): Promise<LensCorrectionReport> {
+  return invoke('auto_lens_correct', { id });
+}
+
+export async function clusterByLocation(
+  dir: string,
+  radiusKm: number
+): Promise<LocationClusterResult> {
+  return invoke('cluster_by_location', { dir, radiusKm });
+}
+
+export async function organizeByDate(
+  paths: string[],
+  destination: string,
+  pattern: string | null,
+  mode: TransferMode,
+  dryRun: boolean
+): Promise<OrganizeResult> {
+  return invoke('organize_by_date', { paths, destination, pattern, mode, dryRun });
+}
+
+export async function detectBursts(
+  dir: string,
+  maxGapMs: number,
+  pickSharpest: boolean
+): Promise<Burst[]> {
+  return invoke('detect_bursts', { dir, maxGapMs, pickSharpest });
+}
+
+export async function exportTimelapse(
+  paths: string[],
+  fps: number,
+  output: string,
+  operations?: Operation[]
+): Promise<TimelapseResult> {
+  return invoke('export_timelapse', { paths, fps, output, operations });
+}
+
+export async function extractVideoFrame(path: string, timestamp: number): Promise<ImageHandle> {
+  return invoke('extract_video_frame', { path, timestamp });
+}
+
+export async function createAnimation(
+  sources: FrameSource[],
+  delays: number[],
+  loopCount: number,
+  format: AnimationFormat,
+  output: string
+): Promise<AnimationResult> {
+  return invoke('create_animation', { sources, delays, loopCount, format, output });
+}
+
+export async function exportComparison(
+  id: number,
+  layout: ComparisonLayout,
+  output: string
+): Promise<ExportResult> {
+  return invoke('export_comparison', { id, layout, output });
+}
+
+// The original and edited image either side of the split, lined up pixel for
+// pixel. The split line itself is left for the viewer to draw.
+export async function getSplitPreview(
+  id: number,
+  splitPosition: number,
+  orientation: SplitOrientation,
+  maxDimension?: number
+): Promise<ImagePreview> {
+  return invoke('get_split_preview', { id, splitPosition, orientation, maxDimension });
+}
+
+// For checking that an export from another tool is visually lossless; the
+// two open images must be the same size.
+export async function compareImages(
+  a: number,
+  b: number,
+  heatmap?: boolean
+): Promise<ImageComparison> {
+  return invoke('compare_images', { a, b, heatmap });
+}
+
+// A separate image showing how the open one looks with `deficiency`.
+export async function simulateColorVision(
+  id: number,
+  deficiency: ColorVisionDeficiency
+): Promise<ImageData> {
+  return invoke('simulate_color_vision', { id, deficiency });
+}
+
+export async function hashFile(path: string, algorithm: HashAlgorithm): Promise<FileHash> {
+  return invoke('hash_file', { path, algorithm });
+}
+
+export async function findExactDuplicates(dir: string): Promise<DuplicateFileGroup[]> {
+  return invoke('find_exact_duplicates', { dir });
+}
+
+export async function computePhash(
+  id: number,
+  algorithm?: PerceptualHashAlgorithm
+): Promise<PerceptualHash> {
+  return invoke('compute_phash', { id, algorithm });
+}
+
+// `paths` may be files or folders. Images whose hashes differ by at most
+// `threshold` bits are grouped; around 10 suits pHash.
+export async function findDuplicates(
+  paths: string[],
+  threshold: number,
+  algorithm?: PerceptualHashAlgorithm
+): Promise<NearDuplicateReport> {
+  return invoke('find_duplicates', { paths, threshold, algorithm });
+}
+
+export async function verifyImage(path: string): Promise<ImageVerification> {
+  return invoke('verify_image', { path });
+}
+
+// Listen for `jpeg-scan-preview` events to show refinements while this resolves.
+export async function openImageProgressive(
+  path: string,
+  previewSize?: number
+): Promise<ImageHandle> {
+  return invoke('open_image_progressive', { path, previewSize });
+}
+
+export async function setBlackWhitePoints(
+  id: number,
+  blackSample: PixelPoint,
+  whiteSample: PixelPoint
+): Promise<ImageHandle> {
+  return invoke('set_black_white_points', { id, blackSample, whiteSample });
+}
+
+export async function listLooks(): Promise<LookInfo[]> {
+  return invoke('list_looks');
+}
+
+export async function applyLook(id: number, name: string, intensity = 1): Promise<ImageHandle> {
+  return invoke('apply_look', { id, name, intensity });
+}
+
+export async function getSystemInfo(): Promise<SystemInfo> {
+  return invoke('get_system_info');
+}
+
+// Pass null to go back to one worker per core. Resolves to the pool size in effect.
+export async function setMaxWorkers(maxWorkers: number | null): Promise<number> {
+  return invoke('set_max_workers', { maxWorkers });
+}
+
+export async function getMemoryUsage(): Promise<MemoryUsage> {
+  return invoke('get_memory_usage');
+}
+
+export async function setMemoryBudget(budgetBytes: number): Promise<MemoryUsage> {
+  return invoke('set_memory_budget', { budgetBytes });
+}
+
+// Serves a token-protected REST API on 127.0.0.1 for scripts and external tools.
+export async function startAutomationServer(port?: number): Promise<AutomationServerInfo> {
+  return invoke('start_automation_server', { port });
+}
+
+export async function stopAutomationServer(): Promise<boolean> {
+  return invoke('stop_automation_server');
+}
+
+export async function getAutomationServer(): Promise<AutomationServerInfo | null> {
+  return invoke('get_automation_server');
+}
+
+export async function loadClipboardImage(): Promise<ClipboardImage> {
+  return invoke('load_clipboard_image');
+}
+
+// Listen for `clipboard-image` events while the watch is on. Resolves to whether it is running.
+export async function setClipboardWatch(enabled: boolean): Promise<boolean> {
+  return invoke('set_clipboard_watch', { enabled });
+}
+
+export async function isClipboardWatchEnabled(): Promise<boolean> {
+  return invoke('is_clipboard_watch_enabled');
+}
+
+export async function openLastFile(): Promise<OpenedImage> {
+  return invoke('open_last_file');
+}
+
+export async function getLastOpenedPath(): Promise<string | null> {
+  return invoke('get_last_opened_path');
+}
+
+// New images in the folder open as they arrive, announced as `image-opened`
+// with source `watch_folder`.
+export async function addWatchFolder(path: string): Promise<WatchFolders> {
+  return invoke('add_watch_folder', { path });
+}
+
+export async function removeWatchFolder(path: string): Promise<WatchFolders> {
+  return invoke('remove_watch_folder', { path });
+}
+
+// Images that arrive while paused are skipped, not opened on resuming.
+export async function setWatchFoldersPaused(paused: boolean): Promise<WatchFolders> {
+  return invoke('set_watch_folders_paused', { paused });
+}
+
+export async function getWatchFolders(): Promise<WatchFolders> {
+  return invoke('get_watch_folders');
+}
+
+// Images passed at launch (double-clicked files, deep links). Call once the
+// window is listening for `image-opened`.
+export async function takePendingOpens(): Promise<OpenedImage[]> {
+  return invoke('take_pending_opens');
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'params' in error;
+}
+
+export async function describeError(code: ErrorCode, locale?: string): Promise<ErrorDescription> {
+  return invoke('describe_error', { code, locale });
+}
+
+// Turns a rejected command into translated text with its params filled in.
+export async function localizeError(
+  error: unknown,
+  locale: string = navigator.language
+): Promise<ErrorDescription> {
+  const appError: AppError = isAppError(error)
+    ? error
+    : { code: 'operation_failed', message: String(error), params: { message: String(error) }, actions: [] };
+  const description = await describeError(appError.code, locale);
+  return {
+    ...description,
+    message: description.message.replace(/\{(\w+)\}/g, (placeholder, name) =>
+      appError.params[name] ?? placeholder
+    ),
+  };
+}
+
+// `tolerance` (0-255, default 0) is the per-channel difference from the border colour still trimmed.
+export async function autoTrim(id: number, tolerance?: number): Promise<TrimResult> {
+  return invoke('auto_trim', { id, tolerance });
+}
+
+export async function extendCanvas(
+  id: number,
+  margins: Margins,
+  fill: CanvasFill
+): Promise<ImageHandle> {
+  return invoke('extend_canvas', { id, ...margins, fill });
+}
+
+export async function analyzeComposition(id: number): Promise<CompositionAnalysis> {
+  return invoke('analyze_composition', { id });
+}
+
+export async function detectHorizon(id: number): Promise<HorizonAnalysis> {
+  return invoke('detect_horizon', { id });
+}
+
+export async function analyzeBalance(id: number): Promise<BalanceAnalysis> {
+  return invoke('analyze_balance', { id });
+}
+
+// Without a model path, uses models/text-detection.onnx in the app data folder.
+export async function detectTextRegions(
+  id: number,
+  modelPath?: string
+): Promise<TextRegion[]> {
+  return invoke('detect_text_regions', { id, modelPath });
+}
+
+// Without a model path, uses models/face-detection.onnx in the app data folder.
+export async function detectFaces(id: number, modelPath?: string): Promise<FaceRegion[]> {
+  return invoke('detect_faces', { id, modelPath });
+}
+
+// A separate image of the edges: an overlay in `color`, cyan by default, or
+// white edges on black. Thresholds default to 20 and 50.
+export async function detectEdges(
+  id: number,
+  method?: EdgeMethod,
+  thresholds?: EdgeThresholds,
+  output?: EdgeOutput,
+  color?: Color
+): Promise<ImageData> {
+  return invoke('detect_edges', { id, method, thresholds, output, color });
+}
+
+// A mask of how closely each pixel matches the colours sampled from
+// `sampleRegion`, white for the most common. Picks up shaded or textured
+// areas a tolerance-based fill would stop short of.
+export async function selectByHistogram(id: number, sampleRegion: PixelRect): Promise<ImageData> {
+  return invoke('select_by_histogram', { id, sampleRegion });
+}
+
+// A subject mask, white for the subject, from a rectangle around it and
+// strokes marking certain subject and background. Rerun with more strokes
+// to correct it. `iterations` defaults to 5.
+export async function grabcut(
+  id: number,
+  rect?: PixelRect,
+  fgScribbles?: Scribble[],
+  bgScribbles?: Scribble[],
+  iterations?: number,
+  jobId?: string
+): Promise<ImageData> {
+  return invoke('grabcut', { id, rect, fgScribbles, bgScribbles, iterations, jobId });
+}
+
+// Redaction is permanent: the stored original is overwritten too.
+export async function redactRegions(
+  id: number,
+  regions: PixelRect[],
+  style: RedactionStyle
+): Promise<ImageHandle> {
+  return invoke('redact_regions', { id, regions, style });
+}
+
+// One-click privacy redaction of detected text and faces, or just the
+// `targets` given, each grown by `padding` pixels (default 8). Models default
+// to the installed ones.
+export async function redactDetected(
+  id: number,
+  style: RedactionStyle,
+  targets?: RedactionTarget[],
+  padding?: number,
+  textModelPath?: string,
+  faceModelPath?: string
+): Promise<AutoRedaction> {
+  return invoke('redact_detected', {
+    id,
+    style,
+    targets,
+    padding,
+    textModelPath,
+    faceModelPath,
+  });
+}
+
+export async function addBorder(id: number, spec: BorderSpec): Promise<ImageHandle> {
+  return invoke('add_border', { id, spec });
+}
+
+export async function applyShapeMask(id: number, shape: MaskShape): Promise<ImageHandle> {
+  return invoke('apply_shape_mask', { id, shape });
+}
+
+// Writes <name>.ico, <name>.icns or <name>-<size>.png into outputDir.
+// `padding` is the transparent margin on each side, as a fraction of the size.
+export async function exportIconSet(
+  id: number,
+  targets: IconTarget[],
+  outputDir: string,
+  name: string,
+  padding?: number
+): Promise<ExportResult[]> {
+  return invoke('export_icon_set', { id, targets, outputDir, name, padding });
+}
+
+export async function listSocialPresets(): Promise<SocialPreset[]> {
+  return invoke('list_social_presets');
+}
+
+// Saves <name>-<preset>.<format> into outputDir for each preset, cropped
+// around the salient subject. `format` is an extension, "jpg" by default.
+export async function exportSocialPresets(
+  id: number,
+  presets: string[],
+  outputDir: string,
+  name: string,
+  format?: string
+): Promise<ExportResult[]> {
+  return invoke('export_social_presets', { id, presets, outputDir, name, format });
+}
+
+// Writes <name>_<tile>.png tiles and a <name>.json manifest into outputDir.
+// Two guides per axis give nine-patch tile names (top_left, center, ...).
+export async function sliceImage(
+  id: number,
+  layout: SliceLayout,
+  outputDir: string,
+  name: string
+): Promise<SliceResult> {
+  return invoke('slice_image', { id, layout, outputDir, name });
+}
+
+// Each sample averages a (2 * radius + 1)^2 box; radius 0 reads single pixels.
+export async function sampleLine(
+  id: number,
+  from: Point,
+  to: Point,
+  samples: number,
+  radius?: number
+): Promise<LineProfile> {
+  return invoke('sample_line', { id, from, to, samples, radius });
+}
+
+// Eyedropper; `radius` averages a (2 * radius + 1)^2 box instead of one pixel.
+export async function sampleColorAt(
+  id: number,
+  x: number,
+  y: number,
+  radius?: number
+): Promise<SampledColor> {
+  return invoke('sample_color_at', { id, x, y, radius });
+}
+
+// `colorA` is the foreground, shown over `colorB` if translucent.
+export async function checkContrast(colorA: Color, colorB: Color): Promise<ContrastReport> {
+  return invoke('check_contrast', { colorA, colorB });
+}
+
+// Foreground is luminance above `threshold`, or below it with `invert`.
+export async function countBlobs(
+  id: number,
+  threshold: number,
+  minArea: number,
+  invert?: boolean
+): Promise<BlobAnalysis> {
+  return invoke('count_blobs', { id, threshold, minArea, invert });
+}
+
+export async function setScale(
+  id: number,
+  pixelDistance: number,
+  realDistance: number,
+  unit: string
+): Promise<SpatialScale> {
+  return invoke('set_scale', { id, pixelDistance, realDistance, unit });
+}
+
+export async function measure(id: number, points: Point[]): Promise<Measurement> {
+  return invoke('measure', { id, points });
+}
+
+// A transparent RGBA layer to draw over the image in the viewer.
+export async function renderGridOverlay(id: number, spec: OverlaySpec): Promise<ImageData> {
+  return invoke('render_grid_overlay', { id, spec });
+}
+
+export async function diffAnalysis(
+  resultA: AnalysisResult,
+  resultB: AnalysisResult
+): Promise<AnalysisDiff> {
+  return invoke('diff_analysis', { resultA, resultB });
+}
+
+// Aspect ratios are width / height. Results are grouped by ratio, best first,
+// with up to `count` (default 3) per ratio.
+export async function suggestCrops(
+  id: number,
+  aspectRatios: number[],
+  count?: number
+): Promise<CropSuggestion[]> {
+  return invoke('suggest_crops', { id, aspectRatios, count });
+}
+
+// Renders `operation` over just `region`, scaled to fit the viewport, without
+// changing the stored image. Commit with applyOperation.
+export async function previewOperation(
+  id: number,
+  operation: Operation,
+  region: PixelRect,
+  viewportWidth: number,
+  viewportHeight: number
+): Promise<OperationPreview> {
+  return invoke('preview_operation', { id, operation, region, viewportWidth, viewportHeight });
+}
+
+export async function applyOperation(id: number, operation: Operation): Promise<ImageHandle> {
+  return invoke('apply_operation', { id, operation });
+}
+
+// Applies to the image's adjustments from now on; export still writes 8-bit.
+export async function setPrecision(id: number, precision: Precision): Promise<void> {
+  return invoke('set_precision', { id, precision });
+}
+
+// Combines image `id` with another image or a constant into a new image,
+// leaving both alone. `floatOutput` also returns the unclamped values.
+export async function imageMath(
+  id: number,
+  operand: MathOperand,
+  op: MathOp,
+  floatOutput?: boolean
+): Promise<ImageMathResult> {
+  return invoke('image_math', { id, operand, op, floatOutput });
+}
+
+// Applies an operation to the low-resolution proxy for responsive editing.
+// The full-resolution image catches up when it's next used (e.g. on export).
+export async function editProxy(id: number, operation: Operation): Promise<ProxyInfo> {
+  return invoke('edit_proxy', { id, operation });
+}
+
+export async function getProxyInfo(id: number): Promise<ProxyInfo> {
+  return invoke('get_proxy_info', { id });
+}
+
+export async function getProxyData(id: number): Promise<ImageData> {
+  return invoke('get_proxy_data', { id });
+}
+
+export async function commitProxyEdits(id: number): Promise<ImageHandle> {
+  return invoke('commit_proxy_edits', { id });
+}
+
+export async function discardProxyEdits(id: number): Promise<ProxyInfo> {
+  return invoke('discard_proxy_edits', { id });
+}
+
+export async function getOperationLog(id: number): Promise<OperationLog> {
+  return invoke('get_operation_log', { id });
+}
+
+// Rebuilds the image from its original by redoing every logged edit.
+export async function replayLog(id: number): Promise<ReplayResult> {
+  return invoke('replay_log', { id });
+}
+
+export async function exportLog(id: number, path: string): Promise<void> {
+  return invoke('export_log', { id, path });
+}
+
+// Writes a signed record of the source hash, every edit and the result's
+// hashes: CSV for a `.csv` path, JSON otherwise. `outputPath` is the
+// exported file to vouch for, if there is one.
+export async function exportAudit(
+  id: number,
+  path: string,
+  outputPath?: string
+): Promise<void> {
+  return invoke('export_audit', { id, path, outputPath });
+}
+
+// Whether an audit file exported by this installation is unaltered.
+export async function verifyAudit(path: string): Promise<boolean> {
+  return invoke('verify_audit', { path });
+}
+
+// History lives with the image in the backend; only the new state comes back.
+export async function undo(id: number): Promise<EditHistory> {
+  return invoke('undo', { id });
+}
+
+export async function redo(id: number): Promise<EditHistory> {
+  return invoke('redo', { id });
+}
+
+export async function getHistory(id: number): Promise<EditHistory> {
+  return invoke('get_history', { id });
+}
+
+// Camera, exposure, location and XMP details without decoding the pixels.
+export async function extractMetadata(path: string): Promise<ImageMetadata> {
+  return invoke('extract_metadata', { path });
+}
+
+// Saves the image's edits as a sidecar next to its source file; resolves to the sidecar's path.
+export async function saveRecipe(id: number): Promise<string> {
+  return invoke('save_recipe', { id });
+}
+
+// Opens an image with its sidecar recipe applied.
+export async function applyRecipe(path: string): Promise<ImageHandle> {
+  return invoke('apply_recipe', { path });
+}
+
+// Re-renders every image in `dir` that has a recipe. `format` is an extension
+// such as 'jpg'; by default each keeps its original format.
+export async function reexportWithRecipes(
+  dir: string,
+  outputDir: string,
+  format?: string
+): Promise<RecipeExportReport> {
+  return invoke('reexport_with_recipes', { dir, outputDir, format });
+}
+
+// Applies `operations` in order to every image in `inputDir`, converting to
+// `format` if given. Listen for 'batch-progress' to follow along; images that
+// fail are listed in `skipped`.
+export async function batchProcess(
+  inputDir: string,
+  outputDir: string,
+  operations: Edit[],
+  format?: string
+): Promise<BatchReport> {
+  return invoke('batch_process', { inputDir, outputDir, operations, format });
+}
+
+// The rectangle may start at negative coordinates or run past the image;
+// only the overlapping part is kept.
+export async function cropImage(
   imageData: ImageData,
-  amount: number
+  x: number,
+  y: number,
+  width: number,
+  height: number
 ): Promise<ImageData> {
-  return invoke('adjust_contrast', { imageData, amount });
+  return invoke('crop_image', { imageData, x, y, width, height });
+}
+
+// Applies Lightroom or darktable settings saved for the image's source file.
+// Without `sidecarPath` the usual `.xmp` sidecars and then the file's own XMP
+// are checked.
+export async function importSettings(
+  id: number,
+  sidecarPath?: string
+): Promise<SettingsImport> {
+  return invoke('import_settings', { id, sidecarPath });
+}
+
+// Dark frames are shots with the lens capped, at the camera and ISO the
+// profile is for. Pass the result to a `denoise` operation.
+export async function buildNoiseProfile(darkFramePaths: string[]): Promise<NoiseProfile> {
+  return invoke('build_noise_profile', { darkFramePaths });
+}
+
+export async function estimateNoise(id: number): Promise<NoiseEstimate> {
+  return invoke('estimate_noise', { id });
+}
+
+// `degrees` is counter-clockwise, matching `detectHorizon`'s rotation. The
+// corners uncovered by other than right angles take `fill`, transparent by
+// default.
+export async function rotateImage(
+  id: number,
+  degrees: number,
+  fill?: CanvasFill
+): Promise<ImageHandle> {
+  return invoke('rotate_image', { id, degrees, fill });
+}
+
+export async function flipImage(id: number, axis: FlipAxis): Promise<ImageHandle> {
+  return invoke('flip_image', { id, axis });
+}
+
+// `flatFramePath` is a shot of an evenly lit blank target (an empty slide,
+// white card) taken on the same rig; its falloff is divided out.
+export async function flatFieldCorrect(id: number, flatFramePath: string): Promise<ImageHandle> {
+  return invoke('flat_field_correct', { id, flatFramePath });
+}
+
+// With `fitWithin` the aspect ratio is kept and the image scaled to the
+// largest size inside `width`x`height`; otherwise it's stretched to fit.
+export async function resizeImage(
+  id: number,
+  width: number,
+  height: number,
+  filter?: ResizeFilter,
+  fitWithin?: boolean,
+  edge?: EdgeMode
+): Promise<ImageHandle> {
+  return invoke('resize_image', { id, width, height, filter, fitWithin, edge });
+}
+
+// Enlarges pixel art by a `factor` of 2, 3 or 4 without blurring its edges.
+export async function scalePixelArt(
+  id: number,
+  algorithm: PixelArtAlgorithm,
+  factor: number
+): Promise<ImageHandle> {
+  return invoke('scale_pixel_art', { id, algorithm, factor });
+}
+
+// The dark frame must be the same size as the image and shot at the same
+// exposure, ISO and temperature with the lens capped.
+export async function subtractDarkFrame(id: number, darkFramePath: string): Promise<ImageHandle> {
+  return invoke('subtract_dark_frame', { id, darkFramePath });
+}
+
+export async function applyBlur(
+  id: number,
+  sigma: number,
+  edge?: EdgeMode,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('apply_blur', { id, sigma, edge, jobId });
+}
+
+// Edge-preserving smoothing for skin and noise. An `epsilon` around 0.01
+// smooths skin; 0.001 only fine noise.
+export async function smoothPreserveEdges(
+  id: number,
+  radius: number,
+  epsilon: number,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('smooth_preserve_edges', { id, radius, epsilon, jobId });
+}
+
+// Unsharp mask. `threshold` is the smallest difference, in levels, that gets
+// sharpened; `radius` is the blur sigma, 1 by default.
+export async function applySharpen(
+  id: number,
+  amount: number,
+  threshold?: number,
+  radius?: number,
+  edge?: EdgeMode,
+  jobId?: string
+): Promise<ImageHandle> {
+  return invoke('apply_sharpen', { id, amount, threshold, radius, edge, jobId });
+}
+
+export async function applyLevels(id: number, levels: ChannelLevels): Promise<ImageHandle> {
+  return invoke('apply_levels', { id, levels });
+}
+
+// `points` are [input, output] pairs on 0-255, joined by a smooth curve that
+// doesn't overshoot them. `channel` defaults to all three.
+export async function applyCurve(
+  id: number,
+  points: [number, number][],
+  channel?: CurveChannel
+): Promise<ImageHandle> {
+  return invoke('apply_curve', { id, points, channel });
+}
+
+// Star count, focus (median FWHM) and roundness for astrophotography frames.
+export async function analyzeStars(id: number): Promise<StarAnalysis> {
+  return invoke('analyze_stars', { id });
+}
+
+// Higher scores are sharper; compare them across shots of one scene.
+export async function measureSharpness(id: number, heatmap = false): Promise<SharpnessReport> {
+  return invoke('measure_sharpness', { id, heatmap });
+}
+
+export async function autoEnhance(id: number, method: EnhanceMethod): Promise<ImageHandle> {
+  return invoke('auto_enhance', { id, method });
+}
+
+// Evens out the brightness of a series, such as timelapse frames, against
+// `reference`. Corrected frames are written to `outputDir` when given.
+export async function matchExposure(
+  paths: string[],
+  reference: string,
+  outputDir?: string
+): Promise<ExposureMatchReport> {
+  return invoke('match_exposure', { paths, reference, outputDir });
+}
+
+// Sorts a folder into keep, review and reject lists by sharpness, clipping,
+// closed eyes and near-duplicates, with the reasons for each. Eyes are only
+// checked with a face model: `faceModelPath`, or models/face-detection.onnx
+// in the app data folder if it's there.
+export async function cullFolder(
+  dir: string,
+  rules?: CullRules,
+  faceModelPath?: string
+): Promise<CullReport> {
+  return invoke('cull_folder', { dir, rules, faceModelPath });
+}
+
+// Re-saves the image as JPEG and maps how much each pixel changed, brightest
+// where it changed most. Regions that stand out from their surroundings may
+// have been pasted in or retouched. `quality` defaults to 90.
+export async function errorLevelAnalysis(id: number, quality?: number): Promise<ImageData> {
+  return invoke('error_level_analysis', { id, quality });
+}
+
+// Regions that appear twice in the image, for review. Repeating textures such
+// as brickwork can match too.
+export async function detectCopyMove(id: number): Promise<CopyMoveMatch[]> {
+  return invoke('detect_copy_move', { id });
+}
+
+// Looks for data hidden in a file: bytes after the image ends, unusual chunks,
+// long embedded text and, for lossless formats, LSB embedding. Findings are
+// leads rather than proof.
+export async function inspectHiddenData(path: string): Promise<HiddenDataReport> {
+  return invoke('inspect_hidden_data', { path });
+}
+
+// Compares the file's EXIF thumbnail with its pixels; null when the file has
+// no thumbnail.
+export async function compareExifThumbnail(path: string): Promise<ThumbnailComparison | null> {
+  return invoke('compare_exif_thumbnail', { path });
+}
+
+// Builds a camera's sensor fingerprint from full-size photos it took, all in
+// the same orientation, and writes it to `outputPath`.
+export async function extractPrnu(paths: string[], outputPath: string): Promise<PrnuFingerprint> {
+  return invoke('extract_prnu', { paths, outputPath });
+}
+
+// Whether an open image was taken by the fingerprinted camera. Crops,
+// resizes and rotations hide the pattern, so a miss on an edited photo
+// proves nothing.
+export async function matchPrnu(id: number, fingerprintPath: string): Promise<PrnuMatch> {
+  return invoke('match_prnu', { id, fingerprintPath });
+}
+
+// A PNG of the file fitting within `maxSize`, for folder grids. JPEGs are
+// decoded at reduced size, so this is much cheaper than opening the file.
+export async function generateThumbnail(path: string, maxSize: number): Promise<ImagePreview> {
+  return invoke('generate_thumbnail', { path, maxSize });
+}
+
+// Reduces the image to a small palette, dithering in the missing colours, for
+// retro looks and e-ink or other limited displays.
+export async function dither(
+  id: number,
+  palette: DitherPalette,
+  algorithm: DitherAlgorithm
+): Promise<ImageHandle> {
+  return invoke('dither', { id, palette, algorithm });
+}
+
+// Writes in the chosen format whatever the path's extension, reporting the
+// file size so quality settings can be compared.
+export async function exportImage(
+  id: number,
+  path: string,
+  options: ExportOptions
+): Promise<ExportResult> {
+  return invoke('export_image', { id, path, options });
+}
+
+// The image as text art, `columns` characters wide.
+export async function exportAscii(
+  id: number,
+  columns: number,
+  charset: AsciiCharset,
+  colorMode: AsciiColorMode
+): Promise<string> {
+  return invoke('export_ascii', { id, columns, charset, colorMode });
+}
+
+export async function loadImageFrames(path: string): Promise<ImageFrames> {
+  return invoke('load_image_frames', { path });
 }
 
-export async function convertToGrayscale(imageData: ImageData): Promise<ImageData> {
-  return invoke('convert_to_grayscale', { imageData });
+// A frame as shown at that point in the animation, or a TIFF page.
+export async function getImageFrame(path: string, index: number): Promise<ImageData> {
+  return invoke('get_image_frame', { path, index });
 }
 
-export async function saveImage(imageData: ImageData, path: string): Promise<void> {
-  return invoke('save_image', { imageData, path });
+// Sensor-level data of a RAW file, with each site tinted in its filter
+// colour unless `colorize` is false.
+export async function getRawMosaic(path: string, colorize?: boolean): Promise<RawMosaic> {
+  return invoke('get_raw_mosaic', { path, colorize });
 }
@@ -3,6 +3,20 @@ export interface ImageData {
   height: number;
   data: number[];
   format: string;
+  recovered?: boolean;
+  source_color_type?: SourceColorType | null;
+}
+
+export type ColorKind = 'gray' | 'gray_alpha' | 'rgb' | 'rgba' | 'indexed' | 'cmyk';
+
+export interface SourceColorType {
+  container: string;
+  kind: ColorKind;
+  bit_depth: number;
+  interlaced: boolean;
+  palette_size: number | null;
+  conversion?: 'icc' | 'naive' | null;
+  icc_profile?: string | null;
 }
 
 export interface HistogramData {
@@ -10,6 +24,9 @@ export interface HistogramData {
   green: number[];
   blue: number[];
   luminosity: number[];
+  // HSL saturation and lightness, 256 bins each.
+  saturation: number[];
+  lightness: number[];
 }
 
 export interface ColorSample {
@@ -17,6 +34,8 @@ export interface ColorSample {
   g: number;
   b: number;
   hex: string;
+  // Share of the visible pixels closest to this colour, 0-100.
+  population: number;
 }
 
 export interface AnalysisResult {
@@ -24,4 +43,1213 @@ export interface AnalysisResult {
   dominant_colors: ColorSample[];
   average_brightness: number;
   contrast: number;
+  warnings: AnalysisWarning[];
+}
+
+export type AnalysisWarningKind =
+  | 'embedded_profile_ignored'
+  | 'missing_profile_wide_gamut'
+  | 'color_cast';
+
+export interface AnalysisWarning {
+  kind: AnalysisWarningKind;
+  message: string;
+}
+
+export interface ImageHandle {
+  id: number;
+  width: number;
+  height: number;
+}
+
+export interface LensCorrectionReport {
+  handle: ImageHandle;
+  lens: string;
+  camera: string | null;
+  focal_length: number;
+  distortion_corrected: boolean;
+  tca_corrected: boolean;
+  vignetting_corrected: boolean;
+}
+
+export interface LocationCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  paths: string[];
+}
+
+export interface LocationClusterResult {
+  clusters: LocationCluster[];
+  unlocated: string[];
+}
+
+export type TransferMode = 'move' | 'copy';
+
+export type DateSource = 'exif' | 'file_modified';
+
+export interface OrganizeAction {
+  source: string;
+  destination: string;
+  date_source: DateSource;
+}
+
+export interface SkippedFile {
+  path: string;
+  reason: string;
+}
+
+export interface OrganizeResult {
+  actions: OrganizeAction[];
+  skipped: SkippedFile[];
+  dry_run: boolean;
+}
+
+export interface BurstFrame {
+  path: string;
+  captured_at: string;
+  sharpness: number | null;
+}
+
+export interface Burst {
+  frames: BurstFrame[];
+  duration_ms: number;
+  sharpest: string | null;
+}
+
+export type Operation =
+  | { type: 'brightness'; amount: number }
+  | { type: 'contrast'; amount: number }
+  | { type: 'grayscale' }
+  // Hue shift in degrees, saturation as a multiplier and lightness from -1
+  // (black) to 1 (white).
+  | { type: 'hsl'; hue_shift: number; saturation: number; lightness: number }
+  // `temperature` is the Kelvin of the light to neutralize and `tint` runs
+  // from -100 (green) to 100 (magenta).
+  | { type: 'white_balance'; temperature: number; tint: number }
+  // Without a profile the noise level is estimated from the image itself.
+  | { type: 'denoise'; strength: number; profile?: NoiseProfile | null }
+  // Gaussian, on the colour channels only, weighted by alpha.
+  | { type: 'blur'; sigma: number; edge?: EdgeMode }
+  // Guided filter over windows `radius` pixels either side; detail with a
+  // variance, on 0-1 values, well under `epsilon` is smoothed and edges kept.
+  | { type: 'smooth_preserve_edges'; radius: number; epsilon: number }
+  // Unsharp mask with a blur of `radius` sigma. Differences of `threshold`
+  // levels or less are left alone.
+  | { type: 'sharpen'; amount: number; radius: number; threshold: number; edge?: EdgeMode };
+
+// What a filter reads past the image's sides: the nearest edge pixel, the
+// image reflected, or the opposite side, for tiling textures.
+export type EdgeMode = 'clamp' | 'mirror' | 'wrap';
+
+// 'gray_world' assumes the scene averages to grey; 'white_patch' that its
+// brightest tones are white.
+export type WhiteBalanceMethod = 'gray_world' | 'white_patch';
+
+// Sensor noise measured from dark frames, so a camera at a given ISO can be
+// denoised the same way across a whole shoot.
+export interface NoiseProfile {
+  camera: string | null;
+  iso: number | null;
+  frames: number;
+  // Per RGB channel, on the 0-255 scale.
+  black_level: [number, number, number];
+  sigma: [number, number, number];
+}
+
+// Noise measured from the image itself, on the 0-255 scale.
+export interface NoiseEstimate {
+  sigma: [number, number, number];
+  luminance_sigma: number;
+  // Percentage of the full range: under 1 looks clean, over 3 grainy.
+  score: number;
+  map: NoiseMap;
+}
+
+// Luminance sigma per grid cell, row by row.
+export interface NoiseMap {
+  columns: number;
+  rows: number;
+  cell_size: number;
+  sigma: number[];
+}
+
+export interface TimelapseResult {
+  output: string;
+  frame_count: number;
+  width: number;
+  height: number;
+  duration_seconds: number;
+}
+
+// Frames are either image handles or file paths.
+export type FrameSource = number | string;
+
+export type AnimationFormat = 'gif' | 'apng' | 'webp';
+
+export interface AnimationResult {
+  output: string;
+  frame_count: number;
+  width: number;
+  height: number;
+  file_size: number;
+}
+
+export interface Color {
+  r: number;
+  g: number;
+  b: number;
+  a?: number;
+}
+
+export type ComparisonOrientation = 'side_by_side' | 'top_bottom';
+
+export interface ComparisonLayout {
+  orientation: ComparisonOrientation;
+  divider_width?: number;
+  divider_color?: Color | null;
+  background?: Color | null;
+  before_label?: string | null;
+  after_label?: string | null;
+}
+
+export interface ExportResult {
+  output: string;
+  width: number;
+  height: number;
+  file_size: number;
+}
+
+export type SplitOrientation = 'vertical' | 'horizontal';
+
+// Missing red, green or blue cones, or no colour vision at all.
+export type ColorVisionDeficiency =
+  | 'protanopia'
+  | 'deuteranopia'
+  | 'tritanopia'
+  | 'achromatopsia';
+
+export type HashAlgorithm = 'sha256' | 'blake3';
+
+export interface FileHash {
+  path: string;
+  algorithm: HashAlgorithm;
+  hash: string;
+  size: number;
+}
+
+export interface DuplicateFileGroup {
+  hash: string;
+  size: number;
+  paths: string[];
+}
+
+// Hashes of how an image looks, so re-encoded or resized copies hash alike.
+// 'phash' is the most robust; 'ahash' and 'dhash' are quicker and stricter.
+export type PerceptualHashAlgorithm = 'ahash' | 'dhash' | 'phash';
+
+// `hash` is 16 hex digits.
+export interface PerceptualHash {
+  algorithm: PerceptualHashAlgorithm;
+  hash: string;
+}
+
+// `max_distance` is the largest Hamming distance between two of the paths.
+export interface NearDuplicateGroup {
+  paths: string[];
+  max_distance: number;
+}
+
+export interface NearDuplicateReport {
+  algorithm: PerceptualHashAlgorithm;
+  groups: NearDuplicateGroup[];
+  skipped: SkippedFile[];
+}
+
+export interface ImageVerification {
+  path: string;
+  format: string | null;
+  valid: boolean;
+  decodes: boolean;
+  truncated: boolean;
+  recoverable: boolean;
+  width: number | null;
+  height: number | null;
+  issues: string[];
+}
+
+// Payload of the `jpeg-scan-preview` event.
+export interface ScanPreview {
+  path: string;
+  scan: number;
+  bytes_read: number;
+  total_bytes: number;
+  width: number;
+  height: number;
+  data_url: string;
+}
+
+// A downscaled copy of an image for display.
+export interface ImagePreview {
+  width: number;
+  height: number;
+  data_url: string;
+}
+
+export interface PixelPoint {
+  x: number;
+  y: number;
+}
+
+export interface LookInfo {
+  name: string;
+  label: string;
+  description: string;
+}
+
+export interface SystemInfo {
+  logical_cores: number;
+  physical_cores: number | null;
+  total_memory: number;
+  available_memory: number;
+  max_workers: number | null;
+  current_workers: number;
+  suggested_workers: number;
+}
+
+export interface MemoryUsage {
+  used_bytes: number;
+  budget_bytes: number;
+  resident_images: number;
+  spilled_images: number;
+}
+
+// Payload of the `memory-pressure` event.
+export interface MemoryPressure {
+  used_bytes: number;
+  budget_bytes: number;
+  spilled: number[];
+  over_budget: boolean;
+  message: string;
+}
+
+export type PerfBackend = 'cpu' | 'rayon' | 'ffmpeg';
+
+// Payload of the `perf-metric` event.
+export interface PerfMetric {
+  command: string;
+  width: number;
+  height: number;
+  megapixels: number;
+  duration_ms: number;
+  backend: PerfBackend;
+}
+
+export interface AutomationServerInfo {
+  url: string;
+  port: number;
+  token: string;
+}
+
+// Also the payload of the `clipboard-image` event.
+export interface ClipboardImage {
+  handle: ImageHandle;
+}
+
+export type OpenSource =
+  | 'clipboard'
+  | 'recent_file'
+  | 'file_association'
+  | 'deep_link'
+  | 'watch_folder';
+
+// Payload of the `image-opened` event, sent when an image is loaded from
+// outside the main window (the tray menu, the OS, or a deep link).
+export interface OpenedImage {
+  handle: ImageHandle;
+  path: string | null;
+  source: OpenSource;
+}
+
+export interface WatchFolders {
+  folders: string[];
+  paused: boolean;
+}
+
+export type ErrorCode =
+  | 'file_not_found'
+  | 'permission_denied'
+  | 'read_failed'
+  | 'write_failed'
+  | 'decode_failed'
+  | 'unsupported_format'
+  | 'invalid_argument'
+  | 'image_not_found'
+  | 'clipboard_empty'
+  | 'clipboard_unavailable'
+  | 'ffmpeg_not_found'
+  | 'ffmpeg_failed'
+  | 'cancelled'
+  | 'operation_failed';
+
+export type ErrorAction = 'retry' | 'open_folder' | 'choose_file';
+
+// What commands reject with. `message` is English; `params` fill the
+// `{name}` placeholders of the catalog text from `describeError`.
+export interface AppError {
+  code: ErrorCode;
+  message: string;
+  params: Record<string, string>;
+  actions: ErrorAction[];
+}
+
+export interface ErrorDescription {
+  code: ErrorCode;
+  locale: string;
+  title: string;
+  message: string;
+  actions: ErrorAction[];
+}
+
+// Pixels removed from (or added to) each side of an image.
+export interface Margins {
+  top: number;
+  right: number;
+  bottom: number;
+  left: number;
+}
+
+export interface TrimResult {
+  handle: ImageHandle;
+  margins: Margins;
+}
+
+// How `extendCanvas` fills the added margins. `mirror` reflects without repeating the edge pixel.
+export type CanvasFill =
+  | { type: 'solid'; color: Color }
+  | { type: 'transparent' }
+  | { type: 'edge' }
+  | { type: 'mirror' };
+
+export type EnhanceMethod =
+  // Spreads the luminosity histogram evenly over the tonal range.
+  | { type: 'equalize' }
+  // Equalizes `tiles` x `tiles` regions separately, for local contrast,
+  // limiting each histogram bin to `clip_limit` times the average. Defaults
+  // are 8 tiles and a limit of 2.
+  | { type: 'clahe'; tiles?: number; clip_limit?: number }
+  // Stretches the range so `clip_percent` of values clip at each end, 0.5
+  // by default.
+  | { type: 'auto_contrast'; clip_percent?: number };
+
+// Input levels: `black` and `white` (0-255) map to 0 and 255, and `gamma`
+// bends the midtones between them, brightening above 1.
+export interface Levels {
+  black: number;
+  white: number;
+  gamma: number;
+}
+
+// `master` applies to all three channels, before each channel's own levels.
+// Any left out are unchanged.
+export interface ChannelLevels {
+  master?: Partial<Levels>;
+  red?: Partial<Levels>;
+  green?: Partial<Levels>;
+  blue?: Partial<Levels>;
+}
+
+export type CurveChannel = 'rgb' | 'red' | 'green' | 'blue';
+
+// 'nearest' keeps hard pixel edges; 'lanczos3', the default, is sharpest.
+export type ResizeFilter = 'nearest' | 'bilinear' | 'bicubic' | 'lanczos3';
+
+// 'scale2x' keeps the source's exact colours; 'xbr' anti-aliases edges for
+// smoother curves.
+export type PixelArtAlgorithm = 'scale2x' | 'xbr';
+
+// 'horizontal' swaps left and right, 'vertical' top and bottom.
+export type FlipAxis = 'horizontal' | 'vertical';
+
+export interface PixelRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+// A position in image pixels.
+export interface Point {
+  x: number;
+  y: number;
+}
+
+// A brush stroke through `points`, `radius` pixels either side.
+export interface Scribble {
+  points: Point[];
+  radius: number;
+}
+
+export interface Subject {
+  center: Point;
+  bounds: PixelRect;
+  // Share of the frame the subject covers, 0-1.
+  coverage: number;
+}
+
+// `dx`/`dy` are subject centre minus guide point in pixels; `distance` is a fraction of the diagonal.
+export interface GuideOffset {
+  point: Point;
+  dx: number;
+  dy: number;
+  distance: number;
+}
+
+export type GuideKind = 'thirds' | 'golden_ratio';
+
+export interface GuideLine {
+  kind: GuideKind;
+  from: Point;
+  to: Point;
+}
+
+export interface CompositionAnalysis {
+  width: number;
+  height: number;
+  subject: Subject;
+  thirds_points: Point[];
+  golden_points: Point[];
+  nearest_third: GuideOffset;
+  nearest_golden: GuideOffset;
+  guides: GuideLine[];
+}
+
+// `angle` is the tilt in degrees, counter-clockwise positive, away from level or plumb.
+export interface LineEstimate {
+  angle: number;
+  confidence: number;
+}
+
+export interface HorizonAnalysis {
+  horizon: LineEstimate | null;
+  vertical: LineEstimate | null;
+  // Counter-clockwise degrees that level the image.
+  rotation: number;
+  confidence: number;
+}
+
+export interface HalfBalance {
+  // First half (left or top) minus the second, -1 to 1.
+  luminance_difference: number;
+  // CIE76 delta E between the halves' mean colours.
+  color_difference: number;
+  score: number;
+}
+
+export interface BalanceAnalysis {
+  left_right: HalfBalance;
+  top_bottom: HalfBalance;
+  left_right_symmetry: number;
+  top_bottom_symmetry: number;
+}
+
+export interface TextRegion {
+  bounds: PixelRect;
+  confidence: number;
+}
+
+export interface FaceRegion {
+  bounds: PixelRect;
+  confidence: number;
+  // A heuristic guess; null when the face is too small or the eyes too
+  // featureless to judge.
+  eyes_open: boolean | null;
+}
+
+export type RedactionStyle =
+  | { type: 'pixelate'; block_size: number }
+  | { type: 'solid'; color: Color }
+  | { type: 'blur'; radius: number };
+
+export type RedactionTarget = 'text' | 'faces';
+
+export interface AutoRedaction {
+  handle: ImageHandle;
+  // What was redacted, padding included; empty when nothing was found.
+  regions: PixelRect[];
+}
+
+export interface DropShadow {
+  mat_width: number;
+  mat_color: Color;
+  color: Color;
+  offset_x?: number;
+  offset_y?: number;
+  // Gaussian sigma in pixels; 0 for a hard shadow.
+  blur?: number;
+}
+
+export interface BorderSpec {
+  width: number;
+  color: Color;
+  corner_radius?: number;
+  shadow?: DropShadow | null;
+}
+
+export type MaskShape =
+  | { type: 'rounded_rect'; radius: number }
+  | { type: 'ellipse' }
+  // Crops to the centred square first.
+  | { type: 'circle' };
+
+export type IconTarget =
+  // Empty sizes give 16 through 256.
+  | { type: 'ico'; sizes?: number[] }
+  | { type: 'icns' }
+  | { type: 'png'; size: number };
+
+export interface SocialPreset {
+  name: string;
+  label: string;
+  width: number;
+  height: number;
+}
+
+// Guides are pixel positions of the cuts.
+export type SliceLayout =
+  | { type: 'grid'; rows: number; columns: number }
+  | { type: 'guides'; x: number[]; y: number[] };
+
+export interface SliceTile {
+  name: string;
+  // Relative to the manifest.
+  file: string;
+  row: number;
+  column: number;
+  bounds: PixelRect;
+}
+
+export interface SliceManifest {
+  width: number;
+  height: number;
+  tiles: SliceTile[];
+}
+
+export interface SliceResult {
+  manifest_path: string;
+  manifest: SliceManifest;
+}
+
+// An eyedropper pick; `relative_luminance` is WCAG's, 0 for black to 1 for white.
+export interface SampledColor {
+  r: number;
+  g: number;
+  b: number;
+  hex: string;
+  relative_luminance: number;
+}
+
+// WCAG 2 contrast, `ratio` from 1 to 21, with the AA and AAA verdicts for
+// normal and large text.
+export interface ContrastReport {
+  luminance_a: number;
+  luminance_b: number;
+  ratio: number;
+  aa_normal: boolean;
+  aa_large: boolean;
+  aaa_normal: boolean;
+  aaa_large: boolean;
+}
+
+// One entry per sample in each list; channels are on 0-255.
+export interface LineProfile {
+  length: number;
+  distances: number[];
+  points: Point[];
+  red: number[];
+  green: number[];
+  blue: number[];
+  luminance: number[];
+}
+
+export interface BlobStats {
+  area: number;
+  centroid: Point;
+  bounds: PixelRect;
+  // Mean luminance, 0-255.
+  mean_intensity: number;
+}
+
+export interface BlobAnalysis {
+  count: number;
+  total_area: number;
+  // Components smaller than minArea.
+  rejected: number;
+  blobs: BlobStats[];
+}
+
+export interface SpatialScale {
+  units_per_pixel: number;
+  unit: string;
+}
+
+// Lengths and areas are in `unit` ("px" when uncalibrated). Angles are in
+// degrees, counter-clockwise from the positive x axis.
+export interface Measurement {
+  unit: string;
+  calibrated: boolean;
+  segment_lengths: number[];
+  segment_angles: number[];
+  total_length: number;
+  vertex_angles: number[];
+  area: number | null;
+  perimeter: number | null;
+}
+
+export type OverlayGuide =
+  // One line every `spacing` image pixels.
+  | { type: 'pixel_grid'; spacing: number }
+  | { type: 'thirds' }
+  | { type: 'golden_ratio' }
+  | { type: 'golden_spiral'; flip_horizontal?: boolean; flip_vertical?: boolean }
+  // Lines at fractions (0-1) of the width and height.
+  | { type: 'custom'; vertical?: number[]; horizontal?: number[] };
+
+export interface OverlaySpec {
+  guides: OverlayGuide[];
+  color: Color;
+  line_width?: number;
+  // Size to render at; defaults to the image's.
+  width?: number | null;
+  height?: number | null;
+}
+
+// Levels (0-255) one channel's histogram moved.
+export interface HistogramShift {
+  emd: number;
+  mean_shift: number;
+}
+
+export interface ChannelShifts {
+  red: HistogramShift;
+  green: HistogramShift;
+  blue: HistogramShift;
+  luminosity: HistogramShift;
+}
+
+export interface PaletteChange {
+  before: ColorSample;
+  after: ColorSample;
+  delta_e: number;
+}
+
+// Colour statistics are over RGB, in levels. `psnr` is in dB and null when
+// the colours are identical; above about 40 differences are rarely visible.
+// The heatmap runs from black through red and yellow to white at the largest
+// difference.
+export interface ImageComparison {
+  identical: boolean;
+  differing_pixels: number;
+  differing_percent: number;
+  alpha_differing_pixels: number;
+  max_difference: number;
+  mean_difference: [number, number, number];
+  rmse: number;
+  psnr: number | null;
+  ssim: number;
+  heatmap: ImageData | null;
+}
+
+// Deltas are b minus a.
+export interface AnalysisDiff {
+  brightness_delta: number;
+  contrast_delta: number;
+  histogram_shift: ChannelShifts;
+  palette_changes: PaletteChange[];
+  palette_distance: number;
+  added_colors: ColorSample[];
+  removed_colors: ColorSample[];
+}
+
+export interface CropSuggestion {
+  aspect_ratio: number;
+  bounds: PixelRect;
+  // Rank score, 0-1.
+  score: number;
+  saliency_coverage: number;
+  // Subject to nearest thirds point, as a fraction of the crop diagonal.
+  thirds_distance: number;
+}
+
+export interface OperationPreview {
+  // The requested region clipped to the image, in image pixels.
+  region: PixelRect;
+  image: ImageData;
+}
+
+// State of an image's low-resolution editing proxy.
+export interface ProxyInfo {
+  id: number;
+  // Full-resolution size.
+  width: number;
+  height: number;
+  // Equal to the full size for images small enough to edit directly.
+  proxy_width: number;
+  proxy_height: number;
+  // Edits shown on the proxy that haven't been applied at full resolution yet.
+  pending_edits: number;
+}
+
+// Multiply and divide treat an image's values as 0-1 fractions, so
+// multiplying by white changes nothing.
+export type MathOp =
+  | 'add'
+  | 'subtract'
+  | 'multiply'
+  | 'divide'
+  | 'min'
+  | 'max'
+  | 'difference';
+
+// A constant is in levels, except for multiply and divide where it's a plain
+// factor.
+export type MathOperand = { type: 'image'; id: number } | { type: 'constant'; value: number };
+
+// `values` is set with float output: the result unclamped, RGB by pixel, in
+// levels.
+export interface ImageMathResult {
+  handle: ImageHandle;
+  values: number[] | null;
+}
+
+// 'f32' keeps a float copy that tone and colour adjustments work on, so
+// chaining them doesn't band; 'u8', the default, rounds after each.
+export type Precision = 'u8' | 'f32';
+
+// A committed change to a stored image, with everything needed to redo it.
+export type Edit =
+  | { type: 'operation'; operation: Operation }
+  | { type: 'auto_trim'; tolerance: number }
+  | { type: 'crop'; rect: PixelRect }
+  // Counter-clockwise degrees.
+  | { type: 'rotate'; degrees: number; fill: CanvasFill }
+  | { type: 'flip'; axis: FlipAxis }
+  | { type: 'resize'; width: number; height: number; filter: ResizeFilter; edge?: EdgeMode }
+  | { type: 'scale_pixel_art'; algorithm: PixelArtAlgorithm; factor: number }
+  | { type: 'extend_canvas'; margins: Margins; fill: CanvasFill }
+  | { type: 'add_border'; spec: BorderSpec }
+  | { type: 'apply_shape_mask'; shape: MaskShape }
+  | { type: 'apply_look'; name: string; intensity: number }
+  | { type: 'auto_enhance'; method: EnhanceMethod }
+  | { type: 'auto_white_balance'; method: WhiteBalanceMethod }
+  | { type: 'dither'; palette: DitherPalette; algorithm: DitherAlgorithm }
+  | { type: 'levels'; levels: ChannelLevels }
+  | { type: 'curve'; points: [number, number][]; channel: CurveChannel }
+  | { type: 'set_black_white_points'; black_sample: PixelPoint; white_sample: PixelPoint }
+  | { type: 'redact_regions'; regions: PixelRect[]; style: RedactionStyle }
+  | { type: 'auto_lens_correct' }
+  // Replays read the flat frame from `flat_frame` again.
+  | { type: 'flat_field_correct'; flat_frame: string }
+  | { type: 'subtract_dark_frame'; dark_frame: string };
+
+export interface LoggedEdit {
+  sequence: number;
+  edit: Edit;
+  // Image size after the edit.
+  width: number;
+  height: number;
+}
+
+export interface OperationLog {
+  id: number;
+  source_path: string | null;
+  // Size of the original the edits start from.
+  width: number;
+  height: number;
+  // Entries before this index are already part of the original and skipped on replay.
+  baseline: number;
+  entries: LoggedEdit[];
+}
+
+export interface ReplayResult {
+  handle: ImageHandle;
+  replayed: number;
+  // False when replaying didn't reproduce the image exactly.
+  matches_current: boolean;
+}
+
+export interface EditHistory {
+  handle: ImageHandle;
+  // Edits making up the current image, oldest first.
+  applied: LoggedEdit[];
+  // Edits `redo` would reapply, next first.
+  undone: LoggedEdit[];
+  can_undo: boolean;
+  can_redo: boolean;
+}
+
+export interface GpsPosition {
+  latitude: number;
+  longitude: number;
+  // Metres above sea level.
+  altitude: number | null;
+}
+
+// Pixels per inch along each axis.
+export interface Resolution {
+  x: number;
+  y: number;
+}
+
+export interface XmpMetadata {
+  // -1 for rejected, otherwise 0-5 stars.
+  rating: number | null;
+  label: string | null;
+  title: string | null;
+  description: string | null;
+  creators: string[];
+  rights: string | null;
+  keywords: string[];
+  creator_tool: string | null;
+  // Set when the packet came from a sidecar file rather than the image.
+  sidecar: string | null;
+}
+
+export interface ImageMetadata {
+  path: string;
+  file_size: number;
+  modified: string | null;
+  format: string | null;
+  width: number | null;
+  height: number | null;
+  camera_make: string | null;
+  camera_model: string | null;
+  lens_model: string | null;
+  // Seconds.
+  exposure_time: number | null;
+  aperture: number | null;
+  iso: number | null;
+  // Millimetres.
+  focal_length: number | null;
+  focal_length_35mm: number | null;
+  captured_at: string | null;
+  gps: GpsPosition | null;
+  // EXIF orientation, 1-8; 1 is upright.
+  orientation: number | null;
+  dpi: Resolution | null;
+  color_profile: string | null;
+  xmp: XmpMetadata | null;
+}
+
+// An edit stack saved next to its original as `<file>.recipe.json`.
+export interface Recipe {
+  version: number;
+  source: string;
+  edits: Edit[];
+}
+
+export interface RecipeExportReport {
+  exported: ExportResult[];
+  skipped: SkippedFile[];
+}
+
+// Payload of the 'analysis-progress' and 'filter-progress' events.
+export interface JobProgress {
+  job_id: string;
+  percent: number;
+}
+
+// Payload of the 'batch-progress' event, sent as each image finishes.
+export interface BatchProgress {
+  completed: number;
+  total: number;
+  path: string;
+  // Why the image was skipped, if it was.
+  error: string | null;
+}
+
+export interface BatchReport {
+  exported: ExportResult[];
+  skipped: SkippedFile[];
+}
+
+export type DevelopTool = 'lightroom' | 'darktable';
+
+// Develop settings read from another editor's XMP, as this app's edits.
+// `unsupported` names settings that have no equivalent here.
+export interface ImportedSettings {
+  tool: DevelopTool;
+  source: string;
+  edits: Edit[];
+  unsupported: string[];
+}
+
+export interface SettingsImport {
+  handle: ImageHandle;
+  settings: ImportedSettings;
+}
+
+// Two regions that are copies of each other; `target` is `source` moved by
+// the offset, and either may be the original.
+export interface CopyMoveMatch {
+  source: PixelRect;
+  target: PixelRect;
+  offset_x: number;
+  offset_y: number;
+  // Matching blocks found, a rough measure of confidence.
+  blocks: number;
+}
+
+// 'trailing_data' is bytes after the image's end marker, 'unknown_chunk' a
+// chunk or segment common software doesn't write, 'comment' long embedded
+// text and 'lsb_anomaly' pixel low bits that look like an embedded message.
+export type HiddenDataKind = 'trailing_data' | 'unknown_chunk' | 'comment' | 'lsb_anomaly';
+
+export interface HiddenDataFinding {
+  kind: HiddenDataKind;
+  // Where in the file, for findings about its structure.
+  offset: number | null;
+  length: number | null;
+  detail: string;
+}
+
+export interface HiddenDataReport {
+  path: string;
+  format: string | null;
+  findings: HiddenDataFinding[];
+  // Estimated fraction of pixels carrying message bits in their least
+  // significant bit. Lossless formats only.
+  lsb_embedding_rate: number | null;
+}
+
+export interface Star {
+  position: Point;
+  // Summed luminance above the sky.
+  flux: number;
+  // Brightest pixel, 0-255.
+  peak: number;
+  // Full width at half maximum, in pixels.
+  fwhm: number;
+  // 0 for a round star, approaching 1 as it stretches into a line.
+  eccentricity: number;
+  // Direction of the long axis, in degrees counter-clockwise from the
+  // positive x axis.
+  angle: number;
+  // Clipped at the top, which makes its FWHM and shape unreliable.
+  saturated: boolean;
+}
+
+// Focus scores on luminance, for ranking shots of the same scene.
+export interface SharpnessReport {
+  laplacian_variance: number;
+  tenengrad: number;
+  heatmap: SharpnessMap | null;
+}
+
+// Tenengrad per grid cell, row by row.
+export interface SharpnessMap {
+  columns: number;
+  rows: number;
+  cell_size: number;
+  tenengrad: number[];
+}
+
+// 'sobel' is the raw gradient; 'canny' thins it to one-pixel lines joined to
+// a strong edge.
+export type EdgeMethod = 'sobel' | 'canny';
+
+// Gradient magnitudes in levels; a hard black-to-white step is 255. Canny
+// keeps edges reaching `high` and those down to `low` joined to them; Sobel
+// drops pixels below `low` and draws the rest more opaque up to `high`.
+export interface EdgeThresholds {
+  low: number;
+  high: number;
+}
+
+export type EdgeOutput = 'overlay' | 'mask';
+
+// Medians are over unsaturated stars where there are any. `stars` lists the
+// brightest first and is capped; `star_count` is every detection.
+export interface StarAnalysis {
+  star_count: number;
+  // Median sky luminance and its noise, 0-255.
+  background: number;
+  noise: number;
+  fwhm: number | null;
+  eccentricity: number | null;
+  // The prevailing elongation direction, in degrees; only meaningful when
+  // eccentricity is high, as with trailing from tracking error.
+  elongation_angle: number | null;
+  stars: Star[];
+}
+
+// `gain` and `offset` map each channel value v to v * gain + offset. Means
+// are luminosity, 0-255, before and after.
+export interface ExposureMatch {
+  path: string;
+  gain: number;
+  offset: number;
+  mean_before: number;
+  mean_after: number;
+  // Set when the corrected frame was written out.
+  output: ExportResult | null;
+}
+
+export interface ExposureMatchReport {
+  reference: string;
+  reference_mean: number;
+  matched: ExposureMatch[];
+  skipped: SkippedFile[];
+}
+
+// Thresholds for `cullFolder`. Sharpness is the variance of the Laplacian
+// on a 1024-pixel thumbnail, as burst detection scores frames; clipping is
+// the percentage of pixels pure black or pure white. Defaults: reject below
+// 20 and review below 80 sharpness, reject over 25% and review over 5%
+// clipped, duplicates within 6 bits.
+export interface CullRules {
+  reject_sharpness?: number;
+  review_sharpness?: number;
+  reject_clipped_percent?: number;
+  review_clipped_percent?: number;
+  // Difference-hash bits two shots may differ by and still be grouped as
+  // near-duplicates; null turns grouping off.
+  duplicate_distance?: number | null;
+  // Sends shots where a face seems to have its eyes closed to review, when
+  // faces are being checked. On by default.
+  review_closed_eyes?: boolean;
+}
+
+export type CullVerdict = 'keep' | 'review' | 'reject';
+
+export interface CullEntry {
+  path: string;
+  verdict: CullVerdict;
+  reasons: string[];
+  sharpness: number;
+  clipped_shadows: number;
+  clipped_highlights: number;
+  // Null when faces weren't checked.
+  faces: number | null;
+  closed_eyes: number;
+  // The best shot of this image's near-duplicate group, when that's another
+  // image.
+  duplicate_of: string | null;
+}
+
+export interface CullReport {
+  keep: CullEntry[];
+  review: CullEntry[];
+  reject: CullEntry[];
+  skipped: SkippedFile[];
+}
+
+export interface ThumbnailComparison {
+  // The part of the EXIF thumbnail showing the picture, without padding.
+  thumbnail_width: number;
+  thumbnail_height: number;
+  // False when the thumbnail shows more of the scene than the image does,
+  // as after a crop.
+  aspect_matches: boolean;
+  // Mean and worst difference between the thumbnail and the downscaled
+  // image over small blocks, in 0-255 levels.
+  difference: number;
+  peak_difference: number;
+  // Where the worst block falls, in the image's pixels.
+  peak_region: PixelRect;
+  likely_edited: boolean;
+}
+
+// A camera's sensor pattern, as written by `extractPrnu`. The pattern itself
+// stays in the file at `path`.
+export interface PrnuFingerprint {
+  path: string;
+  camera: string | null;
+  images: number;
+  // Size of the photos it was built from, which matched photos must share.
+  width: number;
+  height: number;
+  // The central area the pattern covers.
+  crop_width: number;
+  crop_height: number;
+}
+
+export interface PrnuMatch {
+  // Normalized correlation between the photo's noise and the pattern.
+  correlation: number;
+  // Peak-to-correlation energy: how far the correlation stands out from
+  // chance. Above about 60 the photo is taken to be from the camera.
+  pce: number;
+  matches: boolean;
+}
+
+export type DitherPalette =
+  // Exactly these colours, e.g. a display's or a retro system's; alpha is
+  // ignored.
+  | { type: 'fixed'; colors: Color[] }
+  // The image's own `size` most representative colours.
+  | { type: 'extracted'; size: number };
+
+// Floyd-Steinberg and Atkinson diffuse the error for a fine grain, Atkinson
+// with the crisper look of early Macs; Bayer and blue noise are ordered and
+// stay stable frame to frame, blue noise without Bayer's crosshatch.
+export type DitherAlgorithm = 'floyd_steinberg' | 'atkinson' | 'bayer' | 'blue_noise';
+
+export type ExportFormat = 'png' | 'jpeg' | 'webp' | 'tiff' | 'bmp';
+
+export type PngCompression = 'fast' | 'default' | 'best';
+
+// `quality` is 1-100 for JPEG (default 90) and WebP; WebP without one is
+// lossless. JPEG and BMP are always flattened onto white. Unless
+// `strip_metadata` is set, PNG and JPEG keep the source's EXIF and ICC profile.
+export interface ExportOptions {
+  format: ExportFormat;
+  quality?: number;
+  png_compression?: PngCompression;
+  strip_alpha?: boolean;
+  strip_metadata?: boolean;
+}
+
+// Glyph sets for `exportAscii`; custom glyphs go from emptiest to fullest.
+export type AsciiCharset =
+  | { type: 'standard' }
+  | { type: 'detailed' }
+  | { type: 'blocks' }
+  | { type: 'custom'; chars: string };
+
+// Plain text is dark on light; 'ansi' (24-bit terminal colour) and 'html'
+// draw coloured glyphs on black.
+export type AsciiColorMode = 'plain' | 'ansi' | 'html';
+
+// Frames of an animated GIF, PNG or WebP, or pages of a TIFF. `delays` are
+// in milliseconds, 0 for pages and stills; the size is the first frame's.
+export interface ImageFrames {
+  path: string;
+  frame_count: number;
+  delays: number[];
+  width: number;
+  height: number;
+  animated: boolean;
+}
+
+// A RAW file's undemosaiced sensor data, cropped to the usable area.
+export interface RawMosaic {
+  image: ImageData;
+  // Filter colours of the repeating tile, row by row, e.g. 'RGGB'.
+  pattern: string;
+  channels: CfaChannelStats[];
+}
+
+export type CfaColor = 'red' | 'green' | 'blue' | 'emerald';
+
+export interface CfaChannelStats {
+  color: CfaColor;
+  sites: number;
+  black_level: number;
+  white_level: number;
+  // Fractions of the range from black to clipping.
+  mean: number;
+  clipped: number;
+  // 256 bins, linear in sensor values.
+  histogram: number[];
 }
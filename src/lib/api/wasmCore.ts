@@ -0,0 +1,105 @@
+// Runs analysis and basic adjustments in the browser through the WebAssembly
+// build of the processing core (`npm run build:wasm`), for instant previews
+// without an IPC round trip or when running outside Tauri.
+import type { AnalysisDiff, AnalysisResult, LookInfo } from './types';
+
+interface WasmCore {
+  default: () => Promise<unknown>;
+  analyze(data: Uint8Array, paletteSize?: number): AnalysisResult;
+  diffAnalysis(a: AnalysisResult, b: AnalysisResult): AnalysisDiff;
+  adjustBrightness(data: Uint8Array, amount: number): void;
+  adjustContrast(data: Uint8Array, amount: number): void;
+  adjustHsl(data: Uint8Array, hueShift: number, saturation: number, lightness: number): void;
+  convertToGrayscale(data: Uint8Array): void;
+  listLooks(): LookInfo[];
+  applyLook(
+    data: Uint8Array,
+    width: number,
+    height: number,
+    name: string,
+    intensity: number
+  ): void;
+}
+
+const WASM_MODULE_URL = '/wasm/image_analyzer_wasm.js';
+
+let core: Promise<WasmCore> | null = null;
+
+export function loadWasmCore(): Promise<WasmCore> {
+  core ??= import(/* @vite-ignore */ WASM_MODULE_URL).then(async (module: WasmCore) => {
+    await module.default();
+    return module;
+  });
+  return core;
+}
+
+export async function isWasmCoreAvailable(): Promise<boolean> {
+  try {
+    await loadWasmCore();
+    return true;
+  } catch {
+    core = null;
+    return false;
+  }
+}
+
+// The adjustment helpers work in place and return the same buffer for chaining.
+export async function analyzeLocally(
+  data: Uint8Array,
+  paletteSize?: number
+): Promise<AnalysisResult> {
+  return (await loadWasmCore()).analyze(data, paletteSize);
+}
+
+export async function diffAnalysisLocally(
+  a: AnalysisResult,
+  b: AnalysisResult
+): Promise<AnalysisDiff> {
+  return (await loadWasmCore()).diffAnalysis(a, b);
+}
+
+export async function adjustBrightnessLocally(
+  data: Uint8Array,
+  amount: number
+): Promise<Uint8Array> {
+  (await loadWasmCore()).adjustBrightness(data, amount);
+  return data;
+}
+
+export async function adjustContrastLocally(
+  data: Uint8Array,
+  amount: number
+): Promise<Uint8Array> {
+  (await loadWasmCore()).adjustContrast(data, amount);
+  return data;
+}
+
+export async function adjustHslLocally(
+  data: Uint8Array,
+  hueShift: number,
+  saturation: number,
+  lightness: number
+): Promise<Uint8Array> {
+  (await loadWasmCore()).adjustHsl(data, hueShift, saturation, lightness);
+  return data;
+}
+
+export async function convertToGrayscaleLocally(data: Uint8Array): Promise<Uint8Array> {
+  (await loadWasmCore()).convertToGrayscale(data);
+  return data;
+}
+
+export async function listLooksLocally(): Promise<LookInfo[]> {
+  return (await loadWasmCore()).listLooks();
+}
+
+export async function applyLookLocally(
+  data: Uint8Array,
+  width: number,
+  height: number,
+  name: string,
+  intensity = 1
+): Promise<Uint8Array> {
+  (await loadWasmCore()).applyLook(data, width, height, name, intensity);
+  return data;
+}